@@ -0,0 +1,36 @@
+use std::collections::BTreeMap;
+
+use librespot_core::authentication::Credentials;
+use log::error;
+
+use crate::config::cache_path;
+use crate::serialization::{Serializer, JSON};
+
+const ACCOUNTS_FILE: &str = "accounts.json";
+
+/// The reusable credentials of the accounts that were logged in with, by
+/// username, to switch between them.
+pub fn load() -> BTreeMap<String, Credentials> {
+    JSON.load(cache_path(ACCOUNTS_FILE)).unwrap_or_default()
+}
+
+/// Remember `credentials`, replacing those of the same account.
+pub fn save(credentials: &Credentials) {
+    let mut accounts = load();
+    accounts.insert(credentials.username.clone(), credentials.clone());
+    write(accounts);
+}
+
+/// Forget the credentials of the account of `username`.
+pub fn remove(username: &str) {
+    let mut accounts = load();
+    if accounts.remove(username).is_some() {
+        write(accounts);
+    }
+}
+
+fn write(accounts: BTreeMap<String, Credentials>) {
+    if let Err(e) = JSON.write(cache_path(ACCOUNTS_FILE), accounts) {
+        error!("could not save the accounts: {}", e);
+    }
+}
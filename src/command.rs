@@ -1,7 +1,11 @@
+use crate::config::{NormalizationMode, ShuffleMode};
+use crate::playback_speed::{MAX_PLAYBACK_SPEED, MIN_PLAYBACK_SPEED};
 use crate::queue::RepeatSetting;
+use crate::search_query::SearchQuery;
 use crate::spotify_url::SpotifyUrl;
 use std::collections::HashMap;
 use std::fmt;
+use std::time::Duration;
 
 use strum_macros::Display;
 
@@ -50,6 +54,9 @@ pub enum SortKey {
     Artist,
     Album,
     Added,
+    /// The release date of a track's album, or the latest release of an
+    /// artist.
+    Released,
 }
 
 #[derive(Display, Clone, Serialize, Deserialize, Debug)]
@@ -72,6 +79,22 @@ pub enum JumpMode {
 pub enum ShiftMode {
     Up,
     Down,
+    /// Shift to the given position, counting from 1.
+    To,
+}
+
+#[derive(Display, Clone, Serialize, Deserialize, Debug)]
+#[strum(serialize_all = "lowercase")]
+pub enum StatsKind {
+    Tracks,
+    Artists,
+}
+
+#[derive(Display, Clone, Serialize, Deserialize, Debug)]
+#[strum(serialize_all = "lowercase")]
+pub enum HistoryKind {
+    Search,
+    Played,
 }
 
 #[derive(Display, Clone, Serialize, Deserialize, Debug)]
@@ -79,18 +102,23 @@ pub enum ShiftMode {
 pub enum GotoMode {
     Album,
     Artist,
+    /// The artists related to an artist.
+    Related,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum SeekDirection {
     Relative(i32),
     Absolute(u32),
+    /// A position relative to the length of the track, in percent.
+    Percentage(u32),
 }
 
 impl fmt::Display for SeekDirection {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let repr = match self {
             SeekDirection::Absolute(pos) => format!("{pos}"),
+            SeekDirection::Percentage(percent) => format!("{percent}%"),
             SeekDirection::Relative(delta) => {
                 format!("{}{}", if delta > &0 { "+" } else { "" }, delta)
             }
@@ -99,6 +127,155 @@ impl fmt::Display for SeekDirection {
     }
 }
 
+/// The points of an A-B loop, which are set at the current position.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum LoopPoint {
+    Start,
+    End,
+    Clear,
+}
+
+/// What to do with the marks of a track list, which select several tracks
+/// at once.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum MarkMode {
+    /// Mark the selected track, or unmark it if it's marked.
+    Toggle,
+    /// Start selecting the tracks from the selected one to wherever the
+    /// selection moves, or mark the selected range and stop.
+    Visual,
+    /// Mark every track of the list.
+    All,
+    Clear,
+}
+
+impl fmt::Display for MarkMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = match self {
+            MarkMode::Toggle => "toggle",
+            MarkMode::Visual => "visual",
+            MarkMode::All => "all",
+            MarkMode::Clear => "clear",
+        };
+        write!(f, "{repr}")
+    }
+}
+
+impl fmt::Display for LoopPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = match self {
+            LoopPoint::Start => "a",
+            LoopPoint::End => "b",
+            LoopPoint::Clear => "clear",
+        };
+        write!(f, "{repr}")
+    }
+}
+
+/// A change to a playlist, which is the selected one for all but `New`.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum PlaylistAction {
+    /// Create a playlist with the given name, filled with the queue if set.
+    New(String, bool),
+    Rename(String),
+    Delete,
+    Public,
+    Private,
+    /// Let others add to the playlist, which makes it private.
+    Collaborative,
+}
+
+impl PlaylistAction {
+    fn args(&self) -> Vec<String> {
+        match self {
+            PlaylistAction::New(name, false) => vec!["new".into(), name.clone()],
+            PlaylistAction::New(name, true) => {
+                vec!["new".into(), "--from-queue".into(), name.clone()]
+            }
+            PlaylistAction::Rename(name) => vec!["rename".into(), name.clone()],
+            PlaylistAction::Delete => vec!["delete".into()],
+            PlaylistAction::Public => vec!["public".into()],
+            PlaylistAction::Private => vec!["private".into()],
+            PlaylistAction::Collaborative => vec!["collaborative".into()],
+        }
+    }
+}
+
+/// The part of the queue that is cleared, around the current item.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum ClearTarget {
+    Upcoming,
+    Played,
+}
+
+/// How to change the filters of the search results that are shown.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum SearchFilterMode {
+    /// Only search the category of the focused tab, or every category again.
+    Only,
+    /// Search for the text alone again.
+    Clear,
+}
+
+impl fmt::Display for SearchFilterMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = match self {
+            SearchFilterMode::Only => "only",
+            SearchFilterMode::Clear => "clear",
+        };
+        write!(f, "{repr}")
+    }
+}
+
+impl fmt::Display for ClearTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = match self {
+            ClearTarget::Upcoming => "upcoming",
+            ClearTarget::Played => "played",
+        };
+        write!(f, "{repr}")
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum SleepMode {
+    /// Sleep once the duration has passed. If set, the track that is playing
+    /// at that point is finished first.
+    After(Duration, bool),
+    /// Sleep once the current track has finished.
+    Track,
+    Off,
+}
+
+impl fmt::Display for SleepMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = match self {
+            SleepMode::After(duration, false) => format!("{}s", duration.as_secs()),
+            SleepMode::After(duration, true) => format!("{}s track", duration.as_secs()),
+            SleepMode::Track => "track".into(),
+            SleepMode::Off => "off".into(),
+        };
+        write!(f, "{repr}")
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum NormalizationSetting {
+    Mode(NormalizationMode),
+    /// The pregain in dB.
+    Pregain(f64),
+}
+
+impl fmt::Display for NormalizationSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = match self {
+            NormalizationSetting::Mode(mode) => mode.to_string(),
+            NormalizationSetting::Pregain(pregain) => format!("pregain {pregain}"),
+        };
+        write!(f, "{repr}")
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum InsertSource {
     #[cfg(feature = "share_clipboard")]
@@ -124,7 +301,10 @@ pub enum Command {
     Stop,
     Previous,
     Next,
-    Clear,
+    /// Play the current track again from its start.
+    Restart,
+    /// Clear the whole queue, or only one side of the current item.
+    Clear(Option<ClearTarget>),
     Queue,
     PlayNext,
     Play,
@@ -138,26 +318,95 @@ pub enum Command {
     VolumeDown(u16),
     Repeat(Option<RepeatSetting>),
     Shuffle(Option<bool>),
+    ShuffleMode(Option<ShuffleMode>),
+    /// Continue playback with recommendations once the queue runs out.
+    Autoplay(Option<bool>),
     #[cfg(feature = "share_clipboard")]
     Share(TargetMode),
     Back,
     Open(TargetMode),
     Goto(GotoMode),
+    /// Go to any item of the queue or the library, chosen from a dialog.
+    Launcher,
     Move(MoveMode, MoveAmount),
     Shift(ShiftMode, Option<i32>),
-    Search(String),
+    Search(SearchQuery),
+    /// Change the filters of the search results that are shown.
+    SearchFilter(SearchFilterMode),
     Jump(JumpMode),
+    /// Only show the items of the list that match the text, or all of them
+    /// again without one.
+    Filter(String),
     Help,
     ReloadConfig,
     Noop,
     Insert(InsertSource),
     NewPlaylist(String),
-    Sort(SortKey, SortDirection),
+    /// Create or change a playlist.
+    Playlist(PlaylistAction),
+    /// Sort the list by the key, or by the next key without one.
+    Sort(Option<SortKey>, SortDirection),
     Logout,
     ShowRecommendations(TargetMode),
+    /// Play tracks recommended for the target item.
+    Radio(TargetMode),
+    /// Show the details of the target track.
+    Info(TargetMode),
     Redraw,
     Execute(String),
     Reconnect,
+    Mute,
+    StopAfterTrack,
+    /// Add the current track to the user's Liked Songs, or remove it. The
+    /// show of an episode is saved or removed instead.
+    Like,
+    Sleep(SleepMode),
+    PlaybackSpeed(f32),
+    Bitrate(u32),
+    Normalization(NormalizationSetting),
+    ExportQueue(String),
+    ImportQueue(String),
+    /// Append the local audio file at the path to the queue.
+    AddFile(String),
+    /// Add the current track to the playlist with the given ID or name, or
+    /// the configured target playlist, or one chosen from a dialog.
+    SaveCurrent(Option<String>),
+    /// Shift the item at the first position of the queue to the second one,
+    /// counting from 1.
+    Reorder(usize, usize),
+    /// Show the most played tracks or artists, optionally only within the
+    /// given time.
+    Stats(StatsKind, Option<Duration>),
+    /// Show the items that were played, most recent first.
+    History,
+    /// Forget the queries that were searched for, or the items that were
+    /// played.
+    ClearHistory(HistoryKind),
+    /// Set a point of the A-B loop of the current track, or clear the loop.
+    AbLoop(LoopPoint),
+    /// Switch to the account with the given username, or choose one of the
+    /// accounts that were logged in with.
+    Account(Option<String>),
+    /// Quit, to log in with another account on the next start.
+    AddAccount,
+    /// Play the first item of the next album in the playback order.
+    NextAlbum,
+    /// Append all the tracks of the selected item's album to the queue.
+    AddAlbum,
+    /// Remove the later occurrences of items that are listed more than once.
+    Dedup,
+    /// Restore the items that were removed from the queue by the last dedup.
+    Undo,
+    /// Show the user's top tracks and artists according to Spotify.
+    Top,
+    /// Toggle between showing all new releases and only those by followed
+    /// artists.
+    FollowedOnly,
+    /// Mark tracks of a list to act on them at once, or clear the marks.
+    Mark(MarkMode),
+    /// Add the marked tracks, or else the selected or current track, to a
+    /// playlist chosen from a dialog.
+    AddToPlaylist(TargetMode),
 }
 
 impl fmt::Display for Command {
@@ -169,6 +418,7 @@ impl fmt::Display for Command {
             Command::VolumeUp(amount) => vec![amount.to_string()],
             Command::VolumeDown(amount) => vec![amount.to_string()],
             Command::Repeat(mode) => match mode {
+                Some(RepeatSetting::RepeatCount(count)) => vec![count.to_string()],
                 Some(mode) => vec![mode.to_string()],
                 None => vec![],
             },
@@ -176,6 +426,18 @@ impl fmt::Display for Command {
                 Some(b) => vec![(if *b { "on" } else { "off" }).into()],
                 None => vec![],
             },
+            Command::ShuffleMode(mode) => match mode {
+                Some(mode) => vec![mode.to_string()],
+                None => vec![],
+            },
+            Command::Autoplay(on) => match on {
+                Some(b) => vec![(if *b { "on" } else { "off" }).into()],
+                None => vec![],
+            },
+            Command::Clear(target) => match target {
+                Some(target) => vec![target.to_string()],
+                None => vec![],
+            },
             #[cfg(feature = "share_clipboard")]
             Command::Share(mode) => vec![mode.to_string()],
             Command::Open(mode) => vec![mode.to_string()],
@@ -189,22 +451,49 @@ impl fmt::Display for Command {
                 (mode, MoveAmount::Integer(amount)) => vec![mode.to_string(), amount.to_string()],
             },
             Command::Shift(mode, amount) => vec![mode.to_string(), amount.unwrap_or(1).to_string()],
-            Command::Search(term) => vec![term.to_owned()],
+            Command::Search(query) => vec![query.to_string()],
+            Command::SearchFilter(mode) => vec![mode.to_string()],
+            Command::Filter(text) => vec![text.to_owned()],
             Command::Jump(mode) => match mode {
                 JumpMode::Previous | JumpMode::Next => vec![],
                 JumpMode::Query(term) => vec![term.to_owned()],
             },
             Command::Insert(source) => vec![source.to_string()],
             Command::NewPlaylist(name) => vec![name.to_owned()],
-            Command::Sort(key, direction) => vec![key.to_string(), direction.to_string()],
+            Command::Playlist(action) => action.args(),
+            Command::SaveCurrent(playlist) => playlist.iter().cloned().collect(),
+            Command::Sort(key, direction) => match key {
+                Some(key) => vec![key.to_string(), direction.to_string()],
+                None => vec![],
+            },
             Command::ShowRecommendations(mode) => vec![mode.to_string()],
+            Command::Radio(mode) => vec![mode.to_string()],
+            Command::Info(mode) => vec![mode.to_string()],
             Command::Execute(cmd) => vec![cmd.to_owned()],
+            Command::Sleep(mode) => vec![mode.to_string()],
+            Command::AbLoop(point) => vec![point.to_string()],
+            Command::Mark(mode) => vec![mode.to_string()],
+            Command::AddToPlaylist(mode) => vec![mode.to_string()],
+            Command::PlaybackSpeed(speed) => vec![speed.to_string()],
+            Command::Bitrate(bitrate) => vec![bitrate.to_string()],
+            Command::Normalization(setting) => vec![setting.to_string()],
+            Command::ExportQueue(path) | Command::ImportQueue(path) | Command::AddFile(path) => {
+                vec![path.to_owned()]
+            }
+            Command::Reorder(from, to) => vec![from.to_string(), to.to_string()],
+            Command::ClearHistory(kind) => vec![kind.to_string()],
+            Command::Account(username) => username.iter().cloned().collect(),
+            Command::AddAccount => vec!["add".into()],
+            Command::Stats(kind, window) => match window {
+                Some(window) => vec![kind.to_string(), format!("{}s", window.as_secs())],
+                None => vec![kind.to_string()],
+            },
             Command::Quit
             | Command::TogglePlay
             | Command::Stop
             | Command::Previous
             | Command::Next
-            | Command::Clear
+            | Command::Restart
             | Command::Queue
             | Command::PlayNext
             | Command::Play
@@ -216,8 +505,19 @@ impl fmt::Display for Command {
             | Command::Help
             | Command::ReloadConfig
             | Command::Noop
+            | Command::History
             | Command::Logout
             | Command::Reconnect
+            | Command::Mute
+            | Command::StopAfterTrack
+            | Command::Like
+            | Command::Launcher
+            | Command::NextAlbum
+            | Command::AddAlbum
+            | Command::Dedup
+            | Command::Undo
+            | Command::Top
+            | Command::FollowedOnly
             | Command::Redraw => vec![],
         };
         repr_tokens.append(&mut extras_args);
@@ -233,7 +533,8 @@ impl Command {
             Command::Stop => "stop",
             Command::Previous => "previous",
             Command::Next => "next",
-            Command::Clear => "clear",
+            Command::Restart => "restart",
+            Command::Clear(_) => "clear",
             Command::Queue => "queue",
             Command::PlayNext => "playnext",
             Command::Play => "play",
@@ -247,28 +548,61 @@ impl Command {
             Command::VolumeDown(_) => "voldown",
             Command::Repeat(_) => "repeat",
             Command::Shuffle(_) => "shuffle",
+            Command::ShuffleMode(_) => "shufflemode",
+            Command::Autoplay(_) => "autoplay",
             #[cfg(feature = "share_clipboard")]
             Command::Share(_) => "share",
             Command::Back => "back",
             Command::Open(_) => "open",
             Command::Goto(_) => "goto",
+            Command::Launcher => "goto",
             Command::Move(_, _) => "move",
             Command::Shift(_, _) => "shift",
             Command::Search(_) => "search",
+            Command::SearchFilter(_) => "searchfilter",
             Command::Jump(JumpMode::Previous) => "jumpprevious",
             Command::Jump(JumpMode::Next) => "jumpnext",
             Command::Jump(JumpMode::Query(_)) => "jump",
+            Command::Filter(_) => "filter",
             Command::Help => "help",
             Command::ReloadConfig => "reload",
             Command::Noop => "noop",
             Command::Insert(_) => "insert",
             Command::NewPlaylist(_) => "newplaylist",
+            Command::Playlist(_) => "playlist",
             Command::Sort(_, _) => "sort",
             Command::Logout => "logout",
             Command::ShowRecommendations(_) => "similar",
+            Command::Radio(_) => "radio",
+            Command::Info(_) => "info",
             Command::Redraw => "redraw",
             Command::Execute(_) => "exec",
             Command::Reconnect => "reconnect",
+            Command::Mute => "mute",
+            Command::StopAfterTrack => "stopafter",
+            Command::Like => "like",
+            Command::Sleep(_) => "sleep",
+            Command::PlaybackSpeed(_) => "speed",
+            Command::Bitrate(_) => "bitrate",
+            Command::Normalization(_) => "normalization",
+            Command::ExportQueue(_) => "exportqueue",
+            Command::ImportQueue(_) => "importqueue",
+            Command::AddFile(_) => "addfile",
+            Command::SaveCurrent(_) => "savecurrent",
+            Command::Reorder(_, _) => "reorder",
+            Command::Stats(_, _) => "stats",
+            Command::History => "history",
+            Command::ClearHistory(_) => "clearhistory",
+            Command::Account(_) | Command::AddAccount => "account",
+            Command::AbLoop(_) => "abloop",
+            Command::NextAlbum => "nextalbum",
+            Command::AddAlbum => "addalbum",
+            Command::Dedup => "dedup",
+            Command::Undo => "undo",
+            Command::Top => "top",
+            Command::FollowedOnly => "followedonly",
+            Command::Mark(_) => "mark",
+            Command::AddToPlaylist(_) => "addtoplaylist",
         }
     }
 }
@@ -378,7 +712,19 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                 "stop" => Command::Stop,
                 "previous" => Command::Previous,
                 "next" => Command::Next,
-                "clear" => Command::Clear,
+                "restart" => Command::Restart,
+                "clear" => {
+                    let target = match args.first().cloned() {
+                        Some("upcoming") => Ok(Some(ClearTarget::Upcoming)),
+                        Some("played") => Ok(Some(ClearTarget::Played)),
+                        Some(arg) => Err(BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["**omit**".into(), "upcoming".into(), "played".into()],
+                        }),
+                        None => Ok(None),
+                    }?;
+                    Command::Clear(target)
+                }
                 "queue" => Command::Queue,
                 "playnext" => Command::PlayNext,
                 "play" => Command::Play,
@@ -400,6 +746,17 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                     // TODO: this really should be strongly typed
                     Command::Focus(target.into())
                 }
+                "seek" if args.concat().ends_with('%') => {
+                    let arg = args.concat();
+                    let percent: i64 =
+                        arg.trim_end_matches('%')
+                            .parse()
+                            .map_err(|_| ArgParseError {
+                                arg: arg.clone(),
+                                err: "Expected a percentage".into(),
+                            })?;
+                    Command::Seek(SeekDirection::Percentage(percent.clamp(0, 100) as u32))
+                }
                 "seek" => {
                     if args.is_empty() {
                         return Err(InsufficientArgs {
@@ -445,6 +802,108 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                     })?;
                     Command::Seek(seek_direction)
                 }
+                "sleep" => {
+                    let &arg = args.first().ok_or(InsufficientArgs {
+                        cmd: command.into(),
+                        hint: Some("a duration|track|off".into()),
+                    })?;
+                    let finish_track = match args.get(1).cloned() {
+                        Some("track") => Ok(true),
+                        Some(arg) => Err(BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["track".into()],
+                        }),
+                        None => Ok(false),
+                    }?;
+                    let mode = match arg {
+                        "track" => SleepMode::Track,
+                        "off" => SleepMode::Off,
+                        _ => {
+                            let duration = match arg.parse::<u64>() {
+                                // accept raw minutes
+                                Ok(minutes) => Duration::from_secs(minutes * 60),
+                                Err(_) => {
+                                    parse_duration::parse(arg).map_err(|err| ArgParseError {
+                                        arg: arg.into(),
+                                        err: err.to_string(),
+                                    })?
+                                }
+                            };
+                            SleepMode::After(duration, finish_track)
+                        }
+                    };
+                    Command::Sleep(mode)
+                }
+                "normalization" => {
+                    let setting = match args.first().cloned() {
+                        Some("on") => NormalizationSetting::Mode(NormalizationMode::Auto),
+                        Some("off") => NormalizationSetting::Mode(NormalizationMode::Off),
+                        Some("album") => NormalizationSetting::Mode(NormalizationMode::Album),
+                        Some("track") => NormalizationSetting::Mode(NormalizationMode::Track),
+                        Some("pregain") => {
+                            let &pregain_raw = args.get(1).ok_or(InsufficientArgs {
+                                cmd: command.into(),
+                                hint: Some("a pregain in dB".into()),
+                            })?;
+                            let pregain =
+                                pregain_raw.parse::<f64>().map_err(|err| ArgParseError {
+                                    arg: pregain_raw.into(),
+                                    err: err.to_string(),
+                                })?;
+                            NormalizationSetting::Pregain(pregain)
+                        }
+                        Some(arg) => Err(BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec![
+                                "on".into(),
+                                "off".into(),
+                                "album".into(),
+                                "track".into(),
+                                "pregain".into(),
+                            ],
+                        })?,
+                        None => Err(InsufficientArgs {
+                            cmd: command.into(),
+                            hint: Some("on|off|album|track|pregain".into()),
+                        })?,
+                    };
+                    Command::Normalization(setting)
+                }
+                "speed" => {
+                    let &speed_raw = args.first().ok_or(InsufficientArgs {
+                        cmd: command.into(),
+                        hint: Some("a playback speed".into()),
+                    })?;
+                    let speed = speed_raw.parse::<f32>().map_err(|err| ArgParseError {
+                        arg: speed_raw.into(),
+                        err: err.to_string(),
+                    })?;
+                    if !(MIN_PLAYBACK_SPEED..=MAX_PLAYBACK_SPEED).contains(&speed) {
+                        return Err(ArgParseError {
+                            arg: speed_raw.into(),
+                            err: format!(
+                                "Speed must be between {MIN_PLAYBACK_SPEED} and {MAX_PLAYBACK_SPEED}"
+                            ),
+                        });
+                    }
+                    Command::PlaybackSpeed(speed)
+                }
+                "bitrate" => {
+                    let bitrate = match args.first().cloned() {
+                        Some("96") => Ok(96),
+                        Some("160") => Ok(160),
+                        Some("320") => Ok(320),
+                        Some(arg) => Err(BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["96".into(), "160".into(), "320".into()],
+                        }),
+                        None => Err(InsufficientArgs {
+                            cmd: command.into(),
+                            hint: Some("96|160|320".into()),
+                        }),
+                    }?;
+                    Command::Bitrate(bitrate)
+                }
                 "volup" => {
                     let amount = match args.first() {
                         Some(&amount_raw) => {
@@ -476,25 +935,61 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                         }
                         Some("track" | "once" | "single") => Ok(Some(RepeatSetting::RepeatTrack)),
                         Some("none" | "off") => Ok(Some(RepeatSetting::None)),
+                        Some(arg) => match arg.parse::<u8>() {
+                            Ok(count) if count > 0 => Ok(Some(RepeatSetting::RepeatCount(count))),
+                            _ => Err(BadEnumArg {
+                                arg: arg.into(),
+                                accept: vec![
+                                    "**omit**".into(),
+                                    "list".into(),
+                                    "playlist".into(),
+                                    "queue".into(),
+                                    "track".into(),
+                                    "once".into(),
+                                    "single".into(),
+                                    "none".into(),
+                                    "off".into(),
+                                    "1-255".into(),
+                                ],
+                            }),
+                        },
+                        None => Ok(None),
+                    }?;
+                    Command::Repeat(mode)
+                }
+                "shuffle" => {
+                    let switch = match args.first().cloned() {
+                        Some("on") => Ok(Some(true)),
+                        Some("off") => Ok(Some(false)),
+                        Some(arg) => Err(BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["**omit**".into(), "on".into(), "off".into()],
+                        }),
+                        None => Ok(None),
+                    }?;
+                    Command::Shuffle(switch)
+                }
+                "shufflemode" => {
+                    let mode = match args.first().cloned() {
+                        Some("random") => Ok(Some(ShuffleMode::Random)),
+                        Some("spread") => Ok(Some(ShuffleMode::Spread)),
+                        Some("albums") => Ok(Some(ShuffleMode::Albums)),
+                        Some("fresh") => Ok(Some(ShuffleMode::Fresh)),
                         Some(arg) => Err(BadEnumArg {
                             arg: arg.into(),
                             accept: vec![
                                 "**omit**".into(),
-                                "list".into(),
-                                "playlist".into(),
-                                "queue".into(),
-                                "track".into(),
-                                "once".into(),
-                                "single".into(),
-                                "none".into(),
-                                "off".into(),
+                                "random".into(),
+                                "spread".into(),
+                                "albums".into(),
+                                "fresh".into(),
                             ],
                         }),
                         None => Ok(None),
                     }?;
-                    Command::Repeat(mode)
+                    Command::ShuffleMode(mode)
                 }
-                "shuffle" => {
+                "autoplay" => {
                     let switch = match args.first().cloned() {
                         Some("on") => Ok(Some(true)),
                         Some("off") => Ok(Some(false)),
@@ -504,7 +999,7 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                         }),
                         None => Ok(None),
                     }?;
-                    Command::Shuffle(switch)
+                    Command::Autoplay(switch)
                 }
                 #[cfg(feature = "share_clipboard")]
                 "share" => {
@@ -538,17 +1033,16 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                     }?;
                     Command::Open(target_mode)
                 }
+                "goto" if args.is_empty() => Command::Launcher,
                 "goto" => {
-                    let &goto_mode_raw = args.first().ok_or(InsufficientArgs {
-                        cmd: command.into(),
-                        hint: Some("album|artist".into()),
-                    })?;
+                    let goto_mode_raw = args[0];
                     let goto_mode = match goto_mode_raw {
                         "album" => Ok(GotoMode::Album),
                         "artist" => Ok(GotoMode::Artist),
+                        "related" => Ok(GotoMode::Related),
                         _ => Err(BadEnumArg {
                             arg: goto_mode_raw.into(),
-                            accept: vec!["album".into(), "artist".into()],
+                            accept: vec!["album".into(), "artist".into(), "related".into()],
                         }),
                     }?;
                     Command::Goto(goto_mode)
@@ -605,14 +1099,15 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                 "shift" => {
                     let &shift_dir_raw = args.first().ok_or(InsufficientArgs {
                         cmd: command.into(),
-                        hint: Some("up|down".into()),
+                        hint: Some("up|down|to".into()),
                     })?;
                     let shift_dir = match shift_dir_raw {
                         "up" => Ok(ShiftMode::Up),
                         "down" => Ok(ShiftMode::Down),
+                        "to" => Ok(ShiftMode::To),
                         _ => Err(BadEnumArg {
                             arg: shift_dir_raw.into(),
-                            accept: vec!["up".into(), "down".into()],
+                            accept: vec!["up".into(), "down".into(), "to".into()],
                         }),
                     }?;
                     let amount = match args.get(1) {
@@ -624,14 +1119,36 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                                 })?;
                             Some(amount)
                         }
+                        None if matches!(shift_dir, ShiftMode::To) => {
+                            return Err(InsufficientArgs {
+                                cmd: command.into(),
+                                hint: Some("a position".into()),
+                            });
+                        }
                         None => None,
                     };
                     Command::Shift(shift_dir, amount)
                 }
-                "search" => Command::Search(args.join(" ")),
+                "search" => Command::Search(SearchQuery::parse(args)?),
+                "searchfilter" => {
+                    let &mode_raw = args.first().ok_or(InsufficientArgs {
+                        cmd: command.into(),
+                        hint: Some("only|clear".into()),
+                    })?;
+                    let mode = match mode_raw {
+                        "only" => Ok(SearchFilterMode::Only),
+                        "clear" => Ok(SearchFilterMode::Clear),
+                        _ => Err(BadEnumArg {
+                            arg: mode_raw.into(),
+                            accept: vec!["only".into(), "clear".into()],
+                        }),
+                    }?;
+                    Command::SearchFilter(mode)
+                }
                 "jump" => Command::Jump(JumpMode::Query(args.join(" "))),
                 "jumpnext" => Command::Jump(JumpMode::Next),
                 "jumpprevious" => Command::Jump(JumpMode::Previous),
+                "filter" => Command::Filter(args.join(" ")),
                 "help" => Command::Help,
                 "reload" => Command::ReloadConfig,
                 "noop" => Command::Noop,
@@ -664,17 +1181,55 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                         })
                     }?
                 }
-                "sort" => {
-                    let &key_raw = args.first().ok_or(InsufficientArgs {
+                "playlist" => {
+                    let &action_raw = args.first().ok_or(InsufficientArgs {
                         cmd: command.into(),
-                        hint: Some("a sort key".into()),
+                        hint: Some("new|rename|delete|public|private|collaborative".into()),
                     })?;
-                    let key = match key_raw {
+                    let name = |args: &[&str]| {
+                        if args.is_empty() {
+                            Err(InsufficientArgs {
+                                cmd: format!("{command} {action_raw}"),
+                                hint: Some("a name".into()),
+                            })
+                        } else {
+                            Ok(args.join(" "))
+                        }
+                    };
+                    let action = match action_raw {
+                        "new" => match args.get(1) {
+                            Some(&"--from-queue") => {
+                                Ok(PlaylistAction::New(name(&args[2..])?, true))
+                            }
+                            _ => Ok(PlaylistAction::New(name(&args[1..])?, false)),
+                        },
+                        "rename" => Ok(PlaylistAction::Rename(name(&args[1..])?)),
+                        "delete" => Ok(PlaylistAction::Delete),
+                        "public" => Ok(PlaylistAction::Public),
+                        "private" => Ok(PlaylistAction::Private),
+                        "collaborative" => Ok(PlaylistAction::Collaborative),
+                        _ => Err(BadEnumArg {
+                            arg: action_raw.into(),
+                            accept: vec![
+                                "new".into(),
+                                "rename".into(),
+                                "delete".into(),
+                                "public".into(),
+                                "private".into(),
+                                "collaborative".into(),
+                            ],
+                        }),
+                    }?;
+                    Command::Playlist(action)
+                }
+                "sort" => {
+                    let key = args.first().map(|&key_raw| match key_raw {
                         "title" => Ok(SortKey::Title),
                         "duration" => Ok(SortKey::Duration),
                         "album" => Ok(SortKey::Album),
                         "added" => Ok(SortKey::Added),
                         "artist" => Ok(SortKey::Artist),
+                        "released" => Ok(SortKey::Released),
                         _ => Err(BadEnumArg {
                             arg: key_raw.into(),
                             accept: vec![
@@ -683,9 +1238,11 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                                 "album".into(),
                                 "added".into(),
                                 "artist".into(),
+                                "released".into(),
                             ],
                         }),
-                    }?;
+                    });
+                    let key = key.transpose()?;
                     let direction = match args.get(1) {
                         Some(&direction_raw) => match direction_raw {
                             "a" | "asc" | "ascending" => Ok(SortDirection::Ascending),
@@ -722,9 +1279,185 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                     }?;
                     Command::ShowRecommendations(target_mode)
                 }
+                "radio" => {
+                    let &target_mode_raw = args.first().ok_or(InsufficientArgs {
+                        cmd: command.into(),
+                        hint: Some("selected|current".into()),
+                    })?;
+                    let target_mode = match target_mode_raw {
+                        "selected" => Ok(TargetMode::Selected),
+                        "current" => Ok(TargetMode::Current),
+                        _ => Err(BadEnumArg {
+                            arg: target_mode_raw.into(),
+                            accept: vec!["selected".into(), "current".into()],
+                        }),
+                    }?;
+                    Command::Radio(target_mode)
+                }
+                "info" => {
+                    let &target_mode_raw = args.first().ok_or(InsufficientArgs {
+                        cmd: command.into(),
+                        hint: Some("selected|current".into()),
+                    })?;
+                    let target_mode = match target_mode_raw {
+                        "selected" => Ok(TargetMode::Selected),
+                        "current" => Ok(TargetMode::Current),
+                        _ => Err(BadEnumArg {
+                            arg: target_mode_raw.into(),
+                            accept: vec!["selected".into(), "current".into()],
+                        }),
+                    }?;
+                    Command::Info(target_mode)
+                }
                 "redraw" => Command::Redraw,
                 "exec" => Command::Execute(args.join(" ")),
                 "reconnect" => Command::Reconnect,
+                "mute" => Command::Mute,
+                "stopafter" => Command::StopAfterTrack,
+                "like" => Command::Like,
+                "abloop" => {
+                    let point = match args.first().cloned() {
+                        Some("a") => Ok(LoopPoint::Start),
+                        Some("b") => Ok(LoopPoint::End),
+                        Some("clear") => Ok(LoopPoint::Clear),
+                        Some(arg) => Err(BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["a".into(), "b".into(), "clear".into()],
+                        }),
+                        None => Err(InsufficientArgs {
+                            cmd: command.into(),
+                            hint: Some("a, b or clear".into()),
+                        }),
+                    }?;
+                    Command::AbLoop(point)
+                }
+                "nextalbum" => Command::NextAlbum,
+                "addalbum" => Command::AddAlbum,
+                "dedup" => Command::Dedup,
+                "undo" => Command::Undo,
+                "top" => Command::Top,
+                "followedonly" => Command::FollowedOnly,
+                "history" => Command::History,
+                "account" => match args.first() {
+                    Some(&"add") => Command::AddAccount,
+                    username => Command::Account(username.map(|username| username.to_string())),
+                },
+                "clearhistory" => {
+                    let kind = match args.first().cloned() {
+                        Some("search") => Ok(HistoryKind::Search),
+                        Some("played") => Ok(HistoryKind::Played),
+                        Some(arg) => Err(BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["search".into(), "played".into()],
+                        }),
+                        None => Err(InsufficientArgs {
+                            cmd: command.into(),
+                            hint: Some("search|played".into()),
+                        }),
+                    }?;
+                    Command::ClearHistory(kind)
+                }
+                "stats" => {
+                    let kind = match args.first().cloned() {
+                        Some("tracks") | None => Ok(StatsKind::Tracks),
+                        Some("artists") => Ok(StatsKind::Artists),
+                        Some(arg) => Err(BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["**omit**".into(), "tracks".into(), "artists".into()],
+                        }),
+                    }?;
+                    let window = match args.get(1) {
+                        Some(&window_raw) => {
+                            Some(parse_duration::parse(window_raw).map_err(|err| {
+                                ArgParseError {
+                                    arg: window_raw.into(),
+                                    err: err.to_string(),
+                                }
+                            })?)
+                        }
+                        None => None,
+                    };
+                    Command::Stats(kind, window)
+                }
+                "reorder" => {
+                    let positions = args
+                        .iter()
+                        .map(|&position_raw| {
+                            position_raw
+                                .parse::<usize>()
+                                .ok()
+                                .filter(|&position| position > 0)
+                                .ok_or(ArgParseError {
+                                    arg: position_raw.into(),
+                                    err: "expected a position starting at 1".into(),
+                                })
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    match positions[..] {
+                        [from, to] => Command::Reorder(from, to),
+                        _ => {
+                            return Err(InsufficientArgs {
+                                cmd: command.into(),
+                                hint: Some("the position to move from and to".into()),
+                            })
+                        }
+                    }
+                }
+                "exportqueue" | "importqueue" => {
+                    if args.is_empty() {
+                        return Err(InsufficientArgs {
+                            cmd: command.into(),
+                            hint: Some("a path".into()),
+                        });
+                    }
+                    let path = args.join(" ");
+                    if command == "exportqueue" {
+                        Command::ExportQueue(path)
+                    } else {
+                        Command::ImportQueue(path)
+                    }
+                }
+                "addfile" => {
+                    if args.is_empty() {
+                        return Err(InsufficientArgs {
+                            cmd: command.into(),
+                            hint: Some("a path".into()),
+                        });
+                    }
+                    Command::AddFile(args.join(" "))
+                }
+                "mark" => {
+                    let mode = match args.first().cloned() {
+                        None | Some("toggle") => Ok(MarkMode::Toggle),
+                        Some("visual") => Ok(MarkMode::Visual),
+                        Some("all") => Ok(MarkMode::All),
+                        Some("clear") => Ok(MarkMode::Clear),
+                        Some(arg) => Err(BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec![
+                                "toggle".into(),
+                                "visual".into(),
+                                "all".into(),
+                                "clear".into(),
+                            ],
+                        }),
+                    }?;
+                    Command::Mark(mode)
+                }
+                "addtoplaylist" => {
+                    let target_mode = match args.first().cloned() {
+                        None | Some("selected") => Ok(TargetMode::Selected),
+                        Some("current") => Ok(TargetMode::Current),
+                        Some(arg) => Err(BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["selected".into(), "current".into()],
+                        }),
+                    }?;
+                    Command::AddToPlaylist(target_mode)
+                }
+                "savecurrent" => {
+                    Command::SaveCurrent(Some(args.join(" ")).filter(|name| !name.is_empty()))
+                }
                 _ => {
                     return Err(NoSuchCommand {
                         cmd: command.into(),
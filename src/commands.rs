@@ -1,28 +1,46 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
+use crate::accounts;
 use crate::command::{
-    parse, Command, GotoMode, JumpMode, MoveAmount, MoveMode, SeekDirection, ShiftMode, TargetMode,
+    parse, ClearTarget, Command, GotoMode, HistoryKind, JumpMode, LoopPoint, MarkMode, MoveAmount,
+    MoveMode, NormalizationSetting, PlaylistAction, SeekDirection, ShiftMode, SleepMode,
+    SortDirection, StatsKind, TargetMode,
 };
-use crate::config::Config;
+use crate::config::{Config, ShuffleMode};
 use crate::events::EventManager;
 use crate::ext_traits::CursiveExt;
+use crate::history::History;
 use crate::library::Library;
+use crate::local_player::local_path;
+use crate::model::playable::Playable;
+use crate::model::show::Show;
+use crate::model::track::Track;
+use crate::play_counts::PlayCounts;
 use crate::queue::{Queue, RepeatSetting};
-use crate::spotify::{Spotify, VOLUME_PERCENT};
+use crate::radio;
+use crate::search_history::SearchHistory;
+use crate::spotify::{PlayerEvent, Spotify, VOLUME_PERCENT};
 use crate::traits::{IntoBoxedViewExt, ViewExt};
 use crate::ui::contextmenu::{
     AddToPlaylistMenu, ContextMenu, SelectArtistActionMenu, SelectArtistMenu,
 };
 use crate::ui::help::HelpView;
+use crate::ui::launcher::launcher_dialog;
 use crate::ui::layout::Layout;
+use crate::ui::listview::ListView;
 use crate::ui::modal::Modal;
 use crate::ui::search_results::SearchResultsView;
+use crate::ui::stats::StatsView;
+use crate::ui::top::TopView;
+use crate::ui::trackinfo::TrackInfo;
+use crate::utils::{format_duration, ms_to_hms};
 use crate::UserData;
 use cursive::event::{Event, Key};
 use cursive::traits::View;
-use cursive::views::Dialog;
+use cursive::views::{Dialog, SelectView};
 use cursive::Cursive;
 use log::{debug, error, info};
 use std::cell::RefCell;
@@ -42,15 +60,22 @@ pub struct CommandManager {
     library: Arc<Library>,
     config: Arc<Config>,
     events: EventManager,
+    play_counts: Arc<PlayCounts>,
+    history: Arc<History>,
+    search_history: Arc<SearchHistory>,
 }
 
 impl CommandManager {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         spotify: Spotify,
         queue: Arc<Queue>,
         library: Arc<Library>,
         config: Arc<Config>,
         events: EventManager,
+        play_counts: Arc<PlayCounts>,
+        history: Arc<History>,
+        search_history: Arc<SearchHistory>,
     ) -> CommandManager {
         let bindings = RefCell::new(Self::get_bindings(config.clone()));
         CommandManager {
@@ -61,6 +86,9 @@ impl CommandManager {
             library,
             config,
             events,
+            play_counts,
+            history,
+            search_history,
         }
     }
 
@@ -112,25 +140,7 @@ impl CommandManager {
         match cmd {
             Command::Noop => Ok(None),
             Command::Quit => {
-                let queue = self.queue.queue.read().expect("can't readlock queue");
-                self.config.with_state_mut(move |mut s| {
-                    debug!(
-                        "saving state, {} items, current track: {:?}",
-                        queue.len(),
-                        self.queue.get_current_index()
-                    );
-                    s.queuestate.queue = queue.clone();
-                    s.queuestate.random_order = self
-                        .queue
-                        .get_random_order()
-                        .read()
-                        .unwrap()
-                        .as_ref()
-                        .cloned();
-                    s.queuestate.current_track = self.queue.get_current_index();
-                    s.queuestate.track_progress = self.spotify.get_current_progress();
-                });
-                self.config.save_state();
+                self.queue.save_state_on_quit();
                 s.quit();
                 Ok(None)
             }
@@ -143,6 +153,143 @@ impl CommandManager {
                 self.queue.stop();
                 Ok(None)
             }
+            Command::Reorder(from, to) => {
+                let len = self.queue.len();
+                if *from > len || *to > len {
+                    return Err(format!("The queue only has {len} items"));
+                }
+                self.queue.shift(from - 1, to - 1);
+                Ok(None)
+            }
+            Command::ExportQueue(path) => {
+                self.queue.export(Path::new(path))?;
+                Ok(Some(format!("Exported the queue to {path}")))
+            }
+            Command::ImportQueue(path) => {
+                self.queue.import(Path::new(path))?;
+                Ok(Some(format!("Imported the queue from {path}")))
+            }
+            Command::AddFile(path) => {
+                let path = local_path(path).unwrap_or_else(|| PathBuf::from(path));
+                let track = Track::from_local_file(&path)?;
+                let message = format!("Added {track} to the queue");
+                self.queue.append(Playable::Track(track));
+                Ok(Some(message))
+            }
+            Command::Radio(TargetMode::Current) => {
+                let mut current = self
+                    .queue
+                    .get_current()
+                    .ok_or("Nothing is playing")?
+                    .as_listitem();
+                radio::start(current.as_mut(), self.queue.clone(), self.library.clone()).map(Some)
+            }
+            Command::Info(TargetMode::Current) => {
+                let track = match self.queue.get_current().ok_or("Nothing is playing")? {
+                    Playable::Track(track) => track,
+                    Playable::Episode(_) => return Err("Only tracks have details to show".into()),
+                };
+                s.add_layer(TrackInfo::new(
+                    track,
+                    self.queue.clone(),
+                    self.library.clone(),
+                ));
+                Ok(None)
+            }
+            Command::SaveCurrent(playlist) => {
+                let track = self.current_track()?;
+                let target = playlist
+                    .clone()
+                    .or_else(|| self.config.values().target_playlist.clone());
+                match target {
+                    Some(target) => self.save_to_playlist(track, &target).map(Some),
+                    None => {
+                        let dialog = ContextMenu::add_track_dialog(
+                            self.library.clone(),
+                            self.spotify.clone(),
+                            vec![track],
+                        );
+                        s.add_layer(dialog);
+                        Ok(None)
+                    }
+                }
+            }
+            Command::AddToPlaylist(TargetMode::Current) => {
+                let dialog = ContextMenu::add_track_dialog(
+                    self.library.clone(),
+                    self.spotify.clone(),
+                    vec![self.current_track()?],
+                );
+                s.add_layer(dialog);
+                Ok(None)
+            }
+            Command::StopAfterTrack => {
+                self.queue.toggle_stop_after_track();
+                Ok(None)
+            }
+            Command::Launcher => {
+                let dialog = launcher_dialog(s, self.queue.clone(), self.library.clone());
+                s.add_layer(dialog);
+                Ok(None)
+            }
+            Command::Like => {
+                let current = self.queue.get_current().ok_or("Nothing is playing")?;
+                let library = self.library.clone();
+                let sink = s.cb_sink().clone();
+                let report = move |result: Result<Option<String>, String>| {
+                    let sent = sink.send(Box::new(move |s: &mut Cursive| {
+                        s.on_layout(|_, mut layout| layout.set_result(result));
+                    }));
+                    if sent.is_err() {
+                        error!("could not report whether the current item was liked");
+                    }
+                };
+                match current {
+                    Playable::Track(track) => {
+                        if track.id.is_none() {
+                            return Err("Only Spotify tracks can be liked".into());
+                        }
+                        let liked = !library.is_saved_track(&Playable::Track(track.clone()));
+                        std::thread::spawn(move || {
+                            let result = match (library.set_liked(&track, liked), liked) {
+                                (true, true) => Ok(Some(format!("Liked \"{}\"", track.title))),
+                                (true, false) => Ok(Some(format!("Unliked \"{}\"", track.title))),
+                                (false, _) => Err(format!("Could not change \"{}\"", track.title)),
+                            };
+                            report(result);
+                        });
+                    }
+                    // Episodes can't be liked, but their shows can be saved
+                    Playable::Episode(episode) => {
+                        let spotify = self.spotify.clone();
+                        std::thread::spawn(move || {
+                            let show = spotify
+                                .api
+                                .episode(&episode.id)
+                                .map(|episode| Show::from(&episode.show));
+                            let result = match show {
+                                Some(show) if library.is_saved_show(&show) => {
+                                    if library.unsave_show(&show) {
+                                        Ok(Some(format!("Removed \"{}\"", show.name)))
+                                    } else {
+                                        Err(format!("Could not remove \"{}\"", show.name))
+                                    }
+                                }
+                                Some(show) => {
+                                    if library.save_show(&show) {
+                                        Ok(Some(format!("Saved \"{}\"", show.name)))
+                                    } else {
+                                        Err(format!("Could not save \"{}\"", show.name))
+                                    }
+                                }
+                                None => Err("Could not find the show of the episode".into()),
+                            };
+                            report(result);
+                        });
+                    }
+                }
+                Ok(None)
+            }
             Command::Previous => {
                 if self.spotify.get_current_progress() < Duration::from_secs(5) {
                     self.queue.previous();
@@ -155,7 +302,35 @@ impl CommandManager {
                 self.queue.next(true);
                 Ok(None)
             }
-            Command::Clear => {
+            Command::Restart => {
+                if self.queue.len() == 0 {
+                    return Err("The queue is empty".into());
+                }
+                let index = self.queue.get_current_index().ok_or("Nothing is playing")?;
+                match self.spotify.get_current_status() {
+                    // The track was unloaded once it ended
+                    PlayerEvent::Stopped | PlayerEvent::FinishedTrack => {
+                        self.queue.play(index, false, false)
+                    }
+                    _ => {
+                        self.spotify.seek(0);
+                        self.spotify.play();
+                    }
+                }
+                Ok(None)
+            }
+            Command::NextAlbum => {
+                self.queue.next_album();
+                Ok(None)
+            }
+            Command::Clear(Some(target)) => {
+                let removed = match target {
+                    ClearTarget::Upcoming => self.queue.clear_upcoming(),
+                    ClearTarget::Played => self.queue.clear_played(),
+                };
+                Ok(Some(format!("Removed {removed} {target} tracks")))
+            }
+            Command::Clear(None) => {
                 let queue = self.queue.clone();
                 let confirmation = Dialog::text("Clear queue?")
                     .button("Yes", move |s| {
@@ -166,6 +341,39 @@ impl CommandManager {
                 s.add_layer(Modal::new(confirmation));
                 Ok(None)
             }
+            Command::Account(Some(username)) => {
+                let credentials = accounts::load()
+                    .remove(username)
+                    .ok_or_else(|| format!("No saved account {username}"))?;
+                if credentials.username == self.spotify.username() {
+                    return Ok(Some(format!("Already using account {username}")));
+                }
+                self.queue.clear();
+                self.spotify.switch_account(credentials);
+                Ok(Some(format!("Switching to account {username}")))
+            }
+            Command::Account(None) => {
+                let current = self.spotify.username();
+                let mut select = SelectView::new().on_submit(|s, username: &String| {
+                    s.pop_layer();
+                    if let Some(data) = s.user_data::<UserData>().cloned() {
+                        data.cmd.handle(s, Command::Account(Some(username.clone())));
+                    }
+                });
+                for username in accounts::load().into_keys() {
+                    let label = if username == current {
+                        format!("{username} (current)")
+                    } else {
+                        username.clone()
+                    };
+                    select.add_item(label, username);
+                }
+                let dialog = Dialog::around(select)
+                    .title("Switch account")
+                    .dismiss_button("Cancel");
+                s.add_layer(Modal::new(dialog));
+                Ok(None)
+            }
             Command::UpdateLibrary => {
                 self.library.update_library();
                 Ok(None)
@@ -179,11 +387,28 @@ impl CommandManager {
                 self.queue.set_shuffle(mode);
                 Ok(None)
             }
+            Command::ShuffleMode(mode) => {
+                let mode = mode.unwrap_or_else(|| match self.queue.get_shuffle_mode() {
+                    ShuffleMode::Random => ShuffleMode::Spread,
+                    ShuffleMode::Spread => ShuffleMode::Albums,
+                    ShuffleMode::Albums => ShuffleMode::Fresh,
+                    ShuffleMode::Fresh => ShuffleMode::Random,
+                });
+                self.queue.set_shuffle_mode(mode);
+                Ok(Some(format!("Shuffle mode: {mode}")))
+            }
+            Command::Autoplay(on) => {
+                let on = on.unwrap_or_else(|| !self.queue.get_autoplay());
+                self.queue.set_autoplay(on);
+                Ok(Some(format!("Autoplay: {}", if on { "on" } else { "off" })))
+            }
             Command::Repeat(mode) => {
                 let mode = mode.unwrap_or_else(|| match self.queue.get_repeat() {
                     RepeatSetting::None => RepeatSetting::RepeatPlaylist,
                     RepeatSetting::RepeatPlaylist => RepeatSetting::RepeatTrack,
-                    RepeatSetting::RepeatTrack => RepeatSetting::None,
+                    RepeatSetting::RepeatTrack | RepeatSetting::RepeatCount(_) => {
+                        RepeatSetting::None
+                    }
                 });
 
                 self.queue.set_repeat(mode);
@@ -193,6 +418,16 @@ impl CommandManager {
                 match *direction {
                     SeekDirection::Relative(rel) => self.spotify.seek_relative(rel),
                     SeekDirection::Absolute(abs) => self.spotify.seek(abs),
+                    SeekDirection::Percentage(percent) => {
+                        let playing = !matches!(
+                            self.spotify.get_current_status(),
+                            PlayerEvent::Stopped | PlayerEvent::FinishedTrack
+                        );
+                        if let Some(playable) = self.queue.get_current().filter(|_| playing) {
+                            let position = playable.duration() as u64 * percent as u64 / 100;
+                            self.spotify.seek(position as u32);
+                        }
+                    }
                 }
                 Ok(None)
             }
@@ -213,14 +448,62 @@ impl CommandManager {
                 self.spotify.set_volume(volume);
                 Ok(None)
             }
+            Command::Mute => {
+                self.spotify.toggle_mute();
+                Ok(None)
+            }
             Command::Help => {
                 let view = Box::new(HelpView::new(self.bindings.borrow().clone()));
                 s.call_on_name("main", move |v: &mut Layout| v.push_view(view));
                 Ok(None)
             }
+            Command::Stats(kind, window) => {
+                let since = window
+                    .and_then(|window| chrono::Duration::from_std(window).ok())
+                    .map(|window| chrono::Utc::now() - window);
+                let top = match kind {
+                    StatsKind::Tracks => self.play_counts.top_tracks(since),
+                    StatsKind::Artists => self.play_counts.top_artists(since),
+                };
+                let window = match window {
+                    Some(window) => format!("in the last {}", format_duration(window)),
+                    None => "of all time".to_string(),
+                };
+                let view = Box::new(StatsView::new(kind, &window, top));
+                s.call_on_name("main", move |v: &mut Layout| v.push_view(view));
+                Ok(None)
+            }
+            Command::History => {
+                let view = ListView::new(
+                    self.history.items.clone(),
+                    self.queue.clone(),
+                    self.library.clone(),
+                )
+                .with_title("History");
+                s.call_on_name("main", move |v: &mut Layout| v.push_view(Box::new(view)));
+                Ok(None)
+            }
+            Command::ClearHistory(kind) => {
+                match kind {
+                    HistoryKind::Search => self.search_history.clear(),
+                    HistoryKind::Played => self.history.clear(),
+                }
+                Ok(Some(format!("Cleared the {kind} history")))
+            }
+            Command::Top => {
+                let view = TopView::new(self.queue.clone(), self.library.clone());
+                s.call_on_name("main", move |v: &mut Layout| v.push_view(Box::new(view)));
+                Ok(None)
+            }
             Command::ReloadConfig => {
+                let device = Spotify::output_device(&self.config);
                 self.config.reload();
 
+                // Play on a newly configured output device, at its own volume
+                if Spotify::output_device(&self.config) != device {
+                    self.spotify.reconnect();
+                }
+
                 // update theme
                 let theme = self.config.build_theme();
                 s.set_theme(theme);
@@ -233,16 +516,34 @@ impl CommandManager {
                 Ok(None)
             }
             Command::NewPlaylist(name) => {
-                match self.spotify.api.create_playlist(name, None, None) {
-                    Some(_) => self.library.update_library(),
-                    None => error!("could not create playlist {}", name),
+                if !self.library.save_playlist(name, &[], None) {
+                    error!("could not create playlist {}", name);
                 }
                 Ok(None)
             }
-            Command::Search(term) => {
-                let view = if !term.is_empty() {
+            Command::Playlist(PlaylistAction::New(name, from_queue)) => {
+                // Local files can't be added to playlists
+                let tracks: Vec<Playable> = if *from_queue {
+                    let queue = self.queue.queue.read().unwrap();
+                    queue
+                        .iter()
+                        .filter(|playable| local_path(&playable.uri()).is_none())
+                        .cloned()
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                if self.library.save_playlist(name, &tracks, None) {
+                    Ok(Some(format!("Created playlist \"{name}\"")))
+                } else {
+                    Err(format!("Could not create playlist \"{name}\""))
+                }
+            }
+            Command::Search(query) => {
+                self.search_history.add(&query.to_string());
+                let view = if !query.is_empty() {
                     Some(SearchResultsView::new(
-                        term.clone(),
+                        query.clone(),
                         self.events.clone(),
                         self.queue.clone(),
                         self.library.clone(),
@@ -260,6 +561,7 @@ impl CommandManager {
             }
             Command::Logout => {
                 self.spotify.shutdown();
+                accounts::remove(&self.spotify.username());
 
                 let mut credentials_path = crate::config::cache_path("librespot");
                 credentials_path.push("credentials.json");
@@ -268,6 +570,20 @@ impl CommandManager {
                 s.quit();
                 Ok(None)
             }
+            Command::AddAccount => {
+                // Only forget which account to start with, the others that
+                // were logged in with are kept to switch to.
+                self.spotify.shutdown();
+
+                let mut credentials_path = crate::config::cache_path("librespot");
+                credentials_path.push("credentials.json");
+                if let Err(e) = std::fs::remove_file(credentials_path) {
+                    error!("could not remove the credentials: {}", e);
+                }
+
+                s.quit();
+                Ok(None)
+            }
             Command::Execute(cmd) => {
                 log::info!("Executing command: {}", cmd);
                 let cmd = std::ffi::CString::new(cmd.clone()).unwrap();
@@ -276,7 +592,57 @@ impl CommandManager {
                 Ok(None)
             }
             Command::Reconnect => {
-                self.spotify.shutdown();
+                self.spotify.reconnect();
+                Ok(None)
+            }
+            Command::Normalization(setting) => {
+                match setting {
+                    NormalizationSetting::Mode(mode) => self.spotify.set_normalization(*mode),
+                    NormalizationSetting::Pregain(pregain) => {
+                        self.spotify.set_normalization_pregain(*pregain)
+                    }
+                }
+                Ok(None)
+            }
+            Command::Bitrate(bitrate) => {
+                self.spotify.set_bitrate(*bitrate);
+                Ok(None)
+            }
+            Command::PlaybackSpeed(speed) => match self.queue.get_current() {
+                Some(Playable::Episode(_)) => {
+                    self.spotify.set_playback_speed(*speed);
+                    Ok(None)
+                }
+                _ => Err("Playback speed can only be changed for podcast episodes".into()),
+            },
+            Command::AbLoop(point) => {
+                if self.queue.get_current().is_none() {
+                    return Err("Nothing is playing".into());
+                }
+                let position = self.spotify.get_current_progress().as_millis() as u32;
+                match point {
+                    LoopPoint::Start => {
+                        self.spotify.set_loop_start(position);
+                        Ok(Some(format!("A-B loop starts at {}", ms_to_hms(position))))
+                    }
+                    LoopPoint::End => {
+                        self.spotify.set_loop_end(position);
+                        Ok(Some(format!("A-B loop ends at {}", ms_to_hms(position))))
+                    }
+                    LoopPoint::Clear => {
+                        self.spotify.clear_loop();
+                        Ok(Some("A-B loop cleared".into()))
+                    }
+                }
+            }
+            Command::Sleep(mode) => {
+                match *mode {
+                    SleepMode::After(duration, finish_track) => {
+                        self.spotify.sleep_after(Some(duration), finish_track)
+                    }
+                    SleepMode::Track => self.spotify.sleep_after(None, false),
+                    SleepMode::Off => self.spotify.cancel_sleep(),
+                }
                 Ok(None)
             }
 
@@ -292,9 +658,20 @@ impl CommandManager {
             | Command::Goto(_)
             | Command::Move(_, _)
             | Command::Shift(_, _)
+            | Command::AddAlbum
+            | Command::Dedup
+            | Command::Undo
+            | Command::FollowedOnly
             | Command::Jump(_)
+            | Command::Filter(_)
             | Command::Insert(_)
             | Command::ShowRecommendations(_)
+            | Command::Radio(TargetMode::Selected)
+            | Command::Info(TargetMode::Selected)
+            | Command::Playlist(_)
+            | Command::Mark(_)
+            | Command::SearchFilter(_)
+            | Command::AddToPlaylist(TargetMode::Selected)
             | Command::Sort(_, _) => Err(format!(
                 "The command \"{}\" is unsupported in this view",
                 cmd.basename()
@@ -307,11 +684,44 @@ impl CommandManager {
         }
     }
 
+    /// The current track, if it can be added to a playlist.
+    fn current_track(&self) -> Result<Track, String> {
+        match self.queue.get_current() {
+            Some(Playable::Track(track)) if track.id.is_some() => Ok(track),
+            Some(_) => Err("Only Spotify tracks can be added to a playlist".into()),
+            None => Err("Nothing is playing".into()),
+        }
+    }
+
+    /// Add `track` to the playlist with the ID or name `target`, unless it's
+    /// already in it.
+    fn save_to_playlist(&self, track: Track, target: &str) -> Result<String, String> {
+        let mut playlist = self
+            .library
+            .playlists()
+            .iter()
+            .find(|playlist| playlist.id == target || playlist.name == target)
+            .cloned()
+            .ok_or_else(|| format!("There is no playlist \"{target}\""))?;
+        playlist.load_tracks(self.spotify.clone());
+        if playlist.has_track(track.id.as_deref().unwrap_or_default()) {
+            return Ok(format!("{track} is already in {}", playlist.name));
+        }
+        let playable = Playable::Track(track.clone());
+        if playlist.append_tracks(&[playable], self.spotify.clone(), self.library.clone()) {
+            Ok(format!("Added {track} to {}", playlist.name))
+        } else {
+            Err(format!("Could not add {track} to {}", playlist.name))
+        }
+    }
+
     fn handle_callbacks(&self, s: &mut Cursive, cmd: &Command) -> Result<Option<String>, String> {
         let local = if let Some(mut contextmenu) = s.find_name::<ContextMenu>("contextmenu") {
             contextmenu.on_command(s, cmd)?
         } else if let Some(mut add_track_menu) = s.find_name::<AddToPlaylistMenu>("addtrackmenu") {
             add_track_menu.on_command(s, cmd)?
+        } else if let Some(mut track_info) = s.find_name::<TrackInfo>("trackinfo") {
+            track_info.on_command(s, cmd)?
         } else if let Some(mut select_artist) = s.find_name::<SelectArtistMenu>("selectartist") {
             select_artist.on_command(s, cmd)?
         } else if let Some(mut select_artist_action) =
@@ -393,9 +803,13 @@ impl CommandManager {
         kb.insert("Shift+p".into(), vec![Command::TogglePlay]);
         kb.insert("Shift+u".into(), vec![Command::UpdateLibrary]);
         kb.insert("Shift+s".into(), vec![Command::Stop]);
+        kb.insert("Alt+s".into(), vec![Command::StopAfterTrack]);
+        kb.insert("Alt+l".into(), vec![Command::Like]);
+        kb.insert("Ctrl+g".into(), vec![Command::Launcher]);
         kb.insert("<".into(), vec![Command::Previous]);
         kb.insert(">".into(), vec![Command::Next]);
-        kb.insert("c".into(), vec![Command::Clear]);
+        kb.insert("Alt+>".into(), vec![Command::NextAlbum]);
+        kb.insert("c".into(), vec![Command::Clear(None)]);
         kb.insert(
             "Space".into(),
             vec![
@@ -436,6 +850,7 @@ impl CommandManager {
         kb.insert("]".into(), vec![Command::VolumeUp(5)]);
         kb.insert("-".into(), vec![Command::VolumeDown(1)]);
         kb.insert("[".into(), vec![Command::VolumeDown(5)]);
+        kb.insert("m".into(), vec![Command::Mute]);
 
         kb.insert("r".into(), vec![Command::Repeat(None)]);
         kb.insert("z".into(), vec![Command::Shuffle(None)]);
@@ -449,6 +864,7 @@ impl CommandManager {
         kb.insert("F1".into(), vec![Command::Focus("queue".into())]);
         kb.insert("F2".into(), vec![Command::Focus("search".into())]);
         kb.insert("F3".into(), vec![Command::Focus("library".into())]);
+        kb.insert("F7".into(), vec![Command::Focus("lyrics".into())]);
         #[cfg(feature = "cover")]
         kb.insert("F8".into(), vec![Command::Focus("cover".into())]);
         kb.insert("?".into(), vec![Command::Help]);
@@ -460,13 +876,31 @@ impl CommandManager {
         kb.insert("Shift+a".into(), vec![Command::Goto(GotoMode::Artist)]);
 
         kb.insert(
-            "m".into(),
+            "Alt+m".into(),
             vec![Command::ShowRecommendations(TargetMode::Selected)],
         );
         kb.insert(
             "Shift+m".into(),
             vec![Command::ShowRecommendations(TargetMode::Current)],
         );
+        kb.insert("Shift+r".into(), vec![Command::Radio(TargetMode::Current)]);
+        kb.insert("i".into(), vec![Command::Info(TargetMode::Selected)]);
+        kb.insert("Shift+i".into(), vec![Command::Info(TargetMode::Current)]);
+        kb.insert("v".into(), vec![Command::Mark(MarkMode::Visual)]);
+        kb.insert("Shift+v".into(), vec![Command::Mark(MarkMode::Toggle)]);
+        kb.insert("Alt+a".into(), vec![Command::Mark(MarkMode::All)]);
+        kb.insert(
+            "Alt+t".into(),
+            vec![Command::AddToPlaylist(TargetMode::Selected)],
+        );
+        kb.insert(
+            "Shift+t".into(),
+            vec![Command::AddToPlaylist(TargetMode::Current)],
+        );
+        kb.insert(
+            "Ctrl+o".into(),
+            vec![Command::Sort(None, SortDirection::Ascending)],
+        );
 
         kb.insert(
             "Up".into(),
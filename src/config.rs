@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
-use std::{fs, process};
+use std::{fmt, fs, process};
 
 use cursive::theme::Theme;
 use log::{debug, error};
@@ -13,7 +13,45 @@ use crate::queue;
 use crate::serialization::{Serializer, CBOR, TOML};
 
 pub const CLIENT_ID: &str = "d420a117a32841c2b3474932e49fb54b";
-pub const CACHE_VERSION: u16 = 1;
+pub const CACHE_VERSION: u16 = 3;
+pub const DEFAULT_RECONNECT_ATTEMPTS: u32 = 10;
+/// Seconds before expiry at which the Web API token is refreshed.
+pub const DEFAULT_TOKEN_REFRESH_MARGIN: u32 = 60;
+/// Items at least this long (in ms) resume where they were left at startup.
+pub const DEFAULT_RESUME_MIN_DURATION_MS: u32 = 10 * 60 * 1000;
+/// How often a track that failed to load is loaded again before skipping it.
+pub const DEFAULT_LOAD_RETRIES: u32 = 2;
+/// The delay (in ms) before the first retry of a failed load, which doubles
+/// with every further retry.
+pub const DEFAULT_LOAD_RETRY_DELAY_MS: u32 = 1000;
+/// The number of played items that are kept in the history.
+pub const DEFAULT_HISTORY_SIZE: usize = 200;
+/// The number of last played items that the `fresh` shuffle mode plays last.
+pub const DEFAULT_SHUFFLE_FRESH_WINDOW: usize = 50;
+/// The number of queries that are kept in the search history.
+pub const DEFAULT_SEARCH_HISTORY_SIZE: usize = 100;
+/// The number of upcoming items that are loaded ahead of playing them.
+pub const DEFAULT_PRELOAD_COUNT: usize = 1;
+/// At most this many upcoming items are loaded ahead at once.
+pub const MAX_PRELOAD_COUNT: usize = 5;
+/// Audio quieter than this (in dBFS) counts as silence for `skip_silence`.
+pub const DEFAULT_SKIP_SILENCE_THRESHOLD_DB: f64 = -60.0;
+/// How long (in ms) silence has to last before `skip_silence` skips it.
+pub const DEFAULT_SKIP_SILENCE_MIN_MS: u32 = 2000;
+pub const DEFAULT_SCOPES: &[&str] = &[
+    "user-read-private",
+    "playlist-read-private",
+    "playlist-read-collaborative",
+    "playlist-modify-public",
+    "playlist-modify-private",
+    "user-follow-modify",
+    "user-follow-read",
+    "user-library-read",
+    "user-library-modify",
+    "user-top-read",
+    "user-read-recently-played",
+    "user-read-playback-position",
+];
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum PlaybackState {
@@ -23,15 +61,117 @@ pub enum PlaybackState {
     Default,
 }
 
+/// What happens to playback when the sleep timer expires.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SleepTimerAction {
+    Pause,
+    Stop,
+}
+
+/// How the volume of tracks is normalised.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NormalizationMode {
+    Off,
+    /// Let librespot choose between album and track gain.
+    Auto,
+    Album,
+    Track,
+}
+
+impl fmt::Display for NormalizationMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = match self {
+            NormalizationMode::Off => "off",
+            NormalizationMode::Auto => "on",
+            NormalizationMode::Album => "album",
+            NormalizationMode::Track => "track",
+        };
+        write!(f, "{repr}")
+    }
+}
+
+/// What the search bar that is opened with `/` does in lists.
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchBarMode {
+    /// Only show the items that match while typing.
+    #[default]
+    Filter,
+    /// Jump to the items that match, and between them with `n` and `N`.
+    Jump,
+}
+
+/// How the cover of the current track is drawn.
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverProtocol {
+    /// Detect what the terminal supports.
+    #[default]
+    Auto,
+    Kitty,
+    Sixel,
+    /// The inline images of iTerm2, which WezTerm supports as well.
+    Iterm,
+    Ueberzug,
+    /// Unicode half blocks in true color, which work in any terminal.
+    Blocks,
+    None,
+}
+
+/// Where lyrics are fetched from.
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LyricsProvider {
+    /// Spotify if the token allows it, or else lrclib.net.
+    #[default]
+    Auto,
+    Spotify,
+    Lrclib,
+}
+
+/// How the queue is ordered while shuffling.
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ShuffleMode {
+    #[default]
+    Random,
+    /// Spread the tracks of each artist, and within those each album, as
+    /// evenly as possible.
+    Spread,
+    /// Shuffle the order of the albums, keeping the order of the tracks
+    /// within each album.
+    Albums,
+    /// Play the items that weren't played recently first.
+    Fresh,
+}
+
+impl fmt::Display for ShuffleMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = match self {
+            ShuffleMode::Random => "random",
+            ShuffleMode::Spread => "spread",
+            ShuffleMode::Albums => "albums",
+            ShuffleMode::Fresh => "fresh",
+        };
+        write!(f, "{repr}")
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, Hash, strum::EnumIter)]
 #[serde(rename_all = "lowercase")]
 pub enum LibraryTab {
     Tracks,
     Albums,
     Artists,
+    Followed,
     Playlists,
     Podcasts,
     Browse,
+    NewReleases,
+    History,
+    RecentlyPlayed,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
@@ -51,6 +191,106 @@ impl TrackFormat {
     }
 }
 
+/// What a column of track lists shows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnKind {
+    /// The position of the track in the list.
+    Number,
+    Title,
+    Artist,
+    Album,
+    AddedAt,
+    Duration,
+    Popularity,
+    Saved,
+}
+
+/// How wide a column of track lists is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnWidth {
+    /// A share of what the columns of fixed width leave.
+    Auto,
+    Cells(usize),
+    Percent(usize),
+}
+
+/// A column of track lists, written as its kind and optionally its width,
+/// like `"title"`, `"album:30%"` or `"duration:8"`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct TrackColumn {
+    pub kind: ColumnKind,
+    /// The width of the column, which depends on its kind if not given.
+    pub width: Option<ColumnWidth>,
+}
+
+impl TryFrom<String> for TrackColumn {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let (kind, width) = match value.split_once(':') {
+            Some((kind, width)) => (kind, Some(width)),
+            None => (value.as_str(), None),
+        };
+        let kind = match kind {
+            "number" => ColumnKind::Number,
+            "title" => ColumnKind::Title,
+            "artist" => ColumnKind::Artist,
+            "album" => ColumnKind::Album,
+            "added_at" => ColumnKind::AddedAt,
+            "duration" => ColumnKind::Duration,
+            "popularity" => ColumnKind::Popularity,
+            "saved" => ColumnKind::Saved,
+            _ => return Err(format!("unknown column \"{kind}\"")),
+        };
+        let width = match width {
+            Some(width) => Some(
+                ColumnWidth::parse(width)
+                    .ok_or_else(|| format!("invalid width of column \"{value}\""))?,
+            ),
+            None => None,
+        };
+        Ok(TrackColumn { kind, width })
+    }
+}
+
+impl ColumnWidth {
+    fn parse(width: &str) -> Option<Self> {
+        if width == "auto" {
+            return Some(ColumnWidth::Auto);
+        }
+        match width.strip_suffix('%') {
+            Some(percent) => percent
+                .parse()
+                .ok()
+                .filter(|&percent| percent <= 100)
+                .map(ColumnWidth::Percent),
+            None => width.parse().ok().map(ColumnWidth::Cells),
+        }
+    }
+}
+
+impl From<TrackColumn> for String {
+    fn from(column: TrackColumn) -> Self {
+        let kind = match column.kind {
+            ColumnKind::Number => "number",
+            ColumnKind::Title => "title",
+            ColumnKind::Artist => "artist",
+            ColumnKind::Album => "album",
+            ColumnKind::AddedAt => "added_at",
+            ColumnKind::Duration => "duration",
+            ColumnKind::Popularity => "popularity",
+            ColumnKind::Saved => "saved",
+        };
+        match column.width {
+            None => kind.to_string(),
+            Some(ColumnWidth::Auto) => format!("{kind}:auto"),
+            Some(ColumnWidth::Cells(cells)) => format!("{kind}:{cells}"),
+            Some(ColumnWidth::Percent(percent)) => format!("{kind}:{percent}%"),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct NotificationFormat {
     pub title: Option<String>,
@@ -74,7 +314,9 @@ pub struct ConfigValues {
     pub keybindings: Option<HashMap<String, String>>,
     pub theme: Option<ConfigTheme>,
     pub use_nerdfont: Option<bool>,
+    pub saved_indicator: Option<String>,
     pub flip_status_indicators: Option<bool>,
+    pub show_bitrate: Option<bool>,
     pub audio_cache: Option<bool>,
     pub audio_cache_size: Option<u32>,
     pub backend: Option<String>,
@@ -85,15 +327,53 @@ pub struct ConfigValues {
     pub bitrate: Option<u32>,
     pub gapless: Option<bool>,
     pub shuffle: Option<bool>,
+    pub shuffle_mode: Option<ShuffleMode>,
+    pub shuffle_fresh_window: Option<usize>,
     pub repeat: Option<queue::RepeatSetting>,
+    pub autoplay: Option<bool>,
     pub cover_max_scale: Option<f32>,
+    pub cover_protocol: Option<CoverProtocol>,
+    pub cover_blocks: Option<bool>,
     pub playback_state: Option<PlaybackState>,
     pub track_format: Option<TrackFormat>,
+    pub track_columns: Option<Vec<TrackColumn>>,
+    pub track_columns_by_view: Option<HashMap<String, Vec<TrackColumn>>>,
     pub notification_format: Option<NotificationFormat>,
     pub statusbar_format: Option<String>,
     pub library_tabs: Option<Vec<LibraryTab>>,
     pub hide_display_names: Option<bool>,
     pub credentials: Option<Credentials>,
+    pub oauth_scopes: Option<Vec<String>>,
+    pub fade_duration_ms: Option<u32>,
+    pub crossfade_ms: Option<u32>,
+    pub unavailable_skip_limit: Option<usize>,
+    pub sleep_timer_action: Option<SleepTimerAction>,
+    pub inactivity_pause_minutes: Option<u32>,
+    pub reconnect_attempts: Option<u32>,
+    pub load_retries: Option<u32>,
+    pub load_retry_delay_ms: Option<u32>,
+    pub token_refresh_margin: Option<u32>,
+    pub resume_min_duration_ms: Option<u32>,
+    pub remember_queue: Option<bool>,
+    pub resume_playback: Option<bool>,
+    pub history_size: Option<usize>,
+    pub search_bar_mode: Option<SearchBarMode>,
+    pub play_filtered_only: Option<bool>,
+    pub search_history_size: Option<usize>,
+    pub search_history_persist: Option<bool>,
+    pub preload_count: Option<usize>,
+    pub target_playlist: Option<String>,
+    pub radio_replace_queue: Option<bool>,
+    pub radio_exclude_saved: Option<bool>,
+    pub skip_silence: Option<bool>,
+    pub skip_silence_threshold_db: Option<f64>,
+    pub skip_silence_min_ms: Option<u32>,
+    pub progress_events: Option<bool>,
+    pub album_popularity: Option<bool>,
+    pub lyrics_provider: Option<LyricsProvider>,
+    pub lastfm: Option<LastfmConfig>,
+    pub hooks: Option<HooksConfig>,
+    pub discord_client_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
@@ -102,6 +382,24 @@ pub struct Credentials {
     pub password_cmd: Option<String>,
 }
 
+/// The account to scrobble to, and the API account to do so with.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct LastfmConfig {
+    pub api_key: String,
+    pub api_secret: String,
+    pub username: String,
+    pub password_cmd: String,
+}
+
+/// Shell commands that are run as playback changes.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct HooksConfig {
+    pub track_change: Option<String>,
+    pub play: Option<String>,
+    pub pause: Option<String>,
+    pub stop: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct ConfigTheme {
     pub background: Option<String>,
@@ -123,6 +421,7 @@ pub struct ConfigTheme {
     pub cmdline: Option<String>,
     pub cmdline_bg: Option<String>,
     pub search_match: Option<String>,
+    pub marked_bg: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -136,6 +435,10 @@ pub struct QueueState {
     pub current_track: Option<usize>,
     pub random_order: Option<Vec<usize>>,
     pub track_progress: std::time::Duration,
+    /// How many more times the current track is played, while repeating
+    /// tracks a number of times.
+    #[serde(default)]
+    pub repeat_remaining: u8,
     pub queue: Vec<Playable>,
 }
 
@@ -143,11 +446,23 @@ pub struct QueueState {
 pub struct UserState {
     pub volume: u16,
     pub shuffle: bool,
+    #[serde(default)]
+    pub shuffle_mode: ShuffleMode,
     pub repeat: queue::RepeatSetting,
+    #[serde(default)]
+    pub autoplay: bool,
     pub queuestate: QueueState,
     pub playlist_orders: HashMap<String, SortingOrder>,
     pub cache_version: u16,
     pub playback_state: PlaybackState,
+    #[serde(default)]
+    pub bitrate: Option<u32>,
+    #[serde(default)]
+    pub normalization: Option<NormalizationMode>,
+    /// The volume last set on each audio output device, by the name it is
+    /// configured with.
+    #[serde(default)]
+    pub device_volumes: HashMap<String, u16>,
 }
 
 impl Default for UserState {
@@ -155,11 +470,16 @@ impl Default for UserState {
         UserState {
             volume: u16::MAX,
             shuffle: false,
+            shuffle_mode: ShuffleMode::default(),
             repeat: queue::RepeatSetting::None,
+            autoplay: false,
             queuestate: QueueState::default(),
             playlist_orders: HashMap::new(),
             cache_version: 0,
             playback_state: PlaybackState::Default,
+            bitrate: None,
+            normalization: None,
+            device_volumes: HashMap::new(),
         }
     }
 }
@@ -191,14 +511,34 @@ impl Config {
             userstate.shuffle = shuffle;
         }
 
+        if let Some(shuffle_mode) = values.shuffle_mode {
+            userstate.shuffle_mode = shuffle_mode;
+        }
+
         if let Some(repeat) = values.repeat {
             userstate.repeat = repeat;
         }
 
+        if let Some(autoplay) = values.autoplay {
+            userstate.autoplay = autoplay;
+        }
+
         if let Some(playback_state) = values.playback_state.clone() {
             userstate.playback_state = playback_state;
         }
 
+        if let Some(bitrate) = values.bitrate {
+            userstate.bitrate = Some(bitrate);
+        }
+
+        if let Some(volnorm) = values.volnorm {
+            userstate.normalization = Some(if volnorm {
+                NormalizationMode::Auto
+            } else {
+                NormalizationMode::Off
+            });
+        }
+
         Self {
             filename: filename.to_string(),
             values: RwLock::new(values),
@@ -206,6 +546,16 @@ impl Config {
         }
     }
 
+    /// The default configuration and user state, without any files.
+    #[cfg(test)]
+    pub fn default_values() -> Self {
+        Self {
+            filename: String::new(),
+            values: RwLock::new(ConfigValues::default()),
+            state: RwLock::new(UserState::default()),
+        }
+    }
+
     pub fn values(&self) -> RwLockReadGuard<ConfigValues> {
         self.values.read().expect("can't readlock config values")
     }
@@ -0,0 +1,182 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::{debug, error};
+
+use crate::library::Library;
+use crate::utils::{cache_path_for_url, download};
+
+/// What a cover is converted to for the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Format {
+    Png,
+    Sixel,
+    /// Raw 8-bit RGB pixels, exactly as large as asked for.
+    Rgb,
+}
+
+impl Format {
+    fn magick_format(&self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Sixel => "sixel",
+            Format::Rgb => "rgb",
+        }
+    }
+}
+
+/// How long a failed download or conversion isn't tried again.
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+type Key = (String, Format, usize, usize);
+
+#[derive(Default)]
+struct State {
+    /// The URLs of the covers that are being downloaded.
+    downloading: HashSet<String>,
+    /// The conversions that are running.
+    converting: HashSet<Key>,
+    converted: HashMap<Key, Arc<Vec<u8>>>,
+    /// When the downloads and conversions that failed did so.
+    failed_downloads: HashMap<String, Instant>,
+    failed_conversions: HashMap<Key, Instant>,
+}
+
+/// Whether `key` failed less than [RETRY_DELAY] ago.
+fn failed_recently<K: std::hash::Hash + Eq>(failed: &HashMap<K, Instant>, key: &K) -> bool {
+    failed
+        .get(key)
+        .map_or(false, |failed| failed.elapsed() < RETRY_DELAY)
+}
+
+/// Downloads covers to the cache, which keeps them by the ID of the image,
+/// and converts them with ImageMagick. Both happen in the background, so
+/// drawing never waits for them: until a cover is ready, `None` is returned
+/// and the screen is redrawn once it is.
+#[derive(Clone)]
+pub struct CoverCache {
+    state: Arc<Mutex<State>>,
+    library: Arc<Library>,
+}
+
+impl CoverCache {
+    pub fn new(library: Arc<Library>) -> CoverCache {
+        CoverCache {
+            state: Arc::default(),
+            library,
+        }
+    }
+
+    /// The path of the downloaded cover at `url`.
+    pub fn path(&self, url: &str) -> Option<PathBuf> {
+        let url = url.to_string();
+        let mut state = self.state.lock().unwrap();
+        if state.downloading.contains(&url) {
+            return None;
+        }
+        let path = cache_path_for_url(url.clone());
+        if path.exists() {
+            return Some(path);
+        }
+        if failed_recently(&state.failed_downloads, &url) {
+            return None;
+        }
+
+        state.downloading.insert(url.clone());
+        let cache = self.clone();
+        std::thread::spawn(move || {
+            let result = download(url.clone(), path);
+            let mut state = cache.state.lock().unwrap();
+            state.downloading.remove(&url);
+            match result {
+                Ok(()) => {
+                    state.failed_downloads.remove(&url);
+                    drop(state);
+                    cache.library.trigger_redraw();
+                }
+                Err(e) => {
+                    error!("Failed to download cover: {}", e);
+                    state.failed_downloads.insert(url, Instant::now());
+                }
+            }
+        });
+        None
+    }
+
+    /// The cover at `url` in `format`, resized to fit `width` by `height`
+    /// pixels.
+    pub fn converted(
+        &self,
+        url: &str,
+        format: Format,
+        width: usize,
+        height: usize,
+    ) -> Option<Arc<Vec<u8>>> {
+        let path = self.path(url)?;
+        let key = (url.to_string(), format, width, height);
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(converted) = state.converted.get(&key) {
+            return Some(converted.clone());
+        }
+        if failed_recently(&state.failed_conversions, &key) {
+            return None;
+        }
+        if state.converting.insert(key.clone()) {
+            let cache = self.clone();
+            std::thread::spawn(move || {
+                let result = convert(&path, format, width, height);
+                let mut state = cache.state.lock().unwrap();
+                state.converting.remove(&key);
+                match result {
+                    Ok(converted) => {
+                        // Only the conversions of the latest cover are kept.
+                        state.converted.retain(|(url, ..), _| *url == key.0);
+                        state.failed_conversions.remove(&key);
+                        state.converted.insert(key, Arc::new(converted));
+                        drop(state);
+                        cache.library.trigger_redraw();
+                    }
+                    Err(e) => {
+                        error!("Failed to convert cover: {}", e);
+                        state.failed_conversions.insert(key, Instant::now());
+                    }
+                }
+            });
+        }
+        None
+    }
+}
+
+fn convert(path: &Path, format: Format, width: usize, height: usize) -> io::Result<Vec<u8>> {
+    // Raw pixels have to be exactly as large as the caller expects.
+    let exact = if format == Format::Rgb { "!" } else { "" };
+    let args = [
+        path.to_string_lossy().to_string(),
+        "-resize".to_string(),
+        format!("{width}x{height}{exact}"),
+        "-depth".to_string(),
+        "8".to_string(),
+        format!("{}:-", format.magick_format()),
+    ];
+    debug!("converting cover: {:?}", args);
+
+    // ImageMagick 7 is run as `magick`, older versions as `convert`.
+    let output = match Command::new("magick").args(&args).output() {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            Command::new("convert").args(&args).output()?
+        }
+        output => output?,
+    };
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(output.stdout)
+}
@@ -0,0 +1,214 @@
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, UNIX_EPOCH};
+
+use log::{debug, info, warn};
+use serde_json::{json, Value};
+
+use crate::model::playable::Playable;
+use crate::spotify::PlayerEvent;
+
+/// How long to wait before connecting to Discord again, while an update
+/// couldn't be sent.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(30);
+/// How long to wait for Discord to answer a command.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// The longest text Discord accepts in a field of an activity.
+const MAX_TEXT_LENGTH: usize = 128;
+
+const OP_HANDSHAKE: u32 = 0;
+const OP_FRAME: u32 = 1;
+
+/// Shows the currently playing item as Discord Rich Presence. Discord is
+/// talked to on a separate thread, which keeps trying to connect while
+/// Discord isn't running.
+pub struct DiscordPresence {
+    tx: mpsc::Sender<Option<Value>>,
+}
+
+impl DiscordPresence {
+    pub fn new(client_id: String) -> DiscordPresence {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || run(client_id, rx));
+        DiscordPresence { tx }
+    }
+
+    /// Update the presence with a player event, while `playable` is the
+    /// current item of the queue.
+    pub fn update(&self, event: &PlayerEvent, playable: Option<&Playable>) {
+        let activity = match (event, playable) {
+            (PlayerEvent::Playing(playback_start), Some(playable)) => {
+                let start = playback_start
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64;
+                let mut activity = activity(playable);
+                activity["timestamps"] = json!({
+                    "start": start,
+                    "end": start + playable.duration() as u64,
+                });
+                Some(activity)
+            }
+            (PlayerEvent::Paused(_) | PlayerEvent::Loading(_, _), Some(playable)) => {
+                Some(activity(playable))
+            }
+            (PlayerEvent::Stopped, _) => None,
+            _ => return,
+        };
+        if self.tx.send(activity).is_err() {
+            debug!("Discord presence thread stopped");
+        }
+    }
+}
+
+/// The activity that shows `playable`, without timestamps.
+fn activity(playable: &Playable) -> Value {
+    let (details, state, large_text) = match playable {
+        Playable::Track(track) => (
+            track.title.clone(),
+            format!("by {}", track.artists.join(", ")),
+            track.album.clone(),
+        ),
+        Playable::Episode(episode) => (episode.name.clone(), "Podcast".to_string(), None),
+    };
+    let mut activity = json!({
+        // Listening to
+        "type": 2,
+        "details": truncate(&details),
+        "state": truncate(&state),
+    });
+    if let Some(cover_url) = playable.cover_url() {
+        activity["assets"] = json!({ "large_image": cover_url });
+        if let Some(large_text) = large_text {
+            activity["assets"]["large_text"] = json!(truncate(&large_text));
+        }
+    }
+    activity
+}
+
+fn truncate(text: &str) -> String {
+    text.chars().take(MAX_TEXT_LENGTH).collect()
+}
+
+fn run(client_id: String, rx: mpsc::Receiver<Option<Value>>) {
+    let mut connection: Option<UnixStream> = None;
+    let mut activity = None;
+    let mut pending = false;
+    let mut nonce = 0u64;
+    loop {
+        let received = if pending {
+            rx.recv_timeout(RECONNECT_INTERVAL)
+        } else {
+            rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        };
+        match received {
+            Ok(update) => {
+                // Only the latest update is worth sending
+                activity = rx.try_iter().last().unwrap_or(update);
+                pending = true;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        if connection.is_none() {
+            match connect(&client_id) {
+                Ok(stream) => {
+                    info!("Connected to Discord");
+                    connection = Some(stream);
+                }
+                Err(e) => {
+                    debug!("could not connect to Discord: {}", e);
+                    continue;
+                }
+            }
+        }
+        if let Some(stream) = connection.as_mut() {
+            nonce += 1;
+            let command = json!({
+                "cmd": "SET_ACTIVITY",
+                "args": { "pid": std::process::id(), "activity": activity },
+                "nonce": nonce.to_string(),
+            });
+            match send(stream, OP_FRAME, &command) {
+                Ok(()) => pending = false,
+                Err(e) if e.kind() == io::ErrorKind::Other => {
+                    warn!("Discord rejected the activity: {}", e);
+                    pending = false;
+                }
+                Err(e) => {
+                    debug!("lost connection to Discord: {}", e);
+                    connection = None;
+                }
+            }
+        }
+    }
+}
+
+/// Connect to the first Discord IPC socket that accepts the handshake.
+fn connect(client_id: &str) -> io::Result<UnixStream> {
+    let mut error = io::Error::new(io::ErrorKind::NotFound, "no Discord IPC socket found");
+    for path in socket_paths() {
+        let stream = match UnixStream::connect(&path) {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        let handshake = json!({ "v": 1, "client_id": client_id });
+        match stream
+            .set_read_timeout(Some(READ_TIMEOUT))
+            .and_then(|_| send(&mut &stream, OP_HANDSHAKE, &handshake))
+        {
+            Ok(()) => return Ok(stream),
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
+
+/// The paths Discord may listen on, including those of Flatpak and Snap
+/// installations.
+fn socket_paths() -> Vec<PathBuf> {
+    let dirs: Vec<PathBuf> = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .map(PathBuf::from)
+        .chain(std::iter::once(PathBuf::from("/tmp")))
+        .collect();
+    let mut paths = Vec::new();
+    for dir in dirs {
+        for subdir in ["", "app/com.discordapp.Discord", "snap.discord"] {
+            for i in 0..10 {
+                paths.push(dir.join(subdir).join(format!("discord-ipc-{i}")));
+            }
+        }
+    }
+    paths
+}
+
+/// Send a frame and read Discord's answer, which is an error frame if the
+/// frame was rejected.
+fn send<S: Read + Write>(stream: &mut S, op: u32, payload: &Value) -> io::Result<()> {
+    let payload = payload.to_string();
+    let mut frame = Vec::with_capacity(8 + payload.len());
+    frame.extend_from_slice(&op.to_le_bytes());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(payload.as_bytes());
+    stream.write_all(&frame)?;
+
+    let mut header = [0u8; 8];
+    stream.read_exact(&mut header)?;
+    let length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let mut answer = vec![0u8; length as usize];
+    stream.read_exact(&mut answer)?;
+    let answer: Value = serde_json::from_slice(&answer)?;
+    if answer["evt"] == "ERROR" || answer.get("code").is_some() {
+        let message = answer["data"]["message"]
+            .as_str()
+            .or_else(|| answer["message"].as_str())
+            .unwrap_or("unknown error");
+        return Err(io::Error::new(io::ErrorKind::Other, message.to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use std::sync::{Arc, RwLock};
+
+use chrono::Utc;
+use log::{debug, error};
+
+use crate::config::{cache_path, Config, DEFAULT_HISTORY_SIZE};
+use crate::model::playable::Playable;
+use crate::serialization::{Serializer, JSON};
+use crate::spotify::PlayerEvent;
+
+const HISTORY_FILE: &str = "history.json";
+
+/// The items that were played, most recent first. The time an item was
+/// played is kept as the time it was added to the history.
+pub struct History {
+    pub items: Arc<RwLock<Vec<Playable>>>,
+    /// The item that is playing, which is added once another one plays or
+    /// playback stops.
+    current: RwLock<Option<Playable>>,
+    size: usize,
+}
+
+impl History {
+    pub fn new(cfg: &Config) -> History {
+        let size = cfg.values().history_size.unwrap_or(DEFAULT_HISTORY_SIZE);
+        let mut items: Vec<Playable> = match JSON.load(cache_path(HISTORY_FILE)) {
+            Ok(items) => items,
+            Err(e) => {
+                debug!("no history loaded: {}", e);
+                Vec::new()
+            }
+        };
+        items.truncate(size);
+        History {
+            items: Arc::new(RwLock::new(items)),
+            current: RwLock::new(None),
+            size,
+        }
+    }
+
+    /// An empty history, which isn't loaded from the cache.
+    #[cfg(test)]
+    pub fn empty() -> History {
+        History {
+            items: Arc::new(RwLock::new(Vec::new())),
+            current: RwLock::new(None),
+            size: DEFAULT_HISTORY_SIZE,
+        }
+    }
+
+    /// Follow playback with a player event, while `playable` is the current
+    /// item of the queue.
+    pub fn update(&self, event: &PlayerEvent, playable: Option<&Playable>) {
+        match event {
+            PlayerEvent::Playing(_) => {
+                let mut current = self.current.write().unwrap();
+                let uri = playable.map(|playable| playable.uri());
+                if current.as_ref().map(|current| current.uri()) != uri {
+                    if let Some(finished) = current.take() {
+                        self.add(finished);
+                    }
+                    *current = playable.cloned();
+                }
+            }
+            PlayerEvent::FinishedTrack | PlayerEvent::Stopped => {
+                if let Some(finished) = self.current.write().unwrap().take() {
+                    self.add(finished);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut items = self.items.write().unwrap();
+        items.clear();
+        if let Err(e) = JSON.write(cache_path(HISTORY_FILE), &*items) {
+            error!("could not save the history: {}", e);
+        }
+    }
+
+    /// Add `playable` as the most recently played item, unless it already is.
+    fn add(&self, mut playable: Playable) {
+        let mut items = self.items.write().unwrap();
+        if items.first().map(|last| last.uri()) == Some(playable.uri()) {
+            items.remove(0);
+        }
+        playable.set_added_at(Some(Utc::now()));
+        items.insert(0, playable);
+        items.truncate(self.size);
+
+        if let Err(e) = JSON.write(cache_path(HISTORY_FILE), &*items) {
+            error!("could not save the history: {}", e);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::process::{Child, Command, Stdio};
+
+use log::{debug, error, warn};
+
+use crate::config::HooksConfig;
+use crate::model::playable::Playable;
+use crate::spotify::PlayerEvent;
+
+/// Runs the configured shell commands as playback changes, with the current
+/// item in their environment. The commands run detached, so they never hold
+/// up playback, and those that fail are only logged.
+pub struct Hooks {
+    config: HooksConfig,
+    /// The URI of the item the last hooks ran for.
+    uri: Option<String>,
+    playing: bool,
+}
+
+impl Hooks {
+    pub fn new(config: HooksConfig) -> Hooks {
+        Hooks {
+            config,
+            uri: None,
+            playing: false,
+        }
+    }
+
+    /// Run the hooks for a player event, while `playable` is the current
+    /// item of the queue.
+    pub fn update(&mut self, event: &PlayerEvent, playable: Option<&Playable>) {
+        match event {
+            PlayerEvent::Playing(_) => {
+                let uri = playable.map(|playable| playable.uri());
+                if uri != self.uri {
+                    self.uri = uri;
+                    run("track_change", self.config.track_change.as_ref(), playable);
+                }
+                if !self.playing {
+                    self.playing = true;
+                    run("play", self.config.play.as_ref(), playable);
+                }
+            }
+            PlayerEvent::Paused(_) if self.playing => {
+                self.playing = false;
+                run("pause", self.config.pause.as_ref(), playable);
+            }
+            PlayerEvent::Stopped if self.uri.is_some() || self.playing => {
+                self.uri = None;
+                self.playing = false;
+                run("stop", self.config.stop.as_ref(), playable);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The variables the hooks get to know about `playable`.
+fn environment(hook: &str, playable: Option<&Playable>) -> Vec<(&'static str, String)> {
+    let mut env = vec![("NCSPOT_EVENT", hook.to_string())];
+    let playable = match playable {
+        Some(playable) => playable,
+        None => return env,
+    };
+    let (title, artists, album) = match playable {
+        Playable::Track(track) => (
+            track.title.clone(),
+            track.artists.join(", "),
+            track.album.clone().unwrap_or_default(),
+        ),
+        Playable::Episode(episode) => (episode.name.clone(), String::new(), String::new()),
+    };
+    env.extend([
+        ("NCSPOT_TITLE", title),
+        ("NCSPOT_ARTISTS", artists),
+        ("NCSPOT_ALBUM", album),
+        ("NCSPOT_URI", playable.uri()),
+        ("NCSPOT_DURATION_MS", playable.duration().to_string()),
+        ("NCSPOT_COVER_URL", playable.cover_url().unwrap_or_default()),
+    ]);
+    env
+}
+
+fn run(hook: &str, command: Option<&String>, playable: Option<&Playable>) {
+    let command = match command {
+        Some(command) => command,
+        None => return,
+    };
+    debug!("running {} hook: {}", hook, command);
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(environment(hook, playable))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn();
+    match child {
+        Ok(child) => {
+            let hook = hook.to_string();
+            std::thread::spawn(move || wait(&hook, child));
+        }
+        Err(e) => error!("could not run the {} hook: {}", hook, e),
+    }
+}
+
+/// Wait for a hook to finish, to log it if it failed.
+fn wait(hook: &str, child: Child) {
+    match child.wait_with_output() {
+        Ok(output) if !output.status.success() => warn!(
+            "the {} hook failed with {}: {}",
+            hook,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Ok(_) => {}
+        Err(e) => error!("could not wait for the {} hook: {}", hook, e),
+    }
+}
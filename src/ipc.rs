@@ -1,9 +1,11 @@
+use std::time::SystemTime;
 use std::{io, path::PathBuf};
 
 use futures::SinkExt;
 use log::{debug, error, info};
 use tokio::net::{UnixListener, UnixStream};
 use tokio::runtime::Handle;
+use tokio::sync::broadcast::{self, error::RecvError};
 use tokio::sync::watch::{Receiver, Sender};
 use tokio_stream::wrappers::WatchStream;
 use tokio_stream::StreamExt;
@@ -11,22 +13,59 @@ use tokio_util::codec::{FramedRead, FramedWrite, LinesCodec};
 
 use crate::events::{Event, EventManager};
 use crate::model::playable::Playable;
-use crate::spotify::PlayerEvent;
+use crate::queue::QueueEvent;
+use crate::spotify::{PlayerEvent, SleepTimer};
+
+/// How many updates a subscriber of the event stream may fall behind before
+/// it is disconnected.
+const EVENT_BUFFER: usize = 64;
 
 pub struct IpcSocket {
     tx: Sender<Status>,
+    events: broadcast::Sender<PlayerUpdate>,
 }
 
 #[derive(Clone, Debug, Serialize)]
 struct Status {
     mode: PlayerEvent,
     playable: Option<Playable>,
+    sleep_timer: Option<SleepTimer>,
+    muted: bool,
+    stop_after_track: bool,
+}
+
+/// The events that are sent to the subscribers of the event stream.
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+enum UpdateEvent {
+    Player(PlayerEvent),
+    Queue(QueueEvent),
+}
+
+/// An update sent to the subscribers of the event stream for every player
+/// event, and when the queue finished.
+#[derive(Clone, Debug, Serialize)]
+struct PlayerUpdate {
+    event: UpdateEvent,
+    /// The playback state after the event: `playing`, `paused`, `loading` or
+    /// `stopped`.
+    state: &'static str,
+    /// The position in the current item, unless playback is stopped.
+    position_ms: Option<u64>,
+    playable: Option<Playable>,
 }
 
 impl IpcSocket {
-    pub fn new(handle: &Handle, path: PathBuf, ev: EventManager) -> io::Result<IpcSocket> {
-        if path.exists() {
-            std::fs::remove_file(&path)?;
+    pub fn new(
+        handle: &Handle,
+        path: PathBuf,
+        events_path: PathBuf,
+        ev: EventManager,
+    ) -> io::Result<IpcSocket> {
+        for path in [&path, &events_path] {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
         }
 
         info!("Creating IPC domain socket at {path:?}");
@@ -34,6 +73,9 @@ impl IpcSocket {
         let status = Status {
             mode: PlayerEvent::Stopped,
             playable: None,
+            sleep_timer: None,
+            muted: false,
+            stop_after_track: false,
         };
 
         let (tx, rx) = tokio::sync::watch::channel(status);
@@ -42,17 +84,74 @@ impl IpcSocket {
             Self::worker(listener, ev, rx.clone()).await;
         });
 
-        Ok(IpcSocket { tx })
+        info!("Creating IPC event stream socket at {events_path:?}");
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        let subscribers = events.clone();
+        handle.spawn(async move {
+            let listener =
+                UnixListener::bind(events_path).expect("Could not create IPC event stream socket");
+            Self::events_worker(listener, subscribers).await;
+        });
+
+        Ok(IpcSocket { tx, events })
     }
 
-    pub fn publish(&self, event: &PlayerEvent, playable: Option<Playable>) {
+    pub fn publish(
+        &self,
+        event: &PlayerEvent,
+        playable: Option<Playable>,
+        sleep_timer: Option<SleepTimer>,
+        muted: bool,
+        stop_after_track: bool,
+    ) {
         let status = Status {
             mode: event.clone(),
             playable,
+            sleep_timer,
+            muted,
+            stop_after_track,
         };
         self.tx.send(status).expect("Error publishing IPC update");
     }
 
+    /// Send `event` to the subscribers of the event stream, along with the
+    /// playback `status` after it. This never blocks, subscribers that fall
+    /// too far behind are disconnected instead.
+    pub fn broadcast(&self, event: &PlayerEvent, status: &PlayerEvent, playable: Option<Playable>) {
+        self.send_update(UpdateEvent::Player(event.clone()), status, playable);
+    }
+
+    /// Send the queue `event` to the subscribers of the event stream, like
+    /// [IpcSocket::broadcast] does for player events.
+    pub fn broadcast_queue(
+        &self,
+        event: &QueueEvent,
+        status: &PlayerEvent,
+        playable: Option<Playable>,
+    ) {
+        self.send_update(UpdateEvent::Queue(event.clone()), status, playable);
+    }
+
+    fn send_update(&self, event: UpdateEvent, status: &PlayerEvent, playable: Option<Playable>) {
+        let (state, position) = match status {
+            PlayerEvent::Playing(since) => (
+                "playing",
+                Some(SystemTime::now().duration_since(*since).unwrap_or_default()),
+            ),
+            PlayerEvent::Paused(position) => ("paused", Some(*position)),
+            PlayerEvent::Loading(_, position) => ("loading", Some(*position)),
+            _ => ("stopped", None),
+        };
+        let update = PlayerUpdate {
+            event,
+            state,
+            position_ms: position.map(|position| position.as_millis() as u64),
+            playable,
+        };
+        // Fails only while there are no subscribers
+        let _ = self.events.send(update);
+    }
+
     async fn worker(listener: UnixListener, ev: EventManager, tx: Receiver<Status>) {
         loop {
             match listener.accept().await {
@@ -69,6 +168,41 @@ impl IpcSocket {
         }
     }
 
+    async fn events_worker(listener: UnixListener, events: broadcast::Sender<PlayerUpdate>) {
+        loop {
+            match listener.accept().await {
+                Ok((stream, sockaddr)) => {
+                    debug!("Event stream subscription from {:?}", sockaddr);
+                    tokio::spawn(Self::events_handler(stream, events.subscribe()));
+                }
+                Err(e) => error!("Error accepting connection: {e}"),
+            }
+        }
+    }
+
+    async fn events_handler(
+        stream: UnixStream,
+        mut rx: broadcast::Receiver<PlayerUpdate>,
+    ) -> Result<(), String> {
+        let mut framed_writer = FramedWrite::new(stream, LinesCodec::new());
+        loop {
+            match rx.recv().await {
+                Ok(update) => {
+                    let update_str = serde_json::to_string(&update).map_err(|e| e.to_string())?;
+                    framed_writer
+                        .send(update_str)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    info!("Disconnecting event stream subscriber that missed {missed} updates");
+                    return Ok(());
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            }
+        }
+    }
+
     async fn stream_handler(
         mut stream: UnixStream,
         ev: EventManager,
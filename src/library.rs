@@ -1,12 +1,13 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::iter::Iterator;
 use std::ops::Deref;
 use std::path::PathBuf;
-use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
 use std::thread;
 
+use chrono::{DateTime, Duration, Utc};
 use log::{debug, error, info};
-use rspotify::model::Id;
+use rspotify::model::{Country, Id};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
@@ -17,52 +18,180 @@ use crate::model::album::Album;
 use crate::model::artist::Artist;
 use crate::model::playable::Playable;
 use crate::model::playlist::Playlist;
+use crate::model::playlist_folder::RootlistItem;
 use crate::model::show::Show;
 use crate::model::track::Track;
+use crate::serialization::{Serializer, JSON};
 use crate::spotify::Spotify;
 
 const CACHE_TRACKS: &str = "tracks.db";
 const CACHE_ALBUMS: &str = "albums.db";
 const CACHE_ARTISTS: &str = "artists.db";
 const CACHE_PLAYLISTS: &str = "playlists.db";
+const CACHE_ROOTLIST: &str = "rootlist.db";
+const EXPANDED_FOLDERS_FILE: &str = "expanded_folders.json";
+const RECENT_PLAYLISTS_FILE: &str = "recent_playlists.json";
+const CACHE_ARTIST_RELEASES: &str = "artist_releases.db";
+const CACHE_USER_NAMES: &str = "user_names.db";
+
+/// How long the latest release of an artist is kept before it's fetched
+/// again, in hours.
+const ARTIST_RELEASE_TTL_HOURS: i64 = 24;
+
+/// How many tracks are checked for being liked at once, which is the most
+/// Spotify takes.
+const LIKED_CHECK_SIZE: usize = 50;
+
+/// How many playlists that tracks were added to are remembered.
+const RECENT_PLAYLISTS_MAX: usize = 50;
+
+/// How many saved items are checked at once for whether they're still saved.
+const CONTAINS_BATCH_SIZE: usize = 50;
+
+/// The latest release of an artist, as it was when it was fetched.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ArtistRelease {
+    /// The release date, which may only be a year.
+    pub date: Option<String>,
+    fetched_at: DateTime<Utc>,
+}
 
 #[derive(Clone)]
 pub struct Library {
     pub tracks: Arc<RwLock<Vec<Track>>>,
     pub albums: Arc<RwLock<Vec<Album>>>,
     pub artists: Arc<RwLock<Vec<Artist>>>,
+    /// The followed artists, without their saved tracks so that playing one
+    /// plays its top tracks.
+    pub followed_artists: Arc<RwLock<Vec<Artist>>>,
+    /// The latest releases of artists by ID, fetched as they are shown.
+    artist_releases: Arc<RwLock<HashMap<String, ArtistRelease>>>,
+    /// The artists whose latest release is waiting to be fetched.
+    pending_releases: Arc<Mutex<HashSet<String>>>,
+    /// The display names of users by ID, fetched as they are shown.
+    user_names: Arc<RwLock<HashMap<String, String>>>,
+    /// The users whose display name is waiting to be fetched.
+    pending_users: Arc<Mutex<HashSet<String>>>,
+    /// Whether tracks by ID are liked, checked with Spotify as they are shown
+    /// and kept up to date as they are saved or removed.
+    liked: Arc<RwLock<HashMap<String, bool>>>,
+    /// The tracks that are waiting to be checked.
+    pending_liked: Arc<Mutex<HashSet<String>>>,
     pub playlists: Arc<RwLock<Vec<Playlist>>>,
+    /// The order of the playlists along with the folders they are in.
+    pub rootlist: Arc<RwLock<Vec<RootlistItem>>>,
+    /// The IDs of the playlist folders that are expanded in the playlists tab.
+    expanded_folders: Arc<RwLock<HashSet<String>>>,
+    /// The IDs of the playlists that tracks were added to, most recent first.
+    recent_playlists: Arc<RwLock<Vec<String>>>,
     pub shows: Arc<RwLock<Vec<Show>>>,
     pub is_done: Arc<RwLock<bool>>,
-    pub user_id: Option<String>,
-    pub display_name: Option<String>,
+    /// The account the library is of, which changes when switching accounts.
+    account: Arc<RwLock<Account>>,
     ev: EventManager,
     spotify: Spotify,
     pub cfg: Arc<Config>,
 }
 
+/// The user's account, as Spotify tells.
+#[derive(Default)]
+struct Account {
+    user_id: Option<String>,
+    display_name: Option<String>,
+    country: Option<Country>,
+}
+
+impl Account {
+    fn fetch(spotify: &Spotify) -> Account {
+        let current_user = spotify.api.current_user();
+        Account {
+            user_id: current_user.as_ref().map(|u| u.id.id().to_string()),
+            display_name: current_user.as_ref().and_then(|u| u.display_name.clone()),
+            country: current_user.as_ref().and_then(|u| u.country),
+        }
+    }
+}
+
 impl Library {
     pub fn new(ev: &EventManager, spotify: Spotify, cfg: Arc<Config>) -> Self {
-        let current_user = spotify.api.current_user();
-        let user_id = current_user.as_ref().map(|u| u.id.id().to_string());
-        let display_name = current_user.as_ref().and_then(|u| u.display_name.clone());
+        let library = Self::offline(ev, spotify, cfg);
+        *library.account.write().unwrap() = Account::fetch(&library.spotify);
 
-        let library = Self {
+        library.load_artist_releases();
+        library.load_user_names();
+        library.load_expanded_folders();
+        library.load_recent_playlists();
+        library.update_library();
+        library
+    }
+
+    /// An empty library of an unknown account, which is neither loaded from
+    /// the cache nor fetched.
+    pub fn offline(ev: &EventManager, spotify: Spotify, cfg: Arc<Config>) -> Self {
+        Self {
             tracks: Arc::new(RwLock::new(Vec::new())),
             albums: Arc::new(RwLock::new(Vec::new())),
             artists: Arc::new(RwLock::new(Vec::new())),
+            followed_artists: Arc::new(RwLock::new(Vec::new())),
+            artist_releases: Arc::new(RwLock::new(HashMap::new())),
+            pending_releases: Arc::new(Mutex::new(HashSet::new())),
+            user_names: Arc::new(RwLock::new(HashMap::new())),
+            pending_users: Arc::new(Mutex::new(HashSet::new())),
+            liked: Arc::new(RwLock::new(HashMap::new())),
+            pending_liked: Arc::new(Mutex::new(HashSet::new())),
             playlists: Arc::new(RwLock::new(Vec::new())),
+            rootlist: Arc::new(RwLock::new(Vec::new())),
+            expanded_folders: Arc::new(RwLock::new(HashSet::new())),
+            recent_playlists: Arc::new(RwLock::new(Vec::new())),
             shows: Arc::new(RwLock::new(Vec::new())),
             is_done: Arc::new(RwLock::new(false)),
-            user_id,
-            display_name,
+            account: Arc::new(RwLock::new(Account::default())),
             ev: ev.clone(),
             spotify,
             cfg,
-        };
+        }
+    }
 
-        library.update_library();
-        library
+    pub fn user_id(&self) -> Option<String> {
+        self.account.read().unwrap().user_id.clone()
+    }
+
+    pub fn display_name(&self) -> Option<String> {
+        self.account.read().unwrap().display_name.clone()
+    }
+
+    /// The country of the user's account.
+    pub fn country(&self) -> Option<Country> {
+        self.account.read().unwrap().country
+    }
+
+    /// Replace the library by that of the account that was switched to, once
+    /// the session of the account is up.
+    pub fn switch_account(&self) {
+        let library = self.clone();
+        thread::spawn(move || {
+            library.spotify.api.update_token();
+            *library.account.write().unwrap() = Account::fetch(&library.spotify);
+
+            // The cached library is of the previous account
+            for file in [
+                CACHE_TRACKS,
+                CACHE_ALBUMS,
+                CACHE_ARTISTS,
+                CACHE_PLAYLISTS,
+                CACHE_ROOTLIST,
+            ] {
+                let _ = std::fs::remove_file(config::cache_path(file));
+            }
+            library.tracks.write().unwrap().clear();
+            library.albums.write().unwrap().clear();
+            library.artists.write().unwrap().clear();
+            library.followed_artists.write().unwrap().clear();
+            library.playlists.write().unwrap().clear();
+            library.rootlist.write().unwrap().clear();
+            library.shows.write().unwrap().clear();
+            library.update_library();
+        });
     }
 
     pub fn playlists(&self) -> RwLockReadGuard<Vec<Playlist>> {
@@ -110,6 +239,178 @@ impl Library {
         }
     }
 
+    fn load_artist_releases(&self) {
+        match JSON.load(config::cache_path(CACHE_ARTIST_RELEASES)) {
+            Ok(releases) => *self.artist_releases.write().unwrap() = releases,
+            Err(e) => debug!("no artist releases loaded: {}", e),
+        }
+    }
+
+    /// The release date of the latest release of the artist with `id`, as far
+    /// as it is known. Releases that aren't known or are outdated are fetched
+    /// in the background, and the UI is refreshed once they are.
+    pub fn latest_release(&self, id: &str) -> Option<String> {
+        let release = self.artist_releases.read().unwrap().get(id).cloned();
+        let outdated = release.as_ref().map_or(true, |release| {
+            Utc::now() - release.fetched_at > Duration::hours(ARTIST_RELEASE_TTL_HOURS)
+        });
+        if outdated {
+            let mut pending = self.pending_releases.lock().unwrap();
+            // Only one thread fetches releases, which is running while some
+            // are pending.
+            let fetching = !pending.is_empty();
+            if pending.insert(id.to_string()) && !fetching {
+                let library = self.clone();
+                thread::spawn(move || library.fetch_artist_releases());
+            }
+        }
+        release.and_then(|release| release.date)
+    }
+
+    fn fetch_artist_releases(&self) {
+        loop {
+            let id = match self.pending_releases.lock().unwrap().iter().next() {
+                Some(id) => id.clone(),
+                None => break,
+            };
+            let date = self.spotify.api.artist_latest_release(&id);
+            {
+                let mut releases = self.artist_releases.write().unwrap();
+                // Keep what was known if the release couldn't be fetched, and
+                // try again once it's outdated.
+                let date = date
+                    .unwrap_or_else(|| releases.get(&id).and_then(|release| release.date.clone()));
+                let fetched_at = Utc::now();
+                releases.insert(id.clone(), ArtistRelease { date, fetched_at });
+            }
+            self.pending_releases.lock().unwrap().remove(&id);
+            self.ev.trigger();
+        }
+
+        let releases = self.artist_releases.read().unwrap();
+        if let Err(e) = JSON.write(config::cache_path(CACHE_ARTIST_RELEASES), &*releases) {
+            error!("could not save artist releases: {}", e);
+        }
+    }
+
+    fn load_user_names(&self) {
+        match JSON.load(config::cache_path(CACHE_USER_NAMES)) {
+            Ok(names) => *self.user_names.write().unwrap() = names,
+            Err(e) => debug!("no user names loaded: {}", e),
+        }
+    }
+
+    /// The display name of the user with `id`, if it is known. Unknown names
+    /// are fetched in the background, all that are pending at once, and the
+    /// UI is refreshed once they are.
+    pub fn user_name(&self, id: &str) -> Option<String> {
+        let name = self.user_names.read().unwrap().get(id).cloned();
+        if name.is_none() {
+            let mut pending = self.pending_users.lock().unwrap();
+            let fetching = !pending.is_empty();
+            if pending.insert(id.to_string()) && !fetching {
+                let library = self.clone();
+                thread::spawn(move || library.fetch_user_names());
+            }
+        }
+        name
+    }
+
+    fn fetch_user_names(&self) {
+        loop {
+            let ids: Vec<String> = self.pending_users.lock().unwrap().iter().cloned().collect();
+            if ids.is_empty() {
+                break;
+            }
+            for id in ids.iter() {
+                // Users without a display name, or who couldn't be fetched,
+                // are shown by their ID
+                let name = self
+                    .spotify
+                    .api
+                    .user(id)
+                    .and_then(|user| user.display_name)
+                    .unwrap_or_else(|| id.clone());
+                self.user_names.write().unwrap().insert(id.clone(), name);
+            }
+            let mut pending = self.pending_users.lock().unwrap();
+            for id in ids.iter() {
+                pending.remove(id);
+            }
+            self.ev.trigger();
+        }
+
+        let names = self.user_names.read().unwrap();
+        if let Err(e) = JSON.write(config::cache_path(CACHE_USER_NAMES), &*names) {
+            error!("could not save user names: {}", e);
+        }
+    }
+
+    fn load_expanded_folders(&self) {
+        match JSON.load(config::cache_path(EXPANDED_FOLDERS_FILE)) {
+            Ok(expanded) => *self.expanded_folders.write().unwrap() = expanded,
+            Err(e) => debug!("no expanded playlist folders loaded: {}", e),
+        }
+    }
+
+    pub fn expanded_folders(&self) -> HashSet<String> {
+        self.expanded_folders.read().unwrap().clone()
+    }
+
+    /// Expand or collapse the playlist folder with `id`, which is remembered
+    /// across restarts.
+    pub fn set_folder_expanded(&self, id: &str, expanded: bool) {
+        let mut folders = self.expanded_folders.write().unwrap();
+        let changed = if expanded {
+            folders.insert(id.to_string())
+        } else {
+            folders.remove(id)
+        };
+        if changed {
+            if let Err(e) = JSON.write(config::cache_path(EXPANDED_FOLDERS_FILE), &*folders) {
+                error!("could not save expanded playlist folders: {}", e);
+            }
+        }
+    }
+
+    fn load_recent_playlists(&self) {
+        match JSON.load(config::cache_path(RECENT_PLAYLISTS_FILE)) {
+            Ok(recent) => *self.recent_playlists.write().unwrap() = recent,
+            Err(e) => debug!("no recent playlists loaded: {}", e),
+        }
+    }
+
+    /// The playlists the user can add tracks to, those that tracks were most
+    /// recently added to first.
+    pub fn writable_playlists(&self) -> Vec<Playlist> {
+        let recent = self.recent_playlists.read().unwrap();
+        let mut playlists: Vec<Playlist> = self
+            .playlists()
+            .iter()
+            .filter(|playlist| self.is_own_playlist(playlist) || playlist.collaborative)
+            .cloned()
+            .collect();
+        playlists.sort_by_key(|playlist| {
+            recent
+                .iter()
+                .position(|id| id == &playlist.id)
+                .unwrap_or(usize::MAX)
+        });
+        playlists
+    }
+
+    /// Remember that tracks were added to the playlist with `id`, across
+    /// restarts.
+    pub fn playlist_used(&self, id: &str) {
+        let mut recent = self.recent_playlists.write().unwrap();
+        recent.retain(|recent_id| recent_id != id);
+        recent.insert(0, id.to_string());
+        recent.truncate(RECENT_PLAYLISTS_MAX);
+        if let Err(e) = JSON.write(config::cache_path(RECENT_PLAYLISTS_FILE), &*recent) {
+            error!("could not save recent playlists: {}", e);
+        }
+    }
+
     fn needs_download(&self, remote: &Playlist) -> bool {
         self.playlists()
             .iter()
@@ -159,16 +460,84 @@ impl Library {
         self.save_cache(config::cache_path(CACHE_PLAYLISTS), self.playlists.clone());
     }
 
-    pub fn save_playlist(&self, name: &str, tracks: &[Playable]) {
+    /// Save `tracks` as a new playlist, which is private unless `public` is
+    /// `None` or `true`. The playlist is listed first right away, like
+    /// Spotify lists new playlists. Returns whether it was created.
+    pub fn save_playlist(&self, name: &str, tracks: &[Playable], public: Option<bool>) -> bool {
         debug!("saving {} tracks to new list {}", tracks.len(), name);
-        match self.spotify.api.create_playlist(name, None, None) {
-            Some(id) => self.overwrite_playlist(&id, tracks),
-            None => error!("could not create new playlist.."),
+        match self.spotify.api.create_playlist(name, public, None) {
+            Some(mut playlist) => {
+                playlist.tracks = Some(Vec::new());
+                self.playlists.write().unwrap().insert(0, playlist.clone());
+                self.rootlist
+                    .write()
+                    .unwrap()
+                    .insert(0, RootlistItem::Playlist(playlist.id.clone()));
+                self.ev.trigger();
+                if tracks.is_empty() {
+                    self.save_cache(config::cache_path(CACHE_PLAYLISTS), self.playlists.clone());
+                } else {
+                    self.overwrite_playlist(&playlist.id, tracks);
+                }
+                self.save_cache(config::cache_path(CACHE_ROOTLIST), self.rootlist.clone());
+                true
+            }
+            None => {
+                error!("could not create new playlist..");
+                false
+            }
+        }
+    }
+
+    /// Whether the playlist is the user's own, so that it can be changed.
+    pub fn is_own_playlist(&self, playlist: &Playlist) -> bool {
+        self.user_id().as_ref() == Some(&playlist.owner_id)
+    }
+
+    /// Rename the playlist with `id` or change its visibility, leaving what is
+    /// `None` as it is.
+    pub fn change_playlist(
+        &self,
+        id: &str,
+        name: Option<&str>,
+        public: Option<bool>,
+        collaborative: Option<bool>,
+    ) -> Result<(), String> {
+        let playlist = self
+            .playlists()
+            .iter()
+            .find(|playlist| playlist.id == id)
+            .cloned()
+            .ok_or("The playlist is not in your library")?;
+        if !self.is_own_playlist(&playlist) {
+            return Err(format!(
+                "\"{}\" is owned by someone else and can't be changed",
+                playlist.name
+            ));
+        }
+        if !self
+            .spotify
+            .api
+            .change_playlist_details(id, name, public, collaborative)
+        {
+            return Err(format!("Could not change \"{}\"", playlist.name));
         }
+
+        let mut updated = playlist;
+        if let Some(name) = name {
+            updated.name = name.to_string();
+        }
+        if let Some(collaborative) = collaborative {
+            updated.collaborative = collaborative;
+        }
+        self.playlist_update(&updated);
+        self.ev.trigger();
+        Ok(())
     }
 
     pub fn update_library(&self) {
         *self.is_done.write().unwrap() = false;
+        self.liked.write().unwrap().clear();
 
         let library = self.clone();
         thread::spawn(move || {
@@ -205,11 +574,16 @@ impl Library {
                         config::cache_path(CACHE_PLAYLISTS),
                         library.playlists.clone(),
                     );
+                    library
+                        .load_cache(config::cache_path(CACHE_ROOTLIST), library.rootlist.clone());
                     library.fetch_playlists();
                     library.save_cache(
                         config::cache_path(CACHE_PLAYLISTS),
                         library.playlists.clone(),
                     );
+                    library.fetch_rootlist();
+                    library
+                        .save_cache(config::cache_path(CACHE_ROOTLIST), library.rootlist.clone());
                 })
             };
 
@@ -315,6 +689,19 @@ impl Library {
         self.ev.trigger();
     }
 
+    /// Fetch the folders of the playlists. The cached ones are kept if the
+    /// rootlist can't be fetched.
+    fn fetch_rootlist(&self) {
+        debug!("loading rootlist");
+        if let Some(uris) = self.spotify.rootlist() {
+            *self.rootlist.write().unwrap() = uris
+                .iter()
+                .filter_map(|uri| RootlistItem::from_uri(uri))
+                .collect();
+            self.ev.trigger();
+        }
+    }
+
     fn fetch_artists(&self) {
         let mut artists: Vec<Artist> = Vec::new();
         let mut last: Option<&str> = None;
@@ -369,33 +756,23 @@ impl Library {
     }
 
     fn fetch_albums(&self) {
-        let mut albums: Vec<Album> = Vec::new();
-
-        let mut i: u32 = 0;
-
-        loop {
-            let page = self
-                .spotify
-                .api
-                .current_user_saved_albums(albums.len() as u32);
-            debug!("albums page: {}", i);
-
-            i += 1;
-
-            if page.is_none() {
-                error!("Failed to fetch albums.");
-                return;
-            }
-
-            let page = page.unwrap();
-            albums.extend(page.items.iter().map(|a| a.into()));
-
-            if page.next.is_none() {
-                break;
-            }
+        let synced = sync_saved(
+            &self.albums,
+            |album| (album.id.clone(), album.added_at),
+            |offset| {
+                let page = self.spotify.api.current_user_saved_albums(offset)?;
+                debug!("albums page at offset {}", offset);
+                let albums = page.items.iter().map(Album::from).collect();
+                Some((albums, page.total, page.next.is_some()))
+            },
+            |ids| self.spotify.api.current_user_saved_albums_contains(ids),
+        );
+        if synced.is_none() {
+            error!("Failed to fetch albums.");
+            return;
         }
 
-        albums.sort_unstable_by_key(|album| {
+        self.albums.write().unwrap().sort_unstable_by_key(|album| {
             format!(
                 "{}{}{}",
                 album.artists[0].to_lowercase(),
@@ -403,55 +780,23 @@ impl Library {
                 album.title.to_lowercase()
             )
         });
-
-        *(self.albums.write().unwrap()) = albums;
     }
 
     fn fetch_tracks(&self) {
-        let mut tracks: Vec<Track> = Vec::new();
-
-        let mut i: u32 = 0;
-
-        loop {
-            let page = self
-                .spotify
-                .api
-                .current_user_saved_tracks(tracks.len() as u32);
-
-            debug!("tracks page: {}", i);
-            i += 1;
-
-            if page.is_none() {
-                error!("Failed to fetch tracks.");
-                return;
-            }
-            let page = page.unwrap();
-
-            if page.offset == 0 {
-                // If first page matches the first items in store and total is
-                // identical, assume list is unchanged.
-
-                let store = self.tracks.read().unwrap();
-
-                if page.total as usize == store.len()
-                    && !page
-                        .items
-                        .iter()
-                        .enumerate()
-                        .any(|(i, t)| t.track.id.as_ref().map(|id| id.to_string()) != store[i].id)
-                {
-                    return;
-                }
-            }
-
-            tracks.extend(page.items.iter().map(|t| t.into()));
-
-            if page.next.is_none() {
-                break;
-            }
+        let synced = sync_saved(
+            &self.tracks,
+            |track| (track.id.clone(), track.added_at),
+            |offset| {
+                let page = self.spotify.api.current_user_saved_tracks(offset)?;
+                debug!("tracks page at offset {}", offset);
+                let tracks = page.items.iter().map(Track::from).collect();
+                Some((tracks, page.total, page.next.is_some()))
+            },
+            |ids| self.spotify.api.current_user_saved_tracks_contains(ids),
+        );
+        if synced.is_none() {
+            error!("Failed to fetch tracks.");
         }
-
-        *(self.tracks.write().unwrap()) = tracks;
     }
 
     fn populate_artists(&self) {
@@ -485,6 +830,15 @@ impl Library {
 
         artists.sort_unstable_by(|a, b| a.name.partial_cmp(&b.name).unwrap());
 
+        *self.followed_artists.write().unwrap() = artists
+            .iter()
+            .filter(|artist| artist.is_followed)
+            .map(|artist| Artist {
+                tracks: None,
+                ..artist.clone()
+            })
+            .collect();
+
         // Add saved tracks to artists
         {
             let tracks = self.tracks.read().unwrap();
@@ -529,7 +883,28 @@ impl Library {
         self.save_cache(config::cache_path(CACHE_PLAYLISTS), self.playlists.clone());
     }
 
+    /// The indicator shown next to saved items.
+    pub fn saved_indicator(&self) -> String {
+        match self.cfg.values().saved_indicator.clone() {
+            Some(indicator) => indicator,
+            None if self.cfg.values().use_nerdfont.unwrap_or(false) => "\u{f62b}".to_string(),
+            None => "✓".to_string(),
+        }
+    }
+
+    /// Whether `track` is saved. Tracks are checked with Spotify as they are
+    /// asked for, until then the saved tracks that were fetched tell.
     pub fn is_saved_track(&self, track: &Playable) -> bool {
+        if let Playable::Track(Track { id: Some(id), .. }) = track {
+            if let Some(&liked) = self.liked.read().unwrap().get(id) {
+                return liked;
+            }
+            self.check_liked(vec![id.clone()]);
+        }
+        self.is_fetched_track(track)
+    }
+
+    fn is_fetched_track(&self, track: &Playable) -> bool {
         if !*self.is_done.read().unwrap() {
             return false;
         }
@@ -538,9 +913,85 @@ impl Library {
         tracks.iter().any(|t| t.id == track.id())
     }
 
-    pub fn save_tracks(&self, tracks: Vec<&Track>, api: bool) {
+    /// Check whether the tracks with `ids` are liked in the background, if
+    /// it isn't known yet. The UI is refreshed once they are checked.
+    pub fn check_liked(&self, ids: Vec<String>) {
+        let liked = self.liked.read().unwrap();
+        let mut pending = self.pending_liked.lock().unwrap();
+        // Only one thread checks tracks, which is running while some are
+        // pending.
+        let fetching = !pending.is_empty();
+        let mut added = false;
+        for id in ids {
+            if !liked.contains_key(&id) {
+                added |= pending.insert(id);
+            }
+        }
+        if added && !fetching {
+            let library = self.clone();
+            thread::spawn(move || library.fetch_liked());
+        }
+    }
+
+    fn fetch_liked(&self) {
+        loop {
+            let ids: Vec<String> = self
+                .pending_liked
+                .lock()
+                .unwrap()
+                .iter()
+                .take(LIKED_CHECK_SIZE)
+                .cloned()
+                .collect();
+            if ids.is_empty() {
+                break;
+            }
+            let contains = self
+                .spotify
+                .api
+                .current_user_saved_tracks_contains(ids.iter().map(String::as_str).collect());
+            {
+                let mut liked = self.liked.write().unwrap();
+                let mut pending = self.pending_liked.lock().unwrap();
+                for (i, id) in ids.into_iter().enumerate() {
+                    // Tracks that were saved or removed meanwhile are known
+                    // already.
+                    if !pending.remove(&id) {
+                        continue;
+                    }
+                    // Tracks that couldn't be checked aren't checked again,
+                    // the saved tracks that were fetched tell instead.
+                    let saved = match &contains {
+                        Some(contains) => contains.get(i).copied().unwrap_or(false),
+                        None => self
+                            .tracks
+                            .read()
+                            .unwrap()
+                            .iter()
+                            .any(|t| t.id.as_ref() == Some(&id)),
+                    };
+                    liked.insert(id, saved);
+                }
+            }
+            self.ev.trigger();
+        }
+    }
+
+    /// Remember whether `tracks` are liked, after they were saved or removed.
+    fn set_known_liked(&self, tracks: &[&Track], saved: bool) {
+        let mut liked = self.liked.write().unwrap();
+        let mut pending = self.pending_liked.lock().unwrap();
+        for id in tracks.iter().filter_map(|t| t.id.clone()) {
+            pending.remove(&id);
+            liked.insert(id, saved);
+        }
+    }
+
+    /// Save `tracks`, to Spotify as well with `api`. Returns whether they
+    /// were saved.
+    pub fn save_tracks(&self, tracks: Vec<&Track>, api: bool) -> bool {
         if !*self.is_done.read().unwrap() {
-            return;
+            return false;
         }
 
         if api
@@ -552,9 +1003,10 @@ impl Library {
                 )
                 .is_none()
         {
-            return;
+            return false;
         }
 
+        self.set_known_liked(&tracks, true);
         {
             let mut store = self.tracks.write().unwrap();
             let mut i = 0;
@@ -572,11 +1024,14 @@ impl Library {
 
         self.save_cache(config::cache_path(CACHE_TRACKS), self.tracks.clone());
         self.save_cache(config::cache_path(CACHE_ARTISTS), self.artists.clone());
+        true
     }
 
-    pub fn unsave_tracks(&self, tracks: Vec<&Track>, api: bool) {
+    /// Remove `tracks` from the saved ones, on Spotify as well with `api`.
+    /// Returns whether they were removed.
+    pub fn unsave_tracks(&self, tracks: Vec<&Track>, api: bool) -> bool {
         if !*self.is_done.read().unwrap() {
-            return;
+            return false;
         }
 
         if api
@@ -588,9 +1043,10 @@ impl Library {
                 )
                 .is_none()
         {
-            return;
+            return false;
         }
 
+        self.set_known_liked(&tracks, false);
         {
             let mut store = self.tracks.write().unwrap();
             *store = store
@@ -604,6 +1060,43 @@ impl Library {
 
         self.save_cache(config::cache_path(CACHE_TRACKS), self.tracks.clone());
         self.save_cache(config::cache_path(CACHE_ARTISTS), self.artists.clone());
+        true
+    }
+
+    /// Like `track`, adding it to the user's Liked Songs, or unlike it. The
+    /// library is changed right away, so that it shows at once, and changed
+    /// back if Spotify fails to. Returns whether Spotify changed it.
+    pub fn set_liked(&self, track: &Track, liked: bool) -> bool {
+        let id = match track.id.as_deref() {
+            Some(id) => id,
+            None => return false,
+        };
+        let changed = if liked {
+            self.save_tracks(vec![track], false)
+        } else {
+            self.unsave_tracks(vec![track], false)
+        };
+        if !changed {
+            return false;
+        }
+        self.trigger_redraw();
+
+        let api = &self.spotify.api;
+        let synced = if liked {
+            api.current_user_saved_tracks_add(vec![id])
+        } else {
+            api.current_user_saved_tracks_delete(vec![id])
+        }
+        .is_some();
+        if !synced {
+            if liked {
+                self.unsave_tracks(vec![track], false);
+            } else {
+                self.save_tracks(vec![track], false);
+            }
+            self.trigger_redraw();
+        }
+        synced
     }
 
     pub fn is_saved_album(&self, album: &Album) -> bool {
@@ -747,9 +1240,8 @@ impl Library {
     }
 
     pub fn is_followed_playlist(&self, playlist: &Playlist) -> bool {
-        self.user_id
-            .as_ref()
-            .map(|id| id != &playlist.owner_id)
+        self.user_id()
+            .map(|id| id != playlist.owner_id)
             .unwrap_or(false)
     }
 
@@ -789,35 +1281,157 @@ impl Library {
         shows.iter().any(|s| s.id == show.id)
     }
 
-    pub fn save_show(&self, show: &Show) {
+    /// Save `show`, to Spotify as well. Returns whether it was saved.
+    pub fn save_show(&self, show: &Show) -> bool {
         if !*self.is_done.read().unwrap() {
-            return;
+            return false;
         }
 
-        if self.spotify.api.save_shows(vec![show.id.as_str()]) {
-            {
-                let mut store = self.shows.write().unwrap();
-                if !store.iter().any(|s| s.id == show.id) {
-                    store.insert(0, show.clone());
-                }
-            }
+        if !self.spotify.api.save_shows(vec![show.id.as_str()]) {
+            return false;
         }
+        let mut store = self.shows.write().unwrap();
+        if !store.iter().any(|s| s.id == show.id) {
+            store.insert(0, show.clone());
+        }
+        true
     }
 
-    pub fn unsave_show(&self, show: &Show) {
+    /// Remove `show` from the saved ones, on Spotify as well. Returns whether
+    /// it was removed.
+    pub fn unsave_show(&self, show: &Show) -> bool {
         if !*self.is_done.read().unwrap() {
-            return;
+            return false;
         }
 
-        if self.spotify.api.unsave_shows(vec![show.id.as_str()]) {
-            {
-                let mut store = self.shows.write().unwrap();
-                *store = store.iter().filter(|s| s.id != show.id).cloned().collect();
-            }
+        if !self.spotify.api.unsave_shows(vec![show.id.as_str()]) {
+            return false;
         }
+        let mut store = self.shows.write().unwrap();
+        *store = store.iter().filter(|s| s.id != show.id).cloned().collect();
+        true
     }
 
     pub fn trigger_redraw(&self) {
         self.ev.trigger();
     }
 }
+
+/// Bring `store` up to date with the items the user saved, which
+/// `fetch_page` fetches newest first from an offset, as the items along
+/// with the total number of items and whether there are more. Pages are
+/// only fetched until an item is reached that is stored with the same
+/// `key`, its ID and the time it was saved. If items are missing after
+/// that, the stored items are checked with `contains` to drop those that
+/// were removed.
+fn sync_saved<T: Clone>(
+    store: &RwLock<Vec<T>>,
+    key: impl Fn(&T) -> (Option<String>, Option<DateTime<Utc>>),
+    fetch_page: impl Fn(u32) -> Option<(Vec<T>, u32, bool)>,
+    contains: impl Fn(Vec<&str>) -> Option<Vec<bool>>,
+) -> Option<()> {
+    let known: HashMap<String, Option<DateTime<Utc>>> = store
+        .read()
+        .unwrap()
+        .iter()
+        .filter_map(|item| {
+            let (id, added_at) = key(item);
+            id.map(|id| (id, added_at))
+        })
+        .collect();
+
+    let mut added: Vec<T> = Vec::new();
+    let total = loop {
+        let (items, total, more) = fetch_page(added.len() as u32)?;
+        let mut caught_up = !more || items.is_empty();
+        for item in items {
+            let (id, added_at) = key(&item);
+            if id.and_then(|id| known.get(&id)) == Some(&added_at) {
+                caught_up = true;
+                break;
+            }
+            added.push(item);
+        }
+        if caught_up {
+            break total as usize;
+        }
+    };
+
+    // Items that were saved again are moved to the front
+    let added_ids: HashSet<String> = added.iter().filter_map(|item| key(item).0).collect();
+    let mut kept: Vec<T> = store
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|item| key(item).0.map_or(true, |id| !added_ids.contains(&id)))
+        .cloned()
+        .collect();
+
+    if added.len() + kept.len() != total {
+        debug!("checking {} saved items for removals", kept.len());
+        let ids: Vec<String> = kept.iter().filter_map(|item| key(item).0).collect();
+        let mut removed: HashSet<String> = HashSet::new();
+        for batch in ids.chunks(CONTAINS_BATCH_SIZE) {
+            let saved = contains(batch.iter().map(String::as_str).collect())?;
+            removed.extend(
+                batch
+                    .iter()
+                    .zip(saved)
+                    .filter(|(_, saved)| !saved)
+                    .map(|(id, _)| id.clone()),
+            );
+        }
+        kept.retain(|item| key(item).0.map_or(true, |id| !removed.contains(&id)));
+    }
+
+    debug!("{} items were saved since the last sync", added.len());
+    added.extend(kept);
+    *store.write().unwrap() = added;
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::RwLock;
+
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use super::sync_saved;
+
+    type Item = (String, DateTime<Utc>);
+
+    fn item(id: &str, day: u32) -> Item {
+        (
+            id.to_string(),
+            Utc.with_ymd_and_hms(2023, 1, day, 0, 0, 0).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_sync_saved() {
+        let store = RwLock::new(vec![item("c", 3), item("b", 2), item("a", 1)]);
+        // "d" was saved, "b" was removed and "a" was saved again
+        let remote = vec![item("a", 5), item("d", 4), item("c", 3)];
+        let fetched = RwLock::new(0);
+        let result = sync_saved(
+            &store,
+            |(id, added_at)| (Some(id.clone()), Some(*added_at)),
+            |offset| {
+                *fetched.write().unwrap() += 1;
+                let page: Vec<Item> = remote
+                    .iter()
+                    .skip(offset as usize)
+                    .take(2)
+                    .cloned()
+                    .collect();
+                let more = offset as usize + page.len() < remote.len();
+                Some((page, remote.len() as u32, more))
+            },
+            |ids| Some(ids.iter().map(|id| *id != "b").collect()),
+        );
+
+        assert_eq!(result, Some(()));
+        assert_eq!(*store.read().unwrap(), remote);
+        assert_eq!(*fetched.read().unwrap(), 2);
+    }
+}
@@ -0,0 +1,339 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, TryRecvError};
+
+use lewton::inside_ogg::OggStreamReader;
+use librespot_playback::audio_backend::Sink;
+use librespot_playback::convert::Converter;
+use librespot_playback::decoder::{AudioDecoder, AudioPacket, VorbisDecoder};
+use librespot_playback::mixer::VolumeGetter;
+use librespot_playback::{NUM_CHANNELS, SAMPLE_RATE};
+use log::{debug, error, warn};
+use tokio::sync::mpsc as tokio_mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+/// The URI prefix of local files.
+pub const FILE_URI_PREFIX: &str = "file://";
+
+/// How much of the end of a file is searched for the last Ogg page, which
+/// holds the length of the stream.
+const LAST_PAGE_SEARCH_LENGTH: u64 = 64 * 1024;
+
+/// The path of the local file with the given URI, if it is one.
+pub fn local_path(uri: &str) -> Option<PathBuf> {
+    uri.strip_prefix(FILE_URI_PREFIX).map(PathBuf::from)
+}
+
+/// What is known about a local file before playing it.
+pub struct LocalFile {
+    pub title: Option<String>,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    pub duration_ms: u32,
+}
+
+impl LocalFile {
+    /// Read the headers of the Ogg Vorbis file at `path`. Only files that the
+    /// audio backend can play as they are, at librespot's sample rate and
+    /// number of channels, are accepted.
+    pub fn probe(path: &Path) -> Result<LocalFile, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let reader = OggStreamReader::new(file)
+            .map_err(|e| format!("{} is not an Ogg Vorbis file: {e}", path.display()))?;
+        let ident = &reader.ident_hdr;
+        if ident.audio_sample_rate != SAMPLE_RATE || ident.audio_channels != NUM_CHANNELS {
+            return Err(format!(
+                "{} has {} channels at {} Hz, only {} channels at {} Hz can be played",
+                path.display(),
+                ident.audio_channels,
+                ident.audio_sample_rate,
+                NUM_CHANNELS,
+                SAMPLE_RATE
+            ));
+        }
+
+        let comment = |key: &str| {
+            reader
+                .comment_hdr
+                .comment_list
+                .iter()
+                .filter(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, value)| value.clone())
+                .collect::<Vec<String>>()
+        };
+        let title = comment("TITLE").into_iter().next();
+        let artists = comment("ARTIST");
+        let album = comment("ALBUM").into_iter().next();
+
+        let mut file = reader.into_inner().into_inner();
+        let samples = last_granule_position(&mut file).map_err(|e| e.to_string())?;
+        Ok(LocalFile {
+            title,
+            artists,
+            album,
+            duration_ms: (samples * 1000 / SAMPLE_RATE as u64) as u32,
+        })
+    }
+}
+
+/// The granule position of the last page of an Ogg stream, which is the
+/// number of samples per channel in the stream.
+fn last_granule_position<R: Read + Seek>(reader: &mut R) -> io::Result<u64> {
+    let length = reader.seek(SeekFrom::End(0))?;
+    let start = length.saturating_sub(LAST_PAGE_SEARCH_LENGTH);
+    reader.seek(SeekFrom::Start(start))?;
+    let mut tail = Vec::new();
+    reader.read_to_end(&mut tail)?;
+    last_page_granule(&tail)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no Ogg page found"))
+}
+
+/// The granule position of the last complete Ogg page in `data`, skipping
+/// pages without one.
+fn last_page_granule(data: &[u8]) -> Option<u64> {
+    (0..data.len().saturating_sub(13)).rev().find_map(|i| {
+        if &data[i..i + 4] != b"OggS" {
+            return None;
+        }
+        let mut granule = [0u8; 8];
+        granule.copy_from_slice(&data[i + 6..i + 14]);
+        Some(u64::from_le_bytes(granule)).filter(|&granule| granule != u64::MAX)
+    })
+}
+
+pub enum LocalPlayerEvent {
+    Playing {
+        position_ms: u32,
+        duration_ms: u32,
+    },
+    Paused {
+        position_ms: u32,
+        duration_ms: u32,
+    },
+    EndOfTrack,
+    /// The file couldn't be opened or decoded.
+    Unavailable,
+}
+
+enum LocalCommand {
+    Play,
+    Pause,
+    Seek(u32),
+}
+
+/// Plays a local Ogg Vorbis file on its own thread, through the same audio
+/// backend as librespot's player and reporting the same events. Playback
+/// stops when the player is dropped.
+pub struct LocalPlayer {
+    commands: mpsc::Sender<LocalCommand>,
+    pub events: UnboundedReceiverStream<LocalPlayerEvent>,
+}
+
+impl LocalPlayer {
+    pub fn new<F>(
+        path: PathBuf,
+        start_playing: bool,
+        position_ms: u32,
+        sink_builder: F,
+        volume: Box<dyn VolumeGetter + Send>,
+    ) -> LocalPlayer
+    where
+        F: FnOnce() -> Box<dyn Sink> + Send + 'static,
+    {
+        let (commands, commands_rx) = mpsc::channel();
+        let (events_tx, events) = tokio_mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            let sink = sink_builder();
+            let state = PlayState {
+                commands: commands_rx,
+                events: events_tx,
+                sink,
+                volume,
+            };
+            state.run(&path, start_playing, position_ms);
+        });
+        LocalPlayer {
+            commands,
+            events: UnboundedReceiverStream::new(events),
+        }
+    }
+
+    pub fn play(&self) {
+        let _ = self.commands.send(LocalCommand::Play);
+    }
+
+    pub fn pause(&self) {
+        let _ = self.commands.send(LocalCommand::Pause);
+    }
+
+    pub fn seek(&self, position_ms: u32) {
+        let _ = self.commands.send(LocalCommand::Seek(position_ms));
+    }
+}
+
+/// The state of the playback thread of a [LocalPlayer].
+struct PlayState {
+    commands: mpsc::Receiver<LocalCommand>,
+    events: tokio_mpsc::UnboundedSender<LocalPlayerEvent>,
+    sink: Box<dyn Sink>,
+    volume: Box<dyn VolumeGetter + Send>,
+}
+
+impl PlayState {
+    fn run(mut self, path: &Path, start_playing: bool, position_ms: u32) {
+        let (duration_ms, mut decoder) = match LocalFile::probe(path).and_then(|file| {
+            let input = File::open(path).map_err(|e| e.to_string())?;
+            let decoder = VorbisDecoder::new(input).map_err(|e| e.to_string())?;
+            Ok((file.duration_ms, decoder))
+        }) {
+            Ok(opened) => opened,
+            Err(e) => {
+                warn!("could not play {}: {}", path.display(), e);
+                self.send(LocalPlayerEvent::Unavailable);
+                return;
+            }
+        };
+
+        let mut converter = Converter::new(None);
+        // The position in samples per channel.
+        let mut position = 0;
+        let mut playing = false;
+        let mut command = Some(LocalCommand::Seek(position_ms));
+        if start_playing {
+            playing = true;
+            self.start();
+        }
+
+        loop {
+            match command.take() {
+                Some(LocalCommand::Play) if !playing => {
+                    playing = true;
+                    self.start();
+                    self.send_state(playing, position, duration_ms);
+                }
+                Some(LocalCommand::Pause) if playing => {
+                    playing = false;
+                    self.stop();
+                    self.send_state(playing, position, duration_ms);
+                }
+                Some(LocalCommand::Seek(target)) => {
+                    let target = target.min(duration_ms) as u64 * SAMPLE_RATE as u64 / 1000;
+                    match decoder.seek(target) {
+                        Ok(()) => position = target,
+                        Err(e) => warn!("could not seek in {}: {}", path.display(), e),
+                    }
+                    self.send_state(playing, position, duration_ms);
+                }
+                _ => {}
+            }
+
+            if !playing {
+                match self.commands.recv() {
+                    Ok(next) => command = Some(next),
+                    Err(_) => break,
+                }
+                continue;
+            }
+
+            match decoder.next_packet() {
+                Ok(Some(AudioPacket::Samples(mut samples))) => {
+                    let factor = self.volume.attenuation_factor();
+                    for sample in samples.iter_mut() {
+                        *sample *= factor;
+                    }
+                    position += (samples.len() / NUM_CHANNELS as usize) as u64;
+                    if let Err(e) = self
+                        .sink
+                        .write(AudioPacket::Samples(samples), &mut converter)
+                    {
+                        error!("could not write to the audio backend: {}", e);
+                        self.send(LocalPlayerEvent::Unavailable);
+                        break;
+                    }
+                }
+                Ok(Some(AudioPacket::OggData(_))) => {}
+                Ok(None) => {
+                    debug!("end of {}", path.display());
+                    self.stop();
+                    self.send(LocalPlayerEvent::EndOfTrack);
+                    break;
+                }
+                Err(e) => {
+                    warn!("could not decode {}: {}", path.display(), e);
+                    self.stop();
+                    self.send(LocalPlayerEvent::Unavailable);
+                    break;
+                }
+            }
+
+            match self.commands.try_recv() {
+                Ok(next) => command = Some(next),
+                Err(TryRecvError::Empty) => {}
+                // The player was dropped
+                Err(TryRecvError::Disconnected) => {
+                    self.stop();
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Report whether the file is playing at `position` samples.
+    fn send_state(&self, playing: bool, position: u64, duration_ms: u32) {
+        let position_ms = (position * 1000 / SAMPLE_RATE as u64) as u32;
+        self.send(if playing {
+            LocalPlayerEvent::Playing {
+                position_ms,
+                duration_ms,
+            }
+        } else {
+            LocalPlayerEvent::Paused {
+                position_ms,
+                duration_ms,
+            }
+        });
+    }
+
+    fn start(&mut self) {
+        if let Err(e) = self.sink.start() {
+            error!("could not start the audio backend: {}", e);
+        }
+    }
+
+    fn stop(&mut self) {
+        if let Err(e) = self.sink.stop() {
+            error!("could not stop the audio backend: {}", e);
+        }
+    }
+
+    fn send(&self, event: LocalPlayerEvent) {
+        let _ = self.events.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::last_page_granule;
+
+    /// The start of an Ogg page header with the given granule position.
+    fn page(granule: u64) -> Vec<u8> {
+        let mut page = b"OggS\0\0".to_vec();
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&[0; 12]);
+        page
+    }
+
+    #[test]
+    fn test_last_page_granule() {
+        let mut data = page(1000);
+        data.extend(page(44100));
+        assert_eq!(last_page_granule(&data), Some(44100));
+
+        // pages that don't end a packet have no granule position
+        data.extend(page(u64::MAX));
+        assert_eq!(last_page_granule(&data), Some(44100));
+
+        assert_eq!(last_page_granule(b"not an Ogg stream"), None);
+    }
+}
@@ -0,0 +1,222 @@
+use std::time::Duration;
+
+use log::{debug, warn};
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::{cache_path, LyricsProvider};
+use crate::model::track::Track;
+use crate::serialization::{Serializer, JSON};
+use crate::spotify_api::WebApi;
+
+const LYRICS_CACHE_DIR: &str = "lyrics";
+const LRCLIB_URL: &str = "https://lrclib.net/api/get";
+const SPOTIFY_LYRICS_URL: &str = "https://spclient.wg.spotify.com/color-lyrics/v2/track";
+
+/// A line of lyrics, with the time it is sung at if they are synced.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Line {
+    pub time_ms: Option<u32>,
+    pub text: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Lyrics {
+    Lines(Vec<Line>),
+    Instrumental,
+    NotFound,
+}
+
+impl Lyrics {
+    /// Whether every line has the time it is sung at.
+    pub fn synced(&self) -> bool {
+        match self {
+            Lyrics::Lines(lines) => lines.iter().all(|line| line.time_ms.is_some()),
+            _ => false,
+        }
+    }
+
+    /// The index of the line that is sung at `position_ms`, if synced.
+    pub fn current_line(&self, position_ms: u32) -> Option<usize> {
+        match self {
+            Lyrics::Lines(lines) if self.synced() => lines
+                .iter()
+                .rposition(|line| line.time_ms.unwrap_or(0) <= position_ms),
+            _ => None,
+        }
+    }
+}
+
+/// Fetch the lyrics of `track` from `provider`, or from the cache if they
+/// were fetched before. Only found lyrics are cached, so tracks without
+/// lyrics are looked up again later.
+pub fn fetch(api: &WebApi, track: &Track, provider: LyricsProvider) -> Result<Lyrics, String> {
+    let cache_file = track
+        .id
+        .as_ref()
+        .map(|id| cache_path(LYRICS_CACHE_DIR).join(format!("{id}.json")));
+    if let Some(Ok(lyrics)) = cache_file.as_ref().map(|path| JSON.load(path)) {
+        return Ok(lyrics);
+    }
+
+    let client = Client::builder()
+        .user_agent(concat!("ncspot/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let lyrics = match provider {
+        LyricsProvider::Spotify => fetch_spotify(&client, api, track)?,
+        LyricsProvider::Lrclib => fetch_lrclib(&client, track)?,
+        LyricsProvider::Auto => match fetch_spotify(&client, api, track) {
+            Ok(Lyrics::NotFound) => fetch_lrclib(&client, track)?,
+            Ok(lyrics) => lyrics,
+            Err(e) => {
+                debug!("falling back to lrclib.net: {}", e);
+                fetch_lrclib(&client, track)?
+            }
+        },
+    };
+
+    if let (Some(path), Lyrics::Lines(_) | Lyrics::Instrumental) = (cache_file, &lyrics) {
+        if let Err(e) = std::fs::create_dir_all(cache_path(LYRICS_CACHE_DIR))
+            .map_err(|e| e.to_string())
+            .and_then(|_| JSON.write(path, &lyrics).map(|_| ()))
+        {
+            warn!("could not cache lyrics: {}", e);
+        }
+    }
+    Ok(lyrics)
+}
+
+/// Fetch lyrics from the endpoint of the Spotify clients, which only tokens
+/// of some accounts may use. Those that may not are told to fall back.
+fn fetch_spotify(client: &Client, api: &WebApi, track: &Track) -> Result<Lyrics, String> {
+    let id = track.id.as_ref().ok_or("the track has no Spotify ID")?;
+    let token = api.access_token().ok_or("no access token")?;
+    let response = client
+        .get(format!("{SPOTIFY_LYRICS_URL}/{id}"))
+        .query(&[("format", "json"), ("market", "from_token")])
+        .bearer_auth(token)
+        .header("App-Platform", "WebPlayer")
+        .send()
+        .map_err(|e| e.to_string())?;
+    match response.status() {
+        StatusCode::NOT_FOUND => return Ok(Lyrics::NotFound),
+        status if !status.is_success() => return Err(format!("Spotify answered {status}")),
+        _ => {}
+    }
+
+    let body: Value = response.json().map_err(|e| e.to_string())?;
+    let synced = body["lyrics"]["syncType"].as_str() == Some("LINE_SYNCED");
+    let lines: Vec<Line> = body["lyrics"]["lines"]
+        .as_array()
+        .ok_or("Spotify sent no lines")?
+        .iter()
+        .map(|line| Line {
+            time_ms: line["startTimeMs"]
+                .as_str()
+                .and_then(|time| time.parse().ok())
+                .filter(|_| synced),
+            text: line["words"].as_str().unwrap_or_default().to_string(),
+        })
+        .collect();
+    Ok(match lines.is_empty() {
+        true => Lyrics::NotFound,
+        false => Lyrics::Lines(lines),
+    })
+}
+
+fn fetch_lrclib(client: &Client, track: &Track) -> Result<Lyrics, String> {
+    let response = client
+        .get(LRCLIB_URL)
+        .query(&[
+            ("track_name", track.title.clone()),
+            ("artist_name", track.artists.join(", ")),
+            ("album_name", track.album.clone().unwrap_or_default()),
+            ("duration", (track.duration / 1000).to_string()),
+        ])
+        .send()
+        .map_err(|e| e.to_string())?;
+    match response.status() {
+        StatusCode::NOT_FOUND => return Ok(Lyrics::NotFound),
+        status if !status.is_success() => return Err(format!("lrclib.net answered {status}")),
+        _ => {}
+    }
+
+    let body: Value = response.json().map_err(|e| e.to_string())?;
+    if body["instrumental"].as_bool() == Some(true) {
+        return Ok(Lyrics::Instrumental);
+    }
+    let lines = match (body["syncedLyrics"].as_str(), body["plainLyrics"].as_str()) {
+        (Some(synced), _) if !synced.trim().is_empty() => parse_lrc(synced),
+        (_, Some(plain)) => plain
+            .lines()
+            .map(|text| Line {
+                time_ms: None,
+                text: text.to_string(),
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    Ok(match lines.is_empty() {
+        true => Lyrics::NotFound,
+        false => Lyrics::Lines(lines),
+    })
+}
+
+/// Parse lyrics in the LRC format, where lines start with one or more
+/// `[mm:ss.xx]` tags. Lines without a time, like the `[ar:...]` tags of
+/// metadata, are skipped.
+fn parse_lrc(lrc: &str) -> Vec<Line> {
+    let mut lines = Vec::new();
+    for line in lrc.lines() {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+        while let Some(tag) = rest.strip_prefix('[') {
+            let (tag, after) = match tag.split_once(']') {
+                Some(split) => split,
+                None => break,
+            };
+            match parse_lrc_time(tag) {
+                Some(time) => times.push(time),
+                None => break,
+            }
+            rest = after;
+        }
+        lines.extend(times.into_iter().map(|time| Line {
+            time_ms: Some(time),
+            text: rest.trim().to_string(),
+        }));
+    }
+    lines.sort_by_key(|line| line.time_ms);
+    lines
+}
+
+fn parse_lrc_time(tag: &str) -> Option<u32> {
+    let (minutes, seconds) = tag.split_once(':')?;
+    let minutes: u32 = minutes.parse().ok()?;
+    let seconds: f64 = seconds.parse().ok()?;
+    Some(minutes * 60_000 + (seconds * 1000.0).round() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lrc_is_parsed_and_sorted() {
+        let lines = parse_lrc(
+            "[ar:Queen]\n[00:01.50]Is this the real life?\n[00:05.00][01:02.25]Is this just fantasy?\n[00:03]\n",
+        );
+        let times: Vec<Option<u32>> = lines.iter().map(|line| line.time_ms).collect();
+        assert_eq!(times, vec![Some(1500), Some(3000), Some(5000), Some(62250)]);
+        assert_eq!(lines[2].text, "Is this just fantasy?");
+
+        let lyrics = Lyrics::Lines(lines);
+        assert!(lyrics.synced());
+        assert_eq!(lyrics.current_line(1000), None);
+        assert_eq!(lyrics.current_line(4000), Some(1));
+    }
+}
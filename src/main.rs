@@ -11,6 +11,7 @@ use std::io::Write;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use clap::{Arg, Command as ClapCommand};
 use cursive::event::EventTrigger;
@@ -23,26 +24,43 @@ use log::{error, info, trace};
 #[cfg(unix)]
 use signal_hook::{consts::SIGHUP, consts::SIGTERM, iterator::Signals};
 
+mod accounts;
 mod authentication;
 mod command;
 mod commands;
 mod config;
+#[cfg(feature = "cover")]
+mod covers;
 mod events;
 mod ext_traits;
+mod history;
+mod hooks;
 mod library;
+mod local_player;
+mod lyrics;
 mod model;
+mod play_counts;
+mod playback_speed;
 mod queue;
+mod radio;
+mod scrobbler;
+mod search_history;
+mod search_query;
 mod serialization;
 mod sharing;
+mod silence;
 mod spotify;
 mod spotify_api;
 mod spotify_url;
 mod spotify_worker;
+mod template;
 mod theme;
 mod traits;
 mod ui;
 mod utils;
 
+#[cfg(unix)]
+mod discord;
 #[cfg(unix)]
 mod ipc;
 
@@ -51,11 +69,12 @@ mod mpris;
 
 use crate::command::{Command, JumpMode};
 use crate::commands::CommandManager;
-use crate::config::{cache_path, Config};
+use crate::config::{cache_path, Config, SearchBarMode};
 use crate::events::{Event, EventManager};
 use crate::ext_traits::CursiveExt;
 use crate::library::Library;
-use crate::spotify::PlayerEvent;
+use crate::queue::QueueEvent;
+use crate::spotify::{DisconnectReason, PlayerEvent};
 use crate::ui::contextmenu::ContextMenu;
 
 fn setup_logging(filename: &str) -> Result<(), fern::InitError> {
@@ -127,6 +146,9 @@ lazy_static!(
         .unwrap();
 );
 
+/// How often the queue state is saved while playing.
+const STATE_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
 fn main() -> Result<(), String> {
     register_backtrace_panic_handler();
 
@@ -228,10 +250,13 @@ fn main() -> Result<(), String> {
 
     let library = Arc::new(Library::new(&event_manager, spotify.clone(), cfg.clone()));
 
+    let history = Arc::new(history::History::new(&cfg));
+
     let queue = Arc::new(queue::Queue::new(
         spotify.clone(),
         cfg.clone(),
         library.clone(),
+        history.clone(),
     ));
 
     #[cfg(feature = "mpris")]
@@ -242,12 +267,30 @@ fn main() -> Result<(), String> {
         library.clone(),
     ));
 
+    let search_history = Arc::new(search_history::SearchHistory::new(&cfg));
+
+    let play_counts = Arc::new(play_counts::PlayCounts::new());
+
+    let mut scrobbler = cfg.values().lastfm.clone().map(scrobbler::Scrobbler::new);
+
+    let mut hooks = cfg.values().hooks.clone().map(hooks::Hooks::new);
+
+    #[cfg(unix)]
+    let discord = cfg
+        .values()
+        .discord_client_id
+        .clone()
+        .map(discord::DiscordPresence::new);
+
     let mut cmd_manager = CommandManager::new(
         spotify.clone(),
         queue.clone(),
         library.clone(),
         cfg.clone(),
         event_manager.clone(),
+        play_counts.clone(),
+        history.clone(),
+        search_history.clone(),
     );
 
     cmd_manager.register_all();
@@ -256,21 +299,30 @@ fn main() -> Result<(), String> {
     let user_data: UserData = Arc::new(UserDataInner { cmd: cmd_manager });
     cursive.set_user_data(user_data);
 
-    let search = ui::search::SearchView::new(event_manager.clone(), queue.clone(), library.clone());
+    let search = ui::search::SearchView::new(
+        event_manager.clone(),
+        queue.clone(),
+        library.clone(),
+        search_history,
+    );
 
-    let libraryview = ui::library::LibraryView::new(queue.clone(), library.clone());
+    let libraryview =
+        ui::library::LibraryView::new(queue.clone(), library.clone(), history.clone());
 
     let queueview = ui::queue::QueueView::new(queue.clone(), library.clone());
 
+    let lyricsview = ui::lyrics::LyricsView::new(queue.clone(), library.clone());
+
     #[cfg(feature = "cover")]
     let coverview = ui::cover::CoverView::new(queue.clone(), library.clone(), &cfg);
 
-    let status = ui::statusbar::StatusBar::new(queue.clone(), library);
+    let status = ui::statusbar::StatusBar::new(queue.clone(), library.clone());
 
     let mut layout = ui::layout::Layout::new(status, &event_manager, theme)
         .screen("search", search.with_name("search"))
         .screen("library", libraryview.with_name("library"))
-        .screen("queue", queueview);
+        .screen("queue", queueview)
+        .screen("lyrics", lyricsview.with_name("lyrics"));
 
     #[cfg(feature = "cover")]
     layout.add_screen("cover", coverview.with_name("cover"));
@@ -288,6 +340,13 @@ fn main() -> Result<(), String> {
         layout.set_screen("library");
     }
 
+    // The search bar filters the list while typing, unless it only jumps to
+    // the items that match.
+    let filters = {
+        let cfg = cfg.clone();
+        move || cfg.values().search_bar_mode.unwrap_or_default() == SearchBarMode::Filter
+    };
+
     let cmd_key = |cfg: Arc<Config>| cfg.values().command_key.unwrap_or(':');
 
     {
@@ -314,21 +373,42 @@ fn main() -> Result<(), String> {
         }
     });
 
-    cursive.add_global_callback(cursive::event::Key::Esc, move |s| {
-        if s.find_name::<ContextMenu>("contextmenu").is_none() {
+    {
+        let filters = filters.clone();
+        cursive.add_global_callback(cursive::event::Key::Esc, move |s| {
+            if s.find_name::<ContextMenu>("contextmenu").is_none() {
+                let searching = s
+                    .call_on_name("main", |v: &mut ui::layout::Layout| {
+                        let searching = v.cmdline.get_content().starts_with('/');
+                        v.clear_cmdline();
+                        searching
+                    })
+                    .unwrap_or(false);
+                // Show the whole list again
+                if searching && filters() {
+                    if let Some(data) = s.user_data::<UserData>().cloned() {
+                        data.cmd.handle(s, Command::Filter(String::new()));
+                    }
+                }
+            }
+        });
+    }
+
+    {
+        let filters = filters.clone();
+        layout.cmdline.set_on_edit(move |s, cmd, _| {
             s.call_on_name("main", |v: &mut ui::layout::Layout| {
-                v.clear_cmdline();
+                if cmd.is_empty() {
+                    v.clear_cmdline();
+                }
             });
-        }
-    });
-
-    layout.cmdline.set_on_edit(move |s, cmd, _| {
-        s.call_on_name("main", |v: &mut ui::layout::Layout| {
-            if cmd.is_empty() {
-                v.clear_cmdline();
+            if let Some(text) = cmd.strip_prefix('/').filter(|_| filters()) {
+                if let Some(data) = s.user_data::<UserData>().cloned() {
+                    data.cmd.handle(s, Command::Filter(text.to_string()));
+                }
             }
         });
-    });
+    }
 
     {
         let ev = event_manager.clone();
@@ -336,7 +416,12 @@ fn main() -> Result<(), String> {
             s.on_layout(|_, mut layout| layout.clear_cmdline());
             let cmd_without_prefix = &cmd[1..];
             if cmd.strip_prefix('/').is_some() {
-                let command = Command::Jump(JumpMode::Query(cmd_without_prefix.to_string()));
+                // The filter that was typed stays applied
+                let command = if filters() {
+                    Command::Filter(cmd_without_prefix.to_string())
+                } else {
+                    Command::Jump(JumpMode::Query(cmd_without_prefix.to_string()))
+                };
                 if let Some(data) = s.user_data::<UserData>().cloned() {
                     data.cmd.handle(s, command);
                 }
@@ -373,14 +458,27 @@ fn main() -> Result<(), String> {
         ipc::IpcSocket::new(
             ASYNC_RUNTIME.handle(),
             cache_path("ncspot.sock"),
+            cache_path("ncspot-events.sock"),
             event_manager.clone(),
         )
         .map_err(|e| e.to_string())?
     };
 
+    // Save the queue state periodically while playing or after it changed, so
+    // neither the queue nor the position in long items is lost if ncspot
+    // doesn't quit cleanly.
+    let mut last_state_save = Instant::now();
+
     // cursive event loop
     while cursive.is_running() {
         cursive.step();
+        if last_state_save.elapsed() >= STATE_SAVE_INTERVAL {
+            let playing = matches!(spotify.get_current_status(), PlayerEvent::Playing(_));
+            if playing || queue.is_modified() {
+                queue.save_state();
+            }
+            last_state_save = Instant::now();
+        }
         #[cfg(unix)]
         for signal in signals.pending() {
             if signal == SIGTERM || signal == SIGHUP {
@@ -392,6 +490,11 @@ fn main() -> Result<(), String> {
         }
         for event in event_manager.msg_iter() {
             match event {
+                Event::Player(state @ PlayerEvent::Progress { .. }) => {
+                    // Only of interest to the subscribers of the event stream
+                    #[cfg(unix)]
+                    ipc.broadcast(&state, &spotify.get_current_status(), queue.get_current());
+                }
                 Event::Player(state) => {
                     trace!("event received: {:?}", state);
                     spotify.update_status(state.clone());
@@ -399,17 +502,124 @@ fn main() -> Result<(), String> {
                     #[cfg(feature = "mpris")]
                     mpris_manager.update();
 
+                    // The item the player reported is known before the queue
+                    // may have caught up with it, e.g. after a skip.
+                    let current = match state {
+                        PlayerEvent::TrackStarted(_)
+                        | PlayerEvent::Playing(_)
+                        | PlayerEvent::Paused(_) => {
+                            spotify.get_started().or_else(|| queue.get_current())
+                        }
+                        _ => queue.get_current(),
+                    };
+
+                    history.update(&state, current.as_ref());
+                    play_counts.update(&state, current.as_ref());
+
+                    if let Some(scrobbler) = scrobbler.as_mut() {
+                        scrobbler.update(&state, current.as_ref());
+                    }
+
+                    if let Some(hooks) = hooks.as_mut() {
+                        hooks.update(&state, current.as_ref());
+                    }
+
                     #[cfg(unix)]
-                    ipc.publish(&state, queue.get_current());
+                    if let Some(discord) = discord.as_ref() {
+                        discord.update(&state, current.as_ref());
+                    }
 
-                    if state == PlayerEvent::FinishedTrack {
-                        queue.next(false);
+                    #[cfg(unix)]
+                    ipc.publish(
+                        &spotify.get_current_status(),
+                        current.clone(),
+                        spotify.sleep_timer(),
+                        spotify.is_muted(),
+                        queue.get_stop_after_track(),
+                    );
+                    #[cfg(unix)]
+                    ipc.broadcast(&state, &spotify.get_current_status(), current);
+
+                    match state {
+                        PlayerEvent::FinishedTrack => queue.next(false),
+                        PlayerEvent::Playing(_) => queue.reset_unavailable_skips(),
+                        PlayerEvent::Reconnecting(attempt) => {
+                            cursive.on_layout(|_, mut layout| {
+                                layout.set_result(Err(format!(
+                                    "Connection lost, reconnecting (attempt {attempt})…"
+                                )))
+                            });
+                        }
+                        PlayerEvent::Disconnected(reason) => {
+                            let reason = match reason {
+                                DisconnectReason::Requested => None,
+                                DisconnectReason::ConnectionClosed => {
+                                    Some("the connection was closed")
+                                }
+                                DisconnectReason::PlayerDied => Some("the player stopped"),
+                            };
+                            if let Some(reason) = reason {
+                                cursive.on_layout(|_, mut layout| {
+                                    layout.set_result(Err(format!(
+                                        "Disconnected from Spotify because {reason}, reconnecting…"
+                                    )))
+                                });
+                            }
+                        }
+                        PlayerEvent::ConnectionLost => {
+                            cursive.on_layout(|_, mut layout| {
+                                layout.set_result(Err(
+                                    "Could not reconnect, use :reconnect to try again".into(),
+                                ))
+                            });
+                        }
+                        PlayerEvent::Unavailable(uri) => {
+                            let name = queue
+                                .get_current()
+                                .map(|playable| playable.to_string())
+                                .unwrap_or(uri);
+                            cursive.on_layout(|_, mut layout| {
+                                layout.set_result(Err(format!("Skipped unavailable track: {name}")))
+                            });
+                            queue.skip_unavailable();
+                        }
+                        _ => (),
                     }
                 }
                 Event::Queue(event) => {
+                    if event == QueueEvent::Finished {
+                        cursive.on_layout(|_, mut layout| {
+                            layout.set_result(Ok(Some("Queue finished".into())))
+                        });
+                        #[cfg(unix)]
+                        ipc.broadcast_queue(
+                            &event,
+                            &spotify.get_current_status(),
+                            queue.get_current(),
+                        );
+                    }
                     queue.handle_event(event);
                 }
-                Event::SessionDied => spotify.start_worker(None),
+                Event::SessionDied if spotify.take_account_switch() => {
+                    spotify.start_worker(None);
+                    library.switch_account();
+                }
+                Event::SessionDied => {
+                    // Resume the current item where it was before the session died.
+                    let status = spotify.get_current_status();
+                    let progress = spotify.get_current_progress();
+                    spotify.start_worker(None);
+                    if let Some(playable) = queue.get_current() {
+                        match status {
+                            PlayerEvent::Playing(_) | PlayerEvent::Paused(_) => spotify.load(
+                                &playable,
+                                matches!(status, PlayerEvent::Playing(_)),
+                                progress.as_millis() as u32,
+                            ),
+                            _ => (),
+                        }
+                    }
+                }
                 Event::IpcInput(input) => match command::parse(&input) {
                     Ok(commands) => {
                         if let Some(data) = cursive.user_data::<UserData>().cloned() {
@@ -1,5 +1,6 @@
 use rand::{seq::IteratorRandom, thread_rng};
 use rspotify::model::Id;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::sync::{Arc, RwLock};
 
@@ -16,17 +17,26 @@ use crate::spotify::Spotify;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::{album::AlbumView, listview::ListView};
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct Album {
     pub id: Option<String>,
     pub title: String,
     pub artists: Vec<String>,
     pub artist_ids: Vec<String>,
     pub year: String,
+    /// The release date, as precise as Spotify knows it.
+    #[serde(default)]
+    pub release_date: Option<String>,
+    /// The record label, which only full albums tell.
+    #[serde(default)]
+    pub label: Option<String>,
     pub cover_url: Option<String>,
     pub url: Option<String>,
     pub tracks: Option<Vec<Track>>,
     pub added_at: Option<DateTime<Utc>>,
+    /// Whether this is an album, a single or a compilation.
+    #[serde(default)]
+    pub album_type: Option<String>,
     total_tracks: Option<usize>,
 }
 
@@ -39,6 +49,7 @@ impl Album {
         if let Some(ref album_id) = self.id {
             let mut collected_tracks = Vec::new();
             if let Some(full_album) = spotify.api.full_album(album_id) {
+                self.label = full_album.label.clone();
                 let mut tracks_result = Some(full_album.tracks.clone());
                 while let Some(ref tracks) = tracks_result {
                     for t in &tracks.items {
@@ -66,6 +77,46 @@ impl Album {
             self.tracks = Some(collected_tracks);
         }
     }
+
+    /// Look up how popular each of the loaded tracks is, which the tracks
+    /// of an album don't tell.
+    pub fn load_popularity(&mut self, spotify: Spotify) {
+        let tracks = match self.tracks.as_mut() {
+            Some(tracks) => tracks,
+            None => return,
+        };
+        for chunk in tracks.chunks_mut(50) {
+            let ids: Vec<String> = chunk.iter().filter_map(|t| t.id.clone()).collect();
+            let popularity: HashMap<String, u32> = spotify
+                .api
+                .tracks(&ids)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|full| Some((full.id.as_ref()?.id().to_string(), full.popularity)))
+                .collect();
+            for track in chunk {
+                track.popularity = track.id.as_ref().and_then(|id| popularity.get(id).copied());
+            }
+        }
+    }
+}
+
+/// Order `albums` by release date, newest first, and keep only the newest of
+/// albums with the same title, type and number of tracks, which are mostly
+/// re-releases for other markets.
+pub fn discography_order(albums: &mut Vec<Album>) {
+    albums.sort_by(|a, b| {
+        let date = |album: &Album| album.release_date.clone().unwrap_or(album.year.clone());
+        date(b).cmp(&date(a))
+    });
+    let mut seen = HashSet::new();
+    albums.retain(|album| {
+        seen.insert((
+            album.title.to_lowercase(),
+            album.album_type.clone(),
+            album.total_tracks,
+        ))
+    });
 }
 
 impl From<&SimplifiedAlbum> for Album {
@@ -87,10 +138,13 @@ impl From<&SimplifiedAlbum> for Album {
                 .next()
                 .unwrap()
                 .into(),
+            release_date: sa.release_date.clone(),
+            label: None,
             cover_url: sa.images.get(0).map(|i| i.url.clone()),
             url: sa.id.as_ref().map(|id| id.url()),
             tracks: None,
             added_at: None,
+            album_type: sa.album_type.clone(),
             total_tracks: None,
         }
     }
@@ -116,10 +170,13 @@ impl From<&FullAlbum> for Album {
                 .filter_map(|a| a.id.as_ref().map(|id| id.id().to_string()))
                 .collect(),
             year: fa.release_date.split('-').next().unwrap().into(),
+            release_date: Some(fa.release_date.clone()),
+            label: fa.label.clone(),
             cover_url: fa.images.get(0).map(|i| i.url.clone()),
             url: Some(fa.id.uri()),
             tracks,
             added_at: None,
+            album_type: Some(<&str>::from(fa.album_type).to_string()),
             total_tracks: Some(fa.tracks.total as usize),
         }
     }
@@ -175,13 +232,9 @@ impl ListItem for Album {
 
     fn display_right(&self, library: Arc<Library>) -> String {
         let saved = if library.is_saved_album(self) {
-            if library.cfg.values().use_nerdfont.unwrap_or(false) {
-                "\u{f62b} "
-            } else {
-                "✓ "
-            }
+            format!("{} ", library.saved_indicator())
         } else {
-            ""
+            String::new()
         };
         format!("{}{}", saved, self.year)
     }
@@ -203,9 +256,9 @@ impl ListItem for Album {
         self.load_all_tracks(queue.get_spotify());
 
         if let Some(tracks) = self.tracks.as_ref() {
-            for t in tracks.iter().rev() {
-                queue.insert_after_current(Playable::Track(t.clone()));
-            }
+            queue.insert_all_after_current(
+                tracks.iter().map(|t| Playable::Track(t.clone())).collect(),
+            );
         }
     }
 
@@ -219,6 +272,11 @@ impl ListItem for Album {
         }
     }
 
+    fn queue_album(&mut self, queue: Arc<Queue>) -> Option<Album> {
+        self.queue(queue);
+        Some(self.clone())
+    }
+
     fn toggle_saved(&mut self, library: Arc<Library>) {
         if library.is_saved_album(self) {
             library.unsave_album(self);
@@ -244,24 +302,8 @@ impl ListItem for Album {
         queue: Arc<Queue>,
         library: Arc<Library>,
     ) -> Option<Box<dyn ViewExt>> {
-        self.load_all_tracks(queue.get_spotify());
-        const MAX_SEEDS: usize = 5;
-        let track_ids: Vec<&str> = self
-            .tracks
-            .as_ref()?
-            .iter()
-            .filter_map(|t| t.id.as_deref())
-            // spotify allows at max 5 seed items, so choose 4 random tracks...
-            .choose_multiple(&mut thread_rng(), MAX_SEEDS - 1);
-
-        let artist_id: Option<String> = self
-            .artist_ids
-            .iter()
-            .cloned()
-            // ...and one artist
-            .choose(&mut thread_rng());
-
-        if track_ids.is_empty() && artist_id.is_some() {
+        let (artist_ids, track_ids) = self.recommendation_seeds(queue.clone())?;
+        if track_ids.is_empty() && !artist_ids.is_empty() {
             return None;
         }
 
@@ -269,9 +311,9 @@ impl ListItem for Album {
         let recommendations: Option<Vec<Track>> = spotify
             .api
             .recommendations(
-                artist_id.as_ref().map(|aid| vec![aid.as_str()]),
+                artist_ids.first().map(|id| vec![id.as_str()]),
                 None,
-                Some(track_ids),
+                Some(track_ids.iter().map(String::as_str).collect()),
             )
             .map(|r| r.tracks)
             .map(|tracks| tracks.iter().map(Track::from).collect());
@@ -286,6 +328,29 @@ impl ListItem for Album {
         })
     }
 
+    fn recommendation_seeds(&mut self, queue: Arc<Queue>) -> Option<(Vec<String>, Vec<String>)> {
+        self.load_all_tracks(queue.get_spotify());
+        const MAX_SEEDS: usize = 5;
+        let track_ids: Vec<String> = self
+            .tracks
+            .as_ref()?
+            .iter()
+            .filter_map(|t| t.id.clone())
+            // spotify allows at max 5 seed items, so choose 4 random tracks...
+            .choose_multiple(&mut thread_rng(), MAX_SEEDS - 1);
+
+        let artist_ids: Vec<String> = self
+            .artist_ids
+            .iter()
+            .cloned()
+            // ...and one artist
+            .choose(&mut thread_rng())
+            .into_iter()
+            .collect();
+
+        Some((artist_ids, track_ids))
+    }
+
     fn share_url(&self) -> Option<String> {
         self.id
             .clone()
@@ -316,3 +381,32 @@ impl ListItem for Album {
         Box::new(self.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{discography_order, Album};
+
+    fn album(title: &str, album_type: &str, release_date: &str) -> Album {
+        Album {
+            title: title.to_string(),
+            year: release_date[..4].to_string(),
+            release_date: Some(release_date.to_string()),
+            album_type: Some(album_type.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_discography_order() {
+        let mut albums = vec![
+            album("First", "album", "2001-05-01"),
+            album("Second", "album", "2004"),
+            album("first", "album", "2011-02-03"),
+            album("Third", "album", "2004-06-07"),
+            album("Third", "single", "2004-01-01"),
+        ];
+        discography_order(&mut albums);
+        let titles: Vec<&str> = albums.iter().map(|album| album.title.as_str()).collect();
+        assert_eq!(titles, vec!["first", "Third", "Third", "Second"]);
+    }
+}
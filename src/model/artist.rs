@@ -19,6 +19,8 @@ pub struct Artist {
     pub url: Option<String>,
     pub tracks: Option<Vec<Track>>,
     pub is_followed: bool,
+    #[serde(default)]
+    pub followers: Option<u32>,
 }
 
 impl Artist {
@@ -29,6 +31,7 @@ impl Artist {
             url: None,
             tracks: None,
             is_followed: false,
+            followers: None,
         }
     }
 
@@ -49,6 +52,7 @@ impl From<&SimplifiedArtist> for Artist {
             url: sa.id.as_ref().map(|id| id.url()),
             tracks: None,
             is_followed: false,
+            followers: None,
         }
     }
 }
@@ -61,6 +65,7 @@ impl From<&FullArtist> for Artist {
             url: Some(fa.id.url()),
             tracks: None,
             is_followed: false,
+            followers: Some(fa.followers.total),
         }
     }
 }
@@ -100,13 +105,9 @@ impl ListItem for Artist {
 
     fn display_right(&self, library: Arc<Library>) -> String {
         let followed = if library.is_followed_artist(self) {
-            if library.cfg.values().use_nerdfont.unwrap_or(false) {
-                "\u{f62b} "
-            } else {
-                "✓ "
-            }
+            format!("{} ", library.saved_indicator())
         } else {
-            ""
+            String::new()
         };
 
         let tracks = if let Some(tracks) = self.tracks.as_ref() {
@@ -115,7 +116,21 @@ impl ListItem for Artist {
             "".into()
         };
 
-        format!("{followed}{tracks}")
+        // Only the releases of followed artists are fetched, as they are shown
+        let release = match (&self.id, self.is_followed) {
+            (Some(id), true) => library
+                .latest_release(id)
+                .map(|date| format!("latest release {date} "))
+                .unwrap_or_default(),
+            _ => "".into(),
+        };
+
+        let followers = self
+            .followers
+            .map(|followers| format!("{followers} followers "))
+            .unwrap_or_default();
+
+        format!("{release}{followers}{followed}{tracks}")
     }
 
     fn play(&mut self, queue: Arc<Queue>) {
@@ -135,9 +150,9 @@ impl ListItem for Artist {
         self.load_top_tracks(queue.get_spotify());
 
         if let Some(tracks) = self.tracks.as_ref() {
-            for t in tracks.iter().rev() {
-                queue.insert_after_current(Playable::Track(t.clone()));
-            }
+            queue.insert_all_after_current(
+                tracks.iter().map(|t| Playable::Track(t.clone())).collect(),
+            );
         }
     }
 
@@ -196,12 +211,20 @@ impl ListItem for Artist {
         })
     }
 
+    fn recommendation_seeds(&mut self, _queue: Arc<Queue>) -> Option<(Vec<String>, Vec<String>)> {
+        Some((vec![self.id.clone()?], Vec::new()))
+    }
+
     fn share_url(&self) -> Option<String> {
         self.id
             .clone()
             .map(|id| format!("https://open.spotify.com/artist/{id}"))
     }
 
+    fn artist(&self) -> Option<Artist> {
+        Some(self.clone())
+    }
+
     #[inline]
     fn is_saved(&self, library: Arc<Library>) -> Option<bool> {
         Some(library.is_followed_artist(self))
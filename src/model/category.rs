@@ -1,16 +1,69 @@
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use rspotify::model::Country;
 
 use crate::{
+    model::playlist::Playlist,
+    spotify_api::WebApi,
     traits::{IntoBoxedViewExt, ListItem},
-    ui::listview::ListView,
+    ui::{listview::ListView, pagination::ApiResult},
 };
 
+/// The ID of the category of featured playlists, which isn't one of Spotify.
+const FEATURED_ID: &str = "featured";
+
+lazy_static! {
+    /// The playlists of the categories that were opened, which are kept for
+    /// the session along with the pages loaded so far.
+    static ref PLAYLISTS: RwLock<HashMap<String, ApiResult<Playlist>>> = RwLock::new(HashMap::new());
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Category {
     pub id: String,
     pub name: String,
 }
 
+impl Category {
+    /// The category of the playlists Spotify features in `country`, named by
+    /// the message Spotify introduces them with.
+    pub fn featured(api: &WebApi, country: Option<Country>) -> Category {
+        let (message, playlists) = api.featured_playlists(country);
+        PLAYLISTS
+            .write()
+            .unwrap()
+            .insert(FEATURED_ID.to_string(), playlists);
+        Category {
+            id: FEATURED_ID.to_string(),
+            name: if message.is_empty() {
+                "Featured Playlists".to_string()
+            } else {
+                message
+            },
+        }
+    }
+
+    fn is_featured(&self) -> bool {
+        self.id == FEATURED_ID
+    }
+
+    fn playlists(&self, api: &WebApi, country: Option<Country>) -> ApiResult<Playlist> {
+        PLAYLISTS
+            .write()
+            .unwrap()
+            .entry(self.id.clone())
+            .or_insert_with(|| {
+                if self.is_featured() {
+                    api.featured_playlists(country).1
+                } else {
+                    api.category_playlists(&self.id, country)
+                }
+            })
+            .clone()
+    }
+}
+
 impl From<&rspotify::model::Category> for Category {
     fn from(c: &rspotify::model::Category) -> Self {
         Category {
@@ -50,13 +103,16 @@ impl ListItem for Category {
         queue: Arc<crate::queue::Queue>,
         library: Arc<crate::library::Library>,
     ) -> Option<Box<dyn crate::traits::ViewExt>> {
-        let playlists = queue.get_spotify().api.category_playlists(&self.id);
+        let playlists = self.playlists(&queue.get_spotify().api, library.country());
         let view = ListView::new(playlists.items.clone(), queue, library).with_title(&self.name);
         playlists.apply_pagination(view.get_pagination());
         Some(view.into_boxed_view_ext())
     }
 
     fn share_url(&self) -> Option<String> {
+        if self.is_featured() {
+            return None;
+        }
         Some(format!("https://open.spotify.com/genre/{}", self.id))
     }
 
@@ -19,13 +19,45 @@ pub struct Episode {
     pub release_date: String,
     pub cover_url: Option<String>,
     pub added_at: Option<DateTime<Utc>>,
+    /// The ID of the user who added the episode to the playlist it is in.
+    #[serde(default)]
+    pub added_by: Option<String>,
     pub list_index: usize,
+    /// Where the user stopped listening in ms, as synced by Spotify.
+    #[serde(default)]
+    pub resume_position: u32,
+    #[serde(default)]
+    pub fully_played: bool,
 }
 
 impl Episode {
     pub fn duration_str(&self) -> String {
         ms_to_hms(self.duration)
     }
+
+    /// Whether the episode was listened to, fully or in part.
+    pub fn played_str(&self) -> String {
+        if self.fully_played {
+            "played".to_string()
+        } else if self.resume_position > 0 {
+            let remaining = self.duration.saturating_sub(self.resume_position);
+            format!("{} left", ms_to_hms(remaining))
+        } else {
+            String::new()
+        }
+    }
+
+    /// The position to start playing at, which is only the resume position
+    /// the first time. Played episodes start from the top.
+    pub fn take_resume_position(&mut self) -> u32 {
+        let position = if self.fully_played || self.resume_position >= self.duration {
+            0
+        } else {
+            self.resume_position
+        };
+        self.resume_position = 0;
+        position
+    }
 }
 
 impl From<&SimplifiedEpisode> for Episode {
@@ -39,7 +71,16 @@ impl From<&SimplifiedEpisode> for Episode {
             release_date: episode.release_date.clone(),
             cover_url: episode.images.get(0).map(|img| img.url.clone()),
             added_at: None,
+            added_by: None,
             list_index: 0,
+            resume_position: episode
+                .resume_point
+                .as_ref()
+                .map_or(0, |point| point.resume_position.as_millis() as u32),
+            fully_played: episode
+                .resume_point
+                .as_ref()
+                .map_or(false, |point| point.fully_played),
         }
     }
 }
@@ -55,7 +96,16 @@ impl From<&FullEpisode> for Episode {
             release_date: episode.release_date.clone(),
             cover_url: episode.images.get(0).map(|img| img.url.clone()),
             added_at: None,
+            added_by: None,
             list_index: 0,
+            resume_position: episode
+                .resume_point
+                .as_ref()
+                .map_or(0, |point| point.resume_position.as_millis() as u32),
+            fully_played: episode
+                .resume_point
+                .as_ref()
+                .map_or(false, |point| point.fully_played),
         }
     }
 }
@@ -79,7 +129,12 @@ impl ListItem for Episode {
     }
 
     fn display_right(&self, _library: Arc<Library>) -> String {
-        format!("{} [{}]", self.duration_str(), self.release_date)
+        let played = self.played_str();
+        if played.is_empty() {
+            format!("{} [{}]", self.duration_str(), self.release_date)
+        } else {
+            format!("{} {} [{}]", played, self.duration_str(), self.release_date)
+        }
     }
 
     fn play(&mut self, queue: Arc<Queue>) {
@@ -4,5 +4,6 @@ pub mod category;
 pub mod episode;
 pub mod playable;
 pub mod playlist;
+pub mod playlist_folder;
 pub mod show;
 pub mod track;
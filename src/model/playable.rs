@@ -57,14 +57,11 @@ impl Playable {
                     Playable::Episode(episode) => Playable::Episode(episode),
                     Playable::Track(track) => Playable::Track(track),
                 }) {
-                    if library.cfg.values().use_nerdfont.unwrap_or_default() {
-                        "\u{f62b}"
-                    } else {
-                        "✓"
-                    }
+                    library.saved_indicator()
                 } else {
-                    ""
-                },
+                    String::new()
+                }
+                .as_str(),
             )
             .replace("%duration", playable.duration_str().as_str())
     }
@@ -118,6 +115,21 @@ impl Playable {
         }
     }
 
+    pub fn set_added_by(&mut self, added_by: Option<String>) {
+        match self {
+            Playable::Track(track) => track.added_by = added_by,
+            Playable::Episode(episode) => episode.added_by = added_by,
+        }
+    }
+
+    /// The position to start playing at, see [Episode::take_resume_position].
+    pub fn take_resume_position(&mut self) -> u32 {
+        match self {
+            Playable::Track(_) => 0,
+            Playable::Episode(episode) => episode.take_resume_position(),
+        }
+    }
+
     pub fn duration_str(&self) -> String {
         ms_to_hms(self.duration())
     }
@@ -139,15 +151,23 @@ impl From<&PlayableItem> for Playable {
     }
 }
 
-impl From<&Playable> for rspotify::prelude::PlayableId<'_> {
-    fn from(p: &Playable) -> Self {
+/// Local files and items with malformed IDs have no ID on Spotify.
+impl TryFrom<&Playable> for rspotify::prelude::PlayableId<'_> {
+    type Error = String;
+
+    fn try_from(p: &Playable) -> Result<Self, Self::Error> {
         match p {
-            Playable::Track(t) => rspotify::prelude::PlayableId::Track(
-                rspotify::model::TrackId::from_id(t.id.clone().unwrap()).unwrap(),
-            ),
-            Playable::Episode(e) => rspotify::prelude::PlayableId::Episode(
-                rspotify::model::EpisodeId::from_id(e.id.clone()).unwrap(),
-            ),
+            Playable::Track(t) => {
+                let id =
+                    t.id.clone()
+                        .ok_or_else(|| format!("{} is not on Spotify", t.uri))?;
+                rspotify::model::TrackId::from_id(id)
+                    .map(rspotify::prelude::PlayableId::Track)
+                    .map_err(|e| format!("{}: {e:?}", t.uri))
+            }
+            Playable::Episode(e) => rspotify::model::EpisodeId::from_id(e.id.clone())
+                .map(rspotify::prelude::PlayableId::Episode)
+                .map_err(|err| format!("{}: {err:?}", e.uri)),
         }
     }
 }
@@ -214,6 +234,24 @@ impl ListItem for Playable {
         self.as_listitem().album(queue)
     }
 
+    fn album_id(&self) -> Option<String> {
+        self.as_listitem().album_id()
+    }
+
+    fn disc_number(&self) -> Option<i32> {
+        match self {
+            Playable::Track(track) => Some(track.disc_number),
+            Playable::Episode(_) => None,
+        }
+    }
+
+    fn popularity(&self) -> Option<u32> {
+        match self {
+            Playable::Track(track) => track.popularity,
+            Playable::Episode(_) => None,
+        }
+    }
+
     fn artists(&self) -> Option<Vec<Artist>> {
         self.as_listitem().artists()
     }
@@ -222,6 +260,20 @@ impl ListItem for Playable {
         self.as_listitem().track()
     }
 
+    fn added_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Playable::Track(track) => track.added_at,
+            Playable::Episode(episode) => episode.added_at,
+        }
+    }
+
+    fn added_by(&self) -> Option<String> {
+        match self {
+            Playable::Track(track) => track.added_by.clone(),
+            Playable::Episode(episode) => episode.added_by.clone(),
+        }
+    }
+
     fn as_listitem(&self) -> Box<dyn ListItem> {
         self.as_listitem()
     }
@@ -1,6 +1,6 @@
 use std::collections::HashSet;
+use std::iter::Iterator;
 use std::sync::{Arc, RwLock};
-use std::{cmp::Ordering, iter::Iterator};
 
 use rand::{seq::IteratorRandom, thread_rng};
 
@@ -8,13 +8,13 @@ use log::debug;
 use rspotify::model::playlist::{FullPlaylist, SimplifiedPlaylist};
 use rspotify::model::Id;
 
+use crate::library::Library;
 use crate::model::playable::Playable;
 use crate::model::track::Track;
 use crate::queue::Queue;
 use crate::spotify::Spotify;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::{listview::ListView, playlist::PlaylistView};
-use crate::{command::SortDirection, command::SortKey, library::Library};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Playlist {
@@ -56,35 +56,158 @@ impl Playlist {
     }
 
     pub fn delete_track(&mut self, index: usize, spotify: Spotify, library: Arc<Library>) -> bool {
-        let track = self.tracks.as_ref().unwrap()[index].clone();
-        debug!("deleting track: {} {:?}", index, track);
+        debug!("deleting track: {}", index);
+        self.delete_tracks(&[index], spotify, library) == 1
+    }
+
+    /// The indices of the tracks that are listed again after their first
+    /// occurrence in the playlist. Tracks that Spotify relinked count as
+    /// duplicates of the track they replace.
+    pub fn duplicates(&self) -> Vec<usize> {
+        let tracks = match self.tracks.as_ref() {
+            Some(tracks) => tracks,
+            None => return Vec::new(),
+        };
+        let mut by_position: Vec<usize> = (0..tracks.len()).collect();
+        by_position.sort_by_key(|&index| tracks[index].list_index());
+
+        let mut seen = HashSet::new();
+        let mut duplicates = Vec::new();
+        for index in by_position {
+            let ids = ids_of(&tracks[index]);
+            if ids.iter().any(|id| seen.contains(id)) {
+                duplicates.push(index);
+            }
+            seen.extend(ids);
+        }
+        duplicates
+    }
+
+    /// The indices of every occurrence of the tracks at `indices`, including
+    /// those at `indices` themselves.
+    pub fn occurrences(&self, indices: &[usize]) -> Vec<usize> {
+        let tracks = match self.tracks.as_ref() {
+            Some(tracks) => tracks,
+            None => return Vec::new(),
+        };
+        let ids: HashSet<String> = indices
+            .iter()
+            .filter_map(|&index| tracks.get(index))
+            .flat_map(ids_of)
+            .collect();
+        (0..tracks.len())
+            .filter(|&index| {
+                indices.contains(&index) || ids_of(&tracks[index]).iter().any(|id| ids.contains(id))
+            })
+            .collect()
+    }
+
+    /// Delete the tracks at `indices`. Returns the amount of deleted tracks,
+    /// which falls short when Spotify rejects a request.
+    pub fn delete_tracks(
+        &mut self,
+        indices: &[usize],
+        spotify: Spotify,
+        library: Arc<Library>,
+    ) -> usize {
+        let tracks = match self.tracks.as_mut() {
+            Some(tracks) => tracks,
+            None => return 0,
+        };
+
+        // we can only send 100 tracks per request. The positions of all of
+        // them refer to the snapshot from before the first one.
+        let mut deleted = HashSet::new();
+        let mut snapshot_id = None;
+        for chunk in indices.chunks(100) {
+            let playables: Vec<Playable> = chunk.iter().map(|&i| tracks[i].clone()).collect();
+            match spotify
+                .api
+                .delete_tracks(&self.id, &self.snapshot_id, &playables)
+            {
+                Some(snapshot) => snapshot_id = Some(snapshot),
+                None => break,
+            }
+            deleted.extend(chunk.iter().copied());
+        }
+
+        if let Some(snapshot_id) = snapshot_id {
+            let mut index = 0;
+            tracks.retain(|_| {
+                index += 1;
+                !deleted.contains(&(index - 1))
+            });
+            self.changed(snapshot_id);
+            library.playlist_update(self);
+        }
+        deleted.len()
+    }
+
+    /// Move the track at `from` to `to`. Returns whether Spotify accepted it.
+    pub fn move_track(
+        &mut self,
+        from: usize,
+        to: usize,
+        spotify: Spotify,
+        library: Arc<Library>,
+    ) -> bool {
+        let len = self.tracks.as_ref().map_or(0, Vec::len);
+        if from >= len || to >= len {
+            return false;
+        } else if from == to {
+            return true;
+        }
+
+        // The track is inserted before the one at the given position, which
+        // counts the track itself while it's still in place
+        let insert_before = if to > from { to + 1 } else { to };
         match spotify
             .api
-            .delete_tracks(&self.id, &self.snapshot_id, &[track])
+            .reorder_track(&self.id, &self.snapshot_id, from, insert_before)
         {
-            false => false,
-            true => {
-                if let Some(tracks) = &mut self.tracks {
-                    tracks.remove(index);
-                    library.playlist_update(self);
+            Some(snapshot_id) => {
+                if let Some(tracks) = self.tracks.as_mut() {
+                    let track = tracks.remove(from);
+                    tracks.insert(to, track);
                 }
-
+                self.changed(snapshot_id);
+                library.playlist_update(self);
                 true
             }
+            None => false,
         }
     }
 
+    /// Take up the snapshot of the playlist after changing it, and number the
+    /// tracks by their new positions, which later changes refer to.
+    fn changed(&mut self, snapshot_id: String) {
+        self.snapshot_id = snapshot_id;
+        if let Some(tracks) = self.tracks.as_mut() {
+            for (index, track) in tracks.iter_mut().enumerate() {
+                track.set_list_index(index);
+            }
+            self.num_tracks = tracks.len();
+        }
+    }
+
+    /// Append `new_tracks` to the playlist, in one request for up to 50 of
+    /// them. Returns whether Spotify accepted them all.
     pub fn append_tracks(
         &mut self,
         new_tracks: &[Playable],
         spotify: Spotify,
         library: Arc<Library>,
-    ) {
+    ) -> bool {
         let mut has_modified = false;
+        let mut appended = true;
 
-        if spotify.api.append_tracks(&self.id, new_tracks, None) {
+        for chunk in new_tracks.chunks(50) {
+            appended = spotify.api.append_tracks(&self.id, chunk, None);
+            if !appended {
+                break;
+            }
             if let Some(tracks) = &mut self.tracks {
-                tracks.append(&mut new_tracks.to_vec());
+                tracks.extend_from_slice(chunk);
                 has_modified = true;
             }
         }
@@ -92,55 +215,21 @@ impl Playlist {
         if has_modified {
             library.playlist_update(self);
         }
+        appended
     }
+}
 
-    pub fn sort(&mut self, key: &SortKey, direction: &SortDirection) {
-        fn compare_artists(a: &[String], b: &[String]) -> Ordering {
-            let sanitize_artists_name = |x: &[String]| -> Vec<String> {
-                x.iter()
-                    .map(|x| {
-                        x.to_lowercase()
-                            .split(' ')
-                            .skip_while(|x| x == &"the")
-                            .collect()
-                    })
-                    .collect()
-            };
-
-            let a = sanitize_artists_name(a);
-            let b = sanitize_artists_name(b);
-
-            a.cmp(&b)
-        }
-
-        fn compare_album(a: &Track, b: &Track) -> Ordering {
-            a.album
-                .as_ref()
-                .map(|x| x.to_lowercase())
-                .cmp(&b.album.as_ref().map(|x| x.to_lowercase()))
-                .then_with(|| a.disc_number.cmp(&b.disc_number))
-                .then_with(|| a.track_number.cmp(&b.track_number))
-        }
-
-        if let Some(c) = self.tracks.as_mut() {
-            c.sort_by(|a, b| match (a.track(), b.track()) {
-                (Some(a), Some(b)) => {
-                    let (a, b) = match *direction {
-                        SortDirection::Ascending => (a, b),
-                        SortDirection::Descending => (b, a),
-                    };
-                    match *key {
-                        SortKey::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
-                        SortKey::Duration => a.duration.cmp(&b.duration),
-                        SortKey::Album => compare_album(&a, &b),
-                        SortKey::Added => a.added_at.cmp(&b.added_at),
-                        SortKey::Artist => compare_artists(&a.artists, &b.artists)
-                            .then_with(|| compare_album(&a, &b)),
-                    }
-                }
-                _ => std::cmp::Ordering::Equal,
-            })
-        }
+/// The IDs a playable is listed by, which for tracks that Spotify relinked
+/// includes the ID of the track they replace.
+fn ids_of(playable: &Playable) -> Vec<String> {
+    match playable {
+        Playable::Track(track) => track
+            .id
+            .iter()
+            .chain(track.linked_from.iter())
+            .cloned()
+            .collect(),
+        Playable::Episode(episode) => vec![episode.id.clone()],
     }
 }
 
@@ -201,13 +290,9 @@ impl ListItem for Playlist {
 
     fn display_right(&self, library: Arc<Library>) -> String {
         let saved = if library.is_saved_playlist(self) {
-            if library.cfg.values().use_nerdfont.unwrap_or(false) {
-                "\u{f62b} "
-            } else {
-                "✓ "
-            }
+            format!("{} ", library.saved_indicator())
         } else {
-            ""
+            String::new()
         };
 
         let num_tracks = self
@@ -216,7 +301,15 @@ impl ListItem for Playlist {
             .map(|t| t.len())
             .unwrap_or(self.num_tracks);
 
-        format!("{saved}{num_tracks:>4} tracks")
+        let badge = if self.collaborative {
+            "collaborative "
+        } else if library.is_saved_playlist(self) && !library.is_own_playlist(self) {
+            "followed "
+        } else {
+            ""
+        };
+
+        format!("{badge}{saved}{num_tracks:>4} tracks")
     }
 
     fn play(&mut self, queue: Arc<Queue>) {
@@ -232,9 +325,7 @@ impl ListItem for Playlist {
         self.load_tracks(queue.get_spotify());
 
         if let Some(tracks) = self.tracks.as_ref() {
-            for track in tracks.iter().rev() {
-                queue.insert_after_current(track.clone());
-            }
+            queue.insert_all_after_current(tracks.clone());
         }
     }
 
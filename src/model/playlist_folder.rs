@@ -0,0 +1,334 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::library::Library;
+use crate::model::playlist::Playlist;
+use crate::queue::Queue;
+use crate::traits::{ListItem, ViewExt};
+
+/// An item of the user's rootlist, which lists their playlists in the order
+/// they arranged them into folders.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Deserialize, Serialize)]
+pub enum RootlistItem {
+    /// A playlist, by ID.
+    Playlist(String),
+    /// The start of a folder, which holds the items up to its end.
+    FolderStart {
+        id: String,
+        name: String,
+    },
+    FolderEnd,
+}
+
+impl RootlistItem {
+    /// Parse the URI of a rootlist item. Folders are listed as
+    /// `spotify:start-group:<id>:<name>` and `spotify:end-group:<id>`, with
+    /// the name URL encoded.
+    pub fn from_uri(uri: &str) -> Option<RootlistItem> {
+        if let Some(group) = uri.strip_prefix("spotify:start-group:") {
+            let (id, name) = group.split_once(':').unwrap_or((group, ""));
+            let name = url::form_urlencoded::parse(name.as_bytes())
+                .next()
+                .map(|(name, _)| name.into_owned())
+                .unwrap_or_default();
+            Some(RootlistItem::FolderStart {
+                id: id.to_string(),
+                name,
+            })
+        } else if uri.starts_with("spotify:end-group:") {
+            Some(RootlistItem::FolderEnd)
+        } else {
+            uri.split_once("playlist:")
+                .map(|(_, id)| RootlistItem::Playlist(id.to_string()))
+        }
+    }
+}
+
+/// A row of the playlists tab, nested `depth` folders deep.
+#[derive(Clone)]
+pub enum PlaylistEntry {
+    Folder {
+        id: String,
+        name: String,
+        /// The number of playlists in the folder, including nested folders.
+        playlists: usize,
+        expanded: bool,
+        depth: usize,
+    },
+    Playlist {
+        playlist: Playlist,
+        depth: usize,
+    },
+}
+
+impl PlaylistEntry {
+    /// The entries of the playlists tab: the playlists in the order of the
+    /// rootlist, leaving out the contents of folders that aren't expanded.
+    /// Playlists that aren't in the rootlist follow at the top level.
+    pub fn build(
+        rootlist: &[RootlistItem],
+        playlists: &[Playlist],
+        expanded: &HashSet<String>,
+    ) -> Vec<PlaylistEntry> {
+        let by_id: HashMap<&str, &Playlist> = playlists
+            .iter()
+            .map(|playlist| (playlist.id.as_str(), playlist))
+            .collect();
+        let mut listed: HashSet<&str> = HashSet::new();
+        let mut entries = Vec::new();
+        // The open folders, as the index of their entry if it is shown and
+        // whether their contents are shown
+        let mut folders: Vec<(Option<usize>, bool)> = Vec::new();
+
+        for item in rootlist {
+            let shown = folders.last().map_or(true, |&(_, open)| open);
+            match item {
+                RootlistItem::FolderStart { id, name } => {
+                    let open = shown && expanded.contains(id);
+                    let index = shown.then_some(entries.len());
+                    if shown {
+                        entries.push(PlaylistEntry::Folder {
+                            id: id.clone(),
+                            name: name.clone(),
+                            playlists: 0,
+                            expanded: open,
+                            depth: folders.len(),
+                        });
+                    }
+                    folders.push((index, open));
+                }
+                RootlistItem::FolderEnd => {
+                    folders.pop();
+                }
+                RootlistItem::Playlist(id) => {
+                    let playlist = match by_id.get(id.as_str()) {
+                        Some(playlist) => playlist,
+                        None => continue,
+                    };
+                    listed.insert(&playlist.id);
+                    for &(index, _) in folders.iter() {
+                        if let Some(PlaylistEntry::Folder { playlists, .. }) =
+                            index.and_then(|index| entries.get_mut(index))
+                        {
+                            *playlists += 1;
+                        }
+                    }
+                    if shown {
+                        entries.push(PlaylistEntry::Playlist {
+                            playlist: (*playlist).clone(),
+                            depth: folders.len(),
+                        });
+                    }
+                }
+            }
+        }
+
+        entries.extend(
+            playlists
+                .iter()
+                .filter(|playlist| !listed.contains(playlist.id.as_str()))
+                .map(|playlist| PlaylistEntry::Playlist {
+                    playlist: playlist.clone(),
+                    depth: 0,
+                }),
+        );
+        entries
+    }
+
+    /// The IDs of the folders that hold the playlist with `id`, outermost
+    /// first.
+    pub fn folders_of(rootlist: &[RootlistItem], id: &str) -> Vec<String> {
+        let mut folders: Vec<String> = Vec::new();
+        for item in rootlist {
+            match item {
+                RootlistItem::FolderStart { id, .. } => folders.push(id.clone()),
+                RootlistItem::FolderEnd => {
+                    folders.pop();
+                }
+                RootlistItem::Playlist(playlist) if playlist == id => return folders,
+                RootlistItem::Playlist(_) => {}
+            }
+        }
+        Vec::new()
+    }
+
+    pub fn playlist(&self) -> Option<&Playlist> {
+        match self {
+            PlaylistEntry::Playlist { playlist, .. } => Some(playlist),
+            PlaylistEntry::Folder { .. } => None,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        match self {
+            PlaylistEntry::Folder { depth, .. } | PlaylistEntry::Playlist { depth, .. } => *depth,
+        }
+    }
+
+    fn indent(&self) -> String {
+        "  ".repeat(self.depth())
+    }
+}
+
+impl ListItem for PlaylistEntry {
+    fn is_playing(&self, queue: Arc<Queue>) -> bool {
+        self.playlist()
+            .map_or(false, |playlist| playlist.is_playing(queue))
+    }
+
+    fn display_left(&self, library: Arc<Library>) -> String {
+        match self {
+            PlaylistEntry::Folder { name, expanded, .. } => {
+                let marker = if *expanded { "▾" } else { "▸" };
+                format!("{}{marker} {name}", self.indent())
+            }
+            PlaylistEntry::Playlist { playlist, .. } => {
+                format!("{}{}", self.indent(), playlist.display_left(library))
+            }
+        }
+    }
+
+    fn display_right(&self, library: Arc<Library>) -> String {
+        match self {
+            PlaylistEntry::Folder { playlists, .. } => format!("{playlists} playlists"),
+            PlaylistEntry::Playlist { playlist, .. } => playlist.display_right(library),
+        }
+    }
+
+    fn play(&mut self, queue: Arc<Queue>) {
+        if let PlaylistEntry::Playlist { playlist, .. } = self {
+            playlist.play(queue);
+        }
+    }
+
+    fn play_next(&mut self, queue: Arc<Queue>) {
+        if let PlaylistEntry::Playlist { playlist, .. } = self {
+            playlist.play_next(queue);
+        }
+    }
+
+    fn queue(&mut self, queue: Arc<Queue>) {
+        if let PlaylistEntry::Playlist { playlist, .. } = self {
+            playlist.queue(queue);
+        }
+    }
+
+    fn toggle_saved(&mut self, library: Arc<Library>) {
+        if let PlaylistEntry::Playlist { playlist, .. } = self {
+            playlist.toggle_saved(library);
+        }
+    }
+
+    fn save(&mut self, library: Arc<Library>) {
+        if let PlaylistEntry::Playlist { playlist, .. } = self {
+            playlist.save(library);
+        }
+    }
+
+    fn unsave(&mut self, library: Arc<Library>) {
+        if let PlaylistEntry::Playlist { playlist, .. } = self {
+            playlist.unsave(library);
+        }
+    }
+
+    fn open(&self, queue: Arc<Queue>, library: Arc<Library>) -> Option<Box<dyn ViewExt>> {
+        self.playlist()?.open(queue, library)
+    }
+
+    fn open_recommendations(
+        &mut self,
+        queue: Arc<Queue>,
+        library: Arc<Library>,
+    ) -> Option<Box<dyn ViewExt>> {
+        match self {
+            PlaylistEntry::Playlist { playlist, .. } => {
+                playlist.open_recommendations(queue, library)
+            }
+            PlaylistEntry::Folder { .. } => None,
+        }
+    }
+
+    fn share_url(&self) -> Option<String> {
+        self.playlist()?.share_url()
+    }
+
+    fn is_saved(&self, library: Arc<Library>) -> Option<bool> {
+        self.playlist()?.is_saved(library)
+    }
+
+    fn is_playable(&self) -> bool {
+        self.playlist().is_some()
+    }
+
+    fn as_listitem(&self) -> Box<dyn ListItem> {
+        match self {
+            PlaylistEntry::Playlist { playlist, .. } => playlist.as_listitem(),
+            PlaylistEntry::Folder { .. } => Box::new(self.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{PlaylistEntry, RootlistItem};
+    use crate::model::playlist::Playlist;
+
+    fn playlist(id: &str) -> Playlist {
+        Playlist {
+            id: id.to_string(),
+            name: id.to_string(),
+            owner_id: String::new(),
+            owner_name: None,
+            snapshot_id: String::new(),
+            num_tracks: 0,
+            tracks: None,
+            collaborative: false,
+        }
+    }
+
+    fn rows(entries: &[PlaylistEntry]) -> Vec<String> {
+        entries
+            .iter()
+            .map(|entry| match entry {
+                PlaylistEntry::Folder {
+                    name, playlists, ..
+                } => format!("{}{name}/{playlists}", "  ".repeat(entry.depth())),
+                PlaylistEntry::Playlist { playlist, .. } => {
+                    format!("{}{}", "  ".repeat(entry.depth()), playlist.id)
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_build() {
+        let rootlist: Vec<RootlistItem> = [
+            "spotify:playlist:a",
+            "spotify:start-group:f1:Road+trips",
+            "spotify:user:me:playlist:b",
+            "spotify:start-group:f2:Old%2FNew",
+            "spotify:playlist:c",
+            "spotify:end-group:f2",
+            "spotify:end-group:f1",
+        ]
+        .iter()
+        .filter_map(|uri| RootlistItem::from_uri(uri))
+        .collect();
+        let playlists: Vec<Playlist> = ["a", "b", "c", "d"].into_iter().map(playlist).collect();
+
+        let collapsed = PlaylistEntry::build(&rootlist, &playlists, &HashSet::new());
+        assert_eq!(rows(&collapsed), vec!["a", "Road trips/2", "d"]);
+
+        let expanded: HashSet<String> = ["f1".to_string(), "f2".to_string()].into();
+        let entries = PlaylistEntry::build(&rootlist, &playlists, &expanded);
+        assert_eq!(
+            rows(&entries),
+            vec!["a", "Road trips/2", "  b", "  Old/New/1", "    c", "d"]
+        );
+
+        assert_eq!(PlaylistEntry::folders_of(&rootlist, "c"), vec!["f1", "f2"]);
+        assert!(PlaylistEntry::folders_of(&rootlist, "d").is_empty());
+    }
+}
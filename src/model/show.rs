@@ -82,13 +82,9 @@ impl ListItem for Show {
 
     fn display_right(&self, library: Arc<Library>) -> String {
         let saved = if library.is_saved_show(self) {
-            if library.cfg.values().use_nerdfont.unwrap_or(false) {
-                "\u{f62b} "
-            } else {
-                "✓ "
-            }
+            format!("{} ", library.saved_indicator())
         } else {
-            ""
+            String::new()
         };
         saved.to_owned()
     }
@@ -112,9 +108,12 @@ impl ListItem for Show {
         self.load_all_episodes(queue.get_spotify());
 
         if let Some(episodes) = self.episodes.as_ref() {
-            for ep in episodes.iter().rev() {
-                queue.insert_after_current(Playable::Episode(ep.clone()));
-            }
+            queue.insert_all_after_current(
+                episodes
+                    .iter()
+                    .map(|ep| Playable::Episode(ep.clone()))
+                    .collect(),
+            );
         }
     }
 
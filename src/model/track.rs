@@ -1,6 +1,10 @@
+use std::cmp::Ordering;
 use std::fmt;
+use std::path::Path;
 use std::sync::{Arc, RwLock};
+use std::thread;
 
+use crate::command::{SortDirection, SortKey};
 use crate::config;
 use crate::utils::ms_to_hms;
 use chrono::{DateTime, Utc};
@@ -9,6 +13,7 @@ use rspotify::model::track::{FullTrack, SavedTrack, SimplifiedTrack};
 use rspotify::model::Id;
 
 use crate::library::Library;
+use crate::local_player::{LocalFile, FILE_URI_PREFIX};
 use crate::model::album::Album;
 use crate::model::artist::Artist;
 use crate::model::playable::Playable;
@@ -16,7 +21,7 @@ use crate::queue::Queue;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::listview::ListView;
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct Track {
     pub id: Option<String>,
     pub uri: String,
@@ -32,10 +37,60 @@ pub struct Track {
     pub cover_url: Option<String>,
     pub url: String,
     pub added_at: Option<DateTime<Utc>>,
+    /// The ID of the user who added the track to the playlist it is in.
+    #[serde(default)]
+    pub added_by: Option<String>,
+    /// The release date of the album, which may only be a year.
+    #[serde(default)]
+    pub release_date: Option<String>,
     pub list_index: usize,
+    /// The ID of the track that Spotify replaced by this one, because only
+    /// this one is playable in the user's market.
+    #[serde(default)]
+    pub linked_from: Option<String>,
+    /// How popular the track is on Spotify from 0 to 100, which only full
+    /// tracks tell.
+    #[serde(default)]
+    pub popularity: Option<u32>,
 }
 
 impl Track {
+    /// The track of the local file at `path`, titled after its file name if
+    /// it isn't tagged.
+    pub fn from_local_file(path: &Path) -> Result<Track, String> {
+        let path = path
+            .canonicalize()
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        let file = LocalFile::probe(&path)?;
+        let title = file.title.unwrap_or_else(|| {
+            path.file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned()
+        });
+        Ok(Track {
+            id: None,
+            uri: format!("{FILE_URI_PREFIX}{}", path.display()),
+            title,
+            track_number: 0,
+            disc_number: 0,
+            duration: file.duration_ms,
+            artists: file.artists,
+            artist_ids: Vec::new(),
+            album: file.album,
+            album_id: None,
+            album_artists: Vec::new(),
+            cover_url: None,
+            url: String::new(),
+            added_at: None,
+            added_by: None,
+            release_date: None,
+            list_index: 0,
+            linked_from: None,
+            popularity: None,
+        })
+    }
+
     pub fn from_simplified_track(track: &SimplifiedTrack, album: &FullAlbum) -> Track {
         let artists = track
             .artists
@@ -68,7 +123,14 @@ impl Track {
             cover_url: album.images.get(0).map(|img| img.url.clone()),
             url: track.id.as_ref().map(|id| id.url()).unwrap_or_default(),
             added_at: None,
+            added_by: None,
+            release_date: Some(album.release_date.clone()),
             list_index: 0,
+            linked_from: track
+                .linked_from
+                .as_ref()
+                .map(|link| link.id.id().to_string()),
+            popularity: None,
         }
     }
 
@@ -77,6 +139,70 @@ impl Track {
     }
 }
 
+/// The order of `tracks` sorted by `key`, as indices into `tracks`. Items
+/// that aren't tracks keep their order after the tracks.
+pub fn sorted_order(
+    tracks: &[Option<Track>],
+    key: &SortKey,
+    direction: &SortDirection,
+) -> Vec<usize> {
+    fn compare_artists(a: &[String], b: &[String]) -> Ordering {
+        let sanitize_artists_name = |x: &[String]| -> Vec<String> {
+            x.iter()
+                .map(|x| {
+                    x.to_lowercase()
+                        .split(' ')
+                        .skip_while(|x| x == &"the")
+                        .collect()
+                })
+                .collect()
+        };
+
+        let a = sanitize_artists_name(a);
+        let b = sanitize_artists_name(b);
+
+        a.cmp(&b)
+    }
+
+    fn compare_album(a: &Track, b: &Track) -> Ordering {
+        a.album
+            .as_ref()
+            .map(|x| x.to_lowercase())
+            .cmp(&b.album.as_ref().map(|x| x.to_lowercase()))
+            .then_with(|| a.disc_number.cmp(&b.disc_number))
+            .then_with(|| a.track_number.cmp(&b.track_number))
+    }
+
+    let compare = |a: &Track, b: &Track| {
+        let (a, b) = match *direction {
+            SortDirection::Ascending => (a, b),
+            SortDirection::Descending => (b, a),
+        };
+        match *key {
+            SortKey::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            SortKey::Duration => a.duration.cmp(&b.duration),
+            SortKey::Album => compare_album(a, b),
+            SortKey::Added => a.added_at.cmp(&b.added_at),
+            SortKey::Artist => {
+                compare_artists(&a.artists, &b.artists).then_with(|| compare_album(a, b))
+            }
+            SortKey::Released => a
+                .release_date
+                .cmp(&b.release_date)
+                .then_with(|| compare_album(a, b)),
+        }
+    };
+
+    let mut order: Vec<usize> = (0..tracks.len()).collect();
+    order.sort_by(|&a, &b| match (&tracks[a], &tracks[b]) {
+        (Some(a), Some(b)) => compare(a, b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+    order
+}
+
 impl From<&SimplifiedTrack> for Track {
     fn from(track: &SimplifiedTrack) -> Self {
         let artists = track
@@ -105,7 +231,14 @@ impl From<&SimplifiedTrack> for Track {
             cover_url: None,
             url: track.id.as_ref().map(|id| id.url()).unwrap_or_default(),
             added_at: None,
+            added_by: None,
+            release_date: None,
             list_index: 0,
+            linked_from: track
+                .linked_from
+                .as_ref()
+                .map(|link| link.id.id().to_string()),
+            popularity: None,
         }
     }
 }
@@ -144,7 +277,14 @@ impl From<&FullTrack> for Track {
             cover_url: track.album.images.get(0).map(|img| img.url.clone()),
             url: track.id.as_ref().map(|id| id.url()).unwrap_or_default(),
             added_at: None,
+            added_by: None,
+            release_date: track.album.release_date.clone(),
             list_index: 0,
+            linked_from: track
+                .linked_from
+                .as_ref()
+                .map(|link| link.id.id().to_string()),
+            popularity: Some(track.popularity),
         }
     }
 }
@@ -178,7 +318,7 @@ impl fmt::Debug for Track {
 impl ListItem for Track {
     fn is_playing(&self, queue: Arc<Queue>) -> bool {
         let current = queue.get_current();
-        current.map(|t| t.id() == self.id).unwrap_or(false)
+        current.map(|t| t.uri() == self.uri).unwrap_or(false)
     }
 
     fn display_left(&self, library: Arc<Library>) -> String {
@@ -226,13 +366,9 @@ impl ListItem for Track {
             Playable::format(&Playable::Track(self.clone()), &right, library)
         } else {
             let saved = if library.is_saved_track(&Playable::Track(self.clone())) {
-                if library.cfg.values().use_nerdfont.unwrap_or(false) {
-                    "\u{f62b}"
-                } else {
-                    "✓"
-                }
+                library.saved_indicator()
             } else {
-                ""
+                String::new()
             };
             format!("{} {}", saved, self.duration_str())
         }
@@ -252,11 +388,9 @@ impl ListItem for Track {
     }
 
     fn toggle_saved(&mut self, library: Arc<Library>) {
-        if library.is_saved_track(&Playable::Track(self.clone())) {
-            library.unsave_tracks(vec![self], true);
-        } else {
-            library.save_tracks(vec![self], true);
-        }
+        let liked = !library.is_saved_track(&Playable::Track(self.clone()));
+        let track = self.clone();
+        thread::spawn(move || library.set_liked(&track, liked));
     }
 
     fn save(&mut self, library: Arc<Library>) {
@@ -303,6 +437,10 @@ impl ListItem for Track {
         })
     }
 
+    fn recommendation_seeds(&mut self, _queue: Arc<Queue>) -> Option<(Vec<String>, Vec<String>)> {
+        Some((Vec::new(), vec![self.id.clone()?]))
+    }
+
     fn share_url(&self) -> Option<String> {
         self.id
             .clone()
@@ -318,6 +456,18 @@ impl ListItem for Track {
         }
     }
 
+    fn album_id(&self) -> Option<String> {
+        self.album_id.clone()
+    }
+
+    fn disc_number(&self) -> Option<i32> {
+        Some(self.disc_number)
+    }
+
+    fn popularity(&self) -> Option<u32> {
+        self.popularity
+    }
+
     fn artists(&self) -> Option<Vec<Artist>> {
         Some(
             self.artist_ids
@@ -346,3 +496,39 @@ impl ListItem for Track {
         Box::new(self.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{sorted_order, Track};
+    use crate::command::{SortDirection, SortKey};
+
+    fn track(title: &str, artist: &str, release_date: Option<&str>) -> Option<Track> {
+        Some(Track {
+            title: title.to_string(),
+            artists: vec![artist.to_string()],
+            release_date: release_date.map(String::from),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_sorted_order() {
+        let tracks = vec![
+            track("b", "The Cure", Some("1989-05-02")),
+            None,
+            track("A", "Blondie", Some("1978")),
+            track("c", "Cocteau Twins", None),
+        ];
+
+        let order = sorted_order(&tracks, &SortKey::Title, &SortDirection::Ascending);
+        assert_eq!(order, vec![2, 0, 3, 1]);
+
+        // "The" is left out of artist names
+        let order = sorted_order(&tracks, &SortKey::Artist, &SortDirection::Ascending);
+        assert_eq!(order, vec![2, 3, 0, 1]);
+
+        // items that aren't tracks stay last either way
+        let order = sorted_order(&tracks, &SortKey::Released, &SortDirection::Descending);
+        assert_eq!(order, vec![0, 2, 3, 1]);
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::rc::Rc;
-use std::sync::{mpsc, Arc};
+use std::sync::{mpsc, Arc, Mutex};
 use std::time::Duration;
 
 use dbus::arg::{RefArg, Variant};
@@ -18,6 +18,7 @@ use crate::model::playable::Playable;
 use crate::model::playlist::Playlist;
 use crate::model::show::Show;
 use crate::model::track::Track;
+use crate::playback_speed::{MAX_PLAYBACK_SPEED, MIN_PLAYBACK_SPEED};
 use crate::queue::{Queue, RepeatSetting};
 use crate::spotify::{PlayerEvent, Spotify, UriType, VOLUME_PERCENT};
 use crate::traits::ListItem;
@@ -25,11 +26,19 @@ use regex::Regex;
 
 type Metadata = HashMap<String, Variant<Box<dyn RefArg>>>;
 
-struct MprisState(String, Option<Playable>);
+/// Position changes smaller than this are taken as timing jitter rather than
+/// a seek.
+const SEEK_TOLERANCE: Duration = Duration::from_millis(100);
+
+/// The playback status, the current item and the position in microseconds if
+/// it jumped.
+struct MprisState(String, Option<Playable>, Option<i64>);
 
 fn get_playbackstatus(spotify: Spotify) -> String {
     match spotify.get_current_status() {
-        PlayerEvent::Playing(_) | PlayerEvent::FinishedTrack => "Playing",
+        PlayerEvent::Loading(..) | PlayerEvent::Playing(_) | PlayerEvent::FinishedTrack => {
+            "Playing"
+        }
         PlayerEvent::Paused(_) => "Paused",
         _ => "Stopped",
     }
@@ -43,8 +52,9 @@ fn get_metadata(playable: Option<Playable>, spotify: Spotify, library: Arc<Libra
     // This is necessary because SimplifiedTrack objects don't contain a cover_url
     let playable_full = playable.and_then(|p| match p {
         Playable::Track(track) => {
-            if track.cover_url.is_some() {
-                // We already have `cover_url`, no need to fetch the full track
+            if track.cover_url.is_some() || track.id.is_none() {
+                // We already have `cover_url`, or the track is a local file
+                // that can't be fetched
                 Some(Playable::Track(track))
             } else {
                 spotify
@@ -269,7 +279,7 @@ fn run_dbus_server(
                 iter.append(
                     match queue1.get_repeat() {
                         RepeatSetting::None => "None",
-                        RepeatSetting::RepeatTrack => "Track",
+                        RepeatSetting::RepeatTrack | RepeatSetting::RepeatCount(_) => "Track",
                         RepeatSetting::RepeatPlaylist => "Playlist",
                     }
                     .to_string(),
@@ -324,7 +334,11 @@ fn run_dbus_server(
         f.property::<f64, _>("Volume", ())
             .access(Access::ReadWrite)
             .on_get(move |i, _| {
-                i.append(spotify1.volume() as f64 / 65535_f64);
+                if spotify1.is_muted() {
+                    i.append(0.0);
+                } else {
+                    i.append(spotify1.volume() as f64 / 65535_f64);
+                }
                 Ok(())
             })
             .on_set(move |i, _| {
@@ -339,19 +353,21 @@ fn run_dbus_server(
             })
     };
 
-    let property_rate = f
-        .property::<f64, _>("Rate", ())
-        .access(Access::Read)
-        .on_get(|iter, _| {
-            iter.append(1.0);
-            Ok(())
-        });
+    let property_rate = {
+        let spotify = spotify.clone();
+        f.property::<f64, _>("Rate", ())
+            .access(Access::Read)
+            .on_get(move |iter, _| {
+                iter.append(spotify.playback_speed() as f64);
+                Ok(())
+            })
+    };
 
     let property_minrate = f
         .property::<f64, _>("MinimumRate", ())
         .access(Access::Read)
         .on_get(|iter, _| {
-            iter.append(1.0);
+            iter.append(MIN_PLAYBACK_SPEED as f64);
             Ok(())
         });
 
@@ -359,7 +375,7 @@ fn run_dbus_server(
         .property::<f64, _>("MaximumRate", ())
         .access(Access::Read)
         .on_get(|iter, _| {
-            iter.append(1.0);
+            iter.append(MAX_PLAYBACK_SPEED as f64);
             Ok(())
         });
 
@@ -519,20 +535,9 @@ fn run_dbus_server(
         let queue = queue.clone();
         let spotify = spotify.clone();
         f.method("Seek", (), move |m| {
-            if let Some(current_track) = queue.get_current() {
+            if queue.get_current().is_some() {
                 let offset = m.msg.get1::<i64>().unwrap_or(0); // micros
-                let progress = spotify.get_current_progress();
-                let new_position = (progress.as_secs() * 1000) as i32
-                    + progress.subsec_millis() as i32
-                    + (offset / 1000) as i32;
-                let new_position = new_position.max(0) as u32;
-                let duration = current_track.duration();
-
-                if new_position < duration {
-                    spotify.seek(new_position);
-                } else {
-                    queue.next(true);
-                }
+                spotify.seek_relative((offset / 1000) as i32);
             }
             Ok(vec![m.msg.method_return()])
         })
@@ -724,6 +729,18 @@ fn run_dbus_server(
                 changed.to_emit_message(&Path::new("/org/mpris/MediaPlayer2".to_string()).unwrap()),
             )
             .unwrap();
+
+            if let Some(position) = state.2 {
+                debug!("mpris Seeked: {}us", position);
+                let seeked = dbus::Message::new_signal(
+                    "/org/mpris/MediaPlayer2",
+                    "org.mpris.MediaPlayer2.Player",
+                    "Seeked",
+                )
+                .unwrap()
+                .append1(position);
+                conn.send(seeked).unwrap();
+            }
         }
     }
 }
@@ -733,6 +750,9 @@ pub struct MprisManager {
     tx: mpsc::Sender<MprisState>,
     queue: Arc<Queue>,
     spotify: Spotify,
+    /// The URI of the current item and the playback status at the last
+    /// update, to tell seeks from regular playback.
+    last_status: Arc<Mutex<Option<(String, PlayerEvent)>>>,
 }
 
 impl MprisManager {
@@ -752,12 +772,53 @@ impl MprisManager {
             });
         }
 
-        MprisManager { tx, queue, spotify }
+        MprisManager {
+            tx,
+            queue,
+            spotify,
+            last_status: Arc::new(Mutex::new(None)),
+        }
     }
 
     pub fn update(&self) {
         let status = get_playbackstatus(self.spotify.clone());
         let track = self.queue.get_current();
-        self.tx.send(MprisState(status, track)).unwrap();
+        let seeked = self.seeked(track.as_ref());
+        self.tx.send(MprisState(status, track, seeked)).unwrap();
+    }
+
+    /// The position in microseconds if it jumped since the last update, by
+    /// a seek or because the player corrected it. This includes items that
+    /// start at a later position, as clients assume they start at the top.
+    fn seeked(&self, track: Option<&Playable>) -> Option<i64> {
+        let status = self.spotify.get_current_status();
+        let uri = track.map(|track| track.uri());
+        let mut last_status = self.last_status.lock().unwrap();
+        let expected = match last_status.take() {
+            Some((last_uri, last)) if Some(&last_uri) == uri.as_ref() => match last {
+                PlayerEvent::Playing(playback_start) => playback_start
+                    .elapsed()
+                    .unwrap_or_default()
+                    .mul_f32(self.spotify.playback_speed()),
+                PlayerEvent::Loading(_, position) | PlayerEvent::Paused(position) => position,
+                _ => Duration::ZERO,
+            },
+            _ => Duration::ZERO,
+        };
+        *last_status = uri.map(|uri| (uri, status.clone()));
+
+        match status {
+            PlayerEvent::Loading(..) | PlayerEvent::Playing(_) | PlayerEvent::Paused(_) => {
+                // The same position as the status bar shows
+                let position = self.spotify.get_current_progress();
+                let drift = if position > expected {
+                    position - expected
+                } else {
+                    expected - position
+                };
+                (drift > SEEK_TOLERANCE).then(|| position.as_micros() as i64)
+            }
+            _ => None,
+        }
     }
 }
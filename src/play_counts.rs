@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use log::{debug, error};
+
+use crate::config::cache_path;
+use crate::model::playable::Playable;
+use crate::serialization::{Serializer, JSON};
+use crate::spotify::PlayerEvent;
+
+const PLAY_COUNTS_FILE: &str = "playcounts.json";
+
+/// The plays of a track, along with what's needed to show it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct TrackPlays {
+    title: String,
+    artists: Vec<String>,
+    played_at: Vec<DateTime<Utc>>,
+}
+
+/// The track that is playing, and how long it has been played for.
+struct Listen {
+    playable: Playable,
+    /// How long the track was played before the last pause.
+    played: Duration,
+    playing_since: Option<Instant>,
+}
+
+/// Counts how often each track was played to the end, by track ID. Tracks
+/// that were mostly skipped by seeking aren't counted.
+pub struct PlayCounts {
+    tracks: RwLock<HashMap<String, TrackPlays>>,
+    listen: RwLock<Option<Listen>>,
+}
+
+impl PlayCounts {
+    pub fn new() -> PlayCounts {
+        let tracks = match JSON.load(cache_path(PLAY_COUNTS_FILE)) {
+            Ok(tracks) => tracks,
+            Err(e) => {
+                debug!("no play counts loaded: {}", e);
+                HashMap::new()
+            }
+        };
+        PlayCounts {
+            tracks: RwLock::new(tracks),
+            listen: RwLock::new(None),
+        }
+    }
+
+    /// Follow playback with a player event, while `playable` is the current
+    /// item of the queue.
+    pub fn update(&self, event: &PlayerEvent, playable: Option<&Playable>) {
+        let mut listen = self.listen.write().unwrap();
+        match event {
+            PlayerEvent::Playing(_) => {
+                let uri = playable.map(|playable| playable.uri());
+                if listen.as_ref().map(|listen| listen.playable.uri()) != uri {
+                    *listen = playable.map(|playable| Listen {
+                        playable: playable.clone(),
+                        played: Duration::ZERO,
+                        playing_since: None,
+                    });
+                }
+                if let Some(listen) = listen.as_mut() {
+                    listen.playing_since.get_or_insert_with(Instant::now);
+                }
+            }
+            PlayerEvent::Paused(_) | PlayerEvent::Loading(_, _) => {
+                if let Some(listen) = listen.as_mut() {
+                    if let Some(since) = listen.playing_since.take() {
+                        listen.played += since.elapsed();
+                    }
+                }
+            }
+            // Ends the listen, so a track that plays again on repeat starts
+            // a new one
+            PlayerEvent::FinishedTrack => {
+                if let Some(mut listen) = listen.take() {
+                    if let Some(since) = listen.playing_since.take() {
+                        listen.played += since.elapsed();
+                    }
+                    let duration = Duration::from_millis(listen.playable.duration().into());
+                    if listen.played >= duration / 2 {
+                        self.add(&listen.playable);
+                    }
+                }
+            }
+            PlayerEvent::Stopped => *listen = None,
+            _ => {}
+        }
+    }
+
+    fn add(&self, playable: &Playable) {
+        let track = match playable {
+            Playable::Track(track) => track,
+            Playable::Episode(_) => return,
+        };
+        let id = match &track.id {
+            Some(id) => id.clone(),
+            None => return,
+        };
+
+        let mut tracks = self.tracks.write().unwrap();
+        tracks
+            .entry(id)
+            .or_insert_with(|| TrackPlays {
+                title: track.title.clone(),
+                artists: track.artists.clone(),
+                played_at: Vec::new(),
+            })
+            .played_at
+            .push(Utc::now());
+
+        if let Err(e) = JSON.write(cache_path(PLAY_COUNTS_FILE), &*tracks) {
+            error!("could not save play counts: {}", e);
+        }
+    }
+
+    /// The most played tracks since `since`, as their play count and their
+    /// artists and title, most played first.
+    pub fn top_tracks(&self, since: Option<DateTime<Utc>>) -> Vec<(usize, String)> {
+        let tracks = self.tracks.read().unwrap();
+        let counts = tracks.values().map(|track| {
+            let name = format!("{} - {}", track.artists.join(", "), track.title);
+            (name, count_since(&track.played_at, since))
+        });
+        top(counts)
+    }
+
+    /// The most played artists since `since`, as their play count and their
+    /// name, most played first.
+    pub fn top_artists(&self, since: Option<DateTime<Utc>>) -> Vec<(usize, String)> {
+        let tracks = self.tracks.read().unwrap();
+        let counts = tracks.values().flat_map(|track| {
+            let count = count_since(&track.played_at, since);
+            track
+                .artists
+                .iter()
+                .map(move |artist| (artist.clone(), count))
+        });
+        top(counts)
+    }
+}
+
+fn count_since(played_at: &[DateTime<Utc>], since: Option<DateTime<Utc>>) -> usize {
+    match since {
+        Some(since) => played_at.iter().filter(|&&time| time >= since).count(),
+        None => played_at.len(),
+    }
+}
+
+/// Sum up the counts by name, and order them by count and then by name,
+/// leaving out names that weren't played.
+fn top(counts: impl Iterator<Item = (String, usize)>) -> Vec<(usize, String)> {
+    let mut totals: HashMap<String, usize> = HashMap::new();
+    for (name, count) in counts {
+        *totals.entry(name).or_default() += count;
+    }
+    let mut top: Vec<(usize, String)> = totals
+        .into_iter()
+        .filter(|&(_, count)| count > 0)
+        .map(|(name, count)| (count, name))
+        .collect();
+    top.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    top
+}
+
+#[cfg(test)]
+mod tests {
+    use super::top;
+
+    #[test]
+    fn test_top() {
+        let counts = vec![
+            ("b".to_string(), 2),
+            ("a".to_string(), 1),
+            ("c".to_string(), 0),
+            ("a".to_string(), 1),
+            ("d".to_string(), 3),
+        ];
+        assert_eq!(
+            top(counts.into_iter()),
+            vec![(3, "d".into()), (2, "a".into()), (2, "b".into())]
+        );
+    }
+}
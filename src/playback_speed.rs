@@ -0,0 +1,104 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use librespot_playback::audio_backend::{Sink, SinkResult};
+use librespot_playback::convert::Converter;
+use librespot_playback::decoder::AudioPacket;
+use librespot_playback::NUM_CHANNELS;
+
+pub const MIN_PLAYBACK_SPEED: f32 = 0.5;
+pub const MAX_PLAYBACK_SPEED: f32 = 3.0;
+
+/// The playback speed, shared between the worker, the audio sink and the UI.
+#[derive(Clone)]
+pub struct PlaybackSpeed(Arc<AtomicU32>);
+
+impl PlaybackSpeed {
+    pub fn new() -> PlaybackSpeed {
+        PlaybackSpeed(Arc::new(AtomicU32::new(1.0_f32.to_bits())))
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, speed: f32) {
+        let speed = speed.clamp(MIN_PLAYBACK_SPEED, MAX_PLAYBACK_SPEED);
+        self.0.store(speed.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl Default for PlaybackSpeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An audio [Sink] that resamples the decoded audio to change the playback
+/// speed. This also shifts the pitch, which is acceptable for speech.
+pub struct SpeedSink {
+    sink: Box<dyn Sink>,
+    speed: PlaybackSpeed,
+    /// The last frame of the previous packet, to interpolate across packets.
+    last_frame: Vec<f64>,
+    /// The read position relative to `last_frame`, in frames.
+    position: f64,
+}
+
+impl SpeedSink {
+    pub fn new(sink: Box<dyn Sink>, speed: PlaybackSpeed) -> SpeedSink {
+        SpeedSink {
+            sink,
+            speed,
+            last_frame: vec![0.0; NUM_CHANNELS as usize],
+            position: 0.0,
+        }
+    }
+
+    /// Linearly interpolate `samples` at the current speed.
+    fn resample(&mut self, samples: &[f64], speed: f64) -> Vec<f64> {
+        let channels = NUM_CHANNELS as usize;
+        let frames: Vec<&[f64]> = std::iter::once(&self.last_frame[..])
+            .chain(samples.chunks_exact(channels))
+            .collect();
+
+        let mut output = Vec::with_capacity((samples.len() as f64 / speed) as usize + channels);
+        while (self.position as usize) + 1 < frames.len() {
+            let index = self.position as usize;
+            let fraction = self.position - index as f64;
+            for channel in 0..channels {
+                let from = frames[index][channel];
+                let to = frames[index + 1][channel];
+                output.push(from + (to - from) * fraction);
+            }
+            self.position += speed;
+        }
+
+        self.position -= (frames.len() - 1) as f64;
+        self.last_frame = frames[frames.len() - 1].to_vec();
+        output
+    }
+}
+
+impl Sink for SpeedSink {
+    fn start(&mut self) -> SinkResult<()> {
+        self.sink.start()
+    }
+
+    fn stop(&mut self) -> SinkResult<()> {
+        self.last_frame.fill(0.0);
+        self.position = 0.0;
+        self.sink.stop()
+    }
+
+    fn write(&mut self, packet: AudioPacket, converter: &mut Converter) -> SinkResult<()> {
+        let speed = self.speed.get() as f64;
+        match packet {
+            AudioPacket::Samples(samples) if speed != 1.0 => {
+                let samples = self.resample(&samples, speed);
+                self.sink.write(AudioPacket::Samples(samples), converter)
+            }
+            packet => self.sink.write(packet, converter),
+        }
+    }
+}
@@ -1,8 +1,12 @@
 use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 #[cfg(feature = "notify")]
 use std::sync::atomic::AtomicU32;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize};
 use std::sync::{Arc, RwLock};
 
+use librespot_core::spotify_id::{SpotifyAudioType, SpotifyId};
 use log::{debug, error, info};
 #[cfg(feature = "notify")]
 use notify_rust::{Hint, Notification, Urgency};
@@ -10,12 +14,21 @@ use notify_rust::{Hint, Notification, Urgency};
 use rand::prelude::*;
 use strum_macros::Display;
 
-use crate::config::{Config, NotificationFormat, PlaybackState};
+use crate::config::{self, Config, NotificationFormat, PlaybackState, QueueState, ShuffleMode};
+use crate::events::Event;
+use crate::history::History;
 use crate::library::Library;
+use crate::local_player::local_path;
 use crate::model::playable::Playable;
+use crate::model::track::Track;
+use crate::serialization::{Serializer, JSON};
 use crate::spotify::PlayerEvent;
 use crate::spotify::Spotify;
 
+/// The amount of tracks that played last that autoplay bases its
+/// recommendations on, which is the most the API accepts.
+const AUTOPLAY_SEEDS: usize = 5;
+
 /// Repeat behavior for the [Queue].
 #[derive(Display, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum RepeatSetting {
@@ -25,14 +38,49 @@ pub enum RepeatSetting {
     RepeatPlaylist,
     #[serde(rename = "track")]
     RepeatTrack,
+    /// Play each item the given amount of times before advancing.
+    #[serde(rename = "count")]
+    RepeatCount(u8),
+}
+
+/// The queue as exported to and imported from a file.
+#[derive(Serialize, Deserialize, Debug)]
+struct QueueExport {
+    queue: Vec<Playable>,
+    current_track: Option<usize>,
+    /// The position in the current item to resume it at.
+    #[serde(default)]
+    track_progress: std::time::Duration,
+    random_order: Option<Vec<usize>>,
+    shuffle: bool,
+    repeat: RepeatSetting,
+}
+
+/// The queue as it was before duplicates were removed from it, so the removal
+/// can be undone.
+struct Deduplication {
+    queue: Vec<Playable>,
+    random_order: Option<Vec<usize>>,
+    /// Which items of `queue` were kept.
+    keep: Vec<bool>,
 }
 
 /// Events that are specific to the [Queue].
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 pub enum QueueEvent {
     /// Request the player to 'preload' a track, basically making sure that
     /// transitions between tracks can be uninterrupted.
     PreloadTrackRequest,
+    /// The item that follows the currently playing one changed, which makes
+    /// an earlier preload stale.
+    NextTrackChanged,
+    /// The recommendations to continue playback with after the queue ran out
+    /// were fetched.
+    Autoplay,
+    /// The last item of the queue finished playing, and there's nothing to
+    /// continue with.
+    #[serde(rename = "QueueFinished")]
+    Finished,
 }
 
 /// The queue determines the playback order of
@@ -51,12 +99,46 @@ pub struct Queue {
     /// currently playing song.
     #[cfg(feature = "notify")]
     notification_id: Arc<AtomicU32>,
+    /// The unavailable items that were skipped in a row, used to stop
+    /// playback once every item in the queue turned out unavailable.
+    unavailable_skips: UnavailableSkips,
+    /// How many more times the current item is played while repeating it a
+    /// number of times.
+    repeat_remaining: AtomicU8,
+    /// Whether playback stops once the current item has finished, instead of
+    /// advancing the queue.
+    stop_after_track: AtomicBool,
+    /// Whether the queue changed since its state was last saved.
+    modified: AtomicBool,
+    /// Whether the item following the current one was preloaded.
+    preloaded: AtomicBool,
+    /// The last removal of duplicates, which can be undone as long as the
+    /// queue wasn't changed since.
+    deduplication: RwLock<Option<Deduplication>>,
+    /// Whether the current item is the paused one of the restored queue, so
+    /// that an unavailable one is replaced without starting playback.
+    restoring: AtomicBool,
+    /// The recommendations fetched to continue playback with once the queue
+    /// ran out, while autoplay is enabled.
+    recommended: Arc<RwLock<Vec<Playable>>>,
     library: Arc<Library>,
+    /// The played items, which the `fresh` shuffle mode plays last.
+    history: Arc<History>,
 }
 
 impl Queue {
-    pub fn new(spotify: Spotify, cfg: Arc<Config>, library: Arc<Library>) -> Queue {
-        let queue_state = cfg.state().queuestate.clone();
+    pub fn new(
+        spotify: Spotify,
+        cfg: Arc<Config>,
+        library: Arc<Library>,
+        history: Arc<History>,
+    ) -> Queue {
+        let resume_playback = cfg.values().resume_playback.unwrap_or(false);
+        let queue_state = if remember_queue(&cfg) {
+            retain_restorable(cfg.state().queuestate.clone())
+        } else {
+            QueueState::default()
+        };
         let playback_state = cfg.state().playback_state.clone();
         let queue = Queue {
             queue: Arc::new(RwLock::new(queue_state.queue)),
@@ -66,14 +148,36 @@ impl Queue {
             cfg,
             #[cfg(feature = "notify")]
             notification_id: Arc::new(AtomicU32::new(0)),
+            unavailable_skips: UnavailableSkips::default(),
+            repeat_remaining: AtomicU8::new(queue_state.repeat_remaining),
+            stop_after_track: AtomicBool::new(false),
+            modified: AtomicBool::new(false),
+            preloaded: AtomicBool::new(false),
+            deduplication: RwLock::new(None),
+            restoring: AtomicBool::new(false),
+            recommended: Arc::new(RwLock::new(Vec::new())),
             library,
+            history,
         };
 
         if let Some(playable) = queue.get_current() {
+            let min_duration = if resume_playback {
+                0
+            } else {
+                queue
+                    .cfg
+                    .values()
+                    .resume_min_duration_ms
+                    .unwrap_or(config::DEFAULT_RESUME_MIN_DURATION_MS)
+            };
             spotify.load(
                 &playable,
                 playback_state == PlaybackState::Playing,
-                queue_state.track_progress.as_millis() as u32,
+                resume_position(
+                    playable.duration(),
+                    queue_state.track_progress.as_millis() as u32,
+                    min_duration,
+                ),
             );
             spotify.update_track();
             match playback_state {
@@ -84,6 +188,10 @@ impl Queue {
                     spotify.pause();
                 }
             }
+            queue.restoring.store(
+                playback_state != PlaybackState::Playing,
+                std::sync::atomic::Ordering::Relaxed,
+            );
         }
 
         queue
@@ -153,23 +261,29 @@ impl Queue {
     /// Insert `track` as the item that should logically follow the currently
     /// playing item, taking into account shuffle status.
     pub fn insert_after_current(&self, track: Playable) {
+        self.insert_all_after_current(vec![track]);
+    }
+
+    /// Insert `tracks` in their order as the items that should logically
+    /// follow the currently playing item, taking into account shuffle status.
+    pub fn insert_all_after_current(&self, tracks: Vec<Playable>) {
         if let Some(index) = self.get_current_index() {
-            let mut random_order = self.random_order.write().unwrap();
-            if let Some(order) = random_order.as_mut() {
-                let next_i = order.iter().position(|&i| i == index).unwrap();
-                // shift everything after the insertion in order
-                for item in order.iter_mut() {
-                    if *item > index {
-                        *item += 1;
-                    }
+            let count = tracks.len();
+            {
+                let mut random_order = self.random_order.write().unwrap();
+                if let Some(order) = random_order.as_mut() {
+                    let position = order.iter().position(|&i| i == index).unwrap();
+                    insert_order(order, position + 1, index + 1, count);
                 }
-                // finally, add the next track index
-                order.insert(next_i + 1, index + 1);
+                let mut q = self.queue.write().unwrap();
+                q.splice(index + 1..index + 1, tracks);
             }
-            let mut q = self.queue.write().unwrap();
-            q.insert(index + 1, track);
+            self.set_modified();
+            self.handle_event(QueueEvent::NextTrackChanged);
         } else {
-            self.append(track);
+            for track in tracks {
+                self.append(track);
+            }
         }
     }
 
@@ -177,36 +291,38 @@ impl Queue {
     pub fn append(&self, track: Playable) {
         let mut random_order = self.random_order.write().unwrap();
         if let Some(order) = random_order.as_mut() {
-            let index = order.len().saturating_sub(1);
-            order.push(index);
+            order.push(order.len());
         }
 
         let mut q = self.queue.write().unwrap();
         q.push(track);
+        self.set_modified();
     }
 
     /// Append `tracks` after the currently playing item, taking into account
     /// shuffle status. Returns the amount of added items.
     pub fn append_next(&self, tracks: &Vec<Playable>) -> usize {
         let mut q = self.queue.write().unwrap();
+        let current = *self.current_track.read().unwrap();
 
-        {
-            let mut random_order = self.random_order.write().unwrap();
-            if let Some(order) = random_order.as_mut() {
-                order.extend((q.len().saturating_sub(1))..(q.len() + tracks.len()));
-            }
-        }
-
-        let first = match *self.current_track.read().unwrap() {
+        let first = match current {
             Some(index) => index + 1,
             None => q.len(),
         };
 
+        if let Some(order) = self.random_order.write().unwrap().as_mut() {
+            let position = current
+                .and_then(|index| order.iter().position(|&i| i == index))
+                .map_or(order.len(), |position| position + 1);
+            insert_order(order, position, first, tracks.len());
+        }
+
         let mut i = first;
         for track in tracks {
             q.insert(i, track.clone());
             i += 1;
         }
+        self.set_modified();
 
         first
     }
@@ -222,6 +338,7 @@ impl Queue {
             }
             q.remove(index);
         }
+        self.set_modified();
 
         // if the queue is empty stop playback
         let len = self.queue.read().unwrap().len();
@@ -268,6 +385,7 @@ impl Queue {
 
         let mut q = self.queue.write().unwrap();
         q.clear();
+        self.set_modified();
 
         let mut random_order = self.random_order.write().unwrap();
         if let Some(o) = random_order.as_mut() {
@@ -280,24 +398,126 @@ impl Queue {
         self.queue.read().unwrap().len()
     }
 
-    /// Shift the item at `from` in `self.queue` to `to`.
+    /// Shift the item at `from` in `self.queue` to `to`. The currently
+    /// playing item keeps playing, and while shuffling the item moves past the
+    /// same items in the shuffled order.
     pub fn shift(&self, from: usize, to: usize) {
         let mut queue = self.queue.write().unwrap();
+        if from >= queue.len() || to >= queue.len() || from == to {
+            return;
+        }
         let item = queue.remove(from);
         queue.insert(to, item);
+        self.set_modified();
 
-        // if the currently playing track is affected by the shift, update its
-        // index
         let mut current = self.current_track.write().unwrap();
         if let Some(index) = *current {
-            if index == from {
-                current.replace(to);
-            } else if index == to && from > index {
-                current.replace(to + 1);
-            } else if index == to && from < index {
-                current.replace(to - 1);
+            current.replace(shifted_index(index, from, to));
+        }
+
+        if let Some(order) = self.random_order.write().unwrap().as_mut() {
+            shift_order(order, from, to);
+        }
+    }
+
+    /// Remove the later occurrences of items that are in the queue more than
+    /// once, keeping the currently playing one. Returns the amount of removed
+    /// items.
+    pub fn dedup(&self) -> usize {
+        let removed = {
+            let mut queue = self.queue.write().unwrap();
+            let mut current = self.current_track.write().unwrap();
+            let keep = dedup_keep(&queue, *current);
+            let removed = keep.iter().filter(|&&keep| !keep).count();
+            if removed == 0 {
+                return 0;
             }
+
+            let mut random_order = self.random_order.write().unwrap();
+            let previous = Deduplication {
+                queue: queue.clone(),
+                random_order: random_order.clone(),
+                keep: keep.clone(),
+            };
+            (*current, *random_order) = remap_indices(&keep, *current, random_order.take());
+            let mut kept = keep.iter();
+            queue.retain(|_| *kept.next().unwrap());
+            *self.deduplication.write().unwrap() = Some(previous);
+            removed
+        };
+        self.set_modified();
+        self.handle_event(QueueEvent::NextTrackChanged);
+        removed
+    }
+
+    /// Remove the items that would play after the current one, in the
+    /// shuffled order while shuffling. The current item keeps playing, and
+    /// without one the whole queue is upcoming.
+    pub fn clear_upcoming(&self) -> usize {
+        self.clear_around_current(true)
+    }
+
+    /// Remove the items that played before the current one, in the shuffled
+    /// order while shuffling. The current item keeps playing.
+    pub fn clear_played(&self) -> usize {
+        self.clear_around_current(false)
+    }
+
+    /// Remove the items on one side of the current item and return how many
+    /// were removed.
+    fn clear_around_current(&self, upcoming: bool) -> usize {
+        let removed = {
+            let mut queue = self.queue.write().unwrap();
+            let mut current = self.current_track.write().unwrap();
+            let mut random_order = self.random_order.write().unwrap();
+            let keep = clear_keep(queue.len(), *current, random_order.as_deref(), upcoming);
+            let removed = keep.iter().filter(|&&keep| !keep).count();
+            if removed == 0 {
+                return 0;
+            }
+
+            (*current, *random_order) = remap_indices(&keep, *current, random_order.take());
+            let mut kept = keep.iter();
+            queue.retain(|_| *kept.next().unwrap());
+            removed
+        };
+        self.set_modified();
+        self.handle_event(QueueEvent::NextTrackChanged);
+        removed
+    }
+
+    /// Restore the items that were removed by the last [Queue::dedup()].
+    /// Returns whether there was a removal to undo, which is only the case
+    /// while the queue is unchanged since.
+    pub fn undo_dedup(&self) -> bool {
+        {
+            let previous = match self.deduplication.write().unwrap().take() {
+                Some(previous) => previous,
+                None => return false,
+            };
+            let mut queue = self.queue.write().unwrap();
+            let deduplicated = previous
+                .queue
+                .iter()
+                .zip(&previous.keep)
+                .filter(|(_, &keep)| keep)
+                .map(|(playable, _)| playable.uri());
+            if !deduplicated.eq(queue.iter().map(|playable| playable.uri())) {
+                return false;
+            }
+
+            let mut current = self.current_track.write().unwrap();
+            *current = current.and_then(|index| {
+                (0..previous.keep.len())
+                    .filter(|&i| previous.keep[i])
+                    .nth(index)
+            });
+            *queue = previous.queue;
+            *self.random_order.write().unwrap() = previous.random_order;
         }
+        self.set_modified();
+        self.handle_event(QueueEvent::NextTrackChanged);
+        true
     }
 
     /// Play the item at `index` in `self.queue`.
@@ -313,10 +533,22 @@ impl Queue {
             index = rng.gen_range(0..queue_length);
         }
 
+        let position = self
+            .queue
+            .write()
+            .unwrap()
+            .get_mut(index)
+            .map_or(0, |playable| playable.take_resume_position());
         if let Some(track) = &self.queue.read().unwrap().get(index) {
-            self.spotify.load(track, true, 0);
+            self.restoring
+                .store(false, std::sync::atomic::Ordering::Relaxed);
+            self.spotify.load(track, true, position);
+            self.reset_repeat_remaining();
+            self.preloaded
+                .store(false, std::sync::atomic::Ordering::Relaxed);
             let mut current = self.current_track.write().unwrap();
             current.replace(index);
+            self.set_modified();
             self.spotify.update_track();
 
             #[cfg(feature = "notify")]
@@ -377,49 +609,199 @@ impl Queue {
     /// used, and the next track will actually be played. This should be used
     /// when going to the next entry in the queue is the wanted behavior.
     pub fn next(&self, manual: bool) {
-        let q = self.queue.read().unwrap();
+        // Skipping manually disarms stopping after the track as well.
+        let stop_after_track = self.disarm_stop_after_track();
+        if stop_after_track && !manual {
+            info!("track finished, stopping as requested");
+            self.spotify.stop();
+            return;
+        }
+
+        // The queue isn't locked here, as playing an item takes its resume
+        // position out of the queue.
+        let len = self.len();
         let current = *self.current_track.read().unwrap();
         let repeat = self.cfg.state().repeat;
 
+        let remaining = self.get_repeat_remaining();
         if repeat == RepeatSetting::RepeatTrack && !manual {
             if let Some(index) = current {
                 self.play(index, false, false);
             }
+        } else if let (RepeatSetting::RepeatCount(_), false, Some(index), 1..) =
+            (repeat, manual, current, remaining)
+        {
+            self.play(index, false, false);
+            self.repeat_remaining
+                .store(remaining - 1, std::sync::atomic::Ordering::Relaxed);
         } else if let Some(index) = self.next_index() {
             self.play(index, false, false);
             if repeat == RepeatSetting::RepeatTrack && manual {
                 self.set_repeat(RepeatSetting::RepeatPlaylist);
             }
-        } else if repeat == RepeatSetting::RepeatPlaylist && q.len() > 0 {
-            let random_order = self.random_order.read().unwrap();
-            self.play(
-                random_order.as_ref().map(|o| o[0]).unwrap_or(0),
-                false,
-                false,
-            );
+        } else if repeat == RepeatSetting::RepeatPlaylist && len > 0 {
+            let first = self
+                .random_order
+                .read()
+                .unwrap()
+                .as_ref()
+                .map_or(0, |o| o[0]);
+            self.play(first, false, false);
+        } else if !manual
+            && self.get_autoplay()
+            && self.autoplay(&self.queue.read().unwrap(), current)
+        {
+            info!("queue finished, fetching recommendations to continue with");
+        } else if !manual {
+            self.finish();
         } else {
             self.spotify.stop();
         }
     }
 
+    /// Stop playback after the last item of the queue, telling the other
+    /// components that the queue finished rather than that playback stopped.
+    fn finish(&self) {
+        info!("queue finished");
+        self.spotify.stop();
+        self.spotify.send_event(Event::Queue(QueueEvent::Finished));
+    }
+
+    /// Play the first item after the current one in the playback order that
+    /// is of another album, continuing from the start while repeating the
+    /// queue.
+    pub fn next_album(&self) {
+        self.disarm_stop_after_track();
+        let order: Vec<usize> = match self.random_order.read().unwrap().as_ref() {
+            Some(order) => order.clone(),
+            None => (0..self.len()).collect(),
+        };
+        let next = {
+            let q = self.queue.read().unwrap();
+            let current = match *self.current_track.read().unwrap() {
+                Some(current) => current,
+                None => return,
+            };
+            let album = album_key(&q[current]);
+            let position = order.iter().position(|&i| i == current).unwrap_or(0);
+            let mut following = order[position + 1..].iter();
+            let next = following.find(|&&i| album.is_none() || album_key(&q[i]) != album);
+            match (next, self.get_repeat()) {
+                (Some(&next), _) => Some(next),
+                (None, RepeatSetting::RepeatPlaylist) => order.first().copied(),
+                (None, _) => None,
+            }
+        };
+        match next {
+            Some(index) => self.play(index, false, false),
+            None => self.spotify.stop(),
+        }
+    }
+
+    /// Skip the current item because it couldn't be played. Playback stops
+    /// when every item in the queue, or `unavailable_skip_limit` items, were
+    /// skipped in a row. Unlike
+    /// [Queue::next], this doesn't repeat the current item when repeating a
+    /// single track.
+    pub fn skip_unavailable(&self) {
+        // A restored queue stays paused at the next item that can be played
+        let play = |index| {
+            if self.restoring.load(std::sync::atomic::Ordering::Relaxed) {
+                self.load_paused(index);
+            } else {
+                self.play(index, false, false);
+            }
+        };
+
+        let limit = self.cfg.values().unavailable_skip_limit;
+        if !self.unavailable_skips.skip(self.len(), limit) {
+            info!("too many unavailable items in a row, stopping playback");
+            self.spotify.stop();
+        } else if let Some(index) = self.next_index() {
+            play(index);
+        } else if self.cfg.state().repeat == RepeatSetting::RepeatPlaylist {
+            let index = self
+                .random_order
+                .read()
+                .unwrap()
+                .as_ref()
+                .map(|o| o[0])
+                .unwrap_or(0);
+            play(index);
+        } else {
+            self.finish();
+        }
+    }
+
+    /// Make the item at `index` the current one without playing it.
+    fn load_paused(&self, index: usize) {
+        if let Some(playable) = self.queue.read().unwrap().get(index) {
+            self.spotify.load(playable, false, 0);
+            self.current_track.write().unwrap().replace(index);
+            self.set_modified();
+            self.spotify.update_track();
+            self.spotify.pause();
+        }
+    }
+
+    /// Reset the count of unavailable items that were skipped in a row.
+    pub fn reset_unavailable_skips(&self) {
+        self.unavailable_skips.reset();
+    }
+
+    /// Whether playback stops once the current item has finished.
+    pub fn get_stop_after_track(&self) -> bool {
+        self.stop_after_track
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Arm or disarm stopping playback once the current item has finished.
+    /// It is disarmed again once that happens, or when skipping to another
+    /// item.
+    pub fn toggle_stop_after_track(&self) {
+        let armed = !self.get_stop_after_track();
+        self.stop_after_track
+            .store(armed, std::sync::atomic::Ordering::Relaxed);
+        self.spotify.set_stop_after_track(armed);
+        if !armed {
+            // The worker dropped the preloaded item while armed.
+            self.handle_event(QueueEvent::NextTrackChanged);
+        }
+    }
+
+    /// Disarm stopping once the current item has finished, returning whether
+    /// it was armed.
+    fn disarm_stop_after_track(&self) -> bool {
+        let armed = self
+            .stop_after_track
+            .swap(false, std::sync::atomic::Ordering::Relaxed);
+        if armed {
+            self.spotify.set_stop_after_track(false);
+        }
+        armed
+    }
+
     /// Play the previous item in the queue.
     pub fn previous(&self) {
-        let q = self.queue.read().unwrap();
+        self.disarm_stop_after_track();
+        // As in [Queue::next], the queue isn't locked while playing.
+        let len = self.len();
         let current = *self.current_track.read().unwrap();
         let repeat = self.cfg.state().repeat;
 
         if let Some(index) = self.previous_index() {
             self.play(index, false, false);
-        } else if repeat == RepeatSetting::RepeatPlaylist && q.len() > 0 {
+        } else if repeat == RepeatSetting::RepeatPlaylist && len > 0 {
             if self.get_shuffle() {
-                let random_order = self.random_order.read().unwrap();
-                self.play(
-                    random_order.as_ref().map(|o| o[q.len() - 1]).unwrap_or(0),
-                    false,
-                    false,
-                );
+                let last = self
+                    .random_order
+                    .read()
+                    .unwrap()
+                    .as_ref()
+                    .map_or(0, |o| o[len - 1]);
+                self.play(last, false, false);
             } else {
-                self.play(q.len() - 1, false, false);
+                self.play(len - 1, false, false);
             }
         } else if let Some(index) = current {
             self.play(index, false, false);
@@ -434,6 +816,24 @@ impl Queue {
     /// Set the current repeat behavior and save it to the configuration.
     pub fn set_repeat(&self, new: RepeatSetting) {
         self.cfg.with_state_mut(|mut s| s.repeat = new);
+        self.reset_repeat_remaining();
+    }
+
+    /// How many more times the current item is played before advancing,
+    /// while repeating items a number of times.
+    pub fn get_repeat_remaining(&self) -> u8 {
+        self.repeat_remaining
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Start counting the plays of an item anew.
+    fn reset_repeat_remaining(&self) {
+        let remaining = match self.get_repeat() {
+            RepeatSetting::RepeatCount(count) => count.saturating_sub(1),
+            _ => 0,
+        };
+        self.repeat_remaining
+            .store(remaining, std::sync::atomic::Ordering::Relaxed);
     }
 
     /// Get the current shuffle behavior.
@@ -441,6 +841,161 @@ impl Queue {
         self.cfg.state().shuffle
     }
 
+    /// Get how the queue is ordered while shuffling.
+    pub fn get_shuffle_mode(&self) -> ShuffleMode {
+        self.cfg.state().shuffle_mode
+    }
+
+    /// Set how the queue is ordered while shuffling. While shuffling, the
+    /// items after the current one are shuffled again in the new mode.
+    pub fn set_shuffle_mode(&self, new: ShuffleMode) {
+        self.cfg.with_state_mut(|mut s| s.shuffle_mode = new);
+        if !self.get_shuffle() {
+            return;
+        }
+
+        let current = *self.current_track.read().unwrap();
+        let q = self.queue.read().unwrap();
+        let mut random_order = self.random_order.write().unwrap();
+        if let Some(order) = random_order.as_mut() {
+            let played = current
+                .and_then(|current| order.iter().position(|&index| index == current))
+                .map_or(0, |position| position + 1);
+            let remainder = order.split_off(played);
+            order.extend(self.shuffled(&q, remainder, current));
+        }
+        drop(random_order);
+        drop(q);
+        self.set_modified();
+        self.handle_event(QueueEvent::NextTrackChanged);
+    }
+
+    /// Store the queue and the position in the current item in the user
+    /// state, and write it to disk. The queue is left out unless it should be
+    /// remembered.
+    pub fn save_state(&self) {
+        self.store_state(self.spotify.get_current_progress());
+    }
+
+    /// Save the state as ncspot quits. To resume playback where it was left,
+    /// the position is asked from the player rather than estimated.
+    pub fn save_state_on_quit(&self) {
+        let estimated = self.spotify.get_current_progress();
+        let progress = if self.cfg.values().resume_playback.unwrap_or(false) {
+            // The player doesn't know the position once it stopped
+            Some(self.spotify.request_position())
+                .filter(|position| !position.is_zero())
+                .unwrap_or(estimated)
+        } else {
+            estimated
+        };
+        self.store_state(progress);
+    }
+
+    fn store_state(&self, progress: std::time::Duration) {
+        self.modified
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+        let queue = self.queue.read().unwrap();
+        let random_order = self.random_order.read().unwrap();
+        let remember_queue = remember_queue(&self.cfg);
+        self.cfg.with_state_mut(|mut s| {
+            if !remember_queue {
+                s.queuestate = QueueState::default();
+                return;
+            }
+            debug!(
+                "saving state, {} items, current track: {:?}",
+                queue.len(),
+                self.get_current_index()
+            );
+            s.queuestate.queue = queue.clone();
+            s.queuestate.random_order = random_order.clone();
+            s.queuestate.current_track = self.get_current_index();
+            s.queuestate.track_progress = progress;
+            s.queuestate.repeat_remaining = self.get_repeat_remaining();
+        });
+        self.cfg.save_state();
+    }
+
+    /// Write the queue, the current item and the shuffle and repeat settings to
+    /// a JSON file.
+    pub fn export(&self, path: &Path) -> Result<(), String> {
+        let export = QueueExport {
+            queue: self.queue.read().unwrap().clone(),
+            current_track: self.get_current_index(),
+            track_progress: self.spotify.request_position(),
+            random_order: self.random_order.read().unwrap().clone(),
+            shuffle: self.get_shuffle(),
+            repeat: self.get_repeat(),
+        };
+        info!(
+            "exporting {} items to {}",
+            export.queue.len(),
+            path.display()
+        );
+        JSON.write(path, export).map(|_| ())
+    }
+
+    /// Replace the queue by one exported to a JSON file, without starting
+    /// playback. The current item is loaded at its exported position.
+    pub fn import(&self, path: &Path) -> Result<(), String> {
+        let export: QueueExport = JSON.load(path)?;
+        info!(
+            "importing {} items from {}",
+            export.queue.len(),
+            path.display()
+        );
+        self.clear();
+
+        let len = export.queue.len();
+        *self.queue.write().unwrap() = export.queue;
+        *self.current_track.write().unwrap() = export.current_track.filter(|&index| index < len);
+        self.set_repeat(export.repeat);
+        self.cfg.with_state_mut(|mut s| s.shuffle = export.shuffle);
+        let random_order = export.random_order.filter(|order| {
+            let mut sorted = order.clone();
+            sorted.sort_unstable();
+            sorted.into_iter().eq(0..len)
+        });
+        match (export.shuffle, random_order) {
+            (true, Some(order)) => *self.random_order.write().unwrap() = Some(order),
+            (shuffle, _) => self.set_shuffle(shuffle),
+        }
+        self.set_modified();
+
+        // Resume the current item where it was exported, like on startup
+        if let Some(playable) = self.get_current() {
+            let min_duration = self
+                .cfg
+                .values()
+                .resume_min_duration_ms
+                .unwrap_or(config::DEFAULT_RESUME_MIN_DURATION_MS);
+            self.spotify.load(
+                &playable,
+                false,
+                resume_position(
+                    playable.duration(),
+                    export.track_progress.as_millis() as u32,
+                    min_duration,
+                ),
+            );
+            self.spotify.update_track();
+            self.spotify.pause();
+        }
+        Ok(())
+    }
+
+    /// Whether the queue or the current item changed since the state was last
+    /// saved.
+    pub fn is_modified(&self) -> bool {
+        self.modified.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    fn set_modified(&self) {
+        self.modified
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
     /// Get the current order that is used to shuffle.
     pub fn get_random_order(&self) -> Arc<RwLock<Option<Vec<usize>>>> {
         self.random_order.clone()
@@ -452,22 +1007,93 @@ impl Queue {
         let mut order: Vec<usize> = Vec::with_capacity(q.len());
         let mut random: Vec<usize> = (0..q.len()).collect();
 
-        if let Some(current) = *self.current_track.read().unwrap() {
+        let current = *self.current_track.read().unwrap();
+        if let Some(current) = current {
             order.push(current);
             random.remove(current);
         }
 
-        let mut rng = rand::thread_rng();
-        random.shuffle(&mut rng);
-        order.extend(random);
+        order.extend(self.shuffled(&q, random, current));
 
         let mut random_order = self.random_order.write().unwrap();
         *random_order = Some(order);
     }
 
+    /// Shuffle `indices` into `queue` according to the shuffle mode, to be
+    /// played after the item at `previous`.
+    fn shuffled(
+        &self,
+        queue: &[Playable],
+        indices: Vec<usize>,
+        previous: Option<usize>,
+    ) -> Vec<usize> {
+        let mut rng = rand::thread_rng();
+        match self.get_shuffle_mode() {
+            ShuffleMode::Random => {
+                let mut indices = indices;
+                indices.shuffle(&mut rng);
+                indices
+            }
+            ShuffleMode::Albums => {
+                let keys: Vec<Option<&str>> = indices
+                    .iter()
+                    .map(|&index| album_key(&queue[index]))
+                    .collect();
+                let after = previous
+                    .and_then(|previous| queue.get(previous))
+                    .and_then(album_key);
+                album_order(&keys, after, &mut rng)
+                    .into_iter()
+                    .map(|position| indices[position])
+                    .collect()
+            }
+            ShuffleMode::Spread => {
+                let keys: Vec<(&str, &str)> = indices
+                    .iter()
+                    .map(|&index| spread_key(&queue[index]))
+                    .collect();
+                let after = previous
+                    .and_then(|previous| queue.get(previous))
+                    .map(|playable| spread_key(playable).0);
+                spread_order(&keys, after, &mut rng)
+                    .into_iter()
+                    .map(|position| indices[position])
+                    .collect()
+            }
+            ShuffleMode::Fresh => {
+                let window = self
+                    .cfg
+                    .values()
+                    .shuffle_fresh_window
+                    .unwrap_or(config::DEFAULT_SHUFFLE_FRESH_WINDOW);
+                let recent: Vec<String> = self
+                    .history
+                    .items
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .take(window)
+                    .map(Playable::uri)
+                    .collect();
+                let ranks: Vec<Option<usize>> = indices
+                    .iter()
+                    .map(|&index| {
+                        let uri = queue[index].uri();
+                        recent.iter().position(|recent| *recent == uri)
+                    })
+                    .collect();
+                fresh_order(&ranks, window, &mut rng)
+                    .into_iter()
+                    .map(|position| indices[position])
+                    .collect()
+            }
+        }
+    }
+
     /// Set the current shuffle behavior.
     pub fn set_shuffle(&self, new: bool) {
         self.cfg.with_state_mut(|mut s| s.shuffle = new);
+        self.set_modified();
         if new {
             self.generate_random_order();
         } else {
@@ -476,25 +1102,539 @@ impl Queue {
         }
     }
 
+    pub fn get_autoplay(&self) -> bool {
+        self.cfg.state().autoplay
+    }
+
+    /// Set whether playback continues with recommendations once the queue
+    /// runs out.
+    pub fn set_autoplay(&self, new: bool) {
+        self.cfg.with_state_mut(|mut s| s.autoplay = new);
+    }
+
+    /// Fetch recommendations based on the last tracks that played, to be
+    /// appended and played once they arrive. Returns whether there were
+    /// tracks to base them on.
+    fn autoplay(&self, queue: &[Playable], current: Option<usize>) -> bool {
+        let order = match self.random_order.read().unwrap().as_ref() {
+            Some(order) => order.clone(),
+            None => (0..queue.len()).collect(),
+        };
+        let played = current
+            .and_then(|current| order.iter().position(|&index| index == current))
+            .map_or(order.len(), |position| position + 1);
+        let seeds: Vec<String> = order[..played]
+            .iter()
+            .rev()
+            .filter_map(|&index| match &queue[index] {
+                Playable::Track(track) => track.id.clone(),
+                Playable::Episode(_) => None,
+            })
+            .take(AUTOPLAY_SEEDS)
+            .collect();
+        if seeds.is_empty() {
+            return false;
+        }
+
+        let queued: HashSet<String> = queue.iter().filter_map(|item| item.id()).collect();
+        let spotify = self.spotify.clone();
+        let recommended = self.recommended.clone();
+        std::thread::spawn(move || {
+            let seeds = seeds.iter().map(|id| id.as_str()).collect();
+            let tracks = spotify
+                .api
+                .recommendations(None, None, Some(seeds))
+                .map(|recommendations| {
+                    recommendations
+                        .tracks
+                        .iter()
+                        .map(Track::from)
+                        .filter(|track| track.id.as_ref().map_or(true, |id| !queued.contains(id)))
+                        .map(Playable::Track)
+                        .collect()
+                })
+                .unwrap_or_default();
+            *recommended.write().unwrap() = tracks;
+            spotify.send_event(Event::Queue(QueueEvent::Autoplay));
+        });
+        true
+    }
+
     /// Handle events that are specific to the queue.
     pub fn handle_event(&self, event: QueueEvent) {
         match event {
             QueueEvent::PreloadTrackRequest => {
-                if let Some(next_index) = self.next_index() {
-                    let track = self.queue.read().unwrap()[next_index].clone();
+                self.preloaded
+                    .store(true, std::sync::atomic::Ordering::Relaxed);
+                if let Some(track) = self.preload_candidate() {
                     debug!("Preloading track {} as requested by librespot", track);
                     self.spotify.preload(&track);
                 }
+                self.prefetch_upcoming();
+            }
+            QueueEvent::NextTrackChanged => {
+                // Without an earlier preload, librespot still requests one
+                // once the current item nears its end.
+                if !self.preloaded.load(std::sync::atomic::Ordering::Relaxed) {
+                    return;
+                }
+                if let Some(track) = self.preload_candidate() {
+                    debug!("Replacing the preloaded track by {}", track);
+                    self.spotify.preload(&track);
+                }
+                self.prefetch_upcoming();
+            }
+            QueueEvent::Autoplay => {
+                let recommended = std::mem::take(&mut *self.recommended.write().unwrap());
+                // Playback was started or stopped while they were fetched.
+                if self.spotify.get_current_status() != PlayerEvent::FinishedTrack {
+                    return;
+                }
+                if self.next_index().is_none() {
+                    if recommended.is_empty() {
+                        info!("no recommendations to continue with");
+                        self.finish();
+                        return;
+                    }
+                    self.append_next(&recommended);
+                }
+                if let Some(index) = self.next_index() {
+                    self.play(index, false, false);
+                }
+            }
+            QueueEvent::Finished =>
+            {
+                #[cfg(feature = "notify")]
+                if self.cfg.values().notify.unwrap_or(false) {
+                    let notification_id = self.notification_id.clone();
+                    std::thread::spawn(move || {
+                        send_notification("Queue finished", "", None, notification_id)
+                    });
+                }
             }
         }
     }
 
+    /// librespot only preloads a single item, so the ones after it are
+    /// downloaded into the audio cache, up to `preload_count` items in all.
+    /// Downloads of items that no longer follow are stopped.
+    fn prefetch_upcoming(&self) {
+        let count = self
+            .cfg
+            .values()
+            .preload_count
+            .unwrap_or(config::DEFAULT_PRELOAD_COUNT)
+            .clamp(1, config::MAX_PRELOAD_COUNT);
+        let repeat_current = match self.get_repeat() {
+            RepeatSetting::RepeatTrack => true,
+            RepeatSetting::RepeatCount(_) => self.get_repeat_remaining() > 0,
+            _ => false,
+        };
+        let next = match self.next_index() {
+            Some(next) if !repeat_current => next,
+            _ => return self.spotify.prefetch(Vec::new()),
+        };
+
+        let queue = self.queue.read().unwrap();
+        let random_order = self.random_order.read().unwrap();
+        let order = random_order.as_deref();
+        let position = order.map_or(next, |order| {
+            order.iter().position(|&index| index == next).unwrap()
+        });
+        let upcoming = (position + 1..queue.len())
+            .take(count - 1)
+            .map(|position| queue[order.map_or(position, |order| order[position])].clone())
+            .collect();
+        self.spotify.prefetch(upcoming);
+    }
+
+    /// The item that should be preloaded to follow the currently playing one.
+    fn preload_candidate(&self) -> Option<Playable> {
+        let next_index = match self.get_repeat() {
+            RepeatSetting::RepeatTrack => *self.current_track.read().unwrap(),
+            RepeatSetting::RepeatCount(_) if self.get_repeat_remaining() > 0 => {
+                *self.current_track.read().unwrap()
+            }
+            _ => self.next_index(),
+        };
+        next_index.map(|index| self.queue.read().unwrap()[index].clone())
+    }
+
     /// Get the spotify session.
     pub fn get_spotify(&self) -> Spotify {
         self.spotify.clone()
     }
 }
 
+/// The artist and album that items are spread by. Episodes all share the
+/// same key.
+fn spread_key(playable: &Playable) -> (&str, &str) {
+    match playable {
+        Playable::Track(track) => (
+            track.artists.first().map_or("", String::as_str),
+            track.album.as_deref().unwrap_or(""),
+        ),
+        Playable::Episode(_) => ("", ""),
+    }
+}
+
+/// Order items by their `(artist, album)` keys so that the items of each
+/// artist are spread as evenly as possible, and the items of each album within
+/// those of its artist. No two adjacent items share an artist unless one
+/// artist has more than half of the items. `after` is the artist of the item
+/// that precedes the order. Returns the order as indices into `keys`.
+fn spread_order<R: Rng>(keys: &[(&str, &str)], after: Option<&str>, rng: &mut R) -> Vec<usize> {
+    let mut artists: Vec<&str> = Vec::new();
+    let mut buckets: Vec<Vec<usize>> = Vec::new();
+    let mut bucket_indices: HashMap<&str, usize> = HashMap::new();
+    for (index, &(artist, _)) in keys.iter().enumerate() {
+        let bucket = *bucket_indices.entry(artist).or_insert_with(|| {
+            artists.push(artist);
+            buckets.push(Vec::new());
+            buckets.len() - 1
+        });
+        buckets[bucket].push(index);
+    }
+
+    if buckets.len() <= 1 {
+        return spread_albums(keys, (0..keys.len()).collect(), rng);
+    }
+
+    // Take the items of each artist from the back
+    for bucket in buckets.iter_mut() {
+        *bucket = spread_albums(keys, std::mem::take(bucket), rng);
+        bucket.reverse();
+    }
+
+    let mut previous = after.and_then(|artist| bucket_indices.get(artist).copied());
+    let mut order = Vec::with_capacity(keys.len());
+    for remaining in (1..=keys.len()).rev() {
+        // The two largest buckets decide whether the remaining items can
+        // still be ordered without adjacent items of the same artist
+        let mut largest = (0, None);
+        let mut second = 0;
+        for (bucket, items) in buckets.iter().enumerate() {
+            if items.len() > largest.0 {
+                second = largest.0;
+                largest = (items.len(), Some(bucket));
+            } else if items.len() > second {
+                second = items.len();
+            }
+        }
+        let feasible = |bucket: usize| {
+            let count = buckets[bucket].len();
+            let others = if largest.1 == Some(bucket) {
+                second
+            } else {
+                largest.0
+            };
+            Some(bucket) != previous && others <= remaining / 2 && count - 1 <= (remaining - 1) / 2
+        };
+
+        // Pick a feasible artist at random, weighted by its remaining items,
+        // or the largest one if none is feasible
+        let candidates: Vec<usize> = (0..buckets.len())
+            .filter(|&bucket| !buckets[bucket].is_empty() && feasible(bucket))
+            .collect();
+        let bucket = match candidates.choose_weighted(rng, |&bucket| buckets[bucket].len()) {
+            Ok(&bucket) => bucket,
+            Err(_) => (0..buckets.len())
+                .filter(|&bucket| Some(bucket) != previous)
+                .max_by_key(|&bucket| buckets[bucket].len())
+                .filter(|&bucket| !buckets[bucket].is_empty())
+                .or(previous)
+                .expect("no items left to order"),
+        };
+        order.push(buckets[bucket].pop().expect("picked an empty bucket"));
+        previous = Some(bucket);
+    }
+    order
+}
+
+/// Order `items`, which are indices into `keys`, so that their albums are
+/// spread as evenly as possible.
+fn spread_albums<R: Rng>(keys: &[(&str, &str)], mut items: Vec<usize>, rng: &mut R) -> Vec<usize> {
+    let albums: Vec<(&str, &str)> = items.iter().map(|&index| (keys[index].1, "")).collect();
+    if albums.iter().all(|&album| album == albums[0]) {
+        items.shuffle(rng);
+        return items;
+    }
+    spread_order(&albums, None, rng)
+        .into_iter()
+        .map(|position| items[position])
+        .collect()
+}
+
+/// Shuffle items so that those that were played recently, as told by how
+/// many items were played since (their `ranks`), come last, and those played
+/// longest ago first among them. Unless there are no more items than
+/// `window`, which are shuffled at random.
+fn fresh_order<R: Rng>(ranks: &[Option<usize>], window: usize, rng: &mut R) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..ranks.len()).collect();
+    order.shuffle(rng);
+    if ranks.len() > window {
+        // The sort is stable, so the others stay in random order.
+        order.sort_by_key(|&position| ranks[position].map(std::cmp::Reverse));
+    }
+    order
+}
+
+/// Counts the unavailable items that were skipped in a row.
+#[derive(Default)]
+struct UnavailableSkips(AtomicUsize);
+
+impl UnavailableSkips {
+    /// Count another skipped item, and tell whether playback should continue
+    /// with the next one. The count starts over once it shouldn't.
+    fn skip(&self, queue_len: usize, limit: Option<usize>) -> bool {
+        let skipped = self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+        let next = continue_after_skips(skipped, queue_len, limit);
+        if !next {
+            self.reset();
+        }
+        next
+    }
+
+    /// Start counting again, as an item is playing.
+    fn reset(&self) {
+        self.0.store(0, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+/// Whether playback should continue after `skipped` unavailable items were
+/// skipped in a row, given the length of the queue and the configured limit.
+fn continue_after_skips(skipped: usize, queue_len: usize, limit: Option<usize>) -> bool {
+    skipped < limit.unwrap_or(queue_len).min(queue_len)
+}
+
+/// Whether the queue is restored at startup, which resuming playback needs.
+fn remember_queue(cfg: &Config) -> bool {
+    let values = cfg.values();
+    values.remember_queue.unwrap_or(true) || values.resume_playback.unwrap_or(false)
+}
+
+/// Drop the items of a restored queue state that can't be played anymore,
+/// keeping the order of the others. Local files are kept, they are reported
+/// as unavailable if they are gone by the time they are played.
+fn retain_restorable(mut state: QueueState) -> QueueState {
+    let keep: Vec<bool> = state
+        .queue
+        .iter()
+        .map(|playable| {
+            let uri = playable.uri();
+            local_path(&uri).is_some()
+                || SpotifyId::from_uri(&uri)
+                    .map_or(false, |id| id.audio_type != SpotifyAudioType::NonPlayable)
+        })
+        .collect();
+    if keep.iter().all(|&keep| keep) {
+        return state;
+    }
+
+    for (playable, _) in state.queue.iter().zip(&keep).filter(|(_, &keep)| !keep) {
+        info!("not restoring unplayable item {}", playable.uri());
+    }
+    let current = state.current_track;
+    let (current_track, random_order) =
+        remap_indices(&keep, state.current_track, state.random_order.take());
+    if current.map_or(true, |index| !keep[index]) {
+        state.track_progress = Default::default();
+    }
+    state.current_track = current_track;
+    state.random_order = random_order;
+    let mut keep = keep.into_iter();
+    state.queue.retain(|_| keep.next().unwrap_or(false));
+    state
+}
+
+/// Which items of `queue` are kept when removing duplicates: the first
+/// occurrence of each URI, except that the current item is kept over all
+/// others with its URI.
+fn dedup_keep(queue: &[Playable], current: Option<usize>) -> Vec<bool> {
+    let current_uri = current
+        .and_then(|index| queue.get(index))
+        .map(|playable| playable.uri());
+    let mut seen = HashSet::new();
+    queue
+        .iter()
+        .enumerate()
+        .map(|(index, playable)| {
+            let uri = playable.uri();
+            if Some(index) == current {
+                true
+            } else if Some(&uri) == current_uri.as_ref() {
+                false
+            } else {
+                seen.insert(uri)
+            }
+        })
+        .collect()
+}
+
+/// Which of `len` items are kept when clearing the `upcoming` items after
+/// `current`, or else the played ones before it, going by `order` while
+/// shuffling.
+fn clear_keep(
+    len: usize,
+    current: Option<usize>,
+    order: Option<&[usize]>,
+    upcoming: bool,
+) -> Vec<bool> {
+    let mut position: Vec<usize> = (0..len).collect();
+    if let Some(order) = order {
+        for (i, &index) in order.iter().enumerate() {
+            if let Some(position) = position.get_mut(index) {
+                *position = i;
+            }
+        }
+    }
+    let current_position = match current.and_then(|index| position.get(index)) {
+        Some(&current_position) => current_position,
+        None => return vec![!upcoming; len],
+    };
+    (0..len)
+        .map(|index| Some(index) == current || (position[index] < current_position) == upcoming)
+        .collect()
+}
+
+/// The album that items are grouped by when shuffling albums. Episodes are
+/// on their own.
+fn album_key(playable: &Playable) -> Option<&str> {
+    match playable {
+        Playable::Track(track) => track.album_id.as_deref(),
+        Playable::Episode(_) => None,
+    }
+}
+
+/// Shuffle the albums of items with the given `keys`, keeping the order of
+/// the items within each album, as positions into `keys`. The rest of the
+/// album `after` comes first. Items without an album are on their own.
+fn album_order<R: Rng>(keys: &[Option<&str>], after: Option<&str>, rng: &mut R) -> Vec<usize> {
+    let mut albums: Vec<Vec<usize>> = Vec::new();
+    let mut album_index: HashMap<&str, usize> = HashMap::new();
+    for (position, key) in keys.iter().enumerate() {
+        match key.and_then(|key| album_index.get(key)) {
+            Some(&index) => albums[index].push(position),
+            None => {
+                if let Some(key) = key {
+                    album_index.insert(key, albums.len());
+                }
+                albums.push(vec![position]);
+            }
+        }
+    }
+
+    albums.shuffle(rng);
+    if let Some(index) = albums
+        .iter()
+        .position(|album| after.is_some() && keys[album[0]] == after)
+    {
+        let album = albums.remove(index);
+        albums.insert(0, album);
+    }
+    albums.into_iter().flatten().collect()
+}
+
+/// The indices of the current item and the shuffle order once only the items
+/// marked in `keep` are left. A removed current item is replaced by the next
+/// one that is kept.
+fn remap_indices(
+    keep: &[bool],
+    current: Option<usize>,
+    random_order: Option<Vec<usize>>,
+) -> (Option<usize>, Option<Vec<usize>>) {
+    // The amount of kept items before each item, which is its new index.
+    let new_index: Vec<usize> = keep
+        .iter()
+        .scan(0, |kept, &keep| {
+            let index = *kept;
+            *kept += keep as usize;
+            Some(index)
+        })
+        .collect();
+    let kept = keep.iter().filter(|&&keep| keep).count();
+
+    let current = current
+        .and_then(|index| new_index.get(index).copied())
+        .filter(|&index| index < kept);
+    let random_order = random_order.map(|order| {
+        order
+            .into_iter()
+            .filter(|&index| keep.get(index) == Some(&true))
+            .map(|index| new_index[index])
+            .collect()
+    });
+    (current, random_order)
+}
+
+/// The index of the item at `index` once the item at `from` was shifted to
+/// `to`.
+fn shifted_index(index: usize, from: usize, to: usize) -> usize {
+    if index == from {
+        to
+    } else if from < index && index <= to {
+        index - 1
+    } else if to <= index && index < from {
+        index + 1
+    } else {
+        index
+    }
+}
+
+/// Update a shuffled order of indices for `count` items that are inserted at
+/// `at`, so that they are played in their order from `position` on.
+fn insert_order(order: &mut Vec<usize>, position: usize, at: usize, count: usize) {
+    for index in order.iter_mut() {
+        if *index >= at {
+            *index += count;
+        }
+    }
+    order.splice(position..position, at..at + count);
+}
+
+/// Update a shuffled order of indices after the item at `from` was shifted to
+/// `to`. In the shuffled order, the item is moved before the items it was
+/// shifted up past, or after the items it was shifted down past.
+fn shift_order(order: &mut Vec<usize>, from: usize, to: usize) {
+    for index in order.iter_mut() {
+        *index = shifted_index(*index, from, to);
+    }
+
+    // The items that were passed, by their index after the shift.
+    let passed = if to < from {
+        to + 1..from + 1
+    } else {
+        from..to
+    };
+    let position = match order.iter().position(|&index| index == to) {
+        Some(position) => position,
+        None => return,
+    };
+    order.remove(position);
+    let passed_positions = order
+        .iter()
+        .enumerate()
+        .filter(|(_, index)| passed.contains(index))
+        .map(|(position, _)| position);
+    let position = if to < from {
+        passed_positions.min()
+    } else {
+        passed_positions.max().map(|position| position + 1)
+    };
+    order.insert(position.unwrap_or(order.len()), to);
+}
+
+/// The position in ms to resume an item of the given duration at. Items
+/// shorter than `min_duration` start from the top, as does an item that the
+/// saved position doesn't fit into anymore.
+fn resume_position(duration: u32, progress: u32, min_duration: u32) -> u32 {
+    if duration >= min_duration && progress < duration {
+        progress
+    } else {
+        0
+    }
+}
+
 /// Send a notification using the desktops default notification method.
 ///
 /// `summary_txt`: A short title for the notification.
@@ -551,3 +1691,434 @@ pub fn send_notification(
         Err(e) => error!("Failed to send notification cover: {}", e),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        album_order, clear_keep, continue_after_skips, dedup_keep, fresh_order, insert_order,
+        remap_indices, resume_position, shift_order, shifted_index, spread_order, Queue,
+        QueueExport, RepeatSetting, UnavailableSkips,
+    };
+    use crate::config::Config;
+    use crate::events::EventManager;
+    use crate::history::History;
+    use crate::library::Library;
+    use crate::model::episode::Episode;
+    use crate::model::playable::Playable;
+    use crate::spotify::Spotify;
+    use cursive::Cursive;
+    use librespot_core::authentication::Credentials;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::sync::{mpsc, Arc};
+    use std::time::Duration;
+
+    #[test]
+    fn test_unavailable_skips() {
+        // a single unavailable track in the middle of a playlist
+        assert!(continue_after_skips(1, 10, None));
+
+        // every track in the queue is unavailable
+        assert!(continue_after_skips(9, 10, None));
+        assert!(!continue_after_skips(10, 10, None));
+
+        // the configured limit is reached before the end of the queue
+        assert!(continue_after_skips(2, 10, Some(3)));
+        assert!(!continue_after_skips(3, 10, Some(3)));
+        assert!(!continue_after_skips(2, 2, Some(3)));
+    }
+
+    /// Play `rounds` times through a queue of items that are available or
+    /// not, as the events of the player do. Returns the items that played
+    /// and whether playback was stopped.
+    fn play_through(available: &[bool], rounds: usize, limit: Option<usize>) -> (Vec<usize>, bool) {
+        let skips = UnavailableSkips::default();
+        let mut played = Vec::new();
+        for index in (0..available.len()).cycle().take(available.len() * rounds) {
+            if available[index] {
+                skips.reset();
+                played.push(index);
+            } else if !skips.skip(available.len(), limit) {
+                return (played, true);
+            }
+        }
+        (played, false)
+    }
+
+    #[test]
+    fn test_unavailable_items_are_skipped() {
+        // a single unavailable track in the middle of a playlist
+        let queue = [true, true, false, true, true];
+        assert_eq!(play_through(&queue, 1, None), (vec![0, 1, 3, 4], false));
+
+        // the entire queue is unavailable, also while repeating it
+        assert_eq!(play_through(&[false; 4], 1, None), (vec![], true));
+        assert_eq!(play_through(&[false; 4], 3, None), (vec![], true));
+
+        // an unavailable album stops at the limit, runs that are broken up
+        // by a track that plays don't add up
+        let queue = [true, false, false, false, true];
+        assert_eq!(play_through(&queue, 1, Some(3)), (vec![0], true));
+        let queue = [false, false, true, false, false, true];
+        assert_eq!(play_through(&queue, 1, Some(3)), (vec![2, 5], false));
+    }
+
+    #[test]
+    fn test_clear_keep() {
+        // in order, around the current item
+        assert_eq!(
+            clear_keep(4, Some(1), None, true),
+            vec![true, true, false, false]
+        );
+        assert_eq!(
+            clear_keep(4, Some(1), None, false),
+            vec![false, true, true, true]
+        );
+
+        // while shuffling, by the position in the shuffled order
+        let order = [2, 0, 3, 1];
+        assert_eq!(
+            clear_keep(4, Some(0), Some(&order), true),
+            vec![true, false, true, false]
+        );
+        assert_eq!(
+            clear_keep(4, Some(0), Some(&order), false),
+            vec![true, true, false, true]
+        );
+
+        // without a current item, every item is upcoming
+        assert_eq!(clear_keep(2, None, None, true), vec![false, false]);
+        assert_eq!(clear_keep(2, None, None, false), vec![true, true]);
+    }
+
+    #[test]
+    fn test_remap_indices() {
+        let keep = [true, false, true, true, false];
+
+        // the current item moves along with the removed items before it
+        assert_eq!(remap_indices(&keep, Some(3), None), (Some(2), None));
+
+        // a removed current item is replaced by the next one that is kept
+        assert_eq!(remap_indices(&keep, Some(1), None), (Some(1), None));
+        assert_eq!(remap_indices(&keep, Some(4), None), (None, None));
+
+        // the shuffle order keeps its relative order
+        assert_eq!(
+            remap_indices(&keep, None, Some(vec![4, 3, 1, 0, 2])),
+            (None, Some(vec![2, 0, 1]))
+        );
+    }
+
+    #[test]
+    fn test_shift() {
+        // the shifted item and the ones it passed
+        assert_eq!(shifted_index(1, 1, 3), 3);
+        assert_eq!(shifted_index(2, 1, 3), 1);
+        assert_eq!(shifted_index(3, 1, 3), 2);
+        assert_eq!(shifted_index(1, 3, 1), 2);
+        // items that aren't passed keep their index
+        assert_eq!(shifted_index(0, 1, 3), 0);
+        assert_eq!(shifted_index(4, 3, 1), 4);
+
+        // shifting item 2 up past item 1 plays it before item 1
+        let mut order = vec![1, 3, 0, 2];
+        shift_order(&mut order, 2, 1);
+        assert_eq!(order, vec![1, 2, 3, 0]);
+
+        // shifting item 0 down past items 1 and 2 plays it after both
+        let mut order = vec![0, 2, 3, 1];
+        shift_order(&mut order, 0, 2);
+        assert_eq!(order, vec![1, 3, 0, 2]);
+    }
+
+    #[test]
+    fn test_insert_order() {
+        // items inserted after the current item 3 are played right after it,
+        // and the items after them in the queue keep their place in the order
+        let mut order = vec![1, 3, 0, 4, 2];
+        insert_order(&mut order, 2, 4, 2);
+        assert_eq!(order, vec![1, 3, 4, 5, 0, 6, 2]);
+
+        // items appended without a current item are played last
+        let mut order = vec![1, 0];
+        insert_order(&mut order, 2, 2, 1);
+        assert_eq!(order, vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn test_queue_export() {
+        // an empty queue
+        let export = QueueExport {
+            queue: Vec::new(),
+            current_track: None,
+            track_progress: Default::default(),
+            random_order: None,
+            shuffle: false,
+            repeat: RepeatSetting::None,
+        };
+        let json = serde_json::to_string(&export).unwrap();
+        let export: QueueExport = serde_json::from_str(&json).unwrap();
+        assert!(export.queue.is_empty());
+        assert_eq!(export.current_track, None);
+
+        // episodes keep their type
+        let episode = Episode {
+            id: "4rOoJ6Egrf8K2IrywzwOMk".into(),
+            uri: "spotify:episode:4rOoJ6Egrf8K2IrywzwOMk".into(),
+            duration: 3_600_000,
+            name: "Episode".into(),
+            description: "".into(),
+            release_date: "2022-01-01".into(),
+            cover_url: None,
+            added_at: None,
+            added_by: None,
+            list_index: 0,
+            resume_position: 0,
+            fully_played: false,
+        };
+        let export = QueueExport {
+            queue: vec![Playable::Episode(episode)],
+            current_track: Some(0),
+            track_progress: Default::default(),
+            random_order: None,
+            shuffle: false,
+            repeat: RepeatSetting::RepeatTrack,
+        };
+        let json = serde_json::to_string(&export).unwrap();
+        let export: QueueExport = serde_json::from_str(&json).unwrap();
+        assert!(matches!(&export.queue[..], [Playable::Episode(episode)]
+            if episode.uri == "spotify:episode:4rOoJ6Egrf8K2IrywzwOMk"));
+        assert_eq!(export.current_track, Some(0));
+        assert_eq!(export.repeat, RepeatSetting::RepeatTrack);
+    }
+
+    #[test]
+    fn test_resume_position() {
+        // long items resume where they were
+        assert_eq!(resume_position(7_200_000, 3_600_000, 600_000), 3_600_000);
+        assert_eq!(resume_position(600_000, 300_000, 600_000), 300_000);
+
+        // regular songs start from the top
+        assert_eq!(resume_position(200_000, 100_000, 600_000), 0);
+
+        // the saved position doesn't fit the item anymore
+        assert_eq!(resume_position(7_200_000, 7_200_000, 600_000), 0);
+    }
+
+    /// Keys for `counts[i]` items of artist i, with `albums` albums each.
+    fn spread_keys(counts: &[usize], albums: usize) -> Vec<(&'static str, &'static str)> {
+        const NAMES: [&str; 6] = ["a", "b", "c", "d", "e", "f"];
+        counts
+            .iter()
+            .enumerate()
+            .flat_map(|(artist, &count)| {
+                (0..count).map(move |item| (NAMES[artist], NAMES[item % albums]))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_album_order() {
+        let keys = [
+            Some("a"),
+            Some("b"),
+            Some("a"),
+            None,
+            Some("c"),
+            Some("b"),
+            None,
+        ];
+        for seed in 0..100 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            // each album stays together and in order
+            let order = album_order(&keys, None, &mut rng);
+            let albums: Vec<Option<&str>> = order.iter().map(|&position| keys[position]).collect();
+            for album in ["a", "b"] {
+                let positions: Vec<usize> = order
+                    .iter()
+                    .copied()
+                    .filter(|&position| keys[position] == Some(album))
+                    .collect();
+                assert!(positions.windows(2).all(|w| w[0] < w[1]));
+                let start = albums.iter().position(|&key| key == Some(album)).unwrap();
+                assert_eq!(albums[start + 1], Some(album));
+            }
+            let mut sorted = order.clone();
+            sorted.sort();
+            assert_eq!(sorted, (0..keys.len()).collect::<Vec<_>>());
+
+            // the rest of the preceding album comes first
+            let order = album_order(&keys, Some("b"), &mut rng);
+            assert_eq!(&order[..2], &[1, 5]);
+        }
+    }
+
+    #[test]
+    fn test_spread_order() {
+        for seed in 0..100 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            // no artist has more than half of the items, so none repeat
+            for counts in [&[5, 3, 2][..], &[6, 5], &[3, 3, 3, 3], &[1, 1, 1, 1, 1, 6]] {
+                let keys = spread_keys(counts, 1);
+                let mut order = spread_order(&keys, None, &mut rng);
+                assert!(order.windows(2).all(|w| keys[w[0]].0 != keys[w[1]].0));
+                order.sort();
+                assert_eq!(order, (0..keys.len()).collect::<Vec<_>>());
+            }
+
+            // the preceding artist isn't repeated either
+            let keys = spread_keys(&[3, 3], 1);
+            let order = spread_order(&keys, Some("a"), &mut rng);
+            assert_eq!(keys[order[0]].0, "b");
+
+            // albums are spread within an artist
+            let keys = spread_keys(&[4], 2);
+            let order = spread_order(&keys, None, &mut rng);
+            assert!(order.windows(2).all(|w| keys[w[0]].1 != keys[w[1]].1));
+
+            // one artist dominates, so its items repeat as little as possible
+            let keys = spread_keys(&[7, 2], 1);
+            let order = spread_order(&keys, None, &mut rng);
+            let repeats = order
+                .windows(2)
+                .filter(|w| keys[w[0]].0 == keys[w[1]].0)
+                .count();
+            assert_eq!(order.len(), 9);
+            assert_eq!(repeats, 4);
+        }
+    }
+
+    #[test]
+    fn test_fresh_order() {
+        let ranks = [None, Some(3), None, Some(0), None, Some(7)];
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let order = fresh_order(&ranks, 4, &mut rng);
+            let mut fresh = order[..3].to_vec();
+            fresh.sort();
+            assert_eq!(fresh, vec![0, 2, 4]);
+            assert_eq!(order[3..], [5, 1, 3]);
+
+            // too few items to avoid the recent ones
+            let mut order = fresh_order(&ranks, 6, &mut rng);
+            order.sort();
+            assert_eq!(order, (0..6).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_dedup_keep() {
+        let queue: Vec<Playable> = ["a", "b", "a", "c", "b", "a"]
+            .iter()
+            .map(|id| {
+                Playable::Episode(Episode {
+                    id: id.to_string(),
+                    uri: format!("spotify:episode:{id}"),
+                    duration: 1000,
+                    name: id.to_string(),
+                    description: "".into(),
+                    release_date: "2022-01-01".into(),
+                    cover_url: None,
+                    added_at: None,
+                    added_by: None,
+                    list_index: 0,
+                    resume_position: 0,
+                    fully_played: false,
+                })
+            })
+            .collect();
+
+        // the first occurrences are kept
+        assert_eq!(
+            dedup_keep(&queue, None),
+            vec![true, true, false, true, false, false]
+        );
+
+        // the current item is kept over an earlier occurrence
+        assert_eq!(
+            dedup_keep(&queue, Some(2)),
+            vec![false, true, true, true, false, false]
+        );
+    }
+
+    /// A queue of `items` whose player has no worker and whose library isn't
+    /// loaded, along with the cursive instance receiving its events.
+    fn offline_queue(items: Vec<Playable>) -> (Cursive, Arc<Queue>) {
+        let cursive = Cursive::new();
+        let events = EventManager::new(cursive.cb_sink().clone());
+        let cfg = Arc::new(Config::default_values());
+        let credentials = Credentials::with_password("", "");
+        let spotify = Spotify::offline(events.clone(), credentials, cfg.clone());
+        let library = Arc::new(Library::offline(&events, spotify.clone(), cfg.clone()));
+        let queue = Queue::new(spotify, cfg, library, Arc::new(History::empty()));
+        queue.queue.write().unwrap().extend(items);
+        (cursive, Arc::new(queue))
+    }
+
+    /// Run `step` on `queue`, failing if it doesn't return in time.
+    fn step_in_time(queue: &Arc<Queue>, step: fn(&Queue)) {
+        let (tx, rx) = mpsc::channel();
+        let queue = queue.clone();
+        std::thread::spawn(move || {
+            step(&queue);
+            tx.send(()).unwrap();
+        });
+        rx.recv_timeout(Duration::from_secs(5))
+            .expect("the queue is locked while playing");
+    }
+
+    #[test]
+    fn test_step_to_episode() {
+        let episode = |id: &str, resume_position| {
+            Playable::Episode(Episode {
+                id: id.to_string(),
+                uri: format!("spotify:episode:{id}"),
+                duration: 3_600_000,
+                name: id.to_string(),
+                description: "".into(),
+                release_date: "2022-01-01".into(),
+                cover_url: None,
+                added_at: None,
+                added_by: None,
+                list_index: 0,
+                resume_position,
+                fully_played: false,
+            })
+        };
+        let resume_positions = |queue: &Queue| -> Vec<u32> {
+            let q = queue.queue.read().unwrap();
+            q.iter()
+                .map(|item| match item {
+                    Playable::Episode(episode) => episode.resume_position,
+                    Playable::Track(_) => 0,
+                })
+                .collect()
+        };
+        let (_cursive, queue) = offline_queue(vec![
+            episode("a", 600_000),
+            episode("b", 1_200_000),
+            episode("c", 1_800_000),
+        ]);
+
+        // playing an episode takes its resume position out of the queue
+        queue.play(0, false, false);
+        assert_eq!(resume_positions(&queue), vec![0, 1_200_000, 1_800_000]);
+
+        step_in_time(&queue, |queue| queue.next(true));
+        assert_eq!(queue.get_current_index(), Some(1));
+        assert_eq!(resume_positions(&queue), vec![0, 0, 1_800_000]);
+
+        // wrapping around to the last item
+        queue.set_repeat(RepeatSetting::RepeatPlaylist);
+        step_in_time(&queue, |queue| queue.previous());
+        step_in_time(&queue, |queue| queue.previous());
+        assert_eq!(queue.get_current_index(), Some(2));
+        assert_eq!(resume_positions(&queue), vec![0, 0, 0]);
+
+        // and to the first one
+        step_in_time(&queue, |queue| queue.next(false));
+        assert_eq!(queue.get_current_index(), Some(0));
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::model::track::Track;
+use crate::queue::Queue;
+use crate::traits::ListItem;
+
+/// How many recommended tracks a radio adds to the queue at most.
+const RADIO_SIZE: usize = 50;
+
+/// Start playing tracks recommended for `item`, leaving out those that are
+/// already queued and, if `radio_exclude_saved` is set, those in the
+/// library. Depending on `radio_replace_queue`, they replace the queue or
+/// are played after the current item.
+pub fn start(
+    item: &mut dyn ListItem,
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+) -> Result<String, String> {
+    let (artist_ids, track_ids) = item
+        .recommendation_seeds(queue.clone())
+        .filter(|(artists, tracks)| !artists.is_empty() || !tracks.is_empty())
+        .ok_or("There are no recommendations for this item")?;
+
+    let recommendations = queue
+        .get_spotify()
+        .api
+        .recommendations(seeds(&artist_ids), None, seeds(&track_ids))
+        .ok_or("Recommendations are unavailable")?;
+
+    let queued: HashSet<String> = queue
+        .queue
+        .read()
+        .unwrap()
+        .iter()
+        .filter_map(|playable| playable.id())
+        .collect();
+    let exclude_saved = library.cfg.values().radio_exclude_saved.unwrap_or(false);
+    let tracks: Vec<Playable> = recommendations
+        .tracks
+        .iter()
+        .map(|track| Playable::Track(Track::from(track)))
+        .filter(|playable| playable.id().map_or(false, |id| !queued.contains(&id)))
+        .filter(|playable| !(exclude_saved && library.is_saved_track(playable)))
+        .take(RADIO_SIZE)
+        .collect();
+    if tracks.is_empty() {
+        return Err("No new tracks were recommended".into());
+    }
+
+    if library.cfg.values().radio_replace_queue.unwrap_or(false) {
+        queue.clear();
+    }
+    let index = queue.append_next(&tracks);
+    queue.play(index, true, true);
+    Ok(format!("Playing {} recommended tracks", tracks.len()))
+}
+
+/// The seeds of one kind, left out of the request if there are none.
+fn seeds(ids: &[String]) -> Option<Vec<&str>> {
+    (!ids.is_empty()).then(|| ids.iter().map(String::as_str).collect())
+}
@@ -0,0 +1,399 @@
+use std::collections::BTreeMap;
+use std::process::Command;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use log::{debug, error, info, warn};
+use md5::{Digest, Md5};
+
+use crate::config::{cache_path, LastfmConfig};
+use crate::model::playable::Playable;
+use crate::serialization::{Serializer, JSON};
+use crate::spotify::PlayerEvent;
+
+const API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+/// Tracks shorter than this are never scrobbled.
+const MIN_DURATION: Duration = Duration::from_secs(30);
+/// Tracks are scrobbled once half of them or this much was played.
+const MAX_THRESHOLD: Duration = Duration::from_secs(4 * 60);
+/// How long to wait before submitting failed scrobbles again.
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+/// The most scrobbles last.fm accepts in a single request.
+const BATCH_SIZE: usize = 50;
+/// Scrobbles that could not be submitted yet, kept across restarts.
+const PENDING_FILE: &str = "lastfm_pending.json";
+
+/// A listen as submitted to last.fm.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Scrobble {
+    artist: String,
+    track: String,
+    album: Option<String>,
+    /// The duration of the track in seconds.
+    duration: u32,
+    /// When the track started playing, in seconds since the Unix epoch.
+    timestamp: u64,
+}
+
+enum Message {
+    NowPlaying(Scrobble),
+    Scrobble(Scrobble),
+}
+
+/// The track that is currently being listened to.
+struct Listen {
+    uri: String,
+    scrobble: Scrobble,
+    /// How long the track was played before the last pause.
+    played: Duration,
+    playing_since: Option<Instant>,
+}
+
+impl Listen {
+    fn new(playable: &Playable) -> Option<Listen> {
+        let track = match playable {
+            Playable::Track(track) => track,
+            // last.fm only knows about music
+            Playable::Episode(_) => return None,
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs())
+            .unwrap_or_default();
+        Some(Listen {
+            uri: track.uri.clone(),
+            scrobble: Scrobble {
+                artist: track.artists.first()?.clone(),
+                track: track.title.clone(),
+                album: track.album.clone(),
+                duration: track.duration / 1000,
+                timestamp,
+            },
+            played: Duration::ZERO,
+            playing_since: None,
+        })
+    }
+
+    fn pause(&mut self) {
+        if let Some(since) = self.playing_since.take() {
+            self.played += since.elapsed();
+        }
+    }
+}
+
+/// Whether a track of `duration` was played long enough to be scrobbled.
+fn should_scrobble(duration: Duration, played: Duration) -> bool {
+    duration >= MIN_DURATION && played >= (duration / 2).min(MAX_THRESHOLD)
+}
+
+/// Follows playback to submit listens to last.fm. Submissions happen on a
+/// separate thread, which keeps failed scrobbles to retry them later.
+pub struct Scrobbler {
+    tx: mpsc::Sender<Message>,
+    listen: Option<Listen>,
+}
+
+impl Scrobbler {
+    pub fn new(config: LastfmConfig) -> Scrobbler {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || Client::new(config).run(rx));
+        Scrobbler { tx, listen: None }
+    }
+
+    /// Update the current listen with a player event, while `playable` is
+    /// the current item of the queue.
+    pub fn update(&mut self, event: &PlayerEvent, playable: Option<&Playable>) {
+        match event {
+            PlayerEvent::Playing(_) => {
+                let uri = playable.map(|playable| playable.uri());
+                if self.listen.as_ref().map(|listen| &listen.uri) != uri.as_ref() {
+                    self.finish();
+                    self.listen = playable.and_then(Listen::new);
+                    if let Some(listen) = &self.listen {
+                        self.send(Message::NowPlaying(listen.scrobble.clone()));
+                    }
+                }
+                if let Some(listen) = self.listen.as_mut() {
+                    listen.playing_since.get_or_insert_with(Instant::now);
+                }
+            }
+            PlayerEvent::Paused(_) | PlayerEvent::Loading(_, _) => {
+                if let Some(listen) = self.listen.as_mut() {
+                    listen.pause();
+                }
+            }
+            PlayerEvent::Stopped | PlayerEvent::FinishedTrack => self.finish(),
+            _ => {}
+        }
+    }
+
+    /// Scrobble the current listen if it was played long enough.
+    fn finish(&mut self) {
+        if let Some(mut listen) = self.listen.take() {
+            listen.pause();
+            let duration = Duration::from_secs(listen.scrobble.duration.into());
+            if should_scrobble(duration, listen.played) {
+                self.send(Message::Scrobble(listen.scrobble));
+            }
+        }
+    }
+
+    fn send(&self, message: Message) {
+        if self.tx.send(message).is_err() {
+            error!("The last.fm scrobbler stopped");
+        }
+    }
+}
+
+enum SubmitError {
+    /// The submission may succeed when it's retried later.
+    Temporary(String),
+    /// last.fm refused the submission.
+    Rejected(String),
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    error: u32,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct SessionResponse {
+    session: Session,
+}
+
+#[derive(Deserialize)]
+struct Session {
+    key: String,
+}
+
+struct Client {
+    http: reqwest::blocking::Client,
+    config: LastfmConfig,
+    session_key: Option<String>,
+    pending: Vec<Scrobble>,
+}
+
+impl Client {
+    fn new(config: LastfmConfig) -> Client {
+        let pending = match JSON.load(cache_path(PENDING_FILE)) {
+            Ok(pending) => pending,
+            Err(e) => {
+                debug!("no pending scrobbles: {}", e);
+                Vec::new()
+            }
+        };
+        Client {
+            http: reqwest::blocking::Client::new(),
+            config,
+            session_key: None,
+            pending,
+        }
+    }
+
+    fn run(mut self, rx: mpsc::Receiver<Message>) {
+        loop {
+            let message = if self.pending.is_empty() {
+                rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            } else {
+                rx.recv_timeout(RETRY_INTERVAL)
+            };
+            match message {
+                Ok(Message::NowPlaying(scrobble)) => {
+                    // Only useful while the track plays, so not retried
+                    if let Err(SubmitError::Temporary(e) | SubmitError::Rejected(e)) =
+                        self.now_playing(&scrobble)
+                    {
+                        warn!("could not update now playing on last.fm: {}", e);
+                    }
+                }
+                Ok(Message::Scrobble(scrobble)) => {
+                    self.pending.push(scrobble);
+                    self.submit_pending();
+                }
+                Err(RecvTimeoutError::Timeout) => self.submit_pending(),
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    }
+
+    /// Submit the pending scrobbles, keeping those that can be retried.
+    fn submit_pending(&mut self) {
+        while !self.pending.is_empty() {
+            let count = self.pending.len().min(BATCH_SIZE);
+            let batch = self.pending[..count].to_vec();
+            match self.scrobble(&batch) {
+                Ok(()) => {
+                    info!("scrobbled {} tracks to last.fm", count);
+                    self.pending.drain(..count);
+                }
+                Err(SubmitError::Rejected(e)) => {
+                    error!("last.fm rejected {} scrobbles: {}", count, e);
+                    self.pending.drain(..count);
+                }
+                Err(SubmitError::Temporary(e)) => {
+                    warn!("could not scrobble to last.fm, retrying later: {}", e);
+                    break;
+                }
+            }
+        }
+        if let Err(e) = JSON.write(cache_path(PENDING_FILE), &self.pending) {
+            error!("could not save pending scrobbles: {}", e);
+        }
+    }
+
+    fn now_playing(&mut self, scrobble: &Scrobble) -> Result<(), SubmitError> {
+        let mut params = BTreeMap::new();
+        params.insert("artist".to_string(), scrobble.artist.clone());
+        params.insert("track".to_string(), scrobble.track.clone());
+        params.insert("duration".to_string(), scrobble.duration.to_string());
+        if let Some(album) = &scrobble.album {
+            params.insert("album".to_string(), album.clone());
+        }
+        self.call_authenticated("track.updateNowPlaying", params)
+            .map(|_| ())
+    }
+
+    fn scrobble(&mut self, scrobbles: &[Scrobble]) -> Result<(), SubmitError> {
+        let mut params = BTreeMap::new();
+        for (i, scrobble) in scrobbles.iter().enumerate() {
+            params.insert(format!("artist[{i}]"), scrobble.artist.clone());
+            params.insert(format!("track[{i}]"), scrobble.track.clone());
+            params.insert(format!("timestamp[{i}]"), scrobble.timestamp.to_string());
+            params.insert(format!("duration[{i}]"), scrobble.duration.to_string());
+            if let Some(album) = &scrobble.album {
+                params.insert(format!("album[{i}]"), album.clone());
+            }
+        }
+        self.call_authenticated("track.scrobble", params)
+            .map(|_| ())
+    }
+
+    /// Call `method` with a session, which is created first if needed.
+    fn call_authenticated(
+        &mut self,
+        method: &str,
+        mut params: BTreeMap<String, String>,
+    ) -> Result<String, SubmitError> {
+        let session_key = match self.session_key.clone() {
+            Some(session_key) => session_key,
+            None => {
+                let session_key = self.authenticate()?;
+                self.session_key = Some(session_key.clone());
+                session_key
+            }
+        };
+        params.insert("sk".to_string(), session_key);
+        self.call(method, params)
+    }
+
+    fn authenticate(&mut self) -> Result<String, SubmitError> {
+        let password = Command::new("sh")
+            .args(["-c", &self.config.password_cmd])
+            .output()
+            .map_err(|e| SubmitError::Temporary(format!("could not get the password: {e}")))?
+            .stdout;
+        let password = String::from_utf8_lossy(&password);
+
+        let mut params = BTreeMap::new();
+        params.insert("username".to_string(), self.config.username.clone());
+        params.insert(
+            "password".to_string(),
+            password.trim_end_matches('\n').to_string(),
+        );
+        let response = self.call("auth.getMobileSession", params)?;
+        serde_json::from_str::<SessionResponse>(&response)
+            .map(|response| response.session.key)
+            .map_err(|e| SubmitError::Temporary(format!("unexpected session response: {e}")))
+    }
+
+    /// Call a write `method` of the last.fm API, signing the request.
+    fn call(
+        &mut self,
+        method: &str,
+        mut params: BTreeMap<String, String>,
+    ) -> Result<String, SubmitError> {
+        params.insert("method".to_string(), method.to_string());
+        params.insert("api_key".to_string(), self.config.api_key.clone());
+        let signature = sign(&params, &self.config.api_secret);
+        params.insert("api_sig".to_string(), signature);
+        params.insert("format".to_string(), "json".to_string());
+
+        let response = self
+            .http
+            .post(API_URL)
+            .form(&params)
+            .send()
+            .and_then(|response| response.text())
+            .map_err(|e| SubmitError::Temporary(e.to_string()))?;
+
+        match serde_json::from_str::<ApiError>(&response) {
+            // Invalid session key, so start a new session next time
+            Ok(ApiError { error: 9, message }) => {
+                self.session_key = None;
+                Err(SubmitError::Temporary(format!("{message} (9)")))
+            }
+            // Service offline, temporary error and rate limit exceeded
+            Ok(ApiError {
+                error: error @ (11 | 16 | 29),
+                message,
+            }) => Err(SubmitError::Temporary(format!("{message} ({error})"))),
+            Ok(ApiError { error, message }) => {
+                Err(SubmitError::Rejected(format!("{message} ({error})")))
+            }
+            Err(_) => Ok(response),
+        }
+    }
+}
+
+/// The signature of an API call, which is the MD5 hash of the parameters
+/// ordered by name, followed by the API secret.
+fn sign(params: &BTreeMap<String, String>, secret: &str) -> String {
+    let mut data = String::new();
+    for (name, value) in params {
+        data.push_str(name);
+        data.push_str(value);
+    }
+    data.push_str(secret);
+    md5_hex(data.as_bytes())
+}
+
+/// The MD5 hash of `data` as a lowercase hex string, as last.fm requires it
+/// for signatures.
+fn md5_hex(data: &[u8]) -> String {
+    let mut hex = String::with_capacity(32);
+    for byte in Md5::digest(data) {
+        hex.push_str(&format!("{byte:02x}"));
+    }
+    hex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_md5() {
+        assert_eq!(md5_hex(b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(
+            md5_hex(b"The quick brown fox jumps over the lazy dog"),
+            "9e107d9d372bb6826bd81d3542a419d6"
+        );
+        // spans two blocks after padding
+        assert_eq!(md5_hex(&[b'a'; 60]), "cc7ed669cf88f201c3297c6a91e1d18d");
+    }
+
+    #[test]
+    fn test_should_scrobble() {
+        let secs = Duration::from_secs;
+        // too short
+        assert!(!should_scrobble(secs(20), secs(20)));
+        // half of the track
+        assert!(!should_scrobble(secs(200), secs(99)));
+        assert!(should_scrobble(secs(200), secs(100)));
+        // at most four minutes
+        assert!(!should_scrobble(secs(3600), secs(239)));
+        assert!(should_scrobble(secs(3600), secs(240)));
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::RwLock;
+
+use log::{debug, error};
+
+use crate::config::{cache_path, Config, DEFAULT_SEARCH_HISTORY_SIZE};
+use crate::serialization::{Serializer, JSON};
+
+const SEARCH_HISTORY_FILE: &str = "search_history.json";
+
+/// The queries that were searched for, most recent first. Unless persisting
+/// them is disabled, they are kept in the cache directory across sessions.
+pub struct SearchHistory {
+    queries: RwLock<Vec<String>>,
+    size: usize,
+    persist: bool,
+}
+
+impl SearchHistory {
+    pub fn new(cfg: &Config) -> SearchHistory {
+        let size = cfg
+            .values()
+            .search_history_size
+            .unwrap_or(DEFAULT_SEARCH_HISTORY_SIZE);
+        let persist = cfg.values().search_history_persist.unwrap_or(true);
+        let mut queries: Vec<String> = if persist {
+            JSON.load(cache_path(SEARCH_HISTORY_FILE))
+                .unwrap_or_else(|e| {
+                    debug!("no search history loaded: {}", e);
+                    Vec::new()
+                })
+        } else {
+            Vec::new()
+        };
+        queries.truncate(size);
+        SearchHistory {
+            queries: RwLock::new(queries),
+            size,
+            persist,
+        }
+    }
+
+    /// The queries, most recent first.
+    pub fn queries(&self) -> Vec<String> {
+        self.queries.read().unwrap().clone()
+    }
+
+    /// Add `query` as the most recent one, unless it already is.
+    pub fn add(&self, query: &str) {
+        let query = query.trim();
+        let mut queries = self.queries.write().unwrap();
+        if query.is_empty() || queries.first().map(String::as_str) == Some(query) {
+            return;
+        }
+        queries.insert(0, query.to_string());
+        queries.truncate(self.size);
+        self.save(&queries);
+    }
+
+    pub fn clear(&self) {
+        let mut queries = self.queries.write().unwrap();
+        queries.clear();
+        self.save(&queries);
+    }
+
+    fn save(&self, queries: &[String]) {
+        if !self.persist {
+            return;
+        }
+        if let Err(e) = JSON.write(cache_path(SEARCH_HISTORY_FILE), queries) {
+            error!("could not save the search history: {}", e);
+        }
+    }
+}
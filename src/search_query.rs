@@ -0,0 +1,236 @@
+use std::fmt;
+
+use crate::command::CommandParseError;
+
+/// The categories of search results, each shown in its own tab.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum SearchCategory {
+    Tracks,
+    Albums,
+    Artists,
+    Playlists,
+    Shows,
+    Episodes,
+}
+
+impl SearchCategory {
+    pub const ALL: [SearchCategory; 6] = [
+        SearchCategory::Tracks,
+        SearchCategory::Albums,
+        SearchCategory::Artists,
+        SearchCategory::Playlists,
+        SearchCategory::Shows,
+        SearchCategory::Episodes,
+    ];
+
+    /// The name of the category, which is the ID of its tab as well.
+    pub fn id(&self) -> &'static str {
+        match self {
+            SearchCategory::Tracks => "tracks",
+            SearchCategory::Albums => "albums",
+            SearchCategory::Artists => "artists",
+            SearchCategory::Playlists => "playlists",
+            SearchCategory::Shows => "shows",
+            SearchCategory::Episodes => "episodes",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<SearchCategory> {
+        Self::ALL.into_iter().find(|category| category.id() == id)
+    }
+}
+
+impl fmt::Display for SearchCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id())
+    }
+}
+
+/// A search along with the filters that narrow it down.
+#[derive(Clone, Default, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// The text to search for, which is passed to Spotify as it is, so that
+    /// its own syntax like `artist:` or `tag:new` works as well.
+    pub text: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// A year, or a range of years like `1990-1999`.
+    pub year: Option<String>,
+    /// The only category of results that is searched for.
+    pub category: Option<SearchCategory>,
+}
+
+impl SearchQuery {
+    pub fn new(text: String) -> SearchQuery {
+        SearchQuery {
+            text,
+            ..Default::default()
+        }
+    }
+
+    /// Parse the arguments of the `search` command, which are the text
+    /// followed by the filters. Each filter takes the words up to the next
+    /// one.
+    pub fn parse(args: &[&str]) -> Result<SearchQuery, CommandParseError> {
+        let mut query = SearchQuery::default();
+        let mut words = args.iter().peekable();
+        let mut text = Vec::new();
+        while let Some(word) = words.next_if(|word| !is_filter(word)) {
+            text.push(*word);
+        }
+        query.text = text.join(" ");
+
+        while let Some(&filter) = words.next() {
+            let mut value = Vec::new();
+            while let Some(word) = words.next_if(|word| !is_filter(word)) {
+                value.push(*word);
+            }
+            if value.is_empty() {
+                return Err(CommandParseError::InsufficientArgs {
+                    cmd: "search".into(),
+                    hint: Some(format!("{filter} <VALUE>")),
+                });
+            }
+            let value = value.join(" ");
+            match filter {
+                "-a" => query.artist = Some(value),
+                "-b" => query.album = Some(value),
+                "-y" => query.year = Some(parse_years(&value)?),
+                _ => {
+                    query.category = Some(SearchCategory::from_id(&value).ok_or_else(|| {
+                        CommandParseError::BadEnumArg {
+                            arg: value,
+                            accept: SearchCategory::ALL
+                                .iter()
+                                .map(|category| category.to_string())
+                                .collect(),
+                        }
+                    })?)
+                }
+            }
+        }
+        Ok(query)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.artist.is_none() && self.album.is_none() && self.year.is_none()
+    }
+
+    pub fn is_filtered(&self) -> bool {
+        !self.filters().is_empty()
+    }
+
+    /// Whether results of `category` are searched for.
+    pub fn searches(&self, category: SearchCategory) -> bool {
+        self.category.map_or(true, |only| only == category)
+    }
+
+    /// The query as Spotify takes it, with the filters added to the text as
+    /// field filters.
+    pub fn to_spotify(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.text.is_empty() {
+            parts.push(self.text.clone());
+        }
+        if let Some(artist) = &self.artist {
+            parts.push(format!("artist:{}", quoted(artist)));
+        }
+        if let Some(album) = &self.album {
+            parts.push(format!("album:{}", quoted(album)));
+        }
+        if let Some(year) = &self.year {
+            parts.push(format!("year:{year}"));
+        }
+        parts.join(" ")
+    }
+
+    /// The active filters, as they are listed in the title of the results.
+    pub fn filters(&self) -> Vec<String> {
+        let mut filters = Vec::new();
+        if let Some(artist) = &self.artist {
+            filters.push(format!("artist: {artist}"));
+        }
+        if let Some(album) = &self.album {
+            filters.push(format!("album: {album}"));
+        }
+        if let Some(year) = &self.year {
+            filters.push(format!("year: {year}"));
+        }
+        if let Some(category) = &self.category {
+            filters.push(format!("only {category}"));
+        }
+        filters
+    }
+}
+
+/// The query in the form of the arguments of the `search` command.
+impl fmt::Display for SearchQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut args = Vec::new();
+        if !self.text.is_empty() {
+            args.push(self.text.clone());
+        }
+        if let Some(artist) = &self.artist {
+            args.push(format!("-a {artist}"));
+        }
+        if let Some(album) = &self.album {
+            args.push(format!("-b {album}"));
+        }
+        if let Some(year) = &self.year {
+            args.push(format!("-y {year}"));
+        }
+        if let Some(category) = &self.category {
+            args.push(format!("-t {category}"));
+        }
+        write!(f, "{}", args.join(" "))
+    }
+}
+
+fn is_filter(word: &str) -> bool {
+    matches!(word, "-a" | "-b" | "-y" | "-t")
+}
+
+/// A value of a field filter, quoted so that it's matched as a whole. Quotes
+/// within it can't be escaped, so they are left out.
+fn quoted(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', ""))
+}
+
+/// Check that `value` is a year or a range of years, as Spotify takes them.
+fn parse_years(value: &str) -> Result<String, CommandParseError> {
+    let is_year = |year: &str| year.len() == 4 && year.chars().all(|c| c.is_ascii_digit());
+    let valid = match value.split_once('-') {
+        Some((from, to)) => is_year(from) && is_year(to) && from <= to,
+        None => is_year(value),
+    };
+    if valid {
+        Ok(value.to_string())
+    } else {
+        Err(CommandParseError::ArgParseError {
+            arg: value.into(),
+            err: "expected a year like 1995 or a range like 1990-1999".into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SearchCategory, SearchQuery};
+
+    #[test]
+    fn filters_compose_into_the_query() {
+        let args: Vec<&str> = "rock & roll -a Guns N' Roses -y 1985-1989 -t tracks"
+            .split_whitespace()
+            .collect();
+        let query = SearchQuery::parse(&args).unwrap();
+        assert_eq!(query.text, "rock & roll");
+        assert_eq!(query.artist.as_deref(), Some("Guns N' Roses"));
+        assert_eq!(query.category, Some(SearchCategory::Tracks));
+        assert_eq!(
+            query.to_spotify(),
+            "rock & roll artist:\"Guns N' Roses\" year:1985-1989"
+        );
+        assert_eq!(SearchQuery::parse(&["-y", "1999-1990"]).ok(), None);
+        assert_eq!(SearchQuery::parse(&["-a"]).ok(), None);
+    }
+}
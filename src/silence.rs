@@ -0,0 +1,78 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use librespot_playback::audio_backend::{Sink, SinkResult};
+use librespot_playback::convert::Converter;
+use librespot_playback::decoder::AudioPacket;
+use librespot_playback::mixer::VolumeGetter;
+use librespot_playback::{NUM_CHANNELS, SAMPLE_RATE};
+
+/// How long the audio written to the sink has been silent, shared between
+/// the sink and the worker.
+#[derive(Clone, Default)]
+pub struct SilenceMeter(Arc<AtomicU64>);
+
+impl SilenceMeter {
+    /// The length of the silence at the end of the audio written so far.
+    pub fn silence(&self) -> Duration {
+        let frames = self.0.load(Ordering::Relaxed);
+        Duration::from_millis(frames * 1000 / SAMPLE_RATE as u64)
+    }
+
+    /// Start measuring again, e.g. once another track is loaded.
+    pub fn reset(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+}
+
+/// An audio [Sink] that measures how long the audio it writes has been
+/// quieter than a threshold, regardless of the volume.
+pub struct SilenceSink {
+    sink: Box<dyn Sink>,
+    meter: SilenceMeter,
+    /// The threshold as a sample value at full volume.
+    threshold: f64,
+    volume: Box<dyn VolumeGetter + Send>,
+}
+
+impl SilenceSink {
+    pub fn new(
+        sink: Box<dyn Sink>,
+        meter: SilenceMeter,
+        threshold_db: f64,
+        volume: Box<dyn VolumeGetter + Send>,
+    ) -> SilenceSink {
+        SilenceSink {
+            sink,
+            meter,
+            threshold: 10_f64.powf(threshold_db / 20.0),
+            volume,
+        }
+    }
+}
+
+impl Sink for SilenceSink {
+    fn start(&mut self) -> SinkResult<()> {
+        self.sink.start()
+    }
+
+    fn stop(&mut self) -> SinkResult<()> {
+        self.sink.stop()
+    }
+
+    fn write(&mut self, packet: AudioPacket, converter: &mut Converter) -> SinkResult<()> {
+        let factor = self.volume.attenuation_factor();
+        // Nothing can be told about the audio while the mixer is silent
+        if let (AudioPacket::Samples(samples), true) = (&packet, factor > 0.0) {
+            let peak = samples.iter().fold(0.0_f64, |peak, s| peak.max(s.abs())) / factor;
+            if peak < self.threshold {
+                let frames = (samples.len() / NUM_CHANNELS as usize) as u64;
+                self.meter.0.fetch_add(frames, Ordering::Relaxed);
+            } else {
+                self.meter.reset();
+            }
+        }
+        self.sink.write(packet, converter)
+    }
+}
@@ -3,15 +3,15 @@ use librespot_core::cache::Cache;
 use librespot_core::config::SessionConfig;
 use librespot_core::session::Session;
 use librespot_core::session::SessionError;
-use librespot_playback::audio_backend::SinkBuilder;
-use librespot_playback::config::PlayerConfig;
+use librespot_playback::audio_backend::{Sink, SinkBuilder};
+use librespot_playback::config::{NormalisationType, PlayerConfig};
 use librespot_playback::mixer::softmixer::SoftMixer;
-use librespot_playback::mixer::MixerConfig;
-use log::{debug, error, info};
+use librespot_playback::mixer::{Mixer, MixerConfig};
+use log::{debug, error, info, warn};
 
 use librespot_playback::audio_backend;
 use librespot_playback::config::Bitrate;
-use librespot_playback::player::Player;
+use librespot_playback::player::{Player, PlayerEvent as LibrespotPlayerEvent};
 
 use futures::channel::oneshot;
 use tokio::sync::mpsc;
@@ -20,35 +20,108 @@ use url::Url;
 
 use std::env;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, SystemTime};
 
+use crate::accounts;
 use crate::config;
+use crate::config::NormalizationMode;
 use crate::events::{Event, EventManager};
 use crate::model::playable::Playable;
+use crate::playback_speed::{PlaybackSpeed, SpeedSink};
+use crate::silence::{SilenceMeter, SilenceSink};
 use crate::spotify_api::WebApi;
 use crate::spotify_worker::{Worker, WorkerCommand};
 use crate::ASYNC_RUNTIME;
 
 pub const VOLUME_PERCENT: u16 = ((u16::max_value() as f64) * 1.0 / 100.0) as u16;
 
+/// The delay before the first attempt to reconnect, doubled for each attempt.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_DELAY_MAX: Duration = Duration::from_secs(60);
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 pub enum PlayerEvent {
+    /// The track with the given URI is being loaded, to start at the given
+    /// position once enough of it is buffered.
+    Loading(String, Duration),
+    /// The player started on the item with the given URI, sent once per load
+    /// before the first `Playing` or `Paused` of it. The item itself is
+    /// known from [Spotify::get_started()] then.
+    TrackStarted(String),
     Playing(SystemTime),
     Paused(Duration),
     Stopped,
     FinishedTrack,
+    /// The track with the given URI could not be played.
+    Unavailable(String),
+    /// The connection to Spotify was lost, reconnecting with the given
+    /// attempt number.
+    Reconnecting(u32),
+    /// Reconnecting to Spotify failed too many times, giving up.
+    ConnectionLost,
+    /// The worker lost its session for the given reason, and is restarted.
+    Disconnected(DisconnectReason),
+    /// The sleep timer was armed, or cancelled/expired if `None`.
+    SleepTimer(Option<SleepTimer>),
+    /// The audio was muted or unmuted.
+    Muted(bool),
+    /// The volume was changed other than by a command, e.g. restored for the
+    /// audio device.
+    VolumeChanged(u16),
+    /// The position in the current item, sent every time the UI is refreshed
+    /// during playback if `progress_events` is enabled.
+    Progress {
+        position_ms: u64,
+        duration_ms: u64,
+    },
+}
+
+/// Why the worker lost its session. librespot doesn't tell why Spotify closed
+/// a connection, so a takeover by another device can't be told apart from a
+/// network failure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum DisconnectReason {
+    /// The session was shut down on request, e.g. to reconnect.
+    Requested,
+    /// The connection to Spotify was closed or failed.
+    ConnectionClosed,
+    /// The audio player stopped unexpectedly.
+    PlayerDied,
+}
+
+/// When an armed sleep timer pauses or stops playback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum SleepTimer {
+    /// Pause once the given point in time is reached.
+    At(SystemTime),
+    /// Pause once the current track has finished.
+    EndOfTrack,
 }
 
 #[derive(Clone)]
 pub struct Spotify {
     events: EventManager,
-    credentials: Credentials,
+    /// The credentials the worker connects with, which are replaced when
+    /// switching accounts.
+    credentials: Arc<RwLock<Credentials>>,
+    /// Whether the worker is restarted to switch accounts.
+    switching_account: Arc<AtomicBool>,
     cfg: Arc<config::Config>,
     status: Arc<RwLock<PlayerEvent>>,
+    /// The item the player last reported as started, until it stops.
+    started: Arc<RwLock<Option<Playable>>>,
+    /// The items last sent to the worker to load and to preload, one of
+    /// which is the next to be reported as started.
+    requested: Arc<RwLock<Vec<Playable>>>,
     pub api: WebApi,
     elapsed: Arc<RwLock<Option<Duration>>>,
     since: Arc<RwLock<Option<SystemTime>>>,
+    sleep_timer: Arc<RwLock<Option<SleepTimer>>>,
+    speed: PlaybackSpeed,
+    /// Whether the audio is muted, shared with the worker which toggles it.
+    muted: Arc<AtomicBool>,
     channel: Arc<RwLock<Option<mpsc::UnboundedSender<WorkerCommand>>>>,
     user: Option<String>,
 }
@@ -59,22 +132,12 @@ impl Spotify {
         credentials: Credentials,
         cfg: Arc<config::Config>,
     ) -> Spotify {
-        let mut spotify = Spotify {
-            events,
-            credentials,
-            cfg: cfg.clone(),
-            status: Arc::new(RwLock::new(PlayerEvent::Stopped)),
-            api: WebApi::new(),
-            elapsed: Arc::new(RwLock::new(None)),
-            since: Arc::new(RwLock::new(None)),
-            channel: Arc::new(RwLock::new(None)),
-            user: None,
-        };
+        let mut spotify = Self::offline(events, credentials, cfg.clone());
 
         let (user_tx, user_rx) = oneshot::channel();
         spotify.start_worker(Some(user_tx));
         spotify.user = ASYNC_RUNTIME.block_on(user_rx).ok();
-        let volume = cfg.state().volume;
+        let volume = Self::device_volume(&cfg, &Self::output_device(&cfg));
         spotify.set_volume(volume);
 
         spotify.api.set_worker_channel(spotify.channel.clone());
@@ -85,18 +148,45 @@ impl Spotify {
         spotify
     }
 
+    /// A player without a worker, which drops the commands sent to it.
+    pub fn offline(
+        events: EventManager,
+        credentials: Credentials,
+        cfg: Arc<config::Config>,
+    ) -> Spotify {
+        Spotify {
+            events,
+            credentials: Arc::new(RwLock::new(credentials)),
+            switching_account: Arc::new(AtomicBool::new(false)),
+            cfg: cfg.clone(),
+            status: Arc::new(RwLock::new(PlayerEvent::Stopped)),
+            started: Arc::new(RwLock::new(None)),
+            requested: Arc::new(RwLock::new(Vec::new())),
+            api: WebApi::new(),
+            elapsed: Arc::new(RwLock::new(None)),
+            since: Arc::new(RwLock::new(None)),
+            sleep_timer: Arc::new(RwLock::new(None)),
+            speed: PlaybackSpeed::new(),
+            muted: Arc::new(AtomicBool::new(false)),
+            channel: Arc::new(RwLock::new(None)),
+            user: None,
+        }
+    }
+
     pub fn start_worker(&self, user_tx: Option<oneshot::Sender<String>>) {
         let (tx, rx) = mpsc::unbounded_channel();
         *self
             .channel
             .write()
             .expect("can't writelock worker channel") = Some(tx);
+        self.send_worker(WorkerCommand::SubscribeTokens(self.api.token_listener()));
         {
             let worker_channel = self.channel.clone();
             let cfg = self.cfg.clone();
             let events = self.events.clone();
-            let volume = self.volume();
-            let credentials = self.credentials.clone();
+            let speed = self.speed.clone();
+            let muted = self.muted.clone();
+            let credentials = self.credentials.read().unwrap().clone();
             ASYNC_RUNTIME.spawn(Self::worker(
                 worker_channel,
                 events,
@@ -104,7 +194,8 @@ impl Spotify {
                 cfg,
                 credentials,
                 user_tx,
-                volume,
+                speed,
+                muted,
             ));
         }
     }
@@ -128,10 +219,12 @@ impl Spotify {
             .map(|r| r.0)
     }
 
+    /// Connect with `credentials`, returning the session along with reusable
+    /// credentials for the account.
     async fn create_session(
         cfg: &config::Config,
         credentials: Credentials,
-    ) -> Result<Session, SessionError> {
+    ) -> Result<(Session, Credentials), SessionError> {
         let librespot_cache_path = config::cache_path("librespot");
         let audio_cache_path = match cfg.values().audio_cache.unwrap_or(true) {
             true => Some(librespot_cache_path.join("files")),
@@ -148,12 +241,10 @@ impl Spotify {
         .expect("Could not create cache");
         debug!("opening spotify session");
         let session_config = Self::session_config();
-        Session::connect(session_config, credentials, Some(cache), true)
-            .await
-            .map(|r| r.0)
+        Session::connect(session_config, credentials, Some(cache), true).await
     }
 
-    fn init_backend(desired_backend: Option<String>) -> Option<SinkBuilder> {
+    pub(crate) fn init_backend(desired_backend: Option<String>) -> Option<SinkBuilder> {
         let backend = if let Some(name) = desired_backend {
             audio_backend::BACKENDS
                 .iter()
@@ -174,6 +265,68 @@ impl Spotify {
         Some(backend.1)
     }
 
+    /// Create a software mixer set to the given volume.
+    pub(crate) fn create_mixer(volume: u16) -> Box<dyn Mixer> {
+        let create_mixer = librespot_playback::mixer::find(Some(SoftMixer::NAME))
+            .expect("could not create softvol mixer");
+        let mixer = create_mixer(MixerConfig::default());
+        mixer.set_volume(volume);
+        mixer
+    }
+
+    pub(crate) fn normalisation_type(mode: NormalizationMode) -> NormalisationType {
+        match mode {
+            NormalizationMode::Album => NormalisationType::Album,
+            NormalizationMode::Track => NormalisationType::Track,
+            NormalizationMode::Off | NormalizationMode::Auto => NormalisationType::Auto,
+        }
+    }
+
+    /// The configuration of the player as set in the config file and the user
+    /// state, with the given bitrate.
+    fn player_config(cfg: &config::Config, bitrate: Bitrate) -> PlayerConfig {
+        let normalization = cfg.state().normalization.unwrap_or(NormalizationMode::Off);
+        PlayerConfig {
+            gapless: cfg.values().gapless.unwrap_or(true),
+            bitrate,
+            normalisation: normalization != NormalizationMode::Off,
+            normalisation_type: Self::normalisation_type(normalization),
+            normalisation_pregain_db: cfg.values().volnorm_pregain.unwrap_or(0.0),
+            ..Default::default()
+        }
+    }
+
+    /// Create a player that plays to the configured audio backend. The
+    /// silence it plays is measured by `silence`, so that `skip_silence` can
+    /// be turned on without a new player.
+    pub(crate) fn create_player(
+        cfg: &config::Config,
+        session: Session,
+        mixer: &dyn Mixer,
+        speed: PlaybackSpeed,
+        silence: SilenceMeter,
+        player_config: PlayerConfig,
+    ) -> (Player, mpsc::UnboundedReceiver<LibrespotPlayerEvent>) {
+        let backend_name = cfg.values().backend.clone();
+        let backend =
+            Self::init_backend(backend_name).expect("Could not find an audio playback backend");
+        let audio_format: librespot_playback::config::AudioFormat = Default::default();
+        let backend_device = cfg.values().backend_device.clone();
+        let threshold_db = cfg
+            .values()
+            .skip_silence_threshold_db
+            .unwrap_or(config::DEFAULT_SKIP_SILENCE_THRESHOLD_DB);
+        let volume = mixer.get_soft_volume();
+        Player::new(player_config, session, mixer.get_soft_volume(), move || {
+            let sink: Box<dyn Sink> = Box::new(SpeedSink::new(
+                (backend)(backend_device, audio_format),
+                speed,
+            ));
+            Box::new(SilenceSink::new(sink, silence, threshold_db, volume))
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
     async fn worker(
         worker_channel: Arc<RwLock<Option<mpsc::UnboundedSender<WorkerCommand>>>>,
         events: EventManager,
@@ -181,41 +334,63 @@ impl Spotify {
         cfg: Arc<config::Config>,
         credentials: Credentials,
         user_tx: Option<oneshot::Sender<String>>,
-        volume: u16,
+        speed: PlaybackSpeed,
+        muted: Arc<AtomicBool>,
     ) {
-        let bitrate_str = cfg.values().bitrate.unwrap_or(320).to_string();
+        let bitrate_str = cfg.state().bitrate.unwrap_or(320).to_string();
         let bitrate = Bitrate::from_str(&bitrate_str);
         if bitrate.is_err() {
             error!("invalid bitrate, will use 320 instead")
         }
 
-        let player_config = PlayerConfig {
-            gapless: cfg.values().gapless.unwrap_or(true),
-            bitrate: bitrate.unwrap_or(Bitrate::Bitrate320),
-            normalisation: cfg.values().volnorm.unwrap_or(false),
-            normalisation_pregain_db: cfg.values().volnorm_pregain.unwrap_or(0.0),
-            ..Default::default()
+        let mut attempt = 0;
+        let session = loop {
+            match Self::create_session(&cfg, credentials.clone()).await {
+                Ok((session, reusable)) => {
+                    accounts::save(&reusable);
+                    break session;
+                }
+                Err(e) => {
+                    attempt += 1;
+                    let max_attempts = cfg
+                        .values()
+                        .reconnect_attempts
+                        .unwrap_or(config::DEFAULT_RECONNECT_ATTEMPTS);
+                    if attempt > max_attempts {
+                        error!("could not connect after {} attempts: {}", max_attempts, e);
+                        *worker_channel
+                            .write()
+                            .expect("can't writelock worker channel") = None;
+                        events.send(Event::Player(PlayerEvent::ConnectionLost));
+                        return;
+                    }
+                    let delay = RECONNECT_DELAY
+                        .saturating_mul(2_u32.saturating_pow(attempt - 1))
+                        .min(RECONNECT_DELAY_MAX);
+                    warn!("could not connect: {}, retrying in {:?}", e, delay);
+                    events.send(Event::Player(PlayerEvent::Reconnecting(attempt)));
+                    tokio::time::sleep(delay).await;
+                }
+            }
         };
-
-        let session = Self::create_session(&cfg, credentials)
-            .await
-            .expect("Could not create session");
         user_tx.map(|tx| tx.send(session.username()));
 
-        let create_mixer = librespot_playback::mixer::find(Some(SoftMixer::NAME))
-            .expect("could not create softvol mixer");
-        let mixer = create_mixer(MixerConfig::default());
-        mixer.set_volume(volume);
-
-        let backend_name = cfg.values().backend.clone();
-        let backend =
-            Self::init_backend(backend_name).expect("Could not find an audio playback backend");
-        let audio_format: librespot_playback::config::AudioFormat = Default::default();
-        let (player, player_events) = Player::new(
-            player_config,
+        let device = Self::output_device(&cfg);
+        let volume = Self::device_volume(&cfg, &device);
+        if volume != cfg.state().volume {
+            info!("restoring volume {} of {}", volume, device);
+            events.send(Event::Player(PlayerEvent::VolumeChanged(volume)));
+        }
+        let mixer = Self::create_mixer(volume);
+        let player_config = Self::player_config(&cfg, bitrate.unwrap_or(Bitrate::Bitrate320));
+        let silence = SilenceMeter::default();
+        let (player, player_events) = Self::create_player(
+            &cfg,
             session.clone(),
-            mixer.get_soft_volume(),
-            move || (backend)(cfg.values().backend_device.clone(), audio_format),
+            mixer.as_ref(),
+            speed.clone(),
+            silence.clone(),
+            player_config.clone(),
         );
 
         let mut worker = Worker::new(
@@ -225,6 +400,12 @@ impl Spotify {
             session,
             player,
             mixer,
+            speed,
+            silence,
+            muted,
+            player_config,
+            device,
+            cfg,
         );
         debug!("worker thread ready.");
         worker.run_loop().await;
@@ -244,14 +425,40 @@ impl Spotify {
         (*status).clone()
     }
 
+    /// The item that is playing as the player reported it, which is known
+    /// before the queue may have caught up with it.
+    pub fn get_started(&self) -> Option<Playable> {
+        self.started
+            .read()
+            .expect("could not acquire read lock on started item")
+            .clone()
+    }
+
     pub fn get_current_progress(&self) -> Duration {
         self.get_elapsed().unwrap_or_else(|| Duration::from_secs(0))
             + self
                 .get_since()
-                .map(|t| t.elapsed().unwrap())
+                .map(|t| t.elapsed().unwrap().mul_f32(self.playback_speed()))
                 .unwrap_or_else(|| Duration::from_secs(0))
     }
 
+    /// The position in the current track as the worker knows it, which
+    /// unlike [Spotify::get_current_progress()] doesn't depend on the player
+    /// events the UI has handled so far. Zero while nothing is playing.
+    pub fn request_position(&self) -> Duration {
+        let (tx, rx) = oneshot::channel();
+        self.send_worker(WorkerCommand::RequestPosition(tx));
+        ASYNC_RUNTIME.block_on(rx).unwrap_or_default()
+    }
+
+    /// The URIs of the items of the user's rootlist, which orders their
+    /// playlists into folders. `None` if it couldn't be fetched.
+    pub fn rootlist(&self) -> Option<Vec<String>> {
+        let (tx, rx) = oneshot::channel();
+        self.send_worker(WorkerCommand::RequestRootlist(tx));
+        ASYNC_RUNTIME.block_on(rx).ok().flatten()
+    }
+
     fn set_elapsed(&self, new_elapsed: Option<Duration>) {
         let mut elapsed = self
             .elapsed
@@ -286,6 +493,10 @@ impl Spotify {
 
     pub fn load(&self, track: &Playable, start_playing: bool, position_ms: u32) {
         info!("loading track: {:?}", track);
+        *self
+            .requested
+            .write()
+            .expect("could not acquire write lock on requested items") = vec![track.clone()];
         self.send_worker(WorkerCommand::Load(
             track.clone(),
             start_playing,
@@ -295,7 +506,42 @@ impl Spotify {
 
     pub fn update_status(&self, new_status: PlayerEvent) {
         match new_status {
-            PlayerEvent::Paused(position) => {
+            PlayerEvent::SleepTimer(timer) => {
+                // The sleep timer doesn't change the playback status itself.
+                let mut sleep_timer = self
+                    .sleep_timer
+                    .write()
+                    .expect("could not acquire write lock on sleep timer");
+                *sleep_timer = timer;
+                return;
+            }
+            // The muted state is tracked by the worker itself.
+            PlayerEvent::Muted(_) => return,
+            // The volume doesn't change the playback status either.
+            PlayerEvent::VolumeChanged(volume) => {
+                self.cfg.with_state_mut(|mut s| s.volume = volume);
+                return;
+            }
+            PlayerEvent::Progress { .. } => return,
+            // The status is set by the `Playing` that follows.
+            PlayerEvent::TrackStarted(uri) => {
+                let playable = self
+                    .requested
+                    .read()
+                    .expect("could not acquire read lock on requested items")
+                    .iter()
+                    .find(|playable| playable.uri() == uri)
+                    .cloned();
+                *self
+                    .started
+                    .write()
+                    .expect("could not acquire write lock on started item") = playable;
+                return;
+            }
+            // Keep the playback status, so playback can resume from there once
+            // the worker is restarted.
+            PlayerEvent::Disconnected(_) => return,
+            PlayerEvent::Loading(_, position) | PlayerEvent::Paused(position) => {
                 self.set_elapsed(Some(position));
                 self.set_since(None);
             }
@@ -303,7 +549,18 @@ impl Spotify {
                 self.set_since(Some(playback_start));
                 self.set_elapsed(None);
             }
-            PlayerEvent::Stopped | PlayerEvent::FinishedTrack => {
+            PlayerEvent::Stopped => {
+                *self
+                    .started
+                    .write()
+                    .expect("could not acquire write lock on started item") = None;
+                self.set_elapsed(None);
+                self.set_since(None);
+            }
+            PlayerEvent::FinishedTrack
+            | PlayerEvent::Unavailable(_)
+            | PlayerEvent::Reconnecting(_)
+            | PlayerEvent::ConnectionLost => {
                 self.set_elapsed(None);
                 self.set_since(None);
             }
@@ -326,12 +583,11 @@ impl Spotify {
         self.send_worker(WorkerCommand::Play);
     }
 
+    /// Pause or resume playback. The worker decides which by the state of
+    /// the player, which the status here can lag behind.
     pub fn toggleplayback(&self) {
-        match self.get_current_status() {
-            PlayerEvent::Playing(_) => self.pause(),
-            PlayerEvent::Paused(_) => self.play(),
-            _ => (),
-        }
+        info!("toggleplayback()");
+        self.send_worker(WorkerCommand::TogglePlayPause);
     }
 
     fn send_worker(&self, cmd: WorkerCommand) {
@@ -357,15 +613,132 @@ impl Spotify {
     }
 
     pub fn seek_relative(&self, delta: i32) {
-        let progress = self.get_current_progress();
-        let new = (progress.as_secs() * 1000) as i32 + progress.subsec_millis() as i32 + delta;
-        self.seek(std::cmp::max(0, new) as u32);
+        self.send_worker(WorkerCommand::SeekRelative(delta));
+    }
+
+    /// Pause or stop playback after `duration` has passed, or after the
+    /// current track has finished if `None`. With `finish_track`, the track
+    /// that is playing once `duration` has passed is finished first. Replaces
+    /// a previously armed sleep timer.
+    pub fn sleep_after(&self, duration: Option<Duration>, finish_track: bool) {
+        self.send_worker(WorkerCommand::SleepAfter(duration, finish_track));
+    }
+
+    pub fn cancel_sleep(&self) {
+        self.send_worker(WorkerCommand::CancelSleep);
+    }
+
+    /// Tell the worker whether the queue stops once the current item has
+    /// finished, so it doesn't start the next one early.
+    pub fn set_stop_after_track(&self, armed: bool) {
+        self.send_worker(WorkerCommand::StopAfterTrack(armed));
+    }
+
+    /// Loop the current track from `position_ms`, until the end of the loop
+    /// or of the track. The loop is cleared once another track is loaded.
+    pub fn set_loop_start(&self, position_ms: u32) {
+        self.send_worker(WorkerCommand::SetLoopStart(position_ms));
+    }
+
+    /// Return to the start of the loop, or of the current track, once
+    /// `position_ms` is reached.
+    pub fn set_loop_end(&self, position_ms: u32) {
+        self.send_worker(WorkerCommand::SetLoopEnd(position_ms));
+    }
+
+    pub fn clear_loop(&self) {
+        self.send_worker(WorkerCommand::ClearLoop);
+    }
+
+    pub fn sleep_timer(&self) -> Option<SleepTimer> {
+        let sleep_timer = self
+            .sleep_timer
+            .read()
+            .expect("could not acquire read lock on sleep timer");
+        *sleep_timer
+    }
+
+    pub fn playback_speed(&self) -> f32 {
+        self.speed.get()
+    }
+
+    /// Change the playback speed. Only has an effect while playing an episode.
+    pub fn set_playback_speed(&self, speed: f32) {
+        info!("setting playback speed to {}", speed);
+        self.send_worker(WorkerCommand::SetPlaybackSpeed(speed));
+    }
+
+    pub fn bitrate(&self) -> u32 {
+        self.cfg.state().bitrate.unwrap_or(320)
+    }
+
+    pub fn normalization(&self) -> NormalizationMode {
+        self.cfg
+            .state()
+            .normalization
+            .unwrap_or(NormalizationMode::Off)
+    }
+
+    /// Switch to another normalisation mode, which is kept across restarts.
+    pub fn set_normalization(&self, mode: NormalizationMode) {
+        info!("setting normalisation to {:?}", mode);
+        self.cfg
+            .with_state_mut(|mut s| s.normalization = Some(mode));
+        self.send_worker(WorkerCommand::SetNormalization(mode));
+    }
+
+    /// Change the normalisation pregain in dB for the rest of the session.
+    pub fn set_normalization_pregain(&self, pregain: f64) {
+        info!("setting normalisation pregain to {} dB", pregain);
+        self.send_worker(WorkerCommand::SetNormalizationPregain(pregain));
+    }
+
+    /// Switch to another bitrate, which is kept across restarts.
+    pub fn set_bitrate(&self, bitrate: u32) {
+        match Bitrate::from_str(&bitrate.to_string()) {
+            Ok(parsed) => {
+                info!("setting bitrate to {}", bitrate);
+                self.cfg.with_state_mut(|mut s| s.bitrate = Some(bitrate));
+                self.send_worker(WorkerCommand::SetBitrate(parsed));
+            }
+            Err(_) => error!("invalid bitrate {}", bitrate),
+        }
+    }
+
+    /// The audio output device the backend plays on, by the name it is
+    /// configured with, or "default" for the default device of the backend.
+    pub fn output_device(cfg: &config::Config) -> String {
+        cfg.values()
+            .backend_device
+            .clone()
+            .unwrap_or_else(|| "default".to_string())
+    }
+
+    /// The volume last set on `device`, or the last volume set on any device
+    /// if it wasn't used before.
+    fn device_volume(cfg: &config::Config, device: &str) -> u16 {
+        let state = cfg.state();
+        state
+            .device_volumes
+            .get(device)
+            .copied()
+            .unwrap_or(state.volume)
     }
 
     pub fn volume(&self) -> u16 {
         self.cfg.state().volume
     }
 
+    pub fn is_muted(&self) -> bool {
+        self.muted.load(Ordering::Relaxed)
+    }
+
+    /// Mute the audio, or restore the volume from before it was muted.
+    /// Changing the volume while muted unmutes as well.
+    pub fn toggle_mute(&self) {
+        self.send_worker(WorkerCommand::ToggleMute);
+    }
+
     pub fn set_volume(&self, volume: u16) {
         info!("setting volume to {}", volume);
         self.cfg.with_state_mut(|mut s| s.volume = volume);
@@ -373,12 +746,79 @@ impl Spotify {
     }
 
     pub fn preload(&self, track: &Playable) {
+        let mut requested = self
+            .requested
+            .write()
+            .expect("could not acquire write lock on requested items");
+        requested.truncate(1);
+        requested.push(track.clone());
+        drop(requested);
         self.send_worker(WorkerCommand::Preload(track.clone()));
     }
 
+    /// Download `items` into the audio cache ahead of playing them, and stop
+    /// the earlier downloads of other items.
+    pub fn prefetch(&self, items: Vec<Playable>) {
+        self.send_worker(WorkerCommand::Prefetch(items));
+    }
+
+    /// Send `event` to the main loop, for work that finished in the
+    /// background.
+    pub fn send_event(&self, event: Event) {
+        self.events.send(event);
+    }
+
     pub fn shutdown(&self) {
         self.send_worker(WorkerCommand::Shutdown);
     }
+
+    /// Switch to the account of `credentials`. The worker is shut down and
+    /// restarted with them, after which [Spotify::take_account_switch] tells
+    /// that the account changed.
+    pub fn switch_account(&self, credentials: Credentials) {
+        info!("switching to account {}", credentials.username);
+        self.api.set_user(Some(credentials.username.clone()));
+        *self.credentials.write().unwrap() = credentials;
+        self.switching_account.store(true, Ordering::Relaxed);
+        // Tokens of the previous account are renewed once the new session is
+        // up.
+        self.api.expire_token();
+        let connected = self
+            .channel
+            .read()
+            .expect("can't readlock worker channel")
+            .is_some();
+        if connected {
+            self.shutdown();
+        } else {
+            self.events.send(Event::SessionDied);
+        }
+    }
+
+    /// Whether the worker was restarted to switch accounts, which is only
+    /// told once.
+    pub fn take_account_switch(&self) -> bool {
+        self.switching_account.swap(false, Ordering::Relaxed)
+    }
+
+    /// The username of the account that is connected to.
+    pub fn username(&self) -> String {
+        self.credentials.read().unwrap().username.clone()
+    }
+
+    /// Reconnect to Spotify, also after reconnecting automatically gave up.
+    pub fn reconnect(&self) {
+        let connected = self
+            .channel
+            .read()
+            .expect("can't readlock worker channel")
+            .is_some();
+        if connected {
+            self.shutdown();
+        } else {
+            self.start_worker(None);
+        }
+    }
 }
 
 #[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
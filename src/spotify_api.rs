@@ -10,14 +10,15 @@ use crate::ui::pagination::{ApiPage, ApiResult};
 use crate::ASYNC_RUNTIME;
 use chrono::{DateTime, Duration as ChronoDuration, Utc};
 use futures::channel::oneshot;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 
 use rspotify::http::HttpError;
 use rspotify::model::{
-    AlbumId, AlbumType, ArtistId, CursorBasedPage, EpisodeId, FullAlbum, FullArtist, FullEpisode,
-    FullPlaylist, FullShow, FullTrack, ItemPositions, Market, Page, PlayableId, PlaylistId,
-    PrivateUser, Recommendations, SavedAlbum, SavedTrack, SearchResult, SearchType, Show, ShowId,
-    SimplifiedTrack, TrackId, UserId,
+    AlbumId, AlbumType, ArtistId, AudioFeatures, Country, CursorBasedPage, EpisodeId, FullAlbum,
+    FullArtist, FullEpisode, FullPlaylist, FullShow, FullTrack, IdError, ItemPositions, Market,
+    Page, PlayHistory, PlayableId, PlaylistId, PrivateUser, PublicUser, Recommendations,
+    SavedAlbum, SavedTrack, SearchResult, SearchType, Show, ShowId, SimplifiedTrack, TimeLimits,
+    TimeRange, TrackId, UserId,
 };
 use rspotify::{prelude::*, AuthCodeSpotify, ClientError, ClientResult, Token};
 use std::collections::HashSet;
@@ -30,7 +31,7 @@ use tokio::sync::mpsc;
 #[derive(Clone)]
 pub struct WebApi {
     api: AuthCodeSpotify,
-    user: Option<String>,
+    user: Arc<RwLock<Option<String>>>,
     worker_channel: Arc<RwLock<Option<mpsc::UnboundedSender<WorkerCommand>>>>,
     token_expiration: Arc<RwLock<DateTime<Utc>>>,
 }
@@ -39,7 +40,7 @@ impl Default for WebApi {
     fn default() -> Self {
         Self {
             api: AuthCodeSpotify::default(),
-            user: None,
+            user: Arc::new(RwLock::new(None)),
             worker_channel: Arc::new(RwLock::new(None)),
             token_expiration: Arc::new(RwLock::new(Utc::now())),
         }
@@ -51,8 +52,8 @@ impl WebApi {
         Self::default()
     }
 
-    pub fn set_user(&mut self, user: Option<String>) {
-        self.user = user;
+    pub fn set_user(&self, user: Option<String>) {
+        *self.user.write().unwrap() = user;
     }
 
     pub(crate) fn set_worker_channel(
@@ -87,18 +88,7 @@ impl WebApi {
             channel.send(cmd).expect("can't send message to worker");
             let token_option = ASYNC_RUNTIME.block_on(token_rx).unwrap();
             if let Some(token) = token_option {
-                *self.api.token.lock().expect("can't writelock api token") = Some(Token {
-                    access_token: token.access_token,
-                    expires_in: chrono::Duration::seconds(token.expires_in.into()),
-                    scopes: HashSet::from_iter(token.scope),
-                    expires_at: None,
-                    refresh_token: None,
-                });
-                *self
-                    .token_expiration
-                    .write()
-                    .expect("could not writelock token") =
-                    Utc::now() + ChronoDuration::seconds(token.expires_in.into());
+                self.set_token(token);
             } else {
                 error!("Failed to update token");
             }
@@ -107,6 +97,54 @@ impl WebApi {
         }
     }
 
+    /// The current access token, for endpoints that aren't part of the Web
+    /// API.
+    pub fn access_token(&self) -> Option<String> {
+        self.update_token();
+        self.api
+            .token
+            .lock()
+            .expect("can't readlock api token")
+            .as_ref()
+            .map(|token| token.access_token.clone())
+    }
+
+    /// Renew the token with the next call to [WebApi::update_token].
+    pub fn expire_token(&self) {
+        *self
+            .token_expiration
+            .write()
+            .expect("could not writelock token") = Utc::now();
+    }
+
+    fn set_token(&self, token: librespot_core::keymaster::Token) {
+        *self.api.token.lock().expect("can't writelock api token") = Some(Token {
+            access_token: token.access_token,
+            expires_in: chrono::Duration::seconds(token.expires_in.into()),
+            scopes: HashSet::from_iter(token.scope),
+            expires_at: None,
+            refresh_token: None,
+        });
+        *self
+            .token_expiration
+            .write()
+            .expect("could not writelock token") =
+            Utc::now() + ChronoDuration::seconds(token.expires_in.into());
+    }
+
+    /// A channel for the worker to send refreshed tokens to, which are then
+    /// used for subsequent API calls.
+    pub(crate) fn token_listener(&self) -> mpsc::UnboundedSender<librespot_core::keymaster::Token> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let api = self.clone();
+        ASYNC_RUNTIME.spawn(async move {
+            while let Some(token) = rx.recv().await {
+                api.set_token(token);
+            }
+        });
+        tx
+    }
+
     /// retries once when rate limits are hit
     fn api_with_retry<F, R>(&self, cb: F) -> Option<R>
     where
@@ -155,7 +193,7 @@ impl WebApi {
         position: Option<i32>,
     ) -> bool {
         self.api_with_retry(|api| {
-            let trackids: Vec<PlayableId> = tracks.iter().map(|playable| playable.into()).collect();
+            let trackids = playable_ids(tracks);
             api.playlist_add_items(
                 PlaylistId::from_id(playlist_id).unwrap(),
                 trackids.iter().map(|id| id.as_ref()),
@@ -165,19 +203,42 @@ impl WebApi {
         .is_some()
     }
 
+    /// Remove the occurrences of `playables` at their list index, which is
+    /// their position in the playlist as of `snapshot_id`. Returns the new
+    /// snapshot ID of the playlist.
     pub fn delete_tracks(
         &self,
         playlist_id: &str,
         snapshot_id: &str,
         playables: &[Playable],
-    ) -> bool {
+    ) -> Option<String> {
         self.api_with_retry(move |api| {
-            let playable_ids: Vec<PlayableId> =
-                playables.iter().map(|playable| playable.into()).collect();
-            let positions = playables
+            let (playable_ids, positions): (Vec<PlayableId>, Vec<[u32; 1]>) = playables
                 .iter()
-                .map(|playable| [playable.list_index() as u32])
-                .collect::<Vec<_>>();
+                .filter_map(|playable| {
+                    let id = match playable {
+                        // Relinked tracks are listed by their original ID
+                        Playable::Track(Track {
+                            linked_from: Some(id),
+                            ..
+                        }) => match TrackId::from_id(id.clone()) {
+                            Ok(id) => PlayableId::Track(id),
+                            Err(e) => {
+                                warn!("not removing relinked track {}: {}", id, e);
+                                return None;
+                            }
+                        },
+                        _ => match playable.try_into() {
+                            Ok(id) => id,
+                            Err(e) => {
+                                warn!("not removing {}", e);
+                                return None;
+                            }
+                        },
+                    };
+                    Some((id, [playable.list_index() as u32]))
+                })
+                .unzip();
             let item_pos: Vec<ItemPositions> = playable_ids
                 .iter()
                 .zip(positions.iter())
@@ -192,7 +253,29 @@ impl WebApi {
                 Some(snapshot_id),
             )
         })
-        .is_some()
+        .map(|result| result.snapshot_id)
+    }
+
+    /// Move the item at position `from` of the playlist before the item at
+    /// `insert_before`, as of `snapshot_id`. Returns the new snapshot ID of
+    /// the playlist.
+    pub fn reorder_track(
+        &self,
+        playlist_id: &str,
+        snapshot_id: &str,
+        from: usize,
+        insert_before: usize,
+    ) -> Option<String> {
+        self.api_with_retry(|api| {
+            api.playlist_reorder_items(
+                PlaylistId::from_id(playlist_id).unwrap(),
+                Some(from as i32),
+                Some(insert_before as i32),
+                Some(1),
+                Some(snapshot_id),
+            )
+        })
+        .map(|result| result.snapshot_id)
     }
 
     pub fn overwrite_playlist(&self, id: &str, tracks: &[Playable]) {
@@ -207,8 +290,7 @@ impl WebApi {
         };
 
         if let Some(()) = self.api_with_retry(|api| {
-            let playable_ids: Vec<PlayableId> =
-                tracks.iter().map(|playable| playable.into()).collect();
+            let playable_ids = playable_ids(&tracks);
             api.playlist_replace_items(
                 PlaylistId::from_id(id).unwrap(),
                 playable_ids.iter().map(|p| p.as_ref()),
@@ -246,17 +328,38 @@ impl WebApi {
         name: &str,
         public: Option<bool>,
         description: Option<&str>,
-    ) -> Option<String> {
+    ) -> Option<Playlist> {
         let result = self.api_with_retry(|api| {
             api.user_playlist_create(
-                UserId::from_id(self.user.as_ref().unwrap()).unwrap(),
+                UserId::from_id(self.user.read().unwrap().as_ref().unwrap()).unwrap(),
                 name,
                 public,
                 None,
                 description,
             )
         });
-        result.map(|r| r.id.id().to_string())
+        result.map(|playlist| (&playlist).into())
+    }
+
+    /// Change the name or visibility of a playlist, leaving what is `None` as
+    /// it is. Returns whether it was changed.
+    pub fn change_playlist_details(
+        &self,
+        id: &str,
+        name: Option<&str>,
+        public: Option<bool>,
+        collaborative: Option<bool>,
+    ) -> bool {
+        self.api_with_retry(|api| {
+            api.playlist_change_detail(
+                PlaylistId::from_id(id).unwrap(),
+                name,
+                public,
+                None,
+                collaborative,
+            )
+        })
+        .is_some()
     }
 
     pub fn album(&self, album_id: &str) -> Option<FullAlbum> {
@@ -279,6 +382,20 @@ impl WebApi {
         self.api_with_retry(|api| api.track(tid.clone()))
     }
 
+    /// The full tracks of up to 50 IDs.
+    pub fn tracks(&self, track_ids: &[String]) -> Option<Vec<FullTrack>> {
+        let tids: Vec<TrackId> = track_ids
+            .iter()
+            .filter_map(|id| TrackId::from_id(id).ok())
+            .collect();
+        self.api_with_retry(|api| api.tracks(tids.clone(), Some(Market::FromToken)))
+    }
+
+    pub fn track_features(&self, track_id: &str) -> Option<AudioFeatures> {
+        let tid = TrackId::from_id(track_id).ok()?;
+        self.api_with_retry(|api| api.track_features(tid.clone()))
+    }
+
     pub fn get_show(&self, show_id: &str) -> Option<FullShow> {
         let sid = ShowId::from_id(show_id).ok()?;
         self.api_with_retry(|api| api.get_a_show(sid.clone(), Some(Market::FromToken)))
@@ -385,8 +502,10 @@ impl WebApi {
                             .flat_map(|(index, pt)| {
                                 pt.track.as_ref().map(|t| {
                                     let mut playable: Playable = t.into();
-                                    // TODO: set these
                                     playable.set_added_at(pt.added_at);
+                                    playable.set_added_by(
+                                        pt.added_by.as_ref().map(|user| user.id.id().to_string()),
+                                    );
                                     playable.set_list_index(page.offset as usize + index);
                                     playable
                                 })
@@ -419,6 +538,30 @@ impl WebApi {
         })
     }
 
+    /// The release date of the latest album or single of an artist, which is
+    /// `Some(None)` if the artist hasn't released any. The first page of
+    /// each is searched, since Spotify lists the latest releases first.
+    pub fn artist_latest_release(&self, artist_id: &str) -> Option<Option<String>> {
+        let mut latest: Option<String> = None;
+        for album_type in [AlbumType::Album, AlbumType::Single] {
+            let page = self.api_with_retry(|api| {
+                api.artist_albums_manual(
+                    ArtistId::from_id(artist_id).unwrap(),
+                    Some(album_type),
+                    Some(Market::FromToken),
+                    Some(50),
+                    Some(0),
+                )
+            })?;
+            let dates = page
+                .items
+                .into_iter()
+                .filter_map(|album| album.release_date);
+            latest = latest.into_iter().chain(dates).max();
+        }
+        Some(latest)
+    }
+
     pub fn artist_albums(
         &self,
         artist_id: &str,
@@ -515,6 +658,17 @@ impl WebApi {
         self.api_with_retry(|api| api.current_user_followed_artists(last, Some(50)))
     }
 
+    /// The tracks the user played on any device, most recent first, that
+    /// were played before `before` if given.
+    pub fn current_user_recently_played(
+        &self,
+        before: Option<DateTime<Utc>>,
+    ) -> Option<CursorBasedPage<PlayHistory>> {
+        self.api_with_retry(|api| {
+            api.current_user_recently_played(Some(50), before.map(TimeLimits::Before))
+        })
+    }
+
     pub fn user_follow_artists(&self, ids: Vec<&str>) -> Option<()> {
         self.api_with_retry(|api| {
             api.user_follow_artists(
@@ -541,6 +695,13 @@ impl WebApi {
         })
     }
 
+    /// Whether the albums with the given IDs are saved, at most 50 at once.
+    pub fn current_user_saved_albums_contains(&self, ids: Vec<&str>) -> Option<Vec<bool>> {
+        contains_ids(&ids, AlbumId::from_id, |ids| {
+            self.api_with_retry(|api| api.current_user_saved_albums_contains(ids.clone()))
+        })
+    }
+
     pub fn current_user_saved_albums_add(&self, ids: Vec<&str>) -> Option<()> {
         self.api_with_retry(|api| {
             api.current_user_saved_albums_add(
@@ -567,6 +728,13 @@ impl WebApi {
         })
     }
 
+    /// Whether the tracks with the given IDs are saved, at most 50 at once.
+    pub fn current_user_saved_tracks_contains(&self, ids: Vec<&str>) -> Option<Vec<bool>> {
+        contains_ids(&ids, TrackId::from_id, |ids| {
+            self.api_with_retry(|api| api.current_user_saved_tracks_contains(ids.clone()))
+        })
+    }
+
     pub fn current_user_saved_tracks_add(&self, ids: Vec<&str>) -> Option<()> {
         self.api_with_retry(|api| {
             api.current_user_saved_tracks_add(
@@ -603,7 +771,52 @@ impl WebApi {
             .map(|fa| fa.iter().map(|a| a.into()).collect())
     }
 
-    pub fn categories(&self) -> ApiResult<Category> {
+    pub fn current_user_top_tracks(&self, range: TimeRange) -> ApiResult<Track> {
+        const MAX_LIMIT: u32 = 50;
+        let spotify = self.clone();
+        let fetch_page = move |offset: u32| {
+            debug!("fetching top tracks, offset: {}", offset);
+            spotify.api_with_retry(|api| {
+                match api.current_user_top_tracks_manual(Some(range), Some(MAX_LIMIT), Some(offset))
+                {
+                    Ok(page) => Ok(ApiPage {
+                        offset: page.offset,
+                        total: page.total,
+                        items: page.items.iter().map(|track| track.into()).collect(),
+                    }),
+                    Err(e) => Err(e),
+                }
+            })
+        };
+        ApiResult::new(MAX_LIMIT, Arc::new(fetch_page))
+    }
+
+    pub fn current_user_top_artists(&self, range: TimeRange) -> ApiResult<Artist> {
+        const MAX_LIMIT: u32 = 50;
+        let spotify = self.clone();
+        let fetch_page = move |offset: u32| {
+            debug!("fetching top artists, offset: {}", offset);
+            spotify.api_with_retry(|api| {
+                match api.current_user_top_artists_manual(
+                    Some(range),
+                    Some(MAX_LIMIT),
+                    Some(offset),
+                ) {
+                    Ok(page) => Ok(ApiPage {
+                        offset: page.offset,
+                        total: page.total,
+                        items: page.items.iter().map(|artist| artist.into()).collect(),
+                    }),
+                    Err(e) => Err(e),
+                }
+            })
+        };
+        ApiResult::new(MAX_LIMIT, Arc::new(fetch_page))
+    }
+
+    /// The categories of playlists to browse in `country`, or in any country
+    /// if it isn't known.
+    pub fn categories(&self, country: Option<Country>) -> ApiResult<Category> {
         const MAX_LIMIT: u32 = 50;
         let spotify = self.clone();
         let fetch_page = move |offset: u32| {
@@ -611,7 +824,7 @@ impl WebApi {
             spotify.api_with_retry(|api| {
                 match api.categories_manual(
                     None,
-                    Some(Market::FromToken),
+                    country.map(Market::Country),
                     Some(MAX_LIMIT),
                     Some(offset),
                 ) {
@@ -627,7 +840,36 @@ impl WebApi {
         ApiResult::new(MAX_LIMIT, Arc::new(fetch_page))
     }
 
-    pub fn category_playlists(&self, category_id: &str) -> ApiResult<Playlist> {
+    /// The albums that were released recently in `country`, or in any
+    /// country if it isn't known.
+    pub fn new_releases(&self, country: Option<Country>) -> ApiResult<Album> {
+        const MAX_LIMIT: u32 = 50;
+        let spotify = self.clone();
+        let fetch_page = move |offset: u32| {
+            debug!("fetching new releases, offset: {}", offset);
+            spotify.api_with_retry(|api| {
+                match api.new_releases_manual(
+                    country.map(Market::Country),
+                    Some(MAX_LIMIT),
+                    Some(offset),
+                ) {
+                    Ok(page) => Ok(ApiPage {
+                        offset: page.offset,
+                        total: page.total,
+                        items: page.items.iter().map(|album| album.into()).collect(),
+                    }),
+                    Err(e) => Err(e),
+                }
+            })
+        };
+        ApiResult::new(MAX_LIMIT, Arc::new(fetch_page))
+    }
+
+    pub fn category_playlists(
+        &self,
+        category_id: &str,
+        country: Option<Country>,
+    ) -> ApiResult<Playlist> {
         const MAX_LIMIT: u32 = 50;
         let spotify = self.clone();
         let category_id = category_id.to_string();
@@ -636,7 +878,7 @@ impl WebApi {
             spotify.api_with_retry(|api| {
                 match api.category_playlists_manual(
                     &category_id,
-                    Some(Market::FromToken),
+                    country.map(Market::Country),
                     Some(MAX_LIMIT),
                     Some(offset),
                 ) {
@@ -652,7 +894,103 @@ impl WebApi {
         ApiResult::new(MAX_LIMIT, Arc::new(fetch_page))
     }
 
+    /// The playlists Spotify features in `country`, along with the message
+    /// that introduces them.
+    pub fn featured_playlists(&self, country: Option<Country>) -> (String, ApiResult<Playlist>) {
+        const MAX_LIMIT: u32 = 50;
+        let spotify = self.clone();
+        let message = Arc::new(RwLock::new(String::new()));
+        let fetch_page = {
+            let message = message.clone();
+            move |offset: u32| {
+                debug!("fetching featured playlists, offset: {}", offset);
+                spotify.api_with_retry(|api| {
+                    match api.featured_playlists(
+                        None,
+                        country.map(Market::Country),
+                        None,
+                        Some(MAX_LIMIT),
+                        Some(offset),
+                    ) {
+                        Ok(featured) => {
+                            *message.write().unwrap() = featured.message;
+                            Ok(ApiPage {
+                                offset: featured.playlists.offset,
+                                total: featured.playlists.total,
+                                items: featured
+                                    .playlists
+                                    .items
+                                    .iter()
+                                    .map(|sp| sp.into())
+                                    .collect(),
+                            })
+                        }
+                        Err(e) => Err(e),
+                    }
+                })
+            }
+        };
+        let playlists = ApiResult::new(MAX_LIMIT, Arc::new(fetch_page));
+        let message = message.read().unwrap().clone();
+        (message, playlists)
+    }
+
+    pub fn user(&self, user_id: &str) -> Option<PublicUser> {
+        self.api_with_retry(|api| api.user(UserId::from_id(user_id).unwrap()))
+    }
+
     pub fn current_user(&self) -> Option<PrivateUser> {
         self.api_with_retry(|api| api.current_user())
     }
 }
+
+/// The Spotify IDs of `playables`, leaving out local files and the items
+/// whose IDs are malformed.
+fn playable_ids(playables: &[Playable]) -> Vec<PlayableId<'static>> {
+    playables
+        .iter()
+        .filter_map(|playable| match playable.try_into() {
+            Ok(id) => Some(id),
+            Err(e) => {
+                warn!("leaving out {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Ask `contains` whether the items with `ids` are saved. IDs that `parse`
+/// rejects are logged and reported as not saved, so that the answers still
+/// line up with `ids`.
+fn contains_ids<'a, I>(
+    ids: &[&'a str],
+    parse: impl Fn(&'a str) -> Result<I, IdError>,
+    contains: impl FnOnce(Vec<I>) -> Option<Vec<bool>>,
+) -> Option<Vec<bool>> {
+    let mut valid = Vec::with_capacity(ids.len());
+    let mut checked = Vec::with_capacity(ids.len());
+    for id in ids {
+        match parse(id) {
+            Ok(id) => {
+                valid.push(id);
+                checked.push(true);
+            }
+            Err(e) => {
+                warn!("not checking {}: {}", id, e);
+                checked.push(false);
+            }
+        }
+    }
+    let count = valid.len();
+    let answers = contains(valid)?;
+    if answers.len() != count {
+        return None;
+    }
+    let mut answers = answers.into_iter();
+    Some(
+        checked
+            .into_iter()
+            .map(|checked| checked && answers.next().unwrap_or(false))
+            .collect(),
+    )
+}
@@ -1,48 +1,416 @@
 use crate::config;
+use crate::config::{NormalizationMode, SleepTimerAction};
 use crate::events::{Event, EventManager};
+use crate::local_player::{local_path, LocalPlayer, LocalPlayerEvent};
 use crate::model::playable::Playable;
+use crate::playback_speed::PlaybackSpeed;
 use crate::queue::QueueEvent;
-use crate::spotify::PlayerEvent;
+use crate::silence::SilenceMeter;
+use crate::spotify::{DisconnectReason, PlayerEvent, SleepTimer, Spotify};
 use futures::channel::oneshot;
 use futures::{Future, FutureExt};
+use librespot_audio::AudioFile;
 use librespot_core::keymaster::Token;
 use librespot_core::session::Session;
 use librespot_core::spotify_id::{SpotifyAudioType, SpotifyId};
+use librespot_metadata::{AudioItem, FileFormat};
+use librespot_playback::config::{AudioFormat, Bitrate, PlayerConfig};
 use librespot_playback::mixer::Mixer;
 use librespot_playback::player::{Player, PlayerEvent as LibrespotPlayerEvent};
+use librespot_protocol::playlist4changes::SelectedListContent;
 use log::{debug, error, info, warn};
-use std::time::Duration;
+use protobuf::Message;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::{pin::Pin, time::SystemTime};
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tokio::time;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tokio_stream::StreamExt;
 
+/// The log target of the transitions of the playback state, to filter the
+/// log by.
+const STATE_LOG_TARGET: &str = "ncspot::worker::state";
+
+/// What happens once a [Fade] has finished.
+#[derive(PartialEq, Eq)]
+enum FadeEnd {
+    /// Return the mixer to the user's volume.
+    Restore,
+    /// Pause playback, then return the mixer to the user's volume.
+    Pause,
+    /// Keep the mixer silent until the next track is loaded.
+    Mute,
+}
+
+/// A ramp of the mixer volume, advanced while the worker is running.
+struct Fade {
+    from: u16,
+    to: u16,
+    start: Instant,
+    duration: Duration,
+    end: FadeEnd,
+}
+
+impl Fade {
+    fn new(from: u16, to: u16, duration: Duration, end: FadeEnd) -> Fade {
+        Fade {
+            from,
+            to,
+            start: Instant::now(),
+            duration,
+            end,
+        }
+    }
+
+    /// The volume at this point of the fade, `None` once it has finished.
+    fn volume(&self) -> Option<u16> {
+        let progress = self.start.elapsed().as_secs_f64() / self.duration.as_secs_f64();
+        if progress >= 1.0 {
+            return None;
+        }
+        let delta = (self.to as f64 - self.from as f64) * progress;
+        Some((self.from as f64 + delta) as u16)
+    }
+}
+
+/// A second player that plays the next track while the current one is
+/// crossfaded into it. It replaces the current player once the fade is over.
+struct Crossfade {
+    player: Player,
+    events: UnboundedReceiverStream<LibrespotPlayerEvent>,
+    /// The mixer of the second player, so both can be faded independently.
+    mixer: Box<dyn Mixer>,
+    playable: Playable,
+    /// The playback state of the second player, adopted once it takes over.
+    state: PlayerEvent,
+    duration_ms: u32,
+    start: Instant,
+    duration: Duration,
+}
+
+impl Crossfade {
+    /// The progress of the fade between 0 and 1.
+    fn progress(&self) -> f64 {
+        (self.start.elapsed().as_secs_f64() / self.duration.as_secs_f64()).min(1.0)
+    }
+}
+
+/// What the worker is handling: a kind of wakeup, along with the name of the
+/// command or event if it is one. Only formatted when it is logged.
+#[derive(Clone, Copy, Default)]
+struct Cause(&'static str, Option<&'static str>);
+
+impl From<&'static str> for Cause {
+    fn from(kind: &'static str) -> Self {
+        Cause(kind, None)
+    }
+}
+
+impl fmt::Display for Cause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.1 {
+            Some(name) => write!(f, "{} {}", self.0, name),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+/// The name of a playback state, as it is logged.
+fn state_name(state: &PlayerEvent) -> &'static str {
+    match state {
+        PlayerEvent::Loading(..) => "Loading",
+        PlayerEvent::Playing(_) => "Playing",
+        PlayerEvent::Paused(_) => "Paused",
+        PlayerEvent::Stopped => "Stopped",
+        PlayerEvent::TrackStarted(_) => "TrackStarted",
+        PlayerEvent::FinishedTrack => "FinishedTrack",
+        PlayerEvent::Unavailable(_) => "Unavailable",
+        PlayerEvent::Reconnecting(_) => "Reconnecting",
+        PlayerEvent::ConnectionLost => "ConnectionLost",
+        PlayerEvent::Disconnected(_) => "Disconnected",
+        PlayerEvent::SleepTimer(_) => "SleepTimer",
+        PlayerEvent::Muted(_) => "Muted",
+        PlayerEvent::VolumeChanged(_) => "VolumeChanged",
+        PlayerEvent::Progress { .. } => "Progress",
+    }
+}
+
+/// The name of an event of a librespot player, as it is logged.
+fn event_name(event: &LibrespotPlayerEvent) -> &'static str {
+    match event {
+        LibrespotPlayerEvent::Stopped { .. } => "Stopped",
+        LibrespotPlayerEvent::Loading { .. } => "Loading",
+        LibrespotPlayerEvent::Preloading { .. } => "Preloading",
+        LibrespotPlayerEvent::Playing { .. } => "Playing",
+        LibrespotPlayerEvent::Paused { .. } => "Paused",
+        LibrespotPlayerEvent::TimeToPreloadNextTrack { .. } => "TimeToPreloadNextTrack",
+        LibrespotPlayerEvent::EndOfTrack { .. } => "EndOfTrack",
+        LibrespotPlayerEvent::Unavailable { .. } => "Unavailable",
+        LibrespotPlayerEvent::VolumeSet { .. } => "VolumeSet",
+        _ => "other",
+    }
+}
+
+/// The next event of the crossfade player, pending while there is none.
+async fn next_crossfade_event(crossfade: &mut Option<Crossfade>) -> Option<LibrespotPlayerEvent> {
+    match crossfade {
+        Some(crossfade) => crossfade.events.next().await,
+        None => futures::future::pending().await,
+    }
+}
+
+/// The next event of the local player, pending while there is none.
+async fn next_local_event(local: &mut Option<LocalPlayer>) -> Option<LocalPlayerEvent> {
+    match local {
+        Some(local) => local.events.next().await,
+        None => futures::future::pending().await,
+    }
+}
+
+/// Time added to UI refreshes, so they happen after the displayed second has
+/// changed despite timer inaccuracies.
+const UI_REFRESH_SLACK: Duration = Duration::from_millis(20);
+
+/// How long the worker waits without anything to do before it checks whether
+/// its session is still valid. librespot doesn't tell when it becomes invalid.
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long a track may take to load before the load is considered failed.
+const LOAD_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How often a download into the audio cache checks whether it's complete.
+const PREFETCH_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often the worker checks for silence to skip, see `skip_silence`.
+const SILENCE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Silence is only skipped within this long from the start or the end of a
+/// track, so quiet passages in between are played.
+const SILENCE_EDGE: Duration = Duration::from_secs(10);
+
+/// How far each check skips ahead while the start of a track is silent.
+const SILENCE_SEEK_STEP: Duration = Duration::from_millis(500);
+
 #[derive(Debug)]
 pub(crate) enum WorkerCommand {
     Load(Playable, bool, u32),
     Play,
     Pause,
+    /// Pause while playing and resume while paused, going by the state of
+    /// the player rather than the UI's view of it.
+    TogglePlayPause,
     Stop,
     Seek(u32),
+    SeekRelative(i32),
     SetVolume(u16),
+    /// Mute the mixer, or return it to the user's volume if muted.
+    ToggleMute,
+    /// Change the playback speed of the current episode.
+    SetPlaybackSpeed(f32),
+    /// Sleep after the given duration, or after the current track if `None`.
+    /// If set, the track that is playing when the duration has passed is
+    /// finished first.
+    SleepAfter(Option<Duration>, bool),
+    CancelSleep,
+    /// Whether the queue stops once the current item has finished, so the
+    /// next one mustn't start early by a gapless transition or a crossfade.
+    StopAfterTrack(bool),
+    /// Set the position in milliseconds that an A-B loop returns to.
+    SetLoopStart(u32),
+    /// Set the position in milliseconds at which an A-B loop returns to its
+    /// start.
+    SetLoopEnd(u32),
+    ClearLoop,
+    /// Recreate the player with the given bitrate, continuing playback.
+    SetBitrate(Bitrate),
+    /// Change how the volume is normalised, recreating the player.
+    SetNormalization(NormalizationMode),
+    /// Change the normalisation pregain in dB, recreating the player.
+    SetNormalizationPregain(f64),
     RequestToken(oneshot::Sender<Option<Token>>),
+    /// Reply with the position in the current track as the worker knows it,
+    /// which is zero while nothing is playing.
+    RequestPosition(oneshot::Sender<Duration>),
+    /// Reply with the URIs of the items of the user's rootlist, which are
+    /// their playlists along with the start and end markers of folders.
+    RequestRootlist(oneshot::Sender<Option<Vec<String>>>),
+    /// Send every token the worker receives to the given channel, including
+    /// the ones it refreshes by itself before the previous token expires.
+    SubscribeTokens(mpsc::UnboundedSender<Token>),
     Preload(Playable),
+    /// Download the given items into the audio cache, see [Spotify::prefetch].
+    Prefetch(Vec<Playable>),
     Shutdown,
 }
 
+impl WorkerCommand {
+    /// The name of the command, as it is logged.
+    fn name(&self) -> &'static str {
+        match self {
+            WorkerCommand::Load(..) => "Load",
+            WorkerCommand::Play => "Play",
+            WorkerCommand::Pause => "Pause",
+            WorkerCommand::TogglePlayPause => "TogglePlayPause",
+            WorkerCommand::Stop => "Stop",
+            WorkerCommand::Seek(_) => "Seek",
+            WorkerCommand::SeekRelative(_) => "SeekRelative",
+            WorkerCommand::SetVolume(_) => "SetVolume",
+            WorkerCommand::ToggleMute => "ToggleMute",
+            WorkerCommand::SetPlaybackSpeed(_) => "SetPlaybackSpeed",
+            WorkerCommand::SleepAfter(..) => "SleepAfter",
+            WorkerCommand::CancelSleep => "CancelSleep",
+            WorkerCommand::StopAfterTrack(_) => "StopAfterTrack",
+            WorkerCommand::SetLoopStart(_) => "SetLoopStart",
+            WorkerCommand::SetLoopEnd(_) => "SetLoopEnd",
+            WorkerCommand::ClearLoop => "ClearLoop",
+            WorkerCommand::SetBitrate(_) => "SetBitrate",
+            WorkerCommand::SetNormalization(_) => "SetNormalization",
+            WorkerCommand::SetNormalizationPregain(_) => "SetNormalizationPregain",
+            WorkerCommand::RequestToken(_) => "RequestToken",
+            WorkerCommand::RequestPosition(_) => "RequestPosition",
+            WorkerCommand::RequestRootlist(_) => "RequestRootlist",
+            WorkerCommand::SubscribeTokens(_) => "SubscribeTokens",
+            WorkerCommand::Preload(_) => "Preload",
+            WorkerCommand::Prefetch(_) => "Prefetch",
+            WorkerCommand::Shutdown => "Shutdown",
+        }
+    }
+
+    /// Whether the command stems from using ncspot, as opposed to the
+    /// requests for the worker's state that are made in the background.
+    fn is_interaction(&self) -> bool {
+        !matches!(
+            self,
+            WorkerCommand::RequestToken(_)
+                | WorkerCommand::RequestPosition(_)
+                | WorkerCommand::RequestRootlist(_)
+                | WorkerCommand::SubscribeTokens(_)
+                | WorkerCommand::Preload(_)
+                | WorkerCommand::Prefetch(_)
+        )
+    }
+}
+
 pub struct Worker {
     events: EventManager,
     player_events: UnboundedReceiverStream<LibrespotPlayerEvent>,
     commands: UnboundedReceiverStream<WorkerCommand>,
     session: Session,
     player: Player,
-    token_task: Pin<Box<dyn Future<Output = ()> + Send>>,
-    active: bool,
+    token_task: Pin<Box<dyn Future<Output = Option<Token>> + Send>>,
+    /// Senders waiting for the token that is being requested.
+    token_requests: Vec<oneshot::Sender<Option<Token>>>,
+    token_listeners: Vec<mpsc::UnboundedSender<Token>>,
+    /// Resolves when the current token is about to expire and should be
+    /// refreshed, pending if there is no token yet.
+    token_refresh: Pin<Box<dyn Future<Output = ()> + Send>>,
+    /// Resolves when the UI should be refreshed, which is when the displayed
+    /// position changes while playing.
+    ui_refresh: Pin<Box<dyn Future<Output = ()> + Send>>,
+    /// Resolves when the sleep timer expires, pending if it isn't armed.
+    sleep_task: Pin<Box<dyn Future<Output = ()> + Send>>,
+    /// Whether the sleep timer expires once the current track has finished.
+    sleep_after_track: bool,
+    /// Whether the sleep timer waits for the end of the track once its
+    /// duration has passed.
+    sleep_finish_track: bool,
+    /// Whether the queue stops once the current item has finished.
+    stop_after_track: bool,
+    /// Resolves when nothing was done and no track was loaded for the time
+    /// after which playback pauses, pending if that is disabled.
+    inactivity_task: Pin<Box<dyn Future<Output = ()> + Send>>,
+    /// Resolves when the current track should start fading out for a
+    /// crossfade, pending if crossfading is disabled or not playing.
+    crossfade_task: Pin<Box<dyn Future<Output = ()> + Send>>,
+    /// Where an A-B loop of the current track starts in milliseconds, which
+    /// is the start of the track if only the end is set.
+    loop_start: Option<u32>,
+    /// Where an A-B loop of the current track ends in milliseconds, which is
+    /// the end of the track if only the start is set.
+    loop_end: Option<u32>,
+    /// Resolves when the position reaches the end of the A-B loop, pending
+    /// if there is no loop end or not playing.
+    loop_task: Pin<Box<dyn Future<Output = ()> + Send>>,
+    /// The crossfade into the next track that is in progress.
+    crossfade: Option<Crossfade>,
+    /// Whether the crossfade waits for the next track to be preloaded, as
+    /// it wasn't when the crossfade was due.
+    crossfade_waiting: bool,
+    /// The player of the local file that is loaded, which takes the place of
+    /// `player` until a Spotify item is loaded.
+    local: Option<LocalPlayer>,
+    /// The last track the player was asked to load, whether it should start
+    /// playing and where, so a failed load can be retried.
+    last_load: Option<(SpotifyId, bool, u32)>,
+    /// How often the last load was retried.
+    load_retries: u32,
+    /// Resolves when the last load has taken too long, pending if nothing is
+    /// loading.
+    load_timeout: Pin<Box<dyn Future<Output = ()> + Send>>,
+    /// Resolves once a failed load should be retried with whether the failure
+    /// was transient, pending if no retry is waiting.
+    load_retry: Pin<Box<dyn Future<Output = bool> + Send>>,
+    /// Whether the next track should fade in, because the previous one faded
+    /// out at its end.
+    fade_in_next_load: bool,
+    /// Whether the next track should be loaded paused, because the sleep
+    /// timer expired at the end of the previous one.
+    pause_next_load: bool,
+    /// The playback state as last reported by librespot, used to resolve
+    /// relative seeks against the actual player position.
+    state: PlayerEvent,
+    /// What the worker is handling, which is logged as the cause of the
+    /// state transitions it makes.
+    cause: Cause,
+    /// Duration of the currently loaded track in milliseconds.
+    duration_ms: u32,
+    /// The item that is currently loaded.
+    loaded: Option<Playable>,
+    /// The URI of the loaded item once it was announced as started.
+    started: Option<String>,
+    /// The item that was preloaded to follow the current one.
+    preloaded: Option<Playable>,
+    /// The downloads of items after the preloaded one into the audio cache,
+    /// by URI.
+    prefetches: HashMap<String, JoinHandle<()>>,
+    /// The URI of the item that was loaded by a gapless transition, before
+    /// the queue requested it.
+    gapless_loaded: Option<String>,
+    /// Whether the currently loaded item is an episode, which is the only
+    /// kind of item that can be played at a different speed.
+    episode: bool,
+    speed: PlaybackSpeed,
+    /// How long the audio of the current player has been silent.
+    silence: SilenceMeter,
+    /// Whether silence is skipped in the current track, as configured when
+    /// it was loaded.
+    skip_silence: bool,
+    /// The configuration the current player was created with.
+    player_config: PlayerConfig,
     mixer: Box<dyn Mixer>,
+    /// The volume as set by the user, which the mixer returns to after a fade.
+    volume: u16,
+    /// Whether the mixer is muted, keeping it silent regardless of `volume`.
+    muted: Arc<AtomicBool>,
+    /// The audio output device of the player, whose volume is remembered
+    /// separately from those of other devices.
+    device: String,
+    fade: Option<Fade>,
+    /// Whether the session is being shut down on request.
+    shutdown_requested: bool,
+    /// Comma separated OAuth scopes to request with Web API tokens.
+    scopes: String,
+    cfg: Arc<config::Config>,
 }
 
 impl Worker {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         events: EventManager,
         player_events: mpsc::UnboundedReceiver<LibrespotPlayerEvent>,
@@ -50,7 +418,24 @@ impl Worker {
         session: Session,
         player: Player,
         mixer: Box<dyn Mixer>,
+        speed: PlaybackSpeed,
+        silence: SilenceMeter,
+        muted: Arc<AtomicBool>,
+        player_config: PlayerConfig,
+        device: String,
+        cfg: Arc<config::Config>,
     ) -> Worker {
+        let scopes = cfg.values().oauth_scopes.clone().unwrap_or_else(|| {
+            config::DEFAULT_SCOPES
+                .iter()
+                .map(|scope| scope.to_string())
+                .collect()
+        });
+
+        if muted.load(Ordering::Relaxed) {
+            mixer.set_volume(0);
+        }
+
         Worker {
             events,
             player_events: UnboundedReceiverStream::new(player_events),
@@ -58,8 +443,49 @@ impl Worker {
             player,
             session,
             token_task: Box::pin(futures::future::pending()),
-            active: false,
+            token_requests: Vec::new(),
+            token_listeners: Vec::new(),
+            token_refresh: Box::pin(futures::future::pending()),
+            ui_refresh: Box::pin(futures::future::pending()),
+            sleep_task: Box::pin(futures::future::pending()),
+            sleep_after_track: false,
+            sleep_finish_track: false,
+            stop_after_track: false,
+            inactivity_task: Box::pin(futures::future::pending()),
+            crossfade_task: Box::pin(futures::future::pending()),
+            crossfade: None,
+            crossfade_waiting: false,
+            local: None,
+            last_load: None,
+            load_retries: 0,
+            load_timeout: Box::pin(futures::future::pending()),
+            load_retry: Box::pin(futures::future::pending()),
+            loop_start: None,
+            loop_end: None,
+            loop_task: Box::pin(futures::future::pending()),
+            fade_in_next_load: false,
+            pause_next_load: false,
+            state: PlayerEvent::Stopped,
+            cause: Cause::default(),
+            duration_ms: 0,
+            loaded: None,
+            started: None,
+            preloaded: None,
+            prefetches: HashMap::new(),
+            gapless_loaded: None,
+            episode: false,
+            speed,
+            silence,
+            skip_silence: false,
+            player_config,
+            volume: mixer.volume(),
+            muted,
+            device,
             mixer,
+            fade: None,
+            shutdown_requested: false,
+            scopes: scopes.join(","),
+            cfg,
         }
     }
 }
@@ -67,145 +493,1368 @@ impl Worker {
 impl Drop for Worker {
     fn drop(&mut self) {
         debug!("Worker thread is shutting down, stopping player");
+        self.cancel_crossfade();
+        self.local = None;
+        self.prefetch(Vec::new());
         self.player.stop();
     }
 }
 
 impl Worker {
-    fn get_token(
-        &self,
-        sender: oneshot::Sender<Option<Token>>,
-    ) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    fn get_token(&self) -> Pin<Box<dyn Future<Output = Option<Token>> + Send>> {
         let client_id = config::CLIENT_ID;
-        let scopes = "user-read-private,playlist-read-private,playlist-read-collaborative,playlist-modify-public,playlist-modify-private,user-follow-modify,user-follow-read,user-library-read,user-library-modify,user-top-read,user-read-recently-played";
+        let scopes = &self.scopes;
         let url =
             format!("hm://keymaster/token/authenticated?client_id={client_id}&scope={scopes}");
-        Box::pin(
-            self.session
-                .mercury()
-                .get(url)
-                .map(move |response| {
-                    response.ok().and_then(move |response| {
-                        let payload = response.payload.first()?;
-
-                        let data = String::from_utf8(payload.clone()).ok()?;
-                        let token: Token = serde_json::from_str(&data).ok()?;
-                        info!("new token received: {:?}", token);
-                        Some(token)
-                    })
-                })
-                .map(|result| sender.send(result).unwrap()),
+        Box::pin(self.session.mercury().get(url).map(move |response| {
+            response.ok().and_then(move |response| {
+                let payload = response.payload.first()?;
+
+                let data = String::from_utf8(payload.clone()).ok()?;
+                let token: Token = serde_json::from_str(&data).ok()?;
+                info!("new token received: {:?}", token);
+                Some(token)
+            })
+        }))
+    }
+
+    /// Fetch the rootlist of the user in the background and send its item
+    /// URIs to `sender`.
+    fn request_rootlist(&self, sender: oneshot::Sender<Option<Vec<String>>>) {
+        let session = self.session.clone();
+        tokio::spawn(async move {
+            let url = format!("hm://playlist/user/{}/rootlist", session.username());
+            let items = match session.mercury().get(url).await {
+                Ok(response) => response.payload.first().and_then(|payload| {
+                    match SelectedListContent::parse_from_bytes(payload) {
+                        Ok(content) => Some(
+                            content
+                                .get_contents()
+                                .get_items()
+                                .iter()
+                                .map(|item| item.get_uri().to_string())
+                                .collect(),
+                        ),
+                        Err(e) => {
+                            warn!("could not parse the rootlist: {}", e);
+                            None
+                        }
+                    }
+                }),
+                Err(e) => {
+                    warn!("could not fetch the rootlist: {:?}", e);
+                    None
+                }
+            };
+            let _ = sender.send(items);
+        });
+    }
+
+    /// Hand out a received token and schedule its refresh shortly before it
+    /// expires.
+    fn token_received(&mut self, token: Option<Token>) {
+        for sender in self.token_requests.drain(..) {
+            let _ = sender.send(token.clone());
+        }
+
+        if let Some(token) = token {
+            self.token_listeners
+                .retain(|listener| listener.send(token.clone()).is_ok());
+
+            let margin = self
+                .cfg
+                .values()
+                .token_refresh_margin
+                .unwrap_or(config::DEFAULT_TOKEN_REFRESH_MARGIN);
+            let refresh_in = Duration::from_secs(token.expires_in.saturating_sub(margin).into());
+            debug!("refreshing token in {:?}", refresh_in);
+            self.token_refresh = Box::pin(time::sleep(refresh_in));
+        }
+    }
+
+    /// The position in the current track, taking the playback speed into
+    /// account. `None` if nothing is playing.
+    fn position(&self) -> Option<Duration> {
+        match self.state {
+            PlayerEvent::Playing(playback_start) => Some(
+                playback_start
+                    .elapsed()
+                    .unwrap_or_default()
+                    .mul_f32(self.speed.get()),
+            ),
+            PlayerEvent::Loading(_, position) | PlayerEvent::Paused(position) => Some(position),
+            _ => None,
+        }
+    }
+
+    /// Change the playback state, logging the transition along with the
+    /// track, the position and what caused it.
+    fn set_state(&mut self, state: PlayerEvent) {
+        let previous = state_name(&self.state);
+        self.state = state;
+        let track = match &self.state {
+            PlayerEvent::Loading(uri, _) => Some(uri.clone()),
+            _ => self.loaded.as_ref().map(Playable::uri),
+        };
+        info!(
+            target: STATE_LOG_TARGET,
+            "{} -> {} track={} position_ms={} cause=\"{}\"",
+            previous,
+            state_name(&self.state),
+            track.as_deref().unwrap_or("none"),
+            self.position().map_or(0, |position| position.as_millis()),
+            self.cause
+        );
+    }
+
+    /// The point in time at which playback would have started to be at
+    /// `position` now, at the current playback speed.
+    fn playback_start(&self, position: Duration) -> SystemTime {
+        SystemTime::now() - position.div_f32(self.speed.get())
+    }
+
+    fn set_playback_speed(&mut self, speed: f32) {
+        if !self.episode {
+            warn!("playback speed can only be changed for episodes");
+            return;
+        }
+        let position = self.position();
+        self.speed.set(speed);
+        if let (PlayerEvent::Playing(_), Some(position)) = (&self.state, position) {
+            let state = PlayerEvent::Playing(self.playback_start(position));
+            self.set_state(state);
+            self.events.send(Event::Player(self.state.clone()));
+            self.schedule_crossfade();
+            self.schedule_loop();
+            self.schedule_ui_refresh();
+        }
+    }
+
+    /// Seek relative to the last known position of the player. The target is
+    /// clamped to the start of the track, seeking past the end finishes the
+    /// track instead.
+    fn seek_relative(&mut self, delta: i32) {
+        let position = match self.position() {
+            Some(position) => position,
+            None => return,
+        };
+
+        let target = (position.as_millis() as i64 + delta as i64).max(0);
+        if target >= self.duration_ms as i64 {
+            debug!("relative seek past end of track, finishing track");
+            // Further relative seeks are ignored until the next track plays.
+            self.set_state(PlayerEvent::FinishedTrack);
+            self.events.send(Event::Player(PlayerEvent::FinishedTrack));
+            return;
+        }
+
+        self.seek(target as u32);
+    }
+
+    /// Seek to `target` milliseconds, taking the new position as the playback
+    /// state right away. librespot only reports seeks while playing, so this
+    /// is how a seek while paused is reported, which stays paused.
+    fn seek(&mut self, target: u32) {
+        self.cancel_crossfade();
+        self.player_seek(target);
+
+        let position = Duration::from_millis(target as u64);
+        let state = match &self.state {
+            PlayerEvent::Playing(_) => PlayerEvent::Playing(self.playback_start(position)),
+            PlayerEvent::Paused(_) => PlayerEvent::Paused(position),
+            PlayerEvent::Loading(uri, _) => PlayerEvent::Loading(uri.clone(), position),
+            // Nothing is loaded to seek in.
+            _ => return,
+        };
+        self.set_state(state);
+        self.events.send(Event::Player(self.state.clone()));
+        self.schedule_loop();
+        self.schedule_ui_refresh();
+    }
+
+    /// Skip silence that has lasted `skip_silence_min_ms` near the start of
+    /// the current track by seeking ahead, or near its end by finishing it.
+    fn skip_silence(&mut self) {
+        if self.crossfade.is_some() || self.local.is_some() || self.looping() {
+            return;
+        }
+        let position = match (&self.state, self.position()) {
+            (PlayerEvent::Playing(_), Some(position)) => position,
+            _ => return,
+        };
+        let min_silence = self
+            .cfg
+            .values()
+            .skip_silence_min_ms
+            .unwrap_or(config::DEFAULT_SKIP_SILENCE_MIN_MS);
+        if self.silence.silence() < Duration::from_millis(min_silence.into()) {
+            return;
+        }
+
+        let remaining = Duration::from_millis(self.duration_ms.into()).saturating_sub(position);
+        if remaining < SILENCE_EDGE {
+            debug!("skipping silence at the end of the track");
+            self.silence.reset();
+            self.seek_relative(remaining.as_millis() as i32);
+        } else if position < SILENCE_EDGE {
+            debug!("skipping silence at {:?}", position);
+            self.seek((position + SILENCE_SEEK_STEP).as_millis() as u32);
+        }
+    }
+
+    /// Start measuring the silence of a track that was loaded, and read
+    /// whether to skip it, so that reloading the configuration applies to
+    /// the next track.
+    fn reset_silence(&mut self) {
+        self.skip_silence = self.cfg.values().skip_silence.unwrap_or(false);
+        self.silence.reset();
+    }
+
+    /// Whether an A-B loop is set for the current track.
+    fn looping(&self) -> bool {
+        self.loop_start.is_some() || self.loop_end.is_some()
+    }
+
+    fn set_loop_start(&mut self, start: u32) {
+        info!("A-B loop starts at {}ms", start);
+        self.loop_start = Some(start);
+        if self.loop_end.map_or(false, |end| end <= start) {
+            info!("A-B loop end is before its start, looping until the end of the track");
+            self.loop_end = None;
+        }
+        self.schedule_crossfade();
+        self.schedule_loop();
+    }
+
+    fn set_loop_end(&mut self, end: u32) {
+        if end <= self.loop_start.unwrap_or(0) {
+            warn!("A-B loop end at {}ms is not after its start, ignoring", end);
+            return;
+        }
+        info!("A-B loop ends at {}ms", end);
+        self.loop_end = Some(end);
+        self.schedule_crossfade();
+        self.schedule_loop();
+    }
+
+    fn clear_loop(&mut self) {
+        if self.looping() {
+            info!("A-B loop cleared");
+        }
+        self.loop_start = None;
+        self.loop_end = None;
+        self.schedule_crossfade();
+        self.schedule_loop();
+    }
+
+    /// Schedule the return to the start of the A-B loop once its end is
+    /// reached. A loop without an end returns at the end of the track.
+    fn schedule_loop(&mut self) {
+        self.loop_task = match (&self.state, self.position(), self.loop_end) {
+            (PlayerEvent::Playing(_), Some(position), Some(end)) => {
+                let remaining = Duration::from_millis(end as u64).saturating_sub(position);
+                Box::pin(time::sleep(remaining.div_f32(self.speed.get())))
+            }
+            _ => Box::pin(futures::future::pending()),
+        };
+    }
+
+    /// Return to the start of the A-B loop, reloading the current track if it
+    /// has already ended. Returns whether there was a loop to return in.
+    fn restart_loop(&mut self, ended: bool) -> bool {
+        if !self.looping() {
+            return false;
+        }
+        let start = self.loop_start.unwrap_or(0);
+        debug!("A-B loop returning to {}ms", start);
+        if !ended {
+            self.seek(start);
+            return true;
+        }
+        if let Some(path) = self
+            .loaded
+            .as_ref()
+            .and_then(|playable| local_path(&playable.uri()))
+        {
+            self.load_local(path, true, start);
+            return true;
+        }
+        match self
+            .loaded
+            .as_ref()
+            .and_then(|playable| SpotifyId::from_uri(&playable.uri()).ok())
+        {
+            Some(id) => {
+                self.player_load(id, true, start);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn load(&mut self, playable: Playable, start_playing: bool, position_ms: u32) {
+        self.cancel_crossfade();
+        self.clear_loop();
+        self.reset_silence();
+        self.loaded = Some(playable.clone());
+        self.fade = None;
+        self.preloaded = None;
+        let start_playing = start_playing && !self.pause_next_load;
+        self.pause_next_load = false;
+        let crossfade = self.crossfade_duration();
+        if self.fade_in_next_load && start_playing && !crossfade.is_zero() {
+            self.set_mixer_volume(0);
+            self.fade = Some(Fade::new(0, self.volume, crossfade, FadeEnd::Restore));
+        } else {
+            self.set_mixer_volume(self.volume);
+        }
+        self.fade_in_next_load = false;
+        self.episode = matches!(playable, Playable::Episode(_));
+        if !self.episode {
+            self.speed.set(1.0);
+        }
+
+        let uri = playable.uri();
+        if self.gapless_loaded.take().as_ref() == Some(&uri) && start_playing && position_ms == 0 {
+            debug!("{} is already playing after a gapless transition", uri);
+            return;
+        }
+        self.started = None;
+
+        if let Some(path) = local_path(&uri) {
+            self.load_local(path, start_playing, position_ms);
+            return;
+        }
+
+        match SpotifyId::from_uri(&uri) {
+            Ok(id) => {
+                info!("player loading track: {:?}", id);
+                if id.audio_type == SpotifyAudioType::NonPlayable {
+                    warn!("track is not playable");
+                    self.events.send(Event::Player(PlayerEvent::FinishedTrack));
+                } else {
+                    self.player_load(id, start_playing, position_ms);
+                }
+            }
+            Err(e) => {
+                error!("error parsing uri: {:?}", e);
+                self.events.send(Event::Player(PlayerEvent::FinishedTrack));
+            }
+        }
+    }
+
+    /// Load a track into the player, remembering it so that the load can be
+    /// retried if it fails.
+    fn player_load(&mut self, id: SpotifyId, start_playing: bool, position_ms: u32) {
+        self.local = None;
+        self.player.load(id, start_playing, position_ms);
+        self.last_load = Some((id, start_playing, position_ms));
+        self.load_retries = 0;
+        self.load_retry = Box::pin(futures::future::pending());
+        self.load_timeout = Box::pin(time::sleep(LOAD_TIMEOUT));
+    }
+
+    /// Play the local file at `path` instead of a Spotify item, through the
+    /// same audio backend.
+    fn load_local(&mut self, path: PathBuf, start_playing: bool, position_ms: u32) {
+        info!("player loading local file: {}", path.display());
+        self.load_finished();
+        self.player.stop();
+        let backend = Spotify::init_backend(self.cfg.values().backend.clone())
+            .expect("Could not find an audio playback backend");
+        let backend_device = self.cfg.values().backend_device.clone();
+        self.local = Some(LocalPlayer::new(
+            path,
+            start_playing,
+            position_ms,
+            move || backend(backend_device, AudioFormat::default()),
+            self.mixer.get_soft_volume(),
+        ));
+    }
+
+    fn local_event(&mut self, event: Option<LocalPlayerEvent>) {
+        match event {
+            Some(LocalPlayerEvent::Playing {
+                position_ms,
+                duration_ms,
+            }) => self.playing_at(position_ms, duration_ms),
+            Some(LocalPlayerEvent::Paused {
+                position_ms,
+                duration_ms,
+            }) => self.paused_at(position_ms, duration_ms),
+            Some(LocalPlayerEvent::EndOfTrack) => self.end_of_track(),
+            Some(LocalPlayerEvent::Unavailable) => {
+                self.local = None;
+                let uri = self.loaded.as_ref().map(Playable::uri).unwrap_or_default();
+                self.unavailable(uri);
+            }
+            // The file has finished playing, which was reported before.
+            None => self.local = None,
+        }
+    }
+
+    fn player_play(&self) {
+        match &self.local {
+            Some(local) => local.play(),
+            None => self.player.play(),
+        }
+    }
+
+    fn player_pause(&self) {
+        match &self.local {
+            Some(local) => local.pause(),
+            None => self.player.pause(),
+        }
+    }
+
+    fn player_seek(&self, position_ms: u32) {
+        match &self.local {
+            Some(local) => local.seek(position_ms),
+            None => self.player.seek(position_ms),
+        }
+    }
+
+    /// Stop playback. A local player is stopped by dropping it, which leaves
+    /// reporting the stop to the worker.
+    fn player_stop(&mut self) {
+        if self.local.take().is_some() {
+            self.stopped();
+        } else {
+            self.player.stop();
+        }
+    }
+
+    /// Forget about the last load, which either succeeded or was replaced.
+    fn load_finished(&mut self) {
+        self.last_load = None;
+        self.load_timeout = Box::pin(futures::future::pending());
+        self.load_retry = Box::pin(futures::future::pending());
+    }
+
+    /// Forget about the last load once `id` has loaded.
+    fn loaded_track(&mut self, id: SpotifyId) {
+        if self.last_load.map(|(last, _, _)| last) == Some(id) {
+            self.load_finished();
+        }
+    }
+
+    /// Schedule another attempt at the last load of `id` after it failed,
+    /// with an exponentially growing delay. The track's metadata is fetched
+    /// first, and tracks that aren't available to the user aren't retried.
+    /// Returns whether a retry was scheduled.
+    fn retry_load(&mut self, id: SpotifyId) -> bool {
+        let max_retries = self
+            .cfg
+            .values()
+            .load_retries
+            .unwrap_or(config::DEFAULT_LOAD_RETRIES);
+        if self.last_load.map(|(last, _, _)| last) != Some(id) || self.load_retries >= max_retries {
+            return false;
+        }
+        self.load_retries += 1;
+        let delay = Duration::from_millis(
+            self.cfg
+                .values()
+                .load_retry_delay_ms
+                .unwrap_or(config::DEFAULT_LOAD_RETRY_DELAY_MS) as u64,
         )
+        .saturating_mul(2_u32.saturating_pow(self.load_retries - 1));
+        let session = self.session.clone();
+        self.load_timeout = Box::pin(futures::future::pending());
+        self.load_retry = Box::pin(async move {
+            let transient = match AudioItem::get_audio_item(&session, id).await {
+                Ok(item) => {
+                    item.available || item.alternatives.map_or(false, |ids| !ids.is_empty())
+                }
+                // The metadata couldn't be fetched either, so the connection
+                // is to blame.
+                Err(_) => true,
+            };
+            if transient {
+                time::sleep(delay).await;
+            }
+            transient
+        });
+        true
+    }
+
+    /// Load the last track again if its failure was transient, and give up
+    /// on it otherwise.
+    fn finish_load_retry(&mut self, transient: bool) {
+        self.load_retry = Box::pin(futures::future::pending());
+        let (id, start_playing, position_ms) = match self.last_load {
+            Some(last_load) => last_load,
+            None => return,
+        };
+        if transient {
+            info!("retrying to load {:?} ({})", id, self.load_retries);
+            self.player.load(id, start_playing, position_ms);
+            self.load_timeout = Box::pin(time::sleep(LOAD_TIMEOUT));
+        } else {
+            warn!("{:?} is not available, not retrying", id);
+            self.unavailable(id.to_uri().unwrap_or_default());
+        }
+    }
+
+    /// Download `items` into the audio cache unless they're cached already,
+    /// and stop the downloads of any other items. Nothing is downloaded
+    /// without an audio cache.
+    fn prefetch(&mut self, items: Vec<Playable>) {
+        let uris: Vec<String> = items.iter().map(Playable::uri).collect();
+        self.prefetches.retain(|uri, download| {
+            let keep = uris.contains(uri) && !download.is_finished();
+            if !keep {
+                download.abort();
+            }
+            keep
+        });
+        if !self.cfg.values().audio_cache.unwrap_or(true) {
+            return;
+        }
+
+        let formats = match self.player_config.bitrate {
+            Bitrate::Bitrate96 => [FileFormat::OGG_VORBIS_96, FileFormat::OGG_VORBIS_160],
+            Bitrate::Bitrate160 => [FileFormat::OGG_VORBIS_160, FileFormat::OGG_VORBIS_96],
+            Bitrate::Bitrate320 => [FileFormat::OGG_VORBIS_320, FileFormat::OGG_VORBIS_160],
+        };
+        for uri in uris {
+            let id = match SpotifyId::from_uri(&uri) {
+                Ok(id) if !self.prefetches.contains_key(&uri) => id,
+                _ => continue,
+            };
+            let session = self.session.clone();
+            let download = tokio::spawn(async move {
+                let item = match AudioItem::get_audio_item(&session, id).await {
+                    Ok(item) => item,
+                    Err(_) => return,
+                };
+                let (format, file_id) = match formats
+                    .iter()
+                    .find_map(|format| item.files.get(format).map(|file_id| (format, *file_id)))
+                {
+                    Some(file) => file,
+                    None => return,
+                };
+                let bytes_per_second = match format {
+                    FileFormat::OGG_VORBIS_96 => 12 * 1024,
+                    FileFormat::OGG_VORBIS_160 => 20 * 1024,
+                    _ => 40 * 1024,
+                };
+                let file = match AudioFile::open(&session, file_id, bytes_per_second, true).await {
+                    Ok(file) if !file.is_cached() => file,
+                    _ => return,
+                };
+                debug!("prefetching {:?}", id);
+                // The download stops once the file is dropped, and is saved to
+                // the cache once complete.
+                let controller = file.get_stream_loader_controller();
+                controller.fetch_next(controller.len());
+                while !controller.range_to_end_available() {
+                    time::sleep(PREFETCH_POLL_INTERVAL).await;
+                }
+            });
+            self.prefetches.insert(uri, download);
+        }
+    }
+
+    /// Give up on loading the item with `uri`, letting the queue skip it.
+    fn unavailable(&mut self, uri: String) {
+        self.load_finished();
+        warn!("Track unavailable: {}", uri);
+        // Cut over to the next track without fading it in.
+        self.fade = None;
+        self.fade_in_next_load = false;
+        self.set_mixer_volume(self.volume);
+        self.set_state(PlayerEvent::Stopped);
+        self.events
+            .send(Event::Player(PlayerEvent::Unavailable(uri)));
+    }
+
+    /// Start playing the preloaded track as soon as the current one has
+    /// finished, instead of waiting for the queue to load it. Only done for
+    /// gapless playback, which is disabled while shuffling, and not when the
+    /// queue stops after the current track.
+    fn load_preloaded(&mut self) {
+        let gapless = self.cfg.values().gapless.unwrap_or(true)
+            && !self.cfg.state().shuffle
+            && !self.stop_after_track;
+        if let (true, Some(playable)) = (gapless, self.preloaded.take()) {
+            if let Ok(id) = SpotifyId::from_uri(&playable.uri()) {
+                debug!("gapless transition to {:?}", id);
+                self.reset_silence();
+                self.player_load(id, true, 0);
+                self.gapless_loaded = Some(playable.uri());
+                self.loaded = Some(playable);
+                self.started = None;
+            }
+        }
+    }
+
+    /// Replace the player by one with the current `player_config`, and reload
+    /// the current item where it was. Events of the old player are dropped
+    /// with it. librespot can't change the configuration of a running player.
+    fn recreate_player(&mut self) {
+        let position = self.position();
+        let playing = matches!(self.state, PlayerEvent::Playing(_));
+        self.cancel_crossfade();
+        self.player.stop();
+
+        let (player, player_events) = Spotify::create_player(
+            &self.cfg,
+            self.session.clone(),
+            self.mixer.as_ref(),
+            self.speed.clone(),
+            self.silence.clone(),
+            self.player_config.clone(),
+        );
+        self.player = player;
+        self.player_events = UnboundedReceiverStream::new(player_events);
+        self.preloaded = None;
+        self.gapless_loaded = None;
+
+        // A track that has finished is loaded again from its start, one that
+        // was stopped stays stopped.
+        let stopped = matches!(self.state, PlayerEvent::Stopped);
+        if let Some(playable) = self.loaded.as_ref().filter(|_| !stopped) {
+            if let Ok(id) = SpotifyId::from_uri(&playable.uri()) {
+                let position = position.unwrap_or_default();
+                info!("reloading {:?} at {:?} with new player", id, position);
+                self.player_load(id, playing, position.as_millis() as u32);
+            }
+        }
+    }
+
+    /// The given volume, or silence while muted.
+    fn muted_volume(&self, volume: u16) -> u16 {
+        if self.muted.load(Ordering::Relaxed) {
+            0
+        } else {
+            volume
+        }
+    }
+
+    /// Remember the volume for the output device, to set it again once the
+    /// player plays on it after playing on another one.
+    fn remember_volume(&self) {
+        let volume = self.volume;
+        self.cfg.with_state_mut(|mut s| {
+            s.device_volumes.insert(self.device.clone(), volume);
+        });
+    }
+
+    fn set_mixer_volume(&self, volume: u16) {
+        self.mixer.set_volume(self.muted_volume(volume));
+    }
+
+    fn toggle_mute(&mut self) {
+        let muted = !self.muted.load(Ordering::Relaxed);
+        info!("{}", if muted { "muting" } else { "unmuting" });
+        self.muted.store(muted, Ordering::Relaxed);
+        match self.fade.as_ref().and_then(Fade::volume) {
+            Some(volume) => self.set_mixer_volume(volume),
+            None if self.fade.is_none() && self.crossfade.is_none() => {
+                self.set_mixer_volume(self.volume)
+            }
+            None => (),
+        }
+        self.events.send(Event::Player(PlayerEvent::Muted(muted)));
+    }
+
+    fn fade_duration(&self) -> Duration {
+        Duration::from_millis(self.cfg.values().fade_duration_ms.unwrap_or(0) as u64)
+    }
+
+    /// Pause playback, fading out first if enabled.
+    fn pause(&mut self) {
+        self.cancel_crossfade();
+        let duration = self.fade_duration();
+        if duration.is_zero() || !matches!(self.state, PlayerEvent::Playing(_)) {
+            self.finish_fade();
+            self.player_pause();
+        } else {
+            self.fade = Some(Fade::new(self.mixer.volume(), 0, duration, FadeEnd::Pause));
+        }
+    }
+
+    /// Resume playback, fading in if enabled. Cancels a running fade out.
+    fn play(&mut self) {
+        let duration = self.fade_duration();
+        let fading_out = self.fade.as_ref().map_or(false, |fade| fade.to == 0);
+        self.fade = None;
+        if duration.is_zero() {
+            self.set_mixer_volume(self.volume);
+            self.player_play();
+        } else {
+            let from = if fading_out { self.mixer.volume() } else { 0 };
+            self.set_mixer_volume(from);
+            self.player_play();
+            self.fade = Some(Fade::new(from, self.volume, duration, FadeEnd::Restore));
+        }
+    }
+
+    /// Pause or resume depending on the state of the player. While fading
+    /// out to pause, playback counts as paused already.
+    fn toggle_play_pause(&mut self) {
+        let pausing = self
+            .fade
+            .as_ref()
+            .map_or(false, |fade| fade.end == FadeEnd::Pause);
+        match self.state {
+            PlayerEvent::Playing(_) if !pausing => self.pause(),
+            PlayerEvent::Playing(_) | PlayerEvent::Paused(_) => self.play(),
+            _ => (),
+        }
+    }
+
+    fn advance_fade(&mut self) {
+        match self.fade.as_ref().map(Fade::volume) {
+            Some(Some(volume)) => self.set_mixer_volume(volume),
+            Some(None) => self.finish_fade(),
+            None => (),
+        }
+    }
+
+    /// End a running fade immediately, leaving the mixer at the user's volume.
+    fn finish_fade(&mut self) {
+        if let Some(fade) = self.fade.take() {
+            match fade.end {
+                FadeEnd::Restore => self.set_mixer_volume(self.volume),
+                FadeEnd::Pause => {
+                    self.player_pause();
+                    self.set_mixer_volume(self.volume);
+                }
+                FadeEnd::Mute => self.set_mixer_volume(0),
+            }
+        }
+    }
+
+    fn crossfade_duration(&self) -> Duration {
+        Duration::from_millis(self.cfg.values().crossfade_ms.unwrap_or(0) as u64)
+    }
+
+    /// Schedule the fade out at the end of the current track, if crossfading
+    /// is enabled and the track is playing.
+    fn schedule_crossfade(&mut self) {
+        self.crossfade_task = Box::pin(futures::future::pending());
+        self.crossfade_waiting = false;
+        let crossfade = self.crossfade_duration();
+        // A looping track doesn't end, apart from the end of a loop without an
+        // end, which needs the track to have played to its end.
+        if crossfade.is_zero() || self.looping() || self.local.is_some() {
+            return;
+        }
+        if let (PlayerEvent::Playing(_), Some(position)) = (&self.state, self.position()) {
+            let fade_start =
+                Duration::from_millis(self.duration_ms as u64).saturating_sub(crossfade);
+            if let Some(remaining) = fade_start.checked_sub(position) {
+                self.crossfade_task = Box::pin(time::sleep(remaining.div_f32(self.speed.get())));
+            }
+        }
+    }
+
+    /// Crossfade the remainder of the current track into the preloaded one,
+    /// which starts playing on a second player. Without a preloaded track it
+    /// is requested, and the crossfade starts once it arrives. An item that
+    /// can't play on a second player fades in once the current track has
+    /// faded out.
+    fn start_crossfade(&mut self) {
+        let remaining = match (&self.state, self.position()) {
+            (PlayerEvent::Playing(_), Some(position)) => {
+                Duration::from_millis(self.duration_ms as u64)
+                    .saturating_sub(position)
+                    .div_f32(self.speed.get())
+            }
+            _ => return,
+        };
+        if remaining.is_zero() || self.sleep_after_track || self.stop_after_track {
+            return;
+        }
+
+        let next = self
+            .preloaded
+            .take()
+            .and_then(|playable| Some((SpotifyId::from_uri(&playable.uri()).ok()?, playable)));
+        match next {
+            Some((id, playable)) if id.audio_type != SpotifyAudioType::NonPlayable => {
+                debug!("crossfading into {:?} over {:?}", id, remaining);
+                self.fade = None;
+                let mixer = Spotify::create_mixer(0);
+                let (mut player, events) = Spotify::create_player(
+                    &self.cfg,
+                    self.session.clone(),
+                    mixer.as_ref(),
+                    self.speed.clone(),
+                    self.silence.clone(),
+                    self.player_config.clone(),
+                );
+                player.load(id, true, 0);
+                self.crossfade = Some(Crossfade {
+                    player,
+                    events: UnboundedReceiverStream::new(events),
+                    mixer,
+                    playable,
+                    state: PlayerEvent::Stopped,
+                    duration_ms: 0,
+                    start: Instant::now(),
+                    duration: remaining,
+                });
+            }
+            Some(_) => {
+                debug!("crossfading out over {:?}", remaining);
+                self.fade = Some(Fade::new(self.mixer.volume(), 0, remaining, FadeEnd::Mute));
+            }
+            None => {
+                debug!("crossfade waits for the next track to be preloaded");
+                self.crossfade_waiting = true;
+                self.events
+                    .send(Event::Queue(QueueEvent::PreloadTrackRequest));
+            }
+        }
+    }
+
+    fn advance_crossfade(&mut self) {
+        let progress = match &self.crossfade {
+            Some(crossfade) => crossfade.progress(),
+            None => return,
+        };
+        if progress >= 1.0 {
+            self.finish_crossfade();
+        } else if let Some(crossfade) = &self.crossfade {
+            let volume = self.volume as f64;
+            self.set_mixer_volume((volume * (1.0 - progress)) as u16);
+            crossfade
+                .mixer
+                .set_volume(self.muted_volume((volume * progress) as u16));
+        }
+    }
+
+    /// Let the crossfade player take over from the current one, which has
+    /// faded out, and advance the queue to the track it plays.
+    fn finish_crossfade(&mut self) {
+        if let Some(crossfade) = self.crossfade.take() {
+            debug!("crossfade finished, switching players");
+            self.player.stop();
+            self.player = crossfade.player;
+            self.player_events = crossfade.events;
+            self.mixer = crossfade.mixer;
+            self.set_mixer_volume(self.volume);
+            self.gapless_loaded = Some(crossfade.playable.uri());
+            self.loaded = Some(crossfade.playable);
+            self.reset_silence();
+            self.duration_ms = crossfade.duration_ms;
+
+            self.started = None;
+
+            self.set_state(PlayerEvent::FinishedTrack);
+            self.events.send(Event::Player(PlayerEvent::FinishedTrack));
+            if let PlayerEvent::Playing(_) = crossfade.state {
+                self.announce_started();
+                self.set_state(crossfade.state);
+                self.events.send(Event::Player(self.state.clone()));
+            }
+            self.schedule_crossfade();
+            self.schedule_loop();
+            self.schedule_ui_refresh();
+        }
+    }
+
+    /// Stop the crossfade player, leaving the current track playing at the
+    /// user's volume.
+    fn cancel_crossfade(&mut self) {
+        self.crossfade_waiting = false;
+        if let Some(crossfade) = self.crossfade.take() {
+            debug!("crossfade cancelled");
+            crossfade.player.stop();
+            self.set_mixer_volume(self.volume);
+        }
+    }
+
+    /// Track the playback state of the crossfade player until it takes over.
+    fn crossfade_event(&mut self, event: Option<LibrespotPlayerEvent>) {
+        match event {
+            Some(LibrespotPlayerEvent::Playing {
+                position_ms,
+                duration_ms,
+                ..
+            }) => {
+                let start = self.playback_start(Duration::from_millis(position_ms as u64));
+                if let Some(crossfade) = &mut self.crossfade {
+                    crossfade.state = PlayerEvent::Playing(start);
+                    crossfade.duration_ms = duration_ms;
+                }
+            }
+            Some(LibrespotPlayerEvent::Unavailable { track_id, .. }) => {
+                warn!("Track unavailable for crossfade: {:?}", track_id);
+                self.cancel_crossfade();
+            }
+            Some(LibrespotPlayerEvent::EndOfTrack { .. }) | None => self.finish_crossfade(),
+            _ => {}
+        }
+    }
+
+    fn playing_at(&mut self, position_ms: u32, duration_ms: u32) {
+        let position = Duration::from_millis(position_ms as u64);
+        let playback_start = self.playback_start(position);
+        self.set_state(PlayerEvent::Playing(playback_start));
+        self.duration_ms = duration_ms;
+        self.announce_started();
+        self.events
+            .send(Event::Player(PlayerEvent::Playing(playback_start)));
+        self.schedule_crossfade();
+        self.schedule_loop();
+        self.schedule_ui_refresh();
+    }
+
+    /// Tell the listeners which item started playing, once per load, so they
+    /// don't have to look it up themselves.
+    fn announce_started(&mut self) {
+        if let Some(playable) = &self.loaded {
+            let uri = playable.uri();
+            if self.started.as_ref() != Some(&uri) {
+                self.started = Some(uri.clone());
+                self.events
+                    .send(Event::Player(PlayerEvent::TrackStarted(uri)));
+            }
+        }
+    }
+
+    fn paused_at(&mut self, position_ms: u32, duration_ms: u32) {
+        let position = Duration::from_millis(position_ms as u64);
+        self.set_state(PlayerEvent::Paused(position));
+        self.duration_ms = duration_ms;
+        self.announce_started();
+        self.events
+            .send(Event::Player(PlayerEvent::Paused(position)));
+        self.schedule_crossfade();
+        self.schedule_loop();
+        self.schedule_ui_refresh();
+    }
+
+    fn stopped(&mut self) {
+        self.set_state(PlayerEvent::Stopped);
+        self.events.send(Event::Player(PlayerEvent::Stopped));
+        self.schedule_crossfade();
+        self.schedule_loop();
+        self.schedule_ui_refresh();
+    }
+
+    /// Continue after the current track has played to its end, unless an A-B
+    /// loop or the sleep timer keep the queue from advancing.
+    fn end_of_track(&mut self) {
+        if !self.sleep_after_track && self.restart_loop(true) {
+            return;
+        }
+        if self.sleep_after_track {
+            info!("sleep timer expired at end of track");
+            self.cancel_sleep_timer();
+            if self.cfg.values().sleep_timer_action == Some(SleepTimerAction::Stop) {
+                // Don't advance the queue, the player stops here.
+                self.player_stop();
+                return;
+            }
+            self.pause_next_load = true;
+        }
+        self.fade_in_next_load = self.fade.as_ref().map_or(
+            self.mixer.volume() == 0 && !self.muted.load(Ordering::Relaxed),
+            |fade| fade.end == FadeEnd::Mute,
+        );
+        if !self.pause_next_load && !self.fade_in_next_load {
+            self.load_preloaded();
+        }
+        self.set_state(PlayerEvent::FinishedTrack);
+        self.schedule_crossfade();
+        self.schedule_loop();
+        self.schedule_ui_refresh();
+        self.events.send(Event::Player(PlayerEvent::FinishedTrack));
+    }
+
+    /// Refresh the UI, along with a progress event if those are enabled.
+    fn refresh_ui(&self) {
+        match self.position() {
+            Some(position) if self.cfg.values().progress_events == Some(true) => {
+                self.events.send(Event::Player(PlayerEvent::Progress {
+                    position_ms: position.as_millis() as u64,
+                    duration_ms: self.duration_ms as u64,
+                }))
+            }
+            _ => self.events.trigger(),
+        }
+    }
+
+    /// Refresh the UI once the position reaches the next full second, as only
+    /// whole seconds are displayed. Nothing needs refreshing while paused.
+    fn schedule_ui_refresh(&mut self) {
+        self.ui_refresh = match (&self.state, self.position()) {
+            (PlayerEvent::Playing(_), Some(position)) => {
+                let until_next_second =
+                    Duration::from_secs(1) - Duration::from_nanos(position.subsec_nanos() as u64);
+                // Land just after the second has passed, not just before it.
+                let delay = until_next_second.div_f32(self.speed.get()) + UI_REFRESH_SLACK;
+                Box::pin(time::sleep(delay))
+            }
+            _ => Box::pin(futures::future::pending()),
+        };
+    }
+
+    /// Arm the sleep timer, replacing the current one. See
+    /// [WorkerCommand::SleepAfter].
+    fn arm_sleep_timer(&mut self, duration: Option<Duration>, finish_track: bool) {
+        self.sleep_finish_track = finish_track;
+        let timer = match duration {
+            Some(duration) => {
+                self.sleep_task = Box::pin(time::sleep(duration));
+                self.sleep_after_track = false;
+                SleepTimer::At(SystemTime::now() + duration)
+            }
+            None => {
+                self.sleep_task = Box::pin(futures::future::pending());
+                self.sleep_after_track = true;
+                SleepTimer::EndOfTrack
+            }
+        };
+        info!("sleep timer armed: {:?}", timer);
+        self.events
+            .send(Event::Player(PlayerEvent::SleepTimer(Some(timer))));
+    }
+
+    /// Pause or stop playback, as configured, because the sleep timer expired.
+    fn sleep(&mut self) {
+        let action = self.cfg.values().sleep_timer_action;
+        match action {
+            Some(SleepTimerAction::Stop) => {
+                info!("sleep timer expired, stopping playback");
+                self.player_stop();
+            }
+            Some(SleepTimerAction::Pause) | None => {
+                info!("sleep timer expired, pausing playback");
+                self.pause();
+            }
+        }
+        self.cancel_sleep_timer();
+    }
+
+    fn cancel_sleep_timer(&mut self) {
+        self.sleep_task = Box::pin(futures::future::pending());
+        self.sleep_after_track = false;
+        self.sleep_finish_track = false;
+        self.events
+            .send(Event::Player(PlayerEvent::SleepTimer(None)));
+    }
+
+    /// Restart the wait for inactivity, after which playback pauses if
+    /// `inactivity_pause_minutes` is set. Loading a track counts as activity,
+    /// so this happens on every command that isn't a background request.
+    fn reset_inactivity_timer(&mut self) {
+        self.inactivity_task = match self.cfg.values().inactivity_pause_minutes {
+            Some(minutes) if minutes > 0 => {
+                Box::pin(time::sleep(Duration::from_secs(minutes as u64 * 60)))
+            }
+            _ => Box::pin(futures::future::pending()),
+        };
     }
 
     pub async fn run_loop(&mut self) {
-        let mut ui_refresh = time::interval(Duration::from_millis(400));
+        let mut fade_tick = time::interval(Duration::from_millis(20));
+        fade_tick.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
+        // Notice a closed connection while there is nothing else to do. Every
+        // pass checks the session, so this only wakes an idle worker.
+        let mut session_check = time::interval(SESSION_CHECK_INTERVAL);
+        let mut silence_check = time::interval(SILENCE_CHECK_INTERVAL);
+        self.reset_inactivity_timer();
 
         loop {
             if self.session.is_invalid() {
-                info!("Librespot session invalidated, terminating worker");
-                self.events.send(Event::Player(PlayerEvent::Stopped));
+                let reason = if self.shutdown_requested {
+                    DisconnectReason::Requested
+                } else {
+                    DisconnectReason::ConnectionClosed
+                };
+                info!(
+                    "Librespot session invalidated ({:?}), terminating worker",
+                    reason
+                );
+                self.events
+                    .send(Event::Player(PlayerEvent::Disconnected(reason)));
                 break;
             }
+            session_check.reset();
 
             tokio::select! {
-                cmd = self.commands.next() => match cmd {
-                    Some(WorkerCommand::Load(playable, start_playing, position_ms)) => {
-                        match SpotifyId::from_uri(&playable.uri()) {
-                            Ok(id) => {
-                                info!("player loading track: {:?}", id);
-                                if id.audio_type == SpotifyAudioType::NonPlayable {
-                                    warn!("track is not playable");
-                                    self.events.send(Event::Player(PlayerEvent::FinishedTrack));
-                                } else {
-                                    self.player.load(id, start_playing, position_ms);
-                                }
+                cmd = self.commands.next() => {
+                    if let Some(cmd) = &cmd {
+                        self.cause = Cause("command", Some(cmd.name()));
+                        if cmd.is_interaction() {
+                            self.reset_inactivity_timer();
+                        }
+                    }
+                    match cmd {
+                        Some(WorkerCommand::Load(playable, start_playing, position_ms)) => {
+                            self.load(playable, start_playing, position_ms);
+                        }
+                        Some(WorkerCommand::Play) => {
+                            self.play();
+                        }
+                        Some(WorkerCommand::Pause) => {
+                            self.pause();
+                        }
+                        Some(WorkerCommand::TogglePlayPause) => {
+                            self.toggle_play_pause();
+                        }
+                        Some(WorkerCommand::Stop) => {
+                            self.cancel_crossfade();
+                            self.fade = None;
+                            self.fade_in_next_load = false;
+                            self.set_mixer_volume(self.volume);
+                            self.pause_next_load = false;
+                            self.gapless_loaded = None;
+                            self.load_finished();
+                            self.player_stop();
+                        }
+                        Some(WorkerCommand::Seek(pos)) => {
+                            self.seek(pos);
+                        }
+                        Some(WorkerCommand::SeekRelative(delta)) => {
+                            self.seek_relative(delta);
+                        }
+                        Some(WorkerCommand::SetVolume(volume)) => {
+                            self.volume = volume;
+                            self.remember_volume();
+                            // Changing the volume unmutes, so that it's heard
+                            if self.muted.swap(false, Ordering::Relaxed) {
+                                info!("unmuting to change the volume");
+                                self.events.send(Event::Player(PlayerEvent::Muted(false)));
                             }
-                            Err(e) => {
-                                error!("error parsing uri: {:?}", e);
-                                self.events.send(Event::Player(PlayerEvent::FinishedTrack));
+                            self.finish_fade();
+                            if self.crossfade.is_none() {
+                                self.set_mixer_volume(volume);
                             }
                         }
-                    }
-                    Some(WorkerCommand::Play) => {
-                        self.player.play();
-                    }
-                    Some(WorkerCommand::Pause) => {
-                        self.player.pause();
-                    }
-                    Some(WorkerCommand::Stop) => {
-                        self.player.stop();
-                    }
-                    Some(WorkerCommand::Seek(pos)) => {
-                        self.player.seek(pos);
-                    }
-                    Some(WorkerCommand::SetVolume(volume)) => {
-                        self.mixer.set_volume(volume);
-                    }
-                    Some(WorkerCommand::RequestToken(sender)) => {
-                        self.token_task = self.get_token(sender);
-                    }
-                    Some(WorkerCommand::Preload(playable)) => {
-                        if let Ok(id) = SpotifyId::from_uri(&playable.uri()) {
-                            debug!("Preloading {:?}", id);
-                            self.player.preload(id);
+                        Some(WorkerCommand::ToggleMute) => {
+                            self.toggle_mute();
                         }
+                        Some(WorkerCommand::SetPlaybackSpeed(speed)) => {
+                            self.set_playback_speed(speed);
+                        }
+                        Some(WorkerCommand::SleepAfter(duration, finish_track)) => {
+                            self.arm_sleep_timer(duration, finish_track);
+                        }
+                        Some(WorkerCommand::CancelSleep) => {
+                            info!("sleep timer cancelled");
+                            self.cancel_sleep_timer();
+                        }
+                        Some(WorkerCommand::StopAfterTrack(armed)) => {
+                            self.stop_after_track = armed;
+                            if armed {
+                                self.cancel_crossfade();
+                                self.preloaded = None;
+                            }
+                        }
+                        Some(WorkerCommand::SetLoopStart(start)) => {
+                            self.set_loop_start(start);
+                        }
+                        Some(WorkerCommand::SetLoopEnd(end)) => {
+                            self.set_loop_end(end);
+                        }
+                        Some(WorkerCommand::ClearLoop) => {
+                            self.clear_loop();
+                        }
+                        Some(WorkerCommand::SetBitrate(bitrate)) => {
+                            self.player_config.bitrate = bitrate;
+                            self.recreate_player();
+                        }
+                        Some(WorkerCommand::SetNormalization(mode)) => {
+                            self.player_config.normalisation = mode != NormalizationMode::Off;
+                            self.player_config.normalisation_type = Spotify::normalisation_type(mode);
+                            self.recreate_player();
+                        }
+                        Some(WorkerCommand::SetNormalizationPregain(pregain)) => {
+                            self.player_config.normalisation_pregain_db = pregain;
+                            self.recreate_player();
+                        }
+                        Some(WorkerCommand::RequestToken(sender)) => {
+                            self.token_requests.push(sender);
+                            self.token_task = self.get_token();
+                        }
+                        Some(WorkerCommand::RequestPosition(sender)) => {
+                            let _ = sender.send(self.position().unwrap_or_default());
+                        }
+                        Some(WorkerCommand::RequestRootlist(sender)) => {
+                            self.request_rootlist(sender);
+                        }
+                        Some(WorkerCommand::SubscribeTokens(listener)) => {
+                            self.token_listeners.push(listener);
+                        }
+                        Some(WorkerCommand::Preload(_)) if self.stop_after_track => {
+                            debug!("not preloading, stopping after the current track");
+                        }
+                        Some(WorkerCommand::Preload(playable)) => {
+                            if let Ok(id) = SpotifyId::from_uri(&playable.uri()) {
+                                debug!("Preloading {:?}", id);
+                                self.player.preload(id);
+                                self.preloaded = Some(playable);
+                            }
+                            // Only once, local files aren't preloaded.
+                            if std::mem::take(&mut self.crossfade_waiting)
+                                && self.preloaded.is_some()
+                            {
+                                self.start_crossfade();
+                            }
+                        }
+                        Some(WorkerCommand::Prefetch(items)) => {
+                            self.prefetch(items);
+                        }
+                        Some(WorkerCommand::Shutdown) => {
+                            self.shutdown_requested = true;
+                            self.player_stop();
+                            self.session.shutdown();
+                        }
+                        None => info!("empty stream")
                     }
-                    Some(WorkerCommand::Shutdown) => {
-                        self.player.stop();
-                        self.session.shutdown();
-                    }
-                    None => info!("empty stream")
                 },
-                event = self.player_events.next() => match event {
-                    Some(LibrespotPlayerEvent::Playing {
-                        play_request_id: _,
-                        track_id: _,
-                        position_ms,
-                        duration_ms: _,
-                    }) => {
-                        let position = Duration::from_millis(position_ms as u64);
-                        let playback_start = SystemTime::now() - position;
-                        self.events
-                            .send(Event::Player(PlayerEvent::Playing(playback_start)));
-                        self.active = true;
-                    }
-                    Some(LibrespotPlayerEvent::Paused {
-                        play_request_id: _,
-                        track_id: _,
-                        position_ms,
-                        duration_ms: _,
-                    }) => {
-                        let position = Duration::from_millis(position_ms as u64);
-                        self.events
-                            .send(Event::Player(PlayerEvent::Paused(position)));
-                        self.active = false;
+                event = self.player_events.next() => {
+                    if let Some(event) = &event {
+                        self.cause = Cause("event", Some(event_name(event)));
                     }
-                    Some(LibrespotPlayerEvent::Stopped { .. }) => {
-                        self.events.send(Event::Player(PlayerEvent::Stopped));
-                        self.active = false;
+                    match event {
+                        Some(LibrespotPlayerEvent::Loading {
+                            play_request_id: _,
+                            track_id,
+                            position_ms,
+                        }) => {
+                            let uri = track_id.to_uri().unwrap_or_default();
+                            let position = Duration::from_millis(position_ms as u64);
+                            self.set_state(PlayerEvent::Loading(uri, position));
+                            self.events.send(Event::Player(self.state.clone()));
+                            self.schedule_crossfade();
+                            self.schedule_loop();
+                            self.schedule_ui_refresh();
+                        }
+                        Some(LibrespotPlayerEvent::Playing {
+                            play_request_id: _,
+                            track_id,
+                            position_ms,
+                            duration_ms,
+                        }) => {
+                            self.loaded_track(track_id);
+                            self.playing_at(position_ms, duration_ms);
+                        }
+                        Some(LibrespotPlayerEvent::Paused {
+                            play_request_id: _,
+                            track_id,
+                            position_ms,
+                            duration_ms,
+                        }) => {
+                            self.loaded_track(track_id);
+                            self.paused_at(position_ms, duration_ms);
+                        }
+                        // Stopping the player for a local file isn't a stop of playback.
+                        Some(LibrespotPlayerEvent::Stopped { .. }) if self.local.is_none() => {
+                            self.stopped();
+                        }
+                        Some(LibrespotPlayerEvent::EndOfTrack { .. }) if self.crossfade.is_some() => {
+                            // The next track is already playing on the crossfade player.
+                            self.finish_crossfade();
+                        }
+                        Some(LibrespotPlayerEvent::EndOfTrack { .. }) => {
+                            self.end_of_track();
+                        }
+                        Some(LibrespotPlayerEvent::Unavailable { track_id, .. }) => {
+                            if self.retry_load(track_id) {
+                                warn!("could not load {:?}, retrying", track_id);
+                            } else {
+                                self.unavailable(track_id.to_uri().unwrap_or_default());
+                            }
+                        }
+                        Some(LibrespotPlayerEvent::TimeToPreloadNextTrack { .. }) => {
+                            self.events
+                                .send(Event::Queue(QueueEvent::PreloadTrackRequest));
+                        }
+                        None => {
+                            warn!("Librespot player event channel died, terminating worker");
+                            self.events.send(Event::Player(PlayerEvent::Disconnected(
+                                DisconnectReason::PlayerDied,
+                            )));
+                            break
+                        },
+                        _ => {}
                     }
-                    Some(LibrespotPlayerEvent::EndOfTrack { .. }) => {
-                        self.events.send(Event::Player(PlayerEvent::FinishedTrack));
+                },
+                _ = session_check.tick() => {},
+                _ = silence_check.tick(), if self.skip_silence => {
+                    self.cause = "skip silence".into();
+                    self.skip_silence();
+                },
+                _ = self.ui_refresh.as_mut() => {
+                    self.refresh_ui();
+                    self.schedule_ui_refresh();
+                },
+                _ = fade_tick.tick(), if self.fade.is_some() || self.crossfade.is_some() => {
+                    self.cause = "fade".into();
+                    self.advance_fade();
+                    self.advance_crossfade();
+                },
+                event = next_crossfade_event(&mut self.crossfade) => {
+                    self.cause = match &event {
+                        Some(event) => Cause("crossfade event", Some(event_name(event))),
+                        None => "crossfade player ended".into(),
+                    };
+                    self.crossfade_event(event);
+                },
+                event = next_local_event(&mut self.local) => {
+                    self.cause = "local player event".into();
+                    self.local_event(event);
+                },
+                _ = self.load_timeout.as_mut() => {
+                    self.cause = "load timeout".into();
+                    self.load_timeout = Box::pin(futures::future::pending());
+                    if let Some((id, _, _)) = self.last_load {
+                        warn!("loading {:?} timed out", id);
+                        if !self.retry_load(id) {
+                            self.player.stop();
+                            self.unavailable(id.to_uri().unwrap_or_default());
+                        }
                     }
-                    Some(LibrespotPlayerEvent::TimeToPreloadNextTrack { .. }) => {
-                        self.events
-                            .send(Event::Queue(QueueEvent::PreloadTrackRequest));
+                },
+                transient = self.load_retry.as_mut() => {
+                    self.cause = "load retry".into();
+                    self.finish_load_retry(transient);
+                },
+                _ = self.loop_task.as_mut() => {
+                    self.cause = "A-B loop".into();
+                    self.loop_task = Box::pin(futures::future::pending());
+                    self.restart_loop(false);
+                },
+                _ = self.crossfade_task.as_mut() => {
+                    self.cause = "crossfade".into();
+                    self.crossfade_task = Box::pin(futures::future::pending());
+                    self.start_crossfade();
+                },
+                _ = self.sleep_task.as_mut() => {
+                    self.cause = "sleep timer".into();
+                    if self.sleep_finish_track {
+                        info!("sleep timer expired, finishing current track");
+                        self.arm_sleep_timer(None, false);
+                    } else {
+                        self.sleep();
                     }
-                    None => {
-                        warn!("Librespot player event channel died, terminating worker");
-                        break
-                    },
-                    _ => {}
                 },
-                _ = ui_refresh.tick() => {
-                    if self.active {
-                        self.events.trigger();
+                _ = self.inactivity_task.as_mut() => {
+                    self.cause = "inactivity".into();
+                    self.inactivity_task = Box::pin(futures::future::pending());
+                    if matches!(self.state, PlayerEvent::Playing(_)) {
+                        info!("nothing was done for a while, pausing playback");
+                        self.pause();
                     }
                 },
-                _ = self.token_task.as_mut() => {
+                token = self.token_task.as_mut() => {
                     info!("token updated!");
                     self.token_task = Box::pin(futures::future::pending());
+                    self.token_received(token);
+                }
+                _ = self.token_refresh.as_mut() => {
+                    info!("token is about to expire, refreshing");
+                    self.token_refresh = Box::pin(futures::future::pending());
+                    self.token_task = self.get_token();
                 }
             }
         }
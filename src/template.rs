@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// A part of a template.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    /// A field, cut to `max_width` if given.
+    Field {
+        name: String,
+        max_width: Option<usize>,
+    },
+    /// Text that is shown only while the field isn't empty.
+    Conditional {
+        name: String,
+        text: String,
+    },
+}
+
+/// A format string with `{field}` placeholders, like the one of the
+/// statusbar. Placeholders are written as:
+///
+/// - `{name}` for the value of the field
+/// - `{name:20}` for the value cut to 20 cells, ending with `…` if cut
+/// - `{?name:text}` for `text` if the field isn't empty
+///
+/// Braces are escaped by doubling them. The fields a template may use are
+/// given when it is parsed, so mistakes are found before it is rendered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(template: &str, fields: &[&str]) -> Result<Template, String> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => return Err("unmatched \"}\"".into()),
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => return Err("unclosed \"{\"".into()),
+                        }
+                    }
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(parse_placeholder(&placeholder, fields)?);
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts })
+    }
+
+    /// Render the template with the values of its fields, which are empty
+    /// if not given. If it is wider than `width`, the widest fields are cut
+    /// until it fits, so that short ones like times stay visible.
+    pub fn render(&self, values: &HashMap<&str, String>, width: usize) -> String {
+        let value = |name: &str| values.get(name).cloned().unwrap_or_default();
+        let mut pieces: Vec<(String, bool)> = self
+            .parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => (text.clone(), false),
+                Part::Field { name, max_width } => {
+                    let value = value(name);
+                    match max_width {
+                        Some(max_width) => (cut(&value, *max_width), true),
+                        None => (value, true),
+                    }
+                }
+                Part::Conditional { name, text } if !value(name).is_empty() => {
+                    (text.clone(), false)
+                }
+                Part::Conditional { .. } => (String::new(), false),
+            })
+            .collect();
+
+        loop {
+            let total: usize = pieces.iter().map(|(piece, _)| piece.width()).sum();
+            let overflow = match total.checked_sub(width) {
+                Some(overflow) if overflow > 0 => overflow,
+                _ => break,
+            };
+            let widest = pieces
+                .iter_mut()
+                .filter(|(piece, cuttable)| *cuttable && piece.width() > 1)
+                .max_by_key(|(piece, _)| piece.width());
+            match widest {
+                Some((piece, _)) => {
+                    let piece_width = piece.width();
+                    *piece = cut(piece, piece_width.saturating_sub(overflow).max(1));
+                }
+                None => break,
+            }
+        }
+        pieces.into_iter().map(|(piece, _)| piece).collect()
+    }
+}
+
+fn parse_placeholder(placeholder: &str, fields: &[&str]) -> Result<Part, String> {
+    let known = |name: &str| {
+        if fields.contains(&name) {
+            Ok(name.to_string())
+        } else {
+            Err(format!("unknown field \"{name}\""))
+        }
+    };
+    if let Some(conditional) = placeholder.strip_prefix('?') {
+        let (name, text) = conditional
+            .split_once(':')
+            .ok_or_else(|| format!("missing text of \"{{{placeholder}}}\""))?;
+        return Ok(Part::Conditional {
+            name: known(name)?,
+            text: text.to_string(),
+        });
+    }
+    match placeholder.split_once(':') {
+        Some((name, max_width)) => Ok(Part::Field {
+            name: known(name)?,
+            max_width: Some(
+                max_width
+                    .parse()
+                    .map_err(|_| format!("invalid width of \"{{{placeholder}}}\""))?,
+            ),
+        }),
+        None => Ok(Part::Field {
+            name: known(placeholder)?,
+            max_width: None,
+        }),
+    }
+}
+
+/// Cut `text` to `width`, ending with `…` if it had to be cut.
+fn cut(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut cut = String::new();
+    let mut cut_width = 0;
+    for c in text.chars() {
+        let c_width = c.width().unwrap_or(0);
+        if cut_width + c_width + 1 > width {
+            break;
+        }
+        cut.push(c);
+        cut_width += c_width;
+    }
+    if width > 0 {
+        cut.push('…');
+    }
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELDS: &[&str] = &["title", "artists", "position", "saved"];
+
+    fn values() -> HashMap<&'static str, String> {
+        HashMap::from([
+            ("title", "Bohemian Rhapsody".to_string()),
+            ("artists", "Queen".to_string()),
+            ("position", "1:23".to_string()),
+        ])
+    }
+
+    #[test]
+    fn renders_fields_and_conditionals() {
+        let template =
+            Template::parse("{title:8} by {artists}{?saved: ♥} {{{position}}}", FIELDS).unwrap();
+        assert_eq!(template.render(&values(), 80), "Bohemia… by Queen {1:23}");
+
+        let mut saved = values();
+        saved.insert("saved", "✓".to_string());
+        assert_eq!(template.render(&saved, 80), "Bohemia… by Queen ♥ {1:23}");
+    }
+
+    #[test]
+    fn widest_fields_are_cut_to_fit() {
+        let template = Template::parse("{title} - {artists} {position}", FIELDS).unwrap();
+        assert_eq!(template.render(&values(), 20), "Bohemi… - Queen 1:23");
+        assert_eq!(template.render(&values(), 20).width(), 20);
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        assert!(Template::parse("{title", FIELDS).is_err());
+        assert!(Template::parse("title}", FIELDS).is_err());
+        assert!(Template::parse("{album}", FIELDS).is_err());
+        assert!(Template::parse("{title:wide}", FIELDS).is_err());
+        assert!(Template::parse("{?saved}", FIELDS).is_err());
+    }
+}
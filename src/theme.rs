@@ -67,6 +67,10 @@ pub fn load(theme_cfg: &Option<ConfigTheme>) -> Theme {
         "search_match",
         load_color!(theme_cfg, search_match, Light(Red)),
     );
+    palette.set_color(
+        "marked_bg",
+        load_color!(theme_cfg, marked_bg, Dark(Magenta)),
+    );
 
     Theme {
         shadow: false,
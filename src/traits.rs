@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use chrono::{DateTime, Utc};
 use cursive::view::{View, ViewWrapper};
 use cursive::views::NamedView;
 use cursive::Cursive;
@@ -33,12 +34,41 @@ pub trait ListItem: Sync + Send + 'static {
     ) -> Option<Box<dyn ViewExt>> {
         None
     }
+
+    /// The artist IDs and track IDs to base recommendations for the item on,
+    /// at most 5 in total.
+    fn recommendation_seeds(&mut self, _queue: Arc<Queue>) -> Option<(Vec<String>, Vec<String>)> {
+        None
+    }
     fn share_url(&self) -> Option<String>;
 
     fn album(&self, _queue: Arc<Queue>) -> Option<Album> {
         None
     }
 
+    /// Append all the tracks of the item's album to the queue. Returns the
+    /// album, if the item has one.
+    fn queue_album(&mut self, queue: Arc<Queue>) -> Option<Album> {
+        let mut album = self.album(queue.clone())?;
+        album.queue(queue);
+        Some(album)
+    }
+
+    /// The ID of the album the item belongs to, without looking it up.
+    fn album_id(&self) -> Option<String> {
+        None
+    }
+
+    /// The disc of its album the item is on.
+    fn disc_number(&self) -> Option<i32> {
+        None
+    }
+
+    /// How popular the item is on Spotify from 0 to 100, if that is known.
+    fn popularity(&self) -> Option<u32> {
+        None
+    }
+
     fn artists(&self) -> Option<Vec<Artist>> {
         None
     }
@@ -47,6 +77,21 @@ pub trait ListItem: Sync + Send + 'static {
         None
     }
 
+    /// The item itself, if it is an artist.
+    fn artist(&self) -> Option<Artist> {
+        None
+    }
+
+    /// When the item was added to the list it is shown in.
+    fn added_at(&self) -> Option<DateTime<Utc>> {
+        None
+    }
+
+    /// The ID of the user who added the item to the playlist it is in.
+    fn added_by(&self) -> Option<String> {
+        None
+    }
+
     #[allow(unused_variables)]
     #[inline]
     fn is_saved(&self, library: Arc<Library>) -> Option<bool> {
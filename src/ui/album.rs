@@ -1,7 +1,11 @@
+use std::collections::HashSet;
 use std::sync::{Arc, RwLock};
 
-use cursive::view::ViewWrapper;
-use cursive::Cursive;
+use cursive::event::{Event, EventResult};
+use cursive::theme::ColorStyle;
+use cursive::traits::View;
+use cursive::{Cursive, Printer, Vec2};
+use unicode_width::UnicodeWidthStr;
 
 use crate::command::Command;
 use crate::commands::CommandResult;
@@ -13,9 +17,19 @@ use crate::traits::ViewExt;
 use crate::ui::listview::ListView;
 use crate::ui::tabview::TabView;
 
+/// The lines of the header about the album, along with a blank one.
+const HEADER_HEIGHT: usize = 4;
+/// The header leaves at least this many rows to the tabs, or it is hidden.
+const MIN_TAB_HEIGHT: usize = 5;
+/// The cover placeholder is only drawn at this width or wider.
+const COVER_MIN_WIDTH: usize = 40;
+const COVER_WIDTH: usize = 7;
+
 pub struct AlbumView {
     album: Album,
     tabs: TabView,
+    /// The height of the header in the last layout, zero if it's hidden.
+    header_height: usize,
 }
 
 impl AlbumView {
@@ -23,6 +37,10 @@ impl AlbumView {
         let mut album = album.clone();
 
         album.load_all_tracks(queue.get_spotify());
+        let popularity = library.cfg.values().album_popularity.unwrap_or(false);
+        if popularity {
+            album.load_popularity(queue.get_spotify());
+        }
 
         let tracks = if let Some(t) = album.tracks.as_ref() {
             t.clone()
@@ -37,27 +55,100 @@ impl AlbumView {
             .map(|(id, name)| Artist::new(id.clone(), name.clone()))
             .collect();
 
-        let tabs = TabView::new()
-            .tab(
-                "tracks",
-                ListView::new(
-                    Arc::new(RwLock::new(tracks)),
-                    queue.clone(),
-                    library.clone(),
-                )
-                .with_title("Tracks"),
-            )
-            .tab(
-                "artists",
-                ListView::new(Arc::new(RwLock::new(artists)), queue, library).with_title("Artists"),
-            );
-
-        Self { album, tabs }
+        let mut track_list = ListView::new(
+            Arc::new(RwLock::new(tracks)),
+            queue.clone(),
+            library.clone(),
+        )
+        .with_title("Tracks")
+        .with_disc_headings()
+        .with_columns_view("album");
+        if popularity {
+            track_list = track_list.with_popularity();
+        }
+
+        let tabs = TabView::new().tab("tracks", track_list).tab(
+            "artists",
+            ListView::new(Arc::new(RwLock::new(artists)), queue, library).with_title("Artists"),
+        );
+
+        Self {
+            album,
+            tabs,
+            header_height: 0,
+        }
+    }
+
+    /// The lines of the header, which are the artists, the release and the
+    /// extent of the album.
+    fn header_lines(&self) -> [String; 3] {
+        let mut release = format!(
+            "Released {}",
+            self.album.release_date.as_ref().unwrap_or(&self.album.year)
+        );
+        if let Some(label) = &self.album.label {
+            release.push_str(&format!(" by {label}"));
+        }
+
+        let tracks = self.album.tracks.as_deref().unwrap_or_default();
+        let discs: HashSet<i32> = tracks.iter().map(|t| t.disc_number).collect();
+        let mut extent = format!("{} tracks", tracks.len());
+        if discs.len() > 1 {
+            extent.push_str(&format!(" on {} discs", discs.len()));
+        }
+        let duration_secs: u64 = tracks.iter().map(|t| t.duration as u64 / 1000).sum();
+        let duration = std::time::Duration::from_secs(duration_secs);
+        extent.push_str(&format!(", {}", crate::utils::format_duration(&duration)));
+
+        [self.album.artists.join(", "), release, extent]
     }
 }
 
-impl ViewWrapper for AlbumView {
-    wrap_impl!(self.tabs: TabView);
+impl View for AlbumView {
+    fn draw(&self, printer: &Printer<'_, '_>) {
+        if self.header_height > 0 {
+            let text_offset = if printer.size.x >= COVER_MIN_WIDTH {
+                printer.with_color(ColorStyle::secondary(), |printer| {
+                    printer.print_box((0, 0), (COVER_WIDTH, 3), false);
+                    printer.print((COVER_WIDTH / 2, 1), "♫");
+                });
+                COVER_WIDTH + 2
+            } else {
+                0
+            };
+
+            let width = printer.size.x.saturating_sub(text_offset);
+            for (y, line) in self.header_lines().iter().enumerate() {
+                let printer = printer.offset((text_offset, y)).cropped((width, 1));
+                printer.print((0, 0), line);
+                // Cut off like the rows of lists
+                if line.width() > width {
+                    printer.print((width.saturating_sub(2), 0), "..");
+                }
+            }
+        }
+
+        let printer = printer
+            .offset((0, self.header_height))
+            .cropped((printer.size.x, printer.size.y - self.header_height));
+        self.tabs.draw(&printer);
+    }
+
+    fn layout(&mut self, size: Vec2) {
+        // Short terminals are left to the tracks
+        self.header_height = if size.y >= HEADER_HEIGHT + MIN_TAB_HEIGHT {
+            HEADER_HEIGHT
+        } else {
+            0
+        };
+        self.tabs
+            .layout(Vec2::new(size.x, size.y - self.header_height));
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        self.tabs
+            .on_event(event.relativized((0, self.header_height)))
+    }
 }
 
 impl ViewExt for AlbumView {
@@ -1,14 +1,16 @@
+use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 use std::thread;
 
-use cursive::view::ViewWrapper;
+use cursive::event::{Event, EventResult};
+use cursive::view::{View, ViewWrapper};
 use cursive::Cursive;
 use rspotify::model::AlbumType;
 
-use crate::command::Command;
+use crate::command::{Command, GotoMode};
 use crate::commands::CommandResult;
 use crate::library::Library;
-use crate::model::album::Album;
+use crate::model::album::{discography_order, Album};
 use crate::model::artist::Artist;
 use crate::model::track::Track;
 use crate::queue::Queue;
@@ -25,11 +27,6 @@ impl ArtistView {
     pub fn new(queue: Arc<Queue>, library: Arc<Library>, artist: &Artist) -> Self {
         let spotify = queue.get_spotify();
 
-        let albums_view =
-            Self::albums_view(artist, AlbumType::Album, queue.clone(), library.clone());
-        let singles_view =
-            Self::albums_view(artist, AlbumType::Single, queue.clone(), library.clone());
-
         let top_tracks: Arc<RwLock<Vec<Track>>> = Arc::new(RwLock::new(Vec::new()));
         {
             let top_tracks = top_tracks.clone();
@@ -47,18 +44,21 @@ impl ArtistView {
         }
 
         let related: Arc<RwLock<Vec<Artist>>> = Arc::new(RwLock::new(Vec::new()));
-        {
-            let related = related.clone();
-            let id = artist.id.clone();
-            let library = library.clone();
-            thread::spawn(move || {
-                if let Some(id) = id {
+        if let Some(id) = artist.id.clone() {
+            let cached = RELATED_ARTISTS.read().unwrap().get(&id).cloned();
+            if let Some(artists) = cached {
+                *related.write().unwrap() = artists;
+            } else {
+                let related = related.clone();
+                let library = library.clone();
+                thread::spawn(move || {
                     if let Some(artists) = spotify.api.artist_related_artists(&id) {
+                        RELATED_ARTISTS.write().unwrap().insert(id, artists.clone());
                         related.write().unwrap().extend(artists);
                         library.trigger_redraw();
                     }
-                }
-            });
+                });
+            }
         }
 
         let mut tabs = TabView::new();
@@ -82,8 +82,10 @@ impl ArtistView {
             ListView::new(top_tracks, queue.clone(), library.clone()).with_title("Top 10"),
         );
 
-        tabs.add_tab("albums", albums_view.with_title("Albums"));
-        tabs.add_tab("singles", singles_view.with_title("Singles"));
+        tabs.add_tab(
+            "discography",
+            DiscographyView::new(artist.id.clone(), queue.clone(), library.clone()),
+        );
 
         tabs.add_tab(
             "related",
@@ -96,22 +98,11 @@ impl ArtistView {
         }
     }
 
-    fn albums_view(
-        artist: &Artist,
-        album_type: AlbumType,
-        queue: Arc<Queue>,
-        library: Arc<Library>,
-    ) -> ListView<Album> {
-        if let Some(artist_id) = &artist.id {
-            let spotify = queue.get_spotify();
-            let albums_page = spotify.api.artist_albums(artist_id, Some(album_type));
-            let view = ListView::new(albums_page.items.clone(), queue, library);
-            albums_page.apply_pagination(view.get_pagination());
-
-            view
-        } else {
-            ListView::new(Arc::new(RwLock::new(Vec::new())), queue, library)
-        }
+    /// The view of `artist` with its related artists shown.
+    pub fn related(queue: Arc<Queue>, library: Arc<Library>, artist: &Artist) -> Self {
+        let mut view = Self::new(queue, library, artist);
+        view.tabs.move_focus_to_id("related");
+        view
     }
 }
 
@@ -125,6 +116,143 @@ impl ViewExt for ArtistView {
     }
 
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        // Within the related artists, it moves on to those of the selected one
+        if let Command::Goto(GotoMode::Related) = cmd {
+            if self.tabs.selected_id() != Some("related") {
+                self.tabs.move_focus_to_id("related");
+                return Ok(CommandResult::Consumed(None));
+            }
+        }
         self.tabs.on_command(s, cmd)
     }
 }
+
+lazy_static! {
+    /// The related artists of the artists shown this session, by artist ID.
+    static ref RELATED_ARTISTS: RwLock<HashMap<String, Vec<Artist>>> = RwLock::new(HashMap::new());
+}
+
+/// The kinds of releases an artist's discography is filtered by.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum AlbumGroup {
+    Albums,
+    Singles,
+    Compilations,
+    AppearsOn,
+    All,
+}
+
+impl AlbumGroup {
+    fn album_type(self) -> Option<AlbumType> {
+        match self {
+            AlbumGroup::Albums => Some(AlbumType::Album),
+            AlbumGroup::Singles => Some(AlbumType::Single),
+            AlbumGroup::Compilations => Some(AlbumType::Compilation),
+            AlbumGroup::AppearsOn => Some(AlbumType::AppearsOn),
+            AlbumGroup::All => None,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            AlbumGroup::Albums => "Albums",
+            AlbumGroup::Singles => "Singles & EPs",
+            AlbumGroup::Compilations => "Compilations",
+            AlbumGroup::AppearsOn => "Appears On",
+            AlbumGroup::All => "All Releases",
+        }
+    }
+
+    fn next(self) -> AlbumGroup {
+        match self {
+            AlbumGroup::Albums => AlbumGroup::Singles,
+            AlbumGroup::Singles => AlbumGroup::Compilations,
+            AlbumGroup::Compilations => AlbumGroup::AppearsOn,
+            AlbumGroup::AppearsOn => AlbumGroup::All,
+            AlbumGroup::All => AlbumGroup::Albums,
+        }
+    }
+}
+
+lazy_static! {
+    /// The group that discographies show, which is kept for the session.
+    static ref ALBUM_GROUP: RwLock<AlbumGroup> = RwLock::new(AlbumGroup::Albums);
+}
+
+/// The releases of an artist of one [AlbumGroup], newest first and without
+/// re-releases, switched to the next group with `g`. Each group is fetched
+/// in full once it's shown.
+struct DiscographyView {
+    artist_id: Option<String>,
+    group: AlbumGroup,
+    groups: HashMap<AlbumGroup, Arc<RwLock<Vec<Album>>>>,
+    list: ListView<Album>,
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+}
+
+impl DiscographyView {
+    fn new(artist_id: Option<String>, queue: Arc<Queue>, library: Arc<Library>) -> Self {
+        let list = ListView::new(Arc::default(), queue.clone(), library.clone());
+        let mut view = Self {
+            artist_id,
+            group: *ALBUM_GROUP.read().unwrap(),
+            groups: HashMap::new(),
+            list,
+            queue,
+            library,
+        };
+        view.show_group();
+        view
+    }
+
+    fn show_group(&mut self) {
+        let albums = self
+            .groups
+            .entry(self.group)
+            .or_insert_with(|| {
+                let albums = Arc::new(RwLock::new(Vec::new()));
+                if let Some(artist_id) = self.artist_id.clone() {
+                    let albums = albums.clone();
+                    let spotify = self.queue.get_spotify();
+                    let library = self.library.clone();
+                    let album_type = self.group.album_type();
+                    thread::spawn(move || {
+                        let result = spotify.api.artist_albums(&artist_id, album_type);
+                        while result.next().is_some() {}
+                        let mut fetched = result.items.read().unwrap().clone();
+                        discography_order(&mut fetched);
+                        *albums.write().unwrap() = fetched;
+                        library.trigger_redraw();
+                    });
+                }
+                albums
+            })
+            .clone();
+        self.list = ListView::new(albums, self.queue.clone(), self.library.clone());
+    }
+}
+
+impl ViewWrapper for DiscographyView {
+    wrap_impl!(self.list: ListView<Album>);
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        if event == Event::Char('g') {
+            self.group = self.group.next();
+            *ALBUM_GROUP.write().unwrap() = self.group;
+            self.show_group();
+            return EventResult::consumed();
+        }
+        self.list.on_event(event)
+    }
+}
+
+impl ViewExt for DiscographyView {
+    fn title(&self) -> String {
+        self.group.title().to_string()
+    }
+
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        self.list.on_command(s, cmd)
+    }
+}
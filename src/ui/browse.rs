@@ -12,15 +12,22 @@ use crate::traits::ViewExt;
 
 use crate::ui::listview::ListView;
 
+/// Spotify's categories of playlists, after the playlists it features.
 pub struct BrowseView {
     list: ListView<Category>,
 }
 
 impl BrowseView {
     pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> Self {
-        let categories = queue.get_spotify().api.categories();
+        let api = queue.get_spotify().api;
+        let categories = api.categories(library.country());
+        let featured = Category::featured(&api, library.country());
+        let total = categories.total as usize;
+        categories.items.write().unwrap().insert(0, featured);
         let list = ListView::new(categories.items.clone(), queue, library);
         categories.apply_pagination(list.get_pagination());
+        // The featured playlists are listed along with the categories
+        list.get_pagination().set_max_content(total + 1);
 
         Self { list }
     }
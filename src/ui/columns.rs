@@ -0,0 +1,194 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::config::{ColumnKind, ColumnWidth, ConfigValues, TrackColumn};
+
+/// Auto columns narrower than this are dropped instead.
+const MIN_AUTO_WIDTH: usize = 8;
+
+/// A column as it is laid out, at `offset` of the row.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PlacedColumn {
+    pub kind: ColumnKind,
+    pub offset: usize,
+    pub width: usize,
+}
+
+/// The columns that track lists of `view` are shown in, which are those of
+/// `track_columns` unless overridden for the view.
+pub fn configured(cfg: &ConfigValues, view: Option<&str>) -> Option<Vec<TrackColumn>> {
+    cfg.track_columns_by_view
+        .as_ref()
+        .zip(view)
+        .and_then(|(views, view)| views.get(view))
+        .or(cfg.track_columns.as_ref())
+        .cloned()
+}
+
+/// Whether the text of a column is aligned to its right.
+pub fn right_aligned(kind: ColumnKind) -> bool {
+    matches!(kind, ColumnKind::Number | ColumnKind::Duration)
+}
+
+fn default_width(kind: ColumnKind) -> ColumnWidth {
+    match kind {
+        ColumnKind::Number => ColumnWidth::Cells(4),
+        ColumnKind::Title | ColumnKind::Artist | ColumnKind::Album => ColumnWidth::Auto,
+        ColumnKind::AddedAt => ColumnWidth::Cells(16),
+        ColumnKind::Duration => ColumnWidth::Cells(7),
+        ColumnKind::Popularity => ColumnWidth::Cells(5),
+        ColumnKind::Saved => ColumnWidth::Cells(1),
+    }
+}
+
+/// The columns of lower priority are dropped first when not all fit.
+fn priority(kind: ColumnKind) -> u8 {
+    match kind {
+        ColumnKind::Popularity => 0,
+        ColumnKind::AddedAt => 1,
+        ColumnKind::Number => 2,
+        ColumnKind::Saved => 3,
+        ColumnKind::Album => 4,
+        ColumnKind::Duration => 5,
+        ColumnKind::Artist => 6,
+        ColumnKind::Title => 7,
+    }
+}
+
+/// Lay out `columns` in a row of `width`, a cell apart. Those that don't fit
+/// are dropped by their priority, and among the same priority from the
+/// right. The auto columns share what is left equally.
+pub fn layout(columns: &[TrackColumn], width: usize) -> Vec<PlacedColumn> {
+    let mut shown: Vec<&TrackColumn> = columns.iter().collect();
+    loop {
+        if let Some(placed) = place(&shown, width) {
+            return placed;
+        }
+        let dropped = shown
+            .iter()
+            .enumerate()
+            .min_by_key(|(position, column)| (priority(column.kind), std::cmp::Reverse(*position)))
+            .map(|(position, _)| position);
+        match dropped {
+            Some(position) => shown.remove(position),
+            None => return Vec::new(),
+        };
+    }
+}
+
+fn place(columns: &[&TrackColumn], width: usize) -> Option<Vec<PlacedColumn>> {
+    if let [column] = columns {
+        return Some(vec![PlacedColumn {
+            kind: column.kind,
+            offset: 0,
+            width,
+        }]);
+    }
+
+    let widths: Vec<Option<usize>> = columns
+        .iter()
+        .map(
+            |column| match column.width.unwrap_or_else(|| default_width(column.kind)) {
+                ColumnWidth::Auto => None,
+                ColumnWidth::Cells(cells) => Some(cells),
+                ColumnWidth::Percent(percent) => Some(width * percent / 100),
+            },
+        )
+        .collect();
+    let gaps = columns.len().saturating_sub(1);
+    let fixed: usize = widths.iter().flatten().sum();
+    let rest = width.checked_sub(fixed + gaps)?;
+    let autos = widths.iter().filter(|width| width.is_none()).count();
+    let share = rest.checked_div(autos).unwrap_or(0);
+    if autos > 0 && share < MIN_AUTO_WIDTH {
+        return None;
+    }
+
+    // The last auto column also takes what can't be shared equally.
+    let last_auto = widths.iter().rposition(Option::is_none);
+    let mut offset = 0;
+    let placed = columns
+        .iter()
+        .zip(widths)
+        .enumerate()
+        .map(|(position, (column, width))| {
+            let width = match width {
+                Some(width) => width,
+                None if Some(position) == last_auto => share + rest % autos,
+                None => share,
+            };
+            let placed = PlacedColumn {
+                kind: column.kind,
+                offset,
+                width,
+            };
+            offset += width + 1;
+            placed
+        })
+        .collect();
+    Some(placed)
+}
+
+/// Cut `text` to `width`, ending with `..` if it had to be cut.
+pub fn fit(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let max = width.saturating_sub(2);
+    let mut cut = String::new();
+    let mut cut_width = 0;
+    for c in text.chars() {
+        let c_width = c.width().unwrap_or(0);
+        if cut_width + c_width > max {
+            break;
+        }
+        cut.push(c);
+        cut_width += c_width;
+    }
+    cut.push_str(&".."[..width.min(2)]);
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(names: &[&str]) -> Vec<TrackColumn> {
+        names
+            .iter()
+            .map(|name| TrackColumn::try_from(name.to_string()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn auto_columns_share_the_rest() {
+        let placed = layout(&columns(&["number", "title", "artist:25%", "duration"]), 80);
+        let widths: Vec<(usize, usize)> = placed.iter().map(|p| (p.offset, p.width)).collect();
+        assert_eq!(widths, vec![(0, 4), (5, 46), (52, 20), (73, 7)]);
+    }
+
+    #[test]
+    fn columns_that_dont_fit_are_dropped_by_priority() {
+        let placed = layout(
+            &columns(&[
+                "number",
+                "title",
+                "artist",
+                "album",
+                "duration",
+                "popularity",
+            ]),
+            36,
+        );
+        let kinds: Vec<ColumnKind> = placed.iter().map(|p| p.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ColumnKind::Title,
+                ColumnKind::Artist,
+                ColumnKind::Album,
+                ColumnKind::Duration
+            ]
+        );
+        assert_eq!(fit("Bohemian Rhapsody", 10), "Bohemian..");
+    }
+}
@@ -1,7 +1,12 @@
 use std::sync::Arc;
 
+use chrono::Local;
+use log::error;
+
 use cursive::view::{Margins, ViewWrapper};
-use cursive::views::{Dialog, NamedView, ScrollView, SelectView};
+use cursive::views::{
+    Dialog, DummyView, EditView, LinearLayout, NamedView, ScrollView, SelectView, TextView,
+};
 use cursive::Cursive;
 
 use crate::commands::CommandResult;
@@ -17,7 +22,9 @@ use crate::sharing::write_share;
 use crate::spotify::PlayerEvent;
 use crate::traits::{ListItem, ViewExt};
 use crate::ui::layout::Layout;
+use crate::ui::listview::change_in_batches;
 use crate::ui::modal::Modal;
+use crate::utils::fuzzy_score;
 use crate::{command::Command, spotify::Spotify};
 use cursive::traits::{Finder, Nameable};
 
@@ -53,55 +60,86 @@ enum ContextMenuAction {
 }
 
 impl ContextMenu {
+    /// A dialog to add `tracks` to one of the user's playlists, which are
+    /// filtered by fuzzily matching their names with what is typed.
     pub fn add_track_dialog(
         library: Arc<Library>,
         spotify: Spotify,
-        track: Track,
+        tracks: Vec<Track>,
     ) -> NamedView<AddToPlaylistMenu> {
+        let title = match tracks.len() {
+            1 => "Add track to playlist".to_string(),
+            count => format!("Add {count} tracks to playlist"),
+        };
+        let playlists = library.writable_playlists();
         let mut list_select: SelectView<Playlist> = SelectView::new();
-        let current_user_id = library.user_id.as_ref().unwrap();
-
-        for list in library.playlists().iter() {
-            if current_user_id == &list.owner_id || list.collaborative {
-                list_select.add_item(list.name.clone(), list.clone());
-            }
-        }
+        list_select.add_all(
+            playlists
+                .iter()
+                .map(|list| (list.name.clone(), list.clone())),
+        );
 
         list_select.set_autojump(true);
-        list_select.set_on_submit(move |s, selected| {
-            let track = track.clone();
-            let mut playlist = selected.clone();
-            let spotify = spotify.clone();
+        {
             let library = library.clone();
+            let spotify = spotify.clone();
+            let tracks = tracks.clone();
+            list_select.set_on_submit(move |s, selected: &Playlist| {
+                Self::add_tracks(
+                    s,
+                    library.clone(),
+                    spotify.clone(),
+                    selected.clone(),
+                    tracks.clone(),
+                );
+            });
+        }
 
-            if playlist.has_track(track.id.as_ref().unwrap_or(&String::new())) {
-                let mut already_added_dialog = Self::track_already_added();
-
-                already_added_dialog.add_button("Add anyway", move |c| {
-                    let mut playlist = playlist.clone();
-                    let spotify = spotify.clone();
-                    let library = library.clone();
-
-                    playlist.append_tracks(&[Playable::Track(track.clone())], spotify, library);
-                    c.pop_layer();
-
-                    // Close add_track_dialog too
-                    c.pop_layer();
+        let filter = EditView::new()
+            .on_edit(move |s, query, _| {
+                s.call_on_name("addplaylist_select", |select: &mut SelectView<Playlist>| {
+                    let mut matches: Vec<(usize, &Playlist)> = playlists
+                        .iter()
+                        .filter_map(|list| {
+                            fuzzy_score(query, &list.name).map(|score| (score, list))
+                        })
+                        .collect();
+                    // Stable, so that equal matches stay most recent first
+                    matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+                    select.clear();
+                    select.add_all(
+                        matches
+                            .into_iter()
+                            .map(|(_, list)| (list.name.clone(), list.clone())),
+                    );
                 });
-
-                let modal = Modal::new(already_added_dialog);
-                s.add_layer(modal);
-            } else {
-                playlist.append_tracks(&[Playable::Track(track)], spotify, library);
-                s.pop_layer();
-            }
-        });
+            })
+            .on_submit(move |s, _| {
+                let selected = s
+                    .call_on_name("addplaylist_select", |select: &mut SelectView<Playlist>| {
+                        select.selection()
+                    })
+                    .flatten();
+                if let Some(selected) = selected {
+                    Self::add_tracks(
+                        s,
+                        library.clone(),
+                        spotify.clone(),
+                        (*selected).clone(),
+                        tracks.clone(),
+                    );
+                }
+            });
 
         let dialog = Dialog::new()
-            .title("Add track to playlist")
+            .title(title)
             .dismiss_button("Close")
             .padding(Margins::lrtb(1, 1, 1, 0))
-            .content(ScrollView::new(list_select.with_name("addplaylist_select")));
+            .content(
+                LinearLayout::vertical()
+                    .child(filter)
+                    .child(ScrollView::new(list_select.with_name("addplaylist_select"))),
+            );
 
         AddToPlaylistMenu {
             dialog: Modal::new_ext(dialog),
@@ -109,6 +147,79 @@ impl ContextMenu {
         .with_name("addtrackmenu")
     }
 
+    /// Close the dialog and add `tracks` to `playlist` in one go, asking
+    /// first whether to add those that are already in it again. The tracks
+    /// of the playlist are loaded in the background to find those.
+    fn add_tracks(
+        s: &mut Cursive,
+        library: Arc<Library>,
+        spotify: Spotify,
+        mut playlist: Playlist,
+        tracks: Vec<Track>,
+    ) {
+        s.pop_layer();
+        let sink = s.cb_sink().clone();
+        std::thread::spawn(move || {
+            playlist.load_tracks(spotify.clone());
+            let name = playlist.name.clone();
+            let sent = sink.send(Box::new(move |s: &mut Cursive| {
+                Self::add_loaded_tracks(s, library, spotify, playlist, tracks)
+            }));
+            if sent.is_err() {
+                error!("could not add tracks to {}", name);
+            }
+        });
+    }
+
+    fn add_loaded_tracks(
+        s: &mut Cursive,
+        library: Arc<Library>,
+        spotify: Spotify,
+        playlist: Playlist,
+        tracks: Vec<Track>,
+    ) {
+        let (present, new): (Vec<Track>, Vec<Track>) = tracks
+            .iter()
+            .cloned()
+            .partition(|track| playlist.has_track(track.id.as_deref().unwrap_or_default()));
+
+        let append = move |s: &mut Cursive, mut playlist: Playlist, tracks: Vec<Track>| {
+            if tracks.is_empty() {
+                return;
+            }
+            library.playlist_used(&playlist.id);
+            let action = format!("Adding to {}", playlist.name);
+            let spotify = spotify.clone();
+            let library = library.clone();
+            change_in_batches(s, action, tracks, move |batch| {
+                let playables: Vec<Playable> = batch.iter().cloned().map(Playable::Track).collect();
+                playlist.append_tracks(&playables, spotify.clone(), library.clone())
+            });
+        };
+
+        if present.is_empty() {
+            append(s, playlist, tracks);
+            return;
+        }
+
+        let append = Arc::new(append);
+        let mut already_added_dialog = Self::track_already_added(present.len(), tracks.len());
+        {
+            let append = append.clone();
+            let playlist = playlist.clone();
+            already_added_dialog.add_button("Add anyway", move |c| {
+                c.pop_layer();
+                append(c, playlist.clone(), tracks.clone());
+            });
+        }
+        already_added_dialog.add_button("Skip", move |c| {
+            c.pop_layer();
+            append(c, playlist.clone(), new.clone());
+        });
+
+        s.add_layer(Modal::new(already_added_dialog));
+    }
+
     pub fn select_artist_dialog(
         library: Arc<Library>,
         queue: Arc<Queue>,
@@ -194,11 +305,15 @@ impl ContextMenu {
         .with_name("selectartistaction")
     }
 
-    fn track_already_added() -> Dialog {
-        Dialog::text("This track is already in your playlist")
+    fn track_already_added(present: usize, count: usize) -> Dialog {
+        let text = match count {
+            1 => "This track is already in your playlist".to_string(),
+            _ => format!("{present} of the {count} tracks are already in your playlist"),
+        };
+        Dialog::text(text)
             .title("Track already exists")
             .padding(Margins::lrtb(1, 1, 1, 0))
-            .dismiss_button("Close")
+            .dismiss_button("Cancel")
     }
 
     pub fn new(item: &dyn ListItem, queue: Arc<Queue>, library: Arc<Library>) -> NamedView<Self> {
@@ -268,9 +383,11 @@ impl ContextMenu {
         // If the item is saveable, its save state will be set
         if let Some(savestatus) = item.is_saved(library.clone()) {
             content.add_item(
-                match savestatus {
-                    true => "Unsave",
-                    false => "Save",
+                match (savestatus, item.artist().is_some()) {
+                    (true, true) => "Unfollow",
+                    (false, true) => "Follow",
+                    (true, false) => "Unsave",
+                    (false, false) => "Save",
                 },
                 ContextMenuAction::ToggleSavedStatus(item.as_listitem()),
             );
@@ -307,8 +424,11 @@ impl ContextMenu {
                         write_share(url.to_string());
                     }
                     ContextMenuAction::AddToPlaylist(track) => {
-                        let dialog =
-                            Self::add_track_dialog(library, queue.get_spotify(), *track.clone());
+                        let dialog = Self::add_track_dialog(
+                            library,
+                            queue.get_spotify(),
+                            vec![*track.clone()],
+                        );
                         s.add_layer(dialog);
                     }
                     ContextMenuAction::ShowRecommendations(item) => {
@@ -336,11 +456,29 @@ impl ContextMenu {
             });
         }
 
+        // Tell who added the item to a collaborative playlist, and when
+        let added = item.added_by().map(|id| {
+            let name = library.user_name(&id).unwrap_or(id);
+            match item.added_at() {
+                Some(at) => format!(
+                    "Added by {name} on {}",
+                    at.with_timezone(&Local).format("%Y-%m-%d")
+                ),
+                None => format!("Added by {name}"),
+            }
+        });
+        let mut layout = LinearLayout::vertical();
+        if let Some(added) = added {
+            layout.add_child(TextView::new(added));
+            layout.add_child(DummyView);
+        }
+        layout.add_child(content.with_name("contextmenu_select"));
+
         let dialog = Dialog::new()
             .title(item.display_left(library))
             .dismiss_button("Close")
             .padding(Margins::lrtb(1, 1, 1, 0))
-            .content(content.with_name("contextmenu_select"));
+            .content(layout);
         Self {
             dialog: Modal::new_ext(dialog),
         }
@@ -1,17 +1,18 @@
-use std::collections::HashSet;
 use std::io::Write;
-use std::path::PathBuf;
 use std::process::{Child, Stdio};
 
 use std::sync::{Arc, RwLock};
 
-use cursive::theme::{ColorStyle, ColorType, PaletteColor};
+use base64::Engine;
+use cursive::theme::{Color, ColorStyle, ColorType, PaletteColor};
 use cursive::{Cursive, Printer, Vec2, View};
 use ioctl_rs::{ioctl, TIOCGWINSZ};
-use log::{debug, error};
+use log::{debug, error, info};
 
 use crate::command::{Command, GotoMode};
 use crate::commands::CommandResult;
+use crate::config::CoverProtocol;
+use crate::covers::{CoverCache, Format};
 use crate::library::Library;
 use crate::queue::Queue;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
@@ -19,14 +20,34 @@ use crate::ui::album::AlbumView;
 use crate::ui::artist::ArtistView;
 use crate::Config;
 
+/// The most a kitty graphics command may carry at once.
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// The cover as it was last drawn with escape sequences: the URL, and where
+/// and how large it is in cells.
+#[derive(Clone, PartialEq, Eq)]
+struct Drawn {
+    url: String,
+    offset: Vec2,
+    size: Vec2,
+}
+
 pub struct CoverView {
     queue: Arc<Queue>,
     library: Arc<Library>,
-    loading: Arc<RwLock<HashSet<String>>>,
+    protocol: CoverProtocol,
+    covers: CoverCache,
     last_size: RwLock<Vec2>,
     drawn_url: RwLock<Option<String>>,
+    drawn: RwLock<Option<Drawn>>,
+    /// Where the view was drawn last and how large it was. Covers are only
+    /// drawn with escape sequences once this stays the same, so the screen
+    /// is drawn completely first and doesn't draw over them.
+    last_layout: RwLock<Option<(Vec2, Vec2)>>,
     ueberzug: RwLock<Option<Child>>,
     font_size: Vec2,
+    /// The size of a cell in pixels, without `cover_max_scale`.
+    cell_pixels: Vec2,
 }
 
 impl CoverView {
@@ -43,23 +64,160 @@ impl CoverView {
             xpixels, ypixels, cols, rows
         );
 
+        // Terminals that don't tell are assumed to have cells twice as high
+        // as wide.
+        if xpixels == 0 || ypixels == 0 {
+            xpixels = cols.max(1) * 8;
+            ypixels = rows.max(1) * 16;
+        }
+        let cell_pixels = Vec2::new(
+            (xpixels / cols.max(1)) as usize,
+            (ypixels / rows.max(1)) as usize,
+        );
+
         // Determine font size, considering max scale to prevent tiny covers on HiDPI screens
         let scale = config.values().cover_max_scale.unwrap_or(1.0);
         xpixels = ((xpixels as f32) / scale) as u16;
         ypixels = ((ypixels as f32) / scale) as u16;
 
-        let font_size = Vec2::new((xpixels / cols) as usize, (ypixels / rows) as usize);
+        let font_size = Vec2::new(
+            (xpixels / cols.max(1)) as usize,
+            (ypixels / rows.max(1)) as usize,
+        );
 
         debug!("Determined font size: {}x{}", font_size.x, font_size.y);
 
+        let protocol = match config.values().cover_protocol.unwrap_or_default() {
+            CoverProtocol::Auto => detect_protocol(config.values().cover_blocks.unwrap_or(false)),
+            protocol => protocol,
+        };
+        info!("Drawing covers with {:?}", protocol);
+
         Self {
             queue,
+            covers: CoverCache::new(library.clone()),
             library,
+            protocol,
             ueberzug: RwLock::new(None),
-            loading: Arc::new(RwLock::new(HashSet::new())),
             last_size: RwLock::new(Vec2::new(0, 0)),
             drawn_url: RwLock::new(None),
+            drawn: RwLock::new(None),
+            last_layout: RwLock::new(None),
             font_size,
+            cell_pixels,
+        }
+    }
+
+    /// The size in cells of the cover, as large as fits `draw_size` up to
+    /// its native resolution.
+    fn cover_size(&self, draw_size: Vec2) -> Vec2 {
+        let draw_size_pxls = draw_size * self.font_size;
+        let side = draw_size_pxls.x.min(draw_size_pxls.y).min(640);
+        Vec2::new(side / self.font_size.x, side / self.font_size.y)
+    }
+
+    /// Draw the cover with the escape sequences of the terminal, centered in
+    /// the area at `offset`.
+    fn draw_graphics(&self, url: &str, offset: Vec2, draw_size: Vec2) {
+        if self.last_layout.read().unwrap().as_ref() != Some(&(offset, draw_size)) {
+            *self.last_layout.write().unwrap() = Some((offset, draw_size));
+            self.library.trigger_redraw();
+            return;
+        }
+
+        let size = self.cover_size(draw_size);
+        if size.x == 0 || size.y == 0 {
+            return;
+        }
+        let drawn = Drawn {
+            url: url.to_string(),
+            offset: offset + (draw_size - size) / 2,
+            size,
+        };
+        if self.drawn.read().unwrap().as_ref() == Some(&drawn) {
+            return;
+        }
+
+        let pixels = size * self.cell_pixels;
+        let sequence = match self.protocol {
+            CoverProtocol::Iterm => self
+                .covers
+                .path(url)
+                .and_then(|path| std::fs::read(path).ok())
+                .map(|image| iterm_sequence(&image, size)),
+            CoverProtocol::Kitty => self
+                .covers
+                .converted(url, Format::Png, pixels.x, pixels.y)
+                .map(|png| kitty_sequence(&png, size)),
+            CoverProtocol::Sixel => self
+                .covers
+                .converted(url, Format::Sixel, pixels.x, pixels.y)
+                .map(|sixel| sixel.to_vec()),
+            _ => None,
+        };
+        let sequence = match sequence {
+            Some(sequence) => sequence,
+            None => return,
+        };
+
+        self.clear_graphics();
+        let mut output =
+            format!("\x1b7\x1b[{};{}H", drawn.offset.y + 1, drawn.offset.x + 1).into_bytes();
+        output.extend(sequence);
+        output.extend(b"\x1b8");
+        if let Err(e) = write_terminal(&output) {
+            error!("Failed to draw cover: {}", e);
+            return;
+        }
+        *self.drawn.write().unwrap() = Some(drawn);
+    }
+
+    /// Remove the cover drawn with escape sequences.
+    fn clear_graphics(&self) {
+        let drawn = match self.drawn.write().unwrap().take() {
+            Some(drawn) => drawn,
+            None => return,
+        };
+        let output = if self.protocol == CoverProtocol::Kitty {
+            b"\x1b_Ga=d,d=I,i=1,q=2\x1b\\".to_vec()
+        } else {
+            // Other images are gone once something is written over them.
+            let mut output = b"\x1b7\x1b[0m".to_vec();
+            for y in 0..drawn.size.y {
+                let row = drawn.offset.y + y + 1;
+                let line = " ".repeat(drawn.size.x);
+                output.extend(format!("\x1b[{};{}H{}", row, drawn.offset.x + 1, line).bytes());
+            }
+            output.extend(b"\x1b8");
+            output
+        };
+        if let Err(e) = write_terminal(&output) {
+            error!("Failed to clear cover: {}", e);
+        }
+    }
+
+    /// Draw the cover as half blocks, each cell two pixels high.
+    fn draw_blocks(&self, url: &str, printer: &Printer) {
+        let size = self.cover_size(printer.size);
+        if size.x == 0 || size.y == 0 {
+            return;
+        }
+        let pixels = match self.covers.converted(url, Format::Rgb, size.x, size.y * 2) {
+            Some(pixels) if pixels.len() == size.x * size.y * 6 => pixels,
+            _ => return,
+        };
+        let pixel = |x: usize, y: usize| {
+            let i = (y * size.x + x) * 3;
+            Color::Rgb(pixels[i], pixels[i + 1], pixels[i + 2])
+        };
+        let offset = (printer.size - size) / 2;
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let style = ColorStyle::new(pixel(x, y * 2), pixel(x, y * 2 + 1));
+                printer.with_color(style, |printer| {
+                    printer.print(offset + (x, y), "▀");
+                });
+            }
         }
     }
 
@@ -78,7 +236,7 @@ impl CoverView {
             return;
         }
 
-        let path = match self.cache_path(url.clone()) {
+        let path = match self.covers.path(&url) {
             Some(p) => p,
             None => return,
         };
@@ -163,32 +321,6 @@ impl CoverView {
 
         Ok(())
     }
-
-    fn cache_path(&self, url: String) -> Option<PathBuf> {
-        let path = crate::utils::cache_path_for_url(url.clone());
-
-        let mut loading = self.loading.write().unwrap();
-        if loading.contains(&url) {
-            return None;
-        }
-
-        if path.exists() {
-            return Some(path);
-        }
-
-        loading.insert(url.clone());
-
-        let loading_thread = self.loading.clone();
-        std::thread::spawn(move || {
-            if let Err(e) = crate::utils::download(url.clone(), path.clone()) {
-                error!("Failed to download cover: {}", e);
-            }
-            let mut loading = loading_thread.write().unwrap();
-            loading.remove(&url.clone());
-        });
-
-        None
-    }
 }
 
 impl View for CoverView {
@@ -206,10 +338,19 @@ impl View for CoverView {
 
         let cover_url = self.queue.get_current().and_then(|t| t.cover_url());
 
-        if let Some(url) = cover_url {
-            self.draw_cover(url, printer.offset, printer.size);
-        } else {
-            self.clear_cover();
+        match (self.protocol, cover_url) {
+            (CoverProtocol::Ueberzug, Some(url)) => {
+                self.draw_cover(url, printer.offset, printer.size)
+            }
+            (CoverProtocol::Ueberzug, None) => self.clear_cover(),
+            (CoverProtocol::Blocks, Some(url)) => self.draw_blocks(&url, printer),
+            (CoverProtocol::Kitty | CoverProtocol::Sixel | CoverProtocol::Iterm, Some(url)) => {
+                self.draw_graphics(&url, printer.offset, printer.size)
+            }
+            (CoverProtocol::Kitty | CoverProtocol::Sixel | CoverProtocol::Iterm, None) => {
+                self.clear_graphics()
+            }
+            _ => {}
         }
     }
 
@@ -224,7 +365,13 @@ impl ViewExt for CoverView {
     }
 
     fn on_leave(&self) {
-        self.clear_cover();
+        match self.protocol {
+            CoverProtocol::Ueberzug => self.clear_cover(),
+            _ => {
+                self.clear_graphics();
+                *self.last_layout.write().unwrap() = None;
+            }
+        }
     }
 
     fn on_command(&mut self, _s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
@@ -279,6 +426,13 @@ impl ViewExt for CoverView {
                                 };
                             }
                         }
+                        GotoMode::Related => {
+                            if let Some(artist) = track.artists().and_then(|a| a.first().cloned()) {
+                                let view = ArtistView::related(queue, library, &artist)
+                                    .into_boxed_view_ext();
+                                return Ok(CommandResult::View(view));
+                            }
+                        }
                     }
                 }
             }
@@ -288,3 +442,75 @@ impl ViewExt for CoverView {
         Ok(CommandResult::Ignored)
     }
 }
+
+/// The protocol the terminal supports, as told by its environment.
+/// Terminals that support none use Ueberzug if it is installed, or else
+/// half blocks if `blocks` is set.
+fn detect_protocol(blocks: bool) -> CoverProtocol {
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    let term = var("TERM");
+    let term_program = var("TERM_PROGRAM");
+    if std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || term == "xterm-kitty"
+        || term == "xterm-ghostty"
+    {
+        CoverProtocol::Kitty
+    } else if term_program == "iTerm.app"
+        || term_program == "WezTerm"
+        || var("LC_TERMINAL") == "iTerm2"
+    {
+        CoverProtocol::Iterm
+    } else if term.starts_with("foot") || term.starts_with("mlterm") || term.contains("sixel") {
+        CoverProtocol::Sixel
+    } else if is_installed("ueberzug") {
+        CoverProtocol::Ueberzug
+    } else if blocks {
+        CoverProtocol::Blocks
+    } else {
+        CoverProtocol::None
+    }
+}
+
+fn is_installed(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|path| path.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+fn iterm_sequence(image: &[u8], size: Vec2) -> Vec<u8> {
+    let data = base64::engine::general_purpose::STANDARD.encode(image);
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+        image.len(),
+        size.x,
+        size.y,
+        data
+    )
+    .into_bytes()
+}
+
+/// The kitty graphics commands that show `png` in `size` cells, sent in
+/// chunks as the protocol requires.
+fn kitty_sequence(png: &[u8], size: Vec2) -> Vec<u8> {
+    let data = base64::engine::general_purpose::STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+    let mut sequence = Vec::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let control = if i == 0 {
+            format!("a=T,f=100,i=1,q=2,C=1,c={},r={},m={more}", size.x, size.y)
+        } else {
+            format!("m={more}")
+        };
+        sequence.extend(format!("\x1b_G{control};").bytes());
+        sequence.extend_from_slice(chunk);
+        sequence.extend(b"\x1b\\");
+    }
+    sequence
+}
+
+fn write_terminal(output: &[u8]) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(output)?;
+    stdout.flush()
+}
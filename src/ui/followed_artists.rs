@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use cursive::view::ViewWrapper;
+use cursive::Cursive;
+
+use crate::command::{Command, SortDirection, SortKey};
+use crate::commands::CommandResult;
+use crate::library::Library;
+use crate::model::artist::Artist;
+use crate::queue::Queue;
+use crate::traits::ViewExt;
+use crate::ui::listview::ListView;
+
+/// The followed artists along with their latest release, which is fetched
+/// once an artist is shown.
+pub struct FollowedArtistsView {
+    list: ListView<Artist>,
+    library: Arc<Library>,
+    /// What the artists were last sorted by.
+    sorted_by: Option<SortKey>,
+}
+
+impl FollowedArtistsView {
+    pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> Self {
+        Self {
+            list: ListView::new(library.followed_artists.clone(), queue, library.clone()),
+            library,
+            sorted_by: None,
+        }
+    }
+
+    /// Sort the artists by `key`. Artists whose latest release isn't known
+    /// yet come last when sorting by release, newest first.
+    fn sort(&self, key: &SortKey, direction: &SortDirection) -> Result<(), String> {
+        let mut artists = self.library.followed_artists.write().unwrap();
+        match key {
+            SortKey::Title | SortKey::Artist => {
+                artists.sort_by_cached_key(|artist| artist.name.to_lowercase())
+            }
+            SortKey::Released => artists.sort_by_cached_key(|artist| {
+                artist
+                    .id
+                    .as_ref()
+                    .and_then(|id| self.library.latest_release(id))
+            }),
+            _ => return Err("Artists can only be sorted by name or release date".into()),
+        }
+        if let SortDirection::Descending = direction {
+            artists.reverse();
+        }
+        Ok(())
+    }
+}
+
+impl ViewWrapper for FollowedArtistsView {
+    wrap_impl!(self.list: ListView<Artist>);
+}
+
+impl ViewExt for FollowedArtistsView {
+    fn title(&self) -> String {
+        "Followed Artists".to_string()
+    }
+
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            Command::Sort(key, direction) => {
+                // Without a key, switch between sorting by name and release
+                let key = match (key, &self.sorted_by) {
+                    (Some(key), _) => key.clone(),
+                    (None, Some(SortKey::Title)) => SortKey::Released,
+                    (None, _) => SortKey::Title,
+                };
+                self.sort(&key, direction)?;
+                self.sorted_by = Some(key);
+                Ok(CommandResult::Consumed(None))
+            }
+            Command::Delete => {
+                let artist = self
+                    .library
+                    .followed_artists
+                    .read()
+                    .unwrap()
+                    .get(self.list.get_selected_index())
+                    .cloned();
+                if let Some(artist) = artist {
+                    self.library.unfollow_artist(&artist);
+                }
+                Ok(CommandResult::Consumed(None))
+            }
+            _ => self.list.on_command(s, cmd),
+        }
+    }
+}
@@ -62,33 +62,31 @@ impl ViewExt for HelpView {
         match cmd {
             Command::Help => Ok(CommandResult::Consumed(None)),
             Command::Move(mode, amount) => {
-                let scroller = self.view.get_scroller_mut();
-                let viewport = scroller.content_viewport();
-                match mode {
-                    MoveMode::Up => {
-                        match amount {
-                            MoveAmount::Extreme => {
-                                self.view.scroll_to_top();
-                            }
-                            MoveAmount::Integer(amount) => scroller
-                                .scroll_to_y(viewport.top().saturating_sub(*amount as usize)),
-                        };
-                        Ok(CommandResult::Consumed(None))
-                    }
-                    MoveMode::Down => {
-                        match amount {
-                            MoveAmount::Extreme => {
-                                self.view.scroll_to_bottom();
-                            }
-                            MoveAmount::Integer(amount) => scroller
-                                .scroll_to_y(viewport.bottom().saturating_add(*amount as usize)),
-                        };
-                        Ok(CommandResult::Consumed(None))
-                    }
-                    _ => Ok(CommandResult::Consumed(None)),
-                }
+                scroll_text(&mut self.view, mode, amount);
+                Ok(CommandResult::Consumed(None))
             }
             _ => Ok(CommandResult::Ignored),
         }
     }
 }
+
+/// Scroll a text view for a [Command::Move].
+pub fn scroll_text(view: &mut ScrollView<TextView>, mode: &MoveMode, amount: &MoveAmount) {
+    let scroller = view.get_scroller_mut();
+    let viewport = scroller.content_viewport();
+    match (mode, amount) {
+        (MoveMode::Up, MoveAmount::Extreme) => {
+            view.scroll_to_top();
+        }
+        (MoveMode::Up, MoveAmount::Integer(amount)) => {
+            scroller.scroll_to_y(viewport.top().saturating_sub(*amount as usize))
+        }
+        (MoveMode::Down, MoveAmount::Extreme) => {
+            view.scroll_to_bottom();
+        }
+        (MoveMode::Down, MoveAmount::Integer(amount)) => {
+            scroller.scroll_to_y(viewport.bottom().saturating_add(*amount as usize))
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,242 @@
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use cursive::event::Key;
+use cursive::traits::Nameable;
+use cursive::view::Margins;
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, ScrollView, SelectView};
+use cursive::Cursive;
+use log::error;
+
+use crate::library::Library;
+use crate::model::album::Album;
+use crate::model::artist::Artist;
+use crate::model::playable::Playable;
+use crate::model::playlist::Playlist;
+use crate::model::track::Track;
+use crate::queue::Queue;
+use crate::traits::ListItem;
+use crate::ui::layout::Layout;
+use crate::ui::modal::Modal;
+use crate::utils::fuzzy_score;
+
+const SELECT_ID: &str = "launcher_select";
+/// At most this many items of each kind are matched into the results, and
+/// at most this many results are listed.
+const MAX_RESULTS: usize = 100;
+
+/// An item the launcher goes to, in the order of the kinds that are listed
+/// first when they match equally well.
+#[derive(Clone)]
+enum Target {
+    Queued(usize, Playable),
+    Playlist(Playlist),
+    Album(Album),
+    Artist(Artist),
+    Track(Track),
+}
+
+impl Target {
+    fn label(&self) -> String {
+        let (kind, name) = match self {
+            Target::Queued(_, playable) => ("queue", playable.to_string()),
+            Target::Playlist(playlist) => ("playlist", playlist.name.clone()),
+            Target::Album(album) => ("album", album.to_string()),
+            Target::Artist(artist) => ("artist", artist.name.clone()),
+            Target::Track(track) => ("track", track.to_string()),
+        };
+        format!("{kind:<8}  {name}")
+    }
+
+    /// Open the view of the item, or play it if it's a track.
+    fn go(&self, s: &mut Cursive, queue: Arc<Queue>, library: Arc<Library>) {
+        let view = match self {
+            Target::Queued(index, _) => {
+                queue.play(*index, true, false);
+                None
+            }
+            Target::Playlist(playlist) => playlist.open(queue, library),
+            Target::Album(album) => album.open(queue, library),
+            Target::Artist(artist) => artist.open(queue, library),
+            Target::Track(track) => {
+                track.clone().play(queue);
+                None
+            }
+        };
+        if let Some(view) = view {
+            s.call_on_name("main", move |v: &mut Layout| v.push_view(view));
+        }
+    }
+}
+
+/// A dialog to go to any item of the queue or the library by fuzzily
+/// matching its name with what is typed. The items are matched in the
+/// background, from what the library has cached so far.
+pub fn launcher_dialog(
+    s: &Cursive,
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+) -> OnEventView<Modal<Dialog>> {
+    let generation = Arc::new(AtomicUsize::new(0));
+    update_results(s, "", queue.clone(), library.clone(), generation.clone());
+
+    let select: SelectView<Target> = SelectView::new().on_submit({
+        let queue = queue.clone();
+        let library = library.clone();
+        move |s, target: &Target| {
+            s.pop_layer();
+            target.go(s, queue.clone(), library.clone());
+        }
+    });
+
+    let filter = EditView::new()
+        .on_edit({
+            let queue = queue.clone();
+            let library = library.clone();
+            move |s, query, _| {
+                update_results(s, query, queue.clone(), library.clone(), generation.clone())
+            }
+        })
+        .on_submit(move |s, _| {
+            let selected = s
+                .call_on_name(SELECT_ID, |select: &mut SelectView<Target>| {
+                    select.selection()
+                })
+                .flatten();
+            if let Some(target) = selected {
+                s.pop_layer();
+                target.go(s, queue.clone(), library.clone());
+            }
+        });
+    // Keep typing while choosing a result
+    let filter = OnEventView::new(filter)
+        .on_pre_event(Key::Up, |s| {
+            s.call_on_name(SELECT_ID, |select: &mut SelectView<Target>| {
+                select.select_up(1)
+            });
+        })
+        .on_pre_event(Key::Down, |s| {
+            s.call_on_name(SELECT_ID, |select: &mut SelectView<Target>| {
+                select.select_down(1)
+            });
+        });
+
+    let dialog = Dialog::new()
+        .title("Go to")
+        .dismiss_button("Close")
+        .padding(Margins::lrtb(1, 1, 1, 0))
+        .content(
+            LinearLayout::vertical()
+                .child(filter)
+                .child(ScrollView::new(select.with_name(SELECT_ID))),
+        );
+    OnEventView::new(Modal::new(dialog)).on_event(Key::Esc, |s| {
+        s.pop_layer();
+    })
+}
+
+/// Match the items with `query` in the background and list the results,
+/// unless another query was typed meanwhile.
+fn update_results(
+    s: &Cursive,
+    query: &str,
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+    generation: Arc<AtomicUsize>,
+) {
+    let current = generation.fetch_add(1, Ordering::SeqCst) + 1;
+    let sink = s.cb_sink().clone();
+    let query = query.to_string();
+    thread::spawn(move || {
+        let targets = find(&query, &queue, &library);
+        if generation.load(Ordering::SeqCst) != current {
+            return;
+        }
+        let sent = sink.send(Box::new(move |s: &mut Cursive| {
+            if generation.load(Ordering::SeqCst) != current {
+                return;
+            }
+            s.call_on_name(SELECT_ID, |select: &mut SelectView<Target>| {
+                select.clear();
+                select.add_all(targets.into_iter().map(|target| (target.label(), target)));
+            });
+        }));
+        if sent.is_err() {
+            error!("could not list the items that match \"{}\"", query);
+        }
+    });
+}
+
+/// The items that match `query` best, best first. Without a query, the queue
+/// and then the playlists are listed.
+fn find(query: &str, queue: &Queue, library: &Library) -> Vec<Target> {
+    let mut found = Vec::new();
+    best(
+        query,
+        &queue.queue.read().unwrap(),
+        Playable::to_string,
+        &mut found,
+        |index, playable| Target::Queued(index, playable.clone()),
+    );
+    best(
+        query,
+        &library.playlists(),
+        |playlist| playlist.name.clone(),
+        &mut found,
+        |_, playlist| Target::Playlist(playlist.clone()),
+    );
+    best(
+        query,
+        &library.albums.read().unwrap(),
+        Album::to_string,
+        &mut found,
+        |_, album| Target::Album(album.clone()),
+    );
+    best(
+        query,
+        &library.followed_artists.read().unwrap(),
+        |artist| artist.name.clone(),
+        &mut found,
+        |_, artist| Target::Artist(artist.clone()),
+    );
+    best(
+        query,
+        &library.tracks.read().unwrap(),
+        Track::to_string,
+        &mut found,
+        |_, track| Target::Track(track.clone()),
+    );
+
+    // Stable, so that equal matches stay in the order of their kinds
+    found.sort_by_key(|(score, _)| Reverse(*score));
+    found
+        .into_iter()
+        .take(MAX_RESULTS)
+        .map(|(_, target)| target)
+        .collect()
+}
+
+/// Add the items that match `query` best to `found` along with their score,
+/// only copying those that may be listed.
+fn best<T>(
+    query: &str,
+    items: &[T],
+    text: impl Fn(&T) -> String,
+    found: &mut Vec<(usize, Target)>,
+    target: impl Fn(usize, &T) -> Target,
+) {
+    let mut scored: Vec<(usize, usize)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| fuzzy_score(query, &text(item)).map(|score| (score, index)))
+        .collect();
+    scored.sort_by_key(|(score, _)| Reverse(*score));
+    found.extend(
+        scored
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|(score, index)| (score, target(index, &items[index]))),
+    );
+}
@@ -7,21 +7,25 @@ use strum::IntoEnumIterator;
 use crate::command::Command;
 use crate::commands::CommandResult;
 use crate::config::LibraryTab;
+use crate::history::History;
 use crate::library::Library;
 use crate::queue::Queue;
 use crate::traits::ViewExt;
 use crate::ui::browse::BrowseView;
+use crate::ui::followed_artists::FollowedArtistsView;
 use crate::ui::listview::ListView;
+use crate::ui::new_releases::NewReleasesView;
 use crate::ui::playlists::PlaylistsView;
+use crate::ui::recently_played::RecentlyPlayedView;
 use crate::ui::tabview::TabView;
 
 pub struct LibraryView {
     tabs: TabView,
-    display_name: Option<String>,
+    library: Arc<Library>,
 }
 
 impl LibraryView {
-    pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> Self {
+    pub fn new(queue: Arc<Queue>, library: Arc<Library>, history: Arc<History>) -> Self {
         let mut tabview = TabView::new();
         let selected_tabs = library
             .cfg
@@ -35,7 +39,8 @@ impl LibraryView {
                 LibraryTab::Tracks => tabview.add_tab(
                     "tracks",
                     ListView::new(library.tracks.clone(), queue.clone(), library.clone())
-                        .with_title("Tracks"),
+                        .with_title("Tracks")
+                        .with_columns_view("library"),
                 ),
                 LibraryTab::Albums => tabview.add_tab(
                     "albums",
@@ -47,6 +52,10 @@ impl LibraryView {
                     ListView::new(library.artists.clone(), queue.clone(), library.clone())
                         .with_title("Artists"),
                 ),
+                LibraryTab::Followed => tabview.add_tab(
+                    "followed",
+                    FollowedArtistsView::new(queue.clone(), library.clone()),
+                ),
                 LibraryTab::Playlists => tabview.add_tab(
                     "playlists",
                     PlaylistsView::new(queue.clone(), library.clone()),
@@ -59,19 +68,25 @@ impl LibraryView {
                 LibraryTab::Browse => {
                     tabview.add_tab("browse", BrowseView::new(queue.clone(), library.clone()))
                 }
+                LibraryTab::NewReleases => tabview.add_tab(
+                    "newreleases",
+                    NewReleasesView::new(queue.clone(), library.clone()),
+                ),
+                LibraryTab::History => tabview.add_tab(
+                    "history",
+                    ListView::new(history.items.clone(), queue.clone(), library.clone())
+                        .with_title("History"),
+                ),
+                LibraryTab::RecentlyPlayed => tabview.add_tab(
+                    "recentlyplayed",
+                    RecentlyPlayedView::new(queue.clone(), library.clone(), history.clone()),
+                ),
             }
         }
 
         Self {
             tabs: tabview,
-            display_name: {
-                let hide_username = library.cfg.values().hide_display_names.unwrap_or(false);
-                if hide_username {
-                    None
-                } else {
-                    library.display_name.clone()
-                }
-            },
+            library,
         }
     }
 }
@@ -82,7 +97,13 @@ impl ViewWrapper for LibraryView {
 
 impl ViewExt for LibraryView {
     fn title(&self) -> String {
-        if let Some(name) = &self.display_name {
+        let hide_username = self
+            .library
+            .cfg
+            .values()
+            .hide_display_names
+            .unwrap_or(false);
+        if let Some(name) = self.library.display_name().filter(|_| !hide_username) {
             format!("Library of {name}")
         } else {
             "Library".to_string()
@@ -1,18 +1,26 @@
+use chrono::Local;
 use cursive::view::scroll::Scroller;
-use log::info;
+use log::{error, info};
 use std::cmp::{max, min, Ordering};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 use std::sync::{Arc, RwLock};
+use std::thread;
 
 use cursive::align::HAlign;
-use cursive::event::{Callback, Event, EventResult, MouseButton, MouseEvent};
-use cursive::theme::{ColorStyle, ColorType, PaletteColor};
+use cursive::event::{Callback, Event, EventResult, Key, MouseButton, MouseEvent};
+use cursive::theme::{ColorStyle, ColorType, Effect, PaletteColor, Style};
 use cursive::traits::View;
 use cursive::view::scroll;
 use cursive::{Cursive, Printer, Rect, Vec2};
 use unicode_width::UnicodeWidthStr;
 
-use crate::command::{Command, GotoMode, InsertSource, JumpMode, MoveAmount, MoveMode, TargetMode};
+use crate::command::{
+    Command, GotoMode, InsertSource, JumpMode, MarkMode, MoveAmount, MoveMode, SortDirection,
+    SortKey, TargetMode,
+};
 use crate::commands::CommandResult;
+use crate::config::{ColumnKind, SortingOrder, TrackColumn};
 use crate::ext_traits::CursiveExt;
 use crate::library::Library;
 use crate::model::album::Album;
@@ -21,31 +29,110 @@ use crate::model::episode::Episode;
 use crate::model::playable::Playable;
 use crate::model::playlist::Playlist;
 use crate::model::show::Show;
-use crate::model::track::Track;
+use crate::model::track::{sorted_order, Track};
 use crate::queue::Queue;
+use crate::radio;
 #[cfg(feature = "share_clipboard")]
 use crate::sharing::{read_share, write_share};
 use crate::spotify::UriType;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::album::AlbumView;
 use crate::ui::artist::ArtistView;
+use crate::ui::columns;
 use crate::ui::contextmenu::ContextMenu;
 use crate::ui::pagination::Pagination;
+use crate::ui::trackinfo::TrackInfo;
+use crate::utils::fold;
+
+/// How many rows before the last loaded item the next page is loaded.
+const PREFETCH_ROWS: usize = 10;
+
+/// How many rows below the visible ones are checked for liked tracks ahead of
+/// being shown.
+const LIKED_LOOKAHEAD_ROWS: usize = 20;
+
+/// The items that match a filter, which are the only ones shown while it is
+/// applied.
+struct ListFilter {
+    text: String,
+    /// The folded text that the items are matched with.
+    query: String,
+    /// The query that `matches` were found for.
+    matched_query: String,
+    /// Whether each item of the content matches, which is matched again once
+    /// items were added or removed.
+    matches: Vec<bool>,
+    /// The indices into the content of the items that are shown, in the
+    /// order they are shown.
+    shown: Vec<usize>,
+    /// The selected position and the scroll offset before filtering, which
+    /// are restored once the filter is cleared.
+    previous: (usize, Vec2),
+}
+
+impl ListFilter {
+    /// Whether the items must be matched again, as there are more or fewer
+    /// of them or the query changed.
+    fn is_stale(&self, len: usize) -> bool {
+        self.matches.len() != len || self.matched_query != self.query
+    }
+}
+
+/// Whether each of `texts` contains the folded `query`, ignoring case and
+/// diacritics.
+fn matching(query: &str, texts: impl Iterator<Item = String>) -> Vec<bool> {
+    texts.map(|text| fold(&text).contains(query)).collect()
+}
 
 pub struct ListView<I: ListItem> {
     content: Arc<RwLock<Vec<I>>>,
+    /// The order the items are shown in, as indices into `content`.
     order: Arc<RwLock<Option<Vec<usize>>>>,
+    /// Whether `order` is given by the owner of the content, so that the
+    /// list can't be sorted.
+    fixed_order: bool,
+    /// What the tracks are sorted by, along with the length of the content
+    /// when they were, to sort them again once it changes.
+    sorting: Option<(SortingOrder, usize)>,
+    /// Counts the sorts, so that only the latest one sets the order.
+    sorts: Arc<AtomicUsize>,
     last_content_len: usize,
+    /// The indices of the marked tracks in `content`, along with its length
+    /// when they were marked, to drop them once items were added or removed.
+    marked: HashSet<usize>,
+    marked_len: usize,
+    /// The position the visual selection started at, which selects the
+    /// tracks from there to the selected item while it's active.
+    visual: Option<usize>,
     selected: usize,
     search_query: String,
     search_indexes: Vec<usize>,
     search_selected_index: usize,
+    filter: Option<ListFilter>,
+    /// The scroll offset to restore once the list was laid out again.
+    restore_offset: Option<Vec2>,
     last_size: Vec2,
     scroller: scroll::Core,
     queue: Arc<Queue>,
     library: Arc<Library>,
     pagination: Pagination<I>,
     title: String,
+    /// Whether the last item of each album is underlined, to tell the albums
+    /// apart.
+    album_separators: bool,
+    /// Whether the time each item was added is shown in local time, which
+    /// is when it was played in lists of played items.
+    added_at: bool,
+    /// Whether the name of the user who added each item is shown, for
+    /// collaborative playlists.
+    added_by: bool,
+    /// Whether the items are grouped under headings of their disc, while
+    /// they are shown in their own order.
+    disc_headings: bool,
+    /// Whether the popularity of each item is shown as a bar, if known.
+    popularity: bool,
+    /// The view whose `track_columns` override applies to the tracks.
+    columns_view: Option<&'static str>,
 }
 
 impl<I: ListItem> Scroller for ListView<I> {
@@ -63,17 +150,31 @@ impl<I: ListItem + Clone> ListView<I> {
         let result = Self {
             content,
             order: Arc::new(RwLock::new(None)),
+            fixed_order: false,
+            sorting: None,
+            sorts: Arc::new(AtomicUsize::new(0)),
             last_content_len: 0,
+            marked: HashSet::new(),
+            marked_len: 0,
+            visual: None,
             selected: 0,
             search_query: String::new(),
             search_indexes: Vec::new(),
             search_selected_index: 0,
+            filter: None,
+            restore_offset: None,
             last_size: Vec2::new(0, 0),
             scroller: scroll::Core::new(),
             queue,
             library,
             pagination: Pagination::default(),
             title: "".to_string(),
+            album_separators: false,
+            added_at: false,
+            added_by: false,
+            disc_headings: false,
+            popularity: false,
+            columns_view: None,
         };
         result.try_paginate();
         result
@@ -81,6 +182,37 @@ impl<I: ListItem + Clone> ListView<I> {
 
     pub fn with_order(mut self, order: Arc<RwLock<Option<Vec<usize>>>>) -> Self {
         self.order = order;
+        self.fixed_order = true;
+        self
+    }
+
+    pub fn with_album_separators(mut self) -> Self {
+        self.album_separators = true;
+        self
+    }
+
+    pub fn with_added_at(mut self) -> Self {
+        self.added_at = true;
+        self
+    }
+
+    pub fn with_added_by(mut self) -> Self {
+        self.added_by = true;
+        self
+    }
+
+    pub fn with_disc_headings(mut self) -> Self {
+        self.disc_headings = true;
+        self
+    }
+
+    pub fn with_popularity(mut self) -> Self {
+        self.popularity = true;
+        self
+    }
+
+    pub fn with_columns_view(mut self, view: &'static str) -> Self {
+        self.columns_view = Some(view);
         self
     }
 
@@ -93,12 +225,16 @@ impl<I: ListItem + Clone> ListView<I> {
         &self.pagination
     }
 
-    /// Return the current amount of items in `content`
+    /// Return the current amount of items that are shown, which are those of
+    /// `content` unless they are filtered.
     ///
     /// If `include_paginator` is `true`, the pagination entry will be included
     /// in the count.
     pub fn content_len(&self, include_paginator: bool) -> usize {
-        let content_len = self.content.read().unwrap().len();
+        let content_len = match &self.filter {
+            Some(filter) => filter.shown.len(),
+            None => self.items_len(),
+        };
 
         // add 1 more row for paginator if we can paginate
         if self.can_paginate() && include_paginator {
@@ -108,12 +244,20 @@ impl<I: ListItem + Clone> ListView<I> {
         }
     }
 
+    /// The amount of items in `content`, whether they are shown or not.
+    fn items_len(&self) -> usize {
+        self.content.read().unwrap().len()
+    }
+
     /// Return wether there are still items that aren't shown in the listview.
+    /// Only the loaded ones are filtered, so no more are loaded meanwhile.
     ///
     /// `true` if there are unloaded items
     /// `false` if all items are loaded
     fn can_paginate(&self) -> bool {
-        self.get_pagination().max_content().unwrap_or(0) > self.get_pagination().loaded_content()
+        self.filter.is_none()
+            && self.get_pagination().max_content().unwrap_or(0)
+                > self.get_pagination().loaded_content()
     }
 
     /// Try to load more items into the list if neccessary.
@@ -121,19 +265,127 @@ impl<I: ListItem + Clone> ListView<I> {
     fn try_paginate(&self) {
         // Paginate if there are more items
         //  AND
-        //   The selected item is the current last item (keyboard scrolling)
+        //   The selected item is close to the current last item (keyboard
+        //   scrolling)
         //    OR
-        //   The scroller can't scroll further down (mouse scrolling)
+        //   The bottom of the scroller is close to it (mouse scrolling)
+        let len = self.content.read().unwrap().len();
         if self.can_paginate()
-            && (self.selected == self.content.read().unwrap().len().saturating_sub(1)
+            && (self.selected + PREFETCH_ROWS >= len
+                || self.scroller.content_viewport().bottom() + PREFETCH_ROWS >= len
                 || !self.scroller.can_scroll_down())
         {
             self.pagination.call(&self.content, self.library.clone());
         }
     }
 
+    /// The index into the content of the selected item.
     pub fn get_selected_index(&self) -> usize {
-        self.selected
+        self.index_at(self.selected)
+    }
+
+    /// The indices into the content of the marked tracks and those in the
+    /// visual selection, in the order they are shown.
+    pub fn marked(&self) -> Vec<usize> {
+        let len = self.content_len(false);
+        let content = self.content.read().unwrap();
+        (0..len)
+            .filter_map(|position| {
+                let index = self.index_at(position);
+                let marked = self.marked.contains(&index)
+                    || (self.in_visual(position)
+                        && content.get(index).and_then(ListItem::track).is_some());
+                marked.then_some(index)
+            })
+            .collect()
+    }
+
+    /// Whether the item shown at `position` is in the visual selection.
+    fn in_visual(&self, position: usize) -> bool {
+        self.visual.map_or(false, |start| {
+            (min(start, self.selected)..=max(start, self.selected)).contains(&position)
+        })
+    }
+
+    fn clear_marks(&mut self) {
+        self.marked.clear();
+        self.visual = None;
+    }
+
+    /// The marked tracks, or else the selected one if it's a track.
+    fn marked_tracks(&self) -> Vec<Track> {
+        let indices = match self.marked() {
+            marked if marked.is_empty() => vec![self.get_selected_index()],
+            marked => marked,
+        };
+        let content = self.content.read().unwrap();
+        indices
+            .into_iter()
+            .filter_map(|index| content.get(index).and_then(ListItem::track))
+            .collect()
+    }
+
+    /// The index into the content of the item shown at `position`.
+    fn index_at(&self, position: usize) -> usize {
+        match &self.filter {
+            Some(filter) => filter.shown.get(position).copied().unwrap_or(position),
+            None => self.sorted_index_at(position),
+        }
+    }
+
+    /// The index into the content of the item at `position` of the sorted
+    /// list, whether it is filtered or not.
+    fn sorted_index_at(&self, position: usize) -> usize {
+        match self.order.read().unwrap().as_ref() {
+            Some(order) => order.get(position).copied().unwrap_or(position),
+            None => position,
+        }
+    }
+
+    /// The key and direction the tracks are sorted by, if they are.
+    pub fn sorting(&self) -> Option<&SortingOrder> {
+        self.sorting.as_ref().map(|(sorting, _)| sorting)
+    }
+
+    /// Sort the tracks by `sorting`, or show them in their own order without
+    /// one. Tracks are sorted in the background and shown in the new order
+    /// once they are, without changing the content itself.
+    pub fn sort(&mut self, sorting: Option<SortingOrder>) {
+        let sort = self.sorts.fetch_add(1, AtomicOrdering::SeqCst) + 1;
+        let len = self.items_len();
+        self.sorting = sorting.clone().map(|sorting| (sorting, len));
+        let sorting = match sorting {
+            Some(sorting) => sorting,
+            None => {
+                *self.order.write().unwrap() = None;
+                return;
+            }
+        };
+
+        let content = self.content.clone();
+        let order = self.order.clone();
+        let sorts = self.sorts.clone();
+        let library = self.library.clone();
+        thread::spawn(move || {
+            let tracks: Vec<Option<Track>> = content
+                .read()
+                .unwrap()
+                .iter()
+                .map(ListItem::track)
+                .collect();
+            let sorted = sorted_order(&tracks, &sorting.key, &sorting.direction);
+            if sorts.load(AtomicOrdering::SeqCst) == sort {
+                *order.write().unwrap() = Some(sorted);
+                library.trigger_redraw();
+            }
+        });
+    }
+
+    /// Whether the content is a list of tracks, which can be sorted.
+    fn is_sortable(&self) -> bool {
+        let content = self.content.read().unwrap();
+        let any = &(*content) as &dyn std::any::Any;
+        !self.fixed_order && (any.is::<Vec<Playable>>() || any.is::<Vec<Track>>())
     }
 
     pub fn get_indexes_of(&self, query: &str) -> Vec<usize> {
@@ -141,6 +393,7 @@ impl<I: ListItem + Clone> ListView<I> {
         content
             .iter()
             .enumerate()
+            .filter(|(index, _)| self.is_shown(*index))
             .filter(|(_, i)| {
                 i.display_left(self.library.clone())
                     .to_lowercase()
@@ -153,7 +406,13 @@ impl<I: ListItem + Clone> ListView<I> {
     pub fn move_focus_to(&mut self, target: usize) {
         let len = self.content_len(false).saturating_sub(1);
         self.selected = min(target, len);
-        self.scroller.scroll_to_y(self.selected);
+        let starts = self.disc_starts();
+        let row = row_of(&starts, self.selected);
+        // Keep the heading of a disc in view along with its first item
+        if starts.iter().any(|&(start, _)| start == self.selected) {
+            self.scroller.scroll_to_y(row - 1);
+        }
+        self.scroller.scroll_to_y(row);
     }
 
     pub fn move_focus(&mut self, delta: i32) {
@@ -161,7 +420,172 @@ impl<I: ListItem + Clone> ListView<I> {
         self.move_focus_to(max(new, 0) as usize);
     }
 
+    /// The positions of the items that start a disc, with the number of the
+    /// disc, which get a heading above them. Discs are only told apart while
+    /// the items are shown in their own order, and if there are several.
+    fn disc_starts(&self) -> Vec<(usize, i32)> {
+        if !self.disc_headings || self.filter.is_some() || self.order.read().unwrap().is_some() {
+            return Vec::new();
+        }
+        let mut starts: Vec<(usize, i32)> = Vec::new();
+        let content = self.content.read().unwrap();
+        for (position, item) in content.iter().enumerate() {
+            if let Some(disc) = item.disc_number() {
+                if starts.last().map_or(true, |&(_, last)| last != disc) {
+                    starts.push((position, disc));
+                }
+            }
+        }
+        if starts.len() > 1 {
+            starts
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// The position of the item shown in `row`, unless it's a heading.
+    fn position_at_row(&self, row: usize) -> Option<usize> {
+        match row_at(&self.disc_starts(), row) {
+            Row::Item(position) => Some(position),
+            Row::Disc(_) => None,
+        }
+    }
+
+    /// The position of the item at `index` of the content, as it is shown.
+    pub fn position_of(&self, index: usize) -> usize {
+        if let Some(filter) = &self.filter {
+            return filter
+                .shown
+                .iter()
+                .position(|&i| i == index)
+                .unwrap_or(index);
+        }
+        self.sorted_position_of(index)
+    }
+
+    /// The position of the item at `index` of the content in the sorted
+    /// list, whether it is filtered or not.
+    fn sorted_position_of(&self, index: usize) -> usize {
+        match self.order.read().unwrap().as_ref() {
+            Some(order) => order.iter().position(|&i| i == index).unwrap_or(index),
+            None => index,
+        }
+    }
+
+    /// Whether the item at `index` of the content is shown, i.e. not hidden
+    /// by the filter.
+    fn is_shown(&self, index: usize) -> bool {
+        self.filter.as_ref().map_or(true, |filter| {
+            filter.matches.get(index).copied().unwrap_or(false)
+        })
+    }
+
+    /// The position of the playing item as it is shown, if it is in the list.
+    fn playing_position(&self) -> Option<usize> {
+        let len = self.content_len(false);
+        let content = self.content.read().unwrap();
+        (0..len).find(|&position| {
+            content
+                .get(self.index_at(position))
+                .map_or(false, |item| item.is_playing(self.queue.clone()))
+        })
+    }
+
+    /// Only show the items whose title, artists or album contain `text`,
+    /// ignoring case and diacritics, or all of them again if it's empty.
+    pub fn set_filter(&mut self, text: &str) {
+        if text.is_empty() {
+            self.clear_filter();
+            return;
+        }
+        let selected = self.get_selected_index();
+        let filter = match self.filter.take() {
+            Some(filter) => ListFilter {
+                text: text.to_string(),
+                query: fold(text),
+                ..filter
+            },
+            None => ListFilter {
+                text: text.to_string(),
+                query: fold(text),
+                matched_query: String::new(),
+                matches: Vec::new(),
+                shown: Vec::new(),
+                previous: (self.selected, self.scroller.content_viewport().top_left()),
+            },
+        };
+        self.filter = Some(filter);
+        self.update_filter();
+        self.search_indexes.clear();
+
+        // Keep the selected item selected if it matches
+        let position = self
+            .filter
+            .as_ref()
+            .and_then(|filter| filter.shown.iter().position(|&i| i == selected))
+            .unwrap_or(0);
+        self.move_focus_to(position);
+    }
+
+    /// Show all items again, at the position they were shown at before
+    /// filtering.
+    pub fn clear_filter(&mut self) {
+        if let Some(filter) = self.filter.take() {
+            let (selected, offset) = filter.previous;
+            self.search_indexes.clear();
+            self.selected = min(selected, self.content_len(false).saturating_sub(1));
+            self.restore_offset = Some(offset);
+        }
+    }
+
+    /// Whether each item of the content matches the folded `query`.
+    fn filter_matches(&self, query: &str) -> Vec<bool> {
+        let content = self.content.read().unwrap();
+        matching(
+            query,
+            content.iter().map(|item| {
+                format!(
+                    "{} {}",
+                    item.display_left(self.library.clone()),
+                    item.display_center(self.library.clone())
+                )
+            }),
+        )
+    }
+
+    /// Match the items again once items were added or removed or the query
+    /// changed, and show the matching ones in the order the list is sorted
+    /// in.
+    fn update_filter(&mut self) {
+        let len = self.items_len();
+        let stale_query = match &self.filter {
+            Some(filter) => filter.is_stale(len).then(|| filter.query.clone()),
+            None => return,
+        };
+        if let Some(query) = stale_query {
+            let matches = self.filter_matches(&query);
+            if let Some(filter) = self.filter.as_mut() {
+                filter.matches = matches;
+                filter.matched_query = query;
+            }
+        }
+        let shown: Vec<usize> = (0..len)
+            .map(|position| self.sorted_index_at(position))
+            .filter(|&index| self.is_shown(index))
+            .collect();
+        if let Some(filter) = self.filter.as_mut() {
+            filter.shown = shown;
+        }
+        self.selected = min(self.selected, self.content_len(false).saturating_sub(1));
+    }
+
+    /// The text the list is filtered by, if it is.
+    pub fn filter_text(&self) -> Option<&str> {
+        self.filter.as_ref().map(|filter| filter.text.as_str())
+    }
+
     fn attempt_play_all_tracks(&self) -> bool {
+        let shown_len = self.content_len(false);
         let content = self.content.read().unwrap();
         let any = &(*content) as &dyn std::any::Any;
         let playables = any.downcast_ref::<Vec<Playable>>();
@@ -171,40 +595,239 @@ impl<I: ListItem + Clone> ListView<I> {
                 .collect::<Vec<Playable>>()
         });
         if let Some(tracks) = playables.or(tracks.as_ref()) {
-            let index = self.queue.append_next(tracks);
-            self.queue.play(index + self.selected, true, false);
+            // Play the tracks in the order they are shown, all of them even
+            // while they are filtered unless configured otherwise
+            let filtered_only = self
+                .library
+                .cfg
+                .values()
+                .play_filtered_only
+                .unwrap_or(false);
+            let (tracks, selected) = if self.filter.is_some() && !filtered_only {
+                let tracks: Vec<Playable> = (0..tracks.len())
+                    .filter_map(|position| tracks.get(self.sorted_index_at(position)).cloned())
+                    .collect();
+                (tracks, self.sorted_position_of(self.get_selected_index()))
+            } else {
+                let tracks: Vec<Playable> = (0..shown_len)
+                    .filter_map(|position| tracks.get(self.index_at(position)).cloned())
+                    .collect();
+                (tracks, self.selected)
+            };
+            let index = self.queue.append_next(&tracks);
+            self.queue.play(index + selected, true, false);
             true
         } else {
             false
         }
     }
 
-    pub fn remove(&self, index: usize) {
-        let mut c = self.content.write().unwrap();
-        c.remove(index);
+    /// Draw an item as its left, center and right text, the way items that
+    /// aren't shown in columns are.
+    fn draw_sides(
+        &self,
+        printer: &Printer,
+        item: &I,
+        i: usize,
+        style: ColorStyle,
+        row_style: Style,
+    ) {
+        let left = item.display_left(self.library.clone());
+        let center = item.display_center(self.library.clone());
+        let mut right = item.display_right(self.library.clone());
+        if let Some(added_at) = item.added_at().filter(|_| self.added_at) {
+            let added_at = added_at.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+            right = format!("{added_at}  {right}");
+        }
+        if let Some(id) = item.added_by().filter(|_| self.added_by) {
+            let name = self.library.user_name(&id).unwrap_or(id);
+            right = format!("{name}  {right}");
+        }
+        if let Some(popularity) = item.popularity().filter(|_| self.popularity) {
+            right = format!("{}  {right}", popularity_bar(popularity));
+        }
+        let draw_center = !center.is_empty();
+
+        // draw left string
+        printer.with_style(row_style, |printer| {
+            printer.print_hline((0, 0), printer.size.x, " ");
+            printer.print((0, 0), &left);
+        });
+
+        // if line contains search query match, draw on top with
+        // highlight color
+        self.draw_matches(printer, &left, 0, i, style, row_style);
+
+        // left string cut off indicator
+        let center_offset = printer.size.x / 2;
+        let left_max_length = if draw_center {
+            center_offset.saturating_sub(1)
+        } else {
+            printer.size.x.saturating_sub(right.width() + 1)
+        };
+
+        if left_max_length < left.width() {
+            let offset = left_max_length.saturating_sub(1);
+            printer.with_style(row_style, |printer| {
+                printer.print_hline((offset, 0), printer.size.x, " ");
+                printer.print((offset, 0), "..");
+            });
+        }
+
+        // draw center string
+        if draw_center {
+            printer.with_style(row_style, |printer| {
+                printer.print((center_offset, 0), &center);
+            });
+
+            // center string cut off indicator
+            let max_length = printer.size.x.saturating_sub(right.width() + 1);
+            if max_length < center_offset + center.width() {
+                let offset = max_length.saturating_sub(1);
+                printer.with_style(row_style, |printer| {
+                    printer.print((offset, 0), "..");
+                });
+            }
+        }
+
+        // draw right string
+        let offset = HAlign::Right.get_offset(right.width(), printer.size.x);
+
+        printer.with_style(row_style, |printer| {
+            printer.print((offset, 0), &right);
+        });
+    }
+
+    /// Draw a track in the configured columns, dropping those that don't fit.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_columns(
+        &self,
+        printer: &Printer,
+        columns: &[TrackColumn],
+        item: &I,
+        track: &Track,
+        i: usize,
+        style: ColorStyle,
+        row_style: Style,
+    ) {
+        printer.with_style(row_style, |printer| {
+            printer.print_hline((0, 0), printer.size.x, " ");
+        });
+        for column in columns::layout(columns, printer.size.x) {
+            let text = match column.kind {
+                ColumnKind::Number => (i + 1).to_string(),
+                ColumnKind::Title => track.title.clone(),
+                ColumnKind::Artist => track.artists.join(", "),
+                ColumnKind::Album => track.album.clone().unwrap_or_default(),
+                ColumnKind::AddedAt => item
+                    .added_at()
+                    .map(|added_at| {
+                        let added_at = added_at.with_timezone(&Local);
+                        added_at.format("%Y-%m-%d %H:%M").to_string()
+                    })
+                    .unwrap_or_default(),
+                ColumnKind::Duration => track.duration_str(),
+                ColumnKind::Popularity => item.popularity().map(popularity_bar).unwrap_or_default(),
+                ColumnKind::Saved => {
+                    if self.library.is_saved_track(&Playable::Track(track.clone())) {
+                        self.library.saved_indicator()
+                    } else {
+                        String::new()
+                    }
+                }
+            };
+            let text = columns::fit(&text, column.width);
+            let offset = if columns::right_aligned(column.kind) {
+                HAlign::Right.get_offset(text.width(), column.width)
+            } else {
+                0
+            };
+            let printer = printer
+                .offset((column.offset, 0))
+                .cropped((column.width, 1));
+            printer.with_style(row_style, |printer| {
+                printer.print((offset, 0), &text);
+            });
+            self.draw_matches(&printer, &text, offset, i, style, row_style);
+        }
+    }
+
+    /// Draw the matches of the search query in `text` again, highlighted.
+    fn draw_matches(
+        &self,
+        printer: &Printer,
+        text: &str,
+        offset: usize,
+        i: usize,
+        style: ColorStyle,
+        row_style: Style,
+    ) {
+        if !self.search_indexes.contains(&i) {
+            return;
+        }
+        let fg = *printer.theme.palette.custom("search_match").unwrap();
+        let matched_style = ColorStyle::new(fg, style.back);
+
+        let matches: Vec<(usize, usize)> = text
+            .to_lowercase()
+            .match_indices(&self.search_query)
+            .map(|i| (i.0, i.0 + i.1.len()))
+            .collect();
+
+        for m in matches {
+            printer.with_style(row_style.combine(matched_style), |printer| {
+                printer.print((offset + text[0..m.0].width(), 0), &text[m.0..m.1]);
+            });
+        }
     }
 }
 
 impl<I: ListItem + Clone> View for ListView<I> {
     fn draw(&self, printer: &Printer<'_, '_>) {
+        let len = self.content_len(false);
+        let starts = self.disc_starts();
         let content = self.content.read().unwrap();
 
-        scroll::draw_lines(self, printer, |_, printer, i| {
+        // The visible tracks are checked as they are drawn.
+        let below = self.scroller.content_viewport().bottom() + 1;
+        let ahead = (below..(below + LIKED_LOOKAHEAD_ROWS).min(len))
+            .filter_map(|i| content.get(self.index_at(i)))
+            .filter_map(|item| item.track().and_then(|track| track.id))
+            .collect();
+        self.library.check_liked(ahead);
+        let columns = columns::configured(&self.library.cfg.values(), self.columns_view);
+
+        scroll::draw_lines(self, printer, |_, printer, row| {
+            let i = match row_at(&starts, row) {
+                Row::Item(position) => position,
+                Row::Disc(disc) => {
+                    let style = Style::from(ColorStyle::secondary()).combine(Effect::Bold);
+                    printer.with_style(style, |printer| {
+                        printer.print((0, 0), &format!("Disc {disc}"));
+                    });
+                    return;
+                }
+            };
+
             // draw paginator after content
-            if i == content.len() && self.can_paginate() {
+            if i == len && self.can_paginate() {
                 let style = ColorStyle::secondary();
 
                 let max = self.pagination.max_content().unwrap();
-                let buf = format!("{} more items, scroll to load", max - i);
+                let buf = if self.pagination.is_busy() {
+                    "Loading…".to_string()
+                } else if self.pagination.has_failed() {
+                    "Could not load more items, scroll down to try again".to_string()
+                } else {
+                    format!("{} more items, scroll to load", max - i)
+                };
                 printer.with_color(style, |printer| {
                     printer.print((0, 0), &buf);
                 });
-            } else if i < content.len() {
-                let current_index = if self.order.read().unwrap().is_some() {
-                    self.order.read().unwrap().as_ref().unwrap()[i]
-                } else {
-                    i
-                };
+            } else if i < len {
+                let current_index = Some(self.index_at(i))
+                    .filter(|&index| index < content.len())
+                    .unwrap_or(i);
 
                 let item = &content[current_index];
 
@@ -231,74 +854,46 @@ impl<I: ListItem + Clone> View for ListView<I> {
                     ColorStyle::primary()
                 };
 
-                let left = item.display_left(self.library.clone());
-                let center = item.display_center(self.library.clone());
-                let right = item.display_right(self.library.clone());
-                let draw_center = !center.is_empty();
-
-                // draw left string
-                printer.with_color(style, |printer| {
-                    printer.print_hline((0, 0), printer.size.x, " ");
-                    printer.print((0, 0), &left);
-                });
-
-                // if line contains search query match, draw on top with
-                // highlight color
-                if self.search_indexes.contains(&i) {
-                    let fg = *printer.theme.palette.custom("search_match").unwrap();
-                    let matched_style = ColorStyle::new(fg, style.back);
-
-                    let matches: Vec<(usize, usize)> = left
-                        .to_lowercase()
-                        .match_indices(&self.search_query)
-                        .map(|i| (i.0, i.0 + i.1.len()))
-                        .collect();
-
-                    for m in matches {
-                        printer.with_color(matched_style, |printer| {
-                            printer.print((left[0..m.0].width(), 0), &left[m.0..m.1]);
-                        });
-                    }
-                }
-
-                // left string cut off indicator
-                let center_offset = printer.size.x / 2;
-                let left_max_length = if draw_center {
-                    center_offset.saturating_sub(1)
+                // Only albums of several items are set apart, so a list of
+                // single tracks isn't underlined throughout.
+                let album_at = |position: usize| {
+                    content
+                        .get(self.index_at(position))
+                        .and_then(ListItem::album_id)
+                };
+                let same_album = |a: usize, b: usize| {
+                    let album = album_at(a);
+                    album.is_some() && album == album_at(b)
+                };
+                let album_end = self.album_separators
+                    && i + 1 < len
+                    && !same_album(i, i + 1)
+                    && ((i > 0 && same_album(i - 1, i))
+                        || (i + 2 < len && same_album(i + 1, i + 2)));
+                let mut row_style = if album_end {
+                    Style::from(style).combine(Effect::Underline)
                 } else {
-                    printer.size.x.saturating_sub(right.width() + 1)
+                    Style::from(style)
                 };
-
-                if left_max_length < left.width() {
-                    let offset = left_max_length.saturating_sub(1);
-                    printer.with_color(style, |printer| {
-                        printer.print_hline((offset, 0), printer.size.x, " ");
-                        printer.print((offset, 0), "..");
-                    });
+                let marked = self.marked.contains(&current_index)
+                    || (self.in_visual(i) && item.track().is_some());
+                if marked {
+                    row_style = if self.selected == i {
+                        row_style.combine(Effect::Bold)
+                    } else {
+                        row_style.combine(ColorStyle::new(
+                            ColorType::Palette(PaletteColor::HighlightText),
+                            ColorType::Color(*printer.theme.palette.custom("marked_bg").unwrap()),
+                        ))
+                    };
                 }
 
-                // draw center string
-                if draw_center {
-                    printer.with_color(style, |printer| {
-                        printer.print((center_offset, 0), &center);
-                    });
-
-                    // center string cut off indicator
-                    let max_length = printer.size.x.saturating_sub(right.width() + 1);
-                    if max_length < center_offset + center.width() {
-                        let offset = max_length.saturating_sub(1);
-                        printer.with_color(style, |printer| {
-                            printer.print((offset, 0), "..");
-                        });
+                match columns.as_ref().zip(item.track()) {
+                    Some((columns, track)) => {
+                        self.draw_columns(printer, columns, item, &track, i, style, row_style)
                     }
+                    None => self.draw_sides(printer, item, i, style, row_style),
                 }
-
-                // draw right string
-                let offset = HAlign::Right.get_offset(right.width(), printer.size.x);
-
-                printer.with_color(style, |printer| {
-                    printer.print((offset, 0), &right);
-                });
             }
         });
     }
@@ -306,16 +901,35 @@ impl<I: ListItem + Clone> View for ListView<I> {
     fn layout(&mut self, size: Vec2) {
         self.last_size = size;
 
-        let relayout_scroller = self.content_len(false) != self.last_content_len;
-        self.last_content_len = self.content_len(true);
+        // Sort again once tracks were added or removed
+        if let Some((sorting, len)) = self.sorting.clone() {
+            if len != self.items_len() {
+                self.sort(Some(sorting));
+            }
+        }
+
+        if (!self.marked.is_empty() || self.visual.is_some()) && self.marked_len != self.items_len()
+        {
+            self.clear_marks();
+        }
+
+        self.update_filter();
+
+        let headings = self.disc_starts().len();
+        let relayout_scroller = self.content_len(false) + headings != self.last_content_len;
+        self.last_content_len = self.content_len(true) + headings;
 
         scroll::layout(
             self,
             size,
             relayout_scroller,
             |_, _| {},
-            |s, c| Vec2::new(c.x, s.content_len(true)),
+            |s, c| Vec2::new(c.x, s.content_len(true) + headings),
         );
+
+        if let Some(offset) = self.restore_offset.take() {
+            self.scroller.set_offset(offset);
+        }
     }
 
     fn needs_relayout(&self) -> bool {
@@ -328,6 +942,12 @@ impl<I: ListItem + Clone> View for ListView<I> {
 
     fn on_event(&mut self, e: Event) -> EventResult {
         match e {
+            Event::Key(Key::Esc) if !self.marked.is_empty() || self.visual.is_some() => {
+                self.clear_marks();
+            }
+            Event::Key(Key::Esc) if self.filter.is_some() => {
+                self.clear_filter();
+            }
             Event::Mouse {
                 event: MouseEvent::WheelUp,
                 ..
@@ -354,14 +974,19 @@ impl<I: ListItem + Clone> View for ListView<I> {
                 } else {
                     let viewport = self.scroller.content_viewport().top_left();
                     let selected_row = position.checked_sub(offset).map(|p| p.y + viewport.y);
-                    if let Some(y) = selected_row.filter(|row| row < &self.content_len(false)) {
+                    if let Some(y) = selected_row
+                        .and_then(|row| self.position_at_row(row))
+                        .filter(|position| position < &self.content_len(false))
+                    {
                         self.move_focus_to(y);
 
                         let queue = self.queue.clone();
                         let library = self.library.clone();
                         if let Some(target) = {
                             let content = self.content.read().unwrap();
-                            content.get(self.selected).map(|t| t.as_listitem())
+                            content
+                                .get(self.get_selected_index())
+                                .map(|t| t.as_listitem())
                         } {
                             if let Some(view) = target.open(queue, library) {
                                 return EventResult::Consumed(Some(Callback::from_fn_once(
@@ -381,14 +1006,19 @@ impl<I: ListItem + Clone> View for ListView<I> {
             } => {
                 let viewport = self.scroller.content_viewport().top_left();
                 let selected_row = position.checked_sub(offset).map(|p| p.y + viewport.y);
-                if let Some(y) = selected_row.filter(|row| row < &self.content_len(false)) {
+                if let Some(y) = selected_row
+                    .and_then(|row| self.position_at_row(row))
+                    .filter(|position| position < &self.content_len(false))
+                {
                     self.move_focus_to(y);
 
                     let queue = self.queue.clone();
                     let library = self.library.clone();
                     if let Some(target) = {
                         let content = self.content.read().unwrap();
-                        content.get(self.selected).map(|t| t.as_listitem())
+                        content
+                            .get(self.get_selected_index())
+                            .map(|t| t.as_listitem())
                     } {
                         let contextmenu = ContextMenu::new(&*target, queue, library);
                         return EventResult::Consumed(Some(Callback::from_fn_once(move |s| {
@@ -423,7 +1053,7 @@ impl<I: ListItem + Clone> View for ListView<I> {
 
     fn important_area(&self, view_size: Vec2) -> Rect {
         if self.content_len(false) > 0 {
-            Rect::from_point((view_size.x, self.selected))
+            Rect::from_point((view_size.x, row_of(&self.disc_starts(), self.selected)))
         } else {
             Rect::from_point((0, 0))
         }
@@ -432,27 +1062,145 @@ impl<I: ListItem + Clone> View for ListView<I> {
 
 impl<I: ListItem + Clone> ViewExt for ListView<I> {
     fn title(&self) -> String {
-        self.title.clone()
+        let title = match self.sorting() {
+            Some(sorting) if !self.title.is_empty() => {
+                format!("{} {}", self.title, sorting_title(sorting))
+            }
+            _ => self.title.clone(),
+        };
+        match self.filter_text() {
+            Some(text) if !title.is_empty() => format!("{title} /{text}"),
+            _ => title,
+        }
     }
 
-    fn on_command(&mut self, _s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         match cmd {
+            Command::Sort(key, direction) if self.is_sortable() => {
+                let key = match key {
+                    Some(key) => Some(key.clone()),
+                    None => next_sort_key(self.sorting().map(|sorting| &sorting.key)),
+                };
+                self.sort(key.map(|key| SortingOrder {
+                    key,
+                    direction: direction.clone(),
+                }));
+                self.move_focus_to(0);
+                return Ok(CommandResult::Consumed(None));
+            }
+            Command::Mark(MarkMode::Toggle | MarkMode::Visual) => {
+                let index = self.get_selected_index();
+                let is_track = {
+                    let content = self.content.read().unwrap();
+                    content.get(index).and_then(ListItem::track).is_some()
+                };
+                if matches!(cmd, Command::Mark(MarkMode::Visual)) {
+                    match self.visual {
+                        // Keep the selected tracks marked, so that more can be
+                        // selected elsewhere
+                        Some(_) => {
+                            self.marked.extend(self.marked());
+                            self.visual = None;
+                        }
+                        None if is_track => self.visual = Some(self.selected),
+                        None => return Err("Only tracks can be marked".into()),
+                    }
+                } else {
+                    if !is_track {
+                        return Err("Only tracks can be marked".into());
+                    }
+                    if !self.marked.remove(&index) {
+                        self.marked.insert(index);
+                    }
+                    self.move_focus(1);
+                }
+                self.marked_len = self.items_len();
+                return Ok(CommandResult::Consumed(None));
+            }
+            Command::Mark(MarkMode::All) => {
+                let content = self.content.read().unwrap();
+                self.marked = (0..content.len())
+                    .filter(|&index| content[index].track().is_some())
+                    .collect();
+                self.marked_len = content.len();
+                self.visual = None;
+                return Ok(CommandResult::Consumed(None));
+            }
+            Command::Mark(MarkMode::Clear) => {
+                self.clear_marks();
+                return Ok(CommandResult::Consumed(None));
+            }
+            Command::AddToPlaylist(TargetMode::Selected) => {
+                let tracks: Vec<Track> = self
+                    .marked_tracks()
+                    .into_iter()
+                    .filter(|track| track.id.is_some())
+                    .collect();
+                if tracks.is_empty() {
+                    return Err("Only Spotify tracks can be added to a playlist".into());
+                }
+                let dialog = ContextMenu::add_track_dialog(
+                    self.library.clone(),
+                    self.queue.get_spotify(),
+                    tracks,
+                );
+                return Ok(CommandResult::Modal(Box::new(dialog)));
+            }
             Command::Play => {
                 self.queue.clear();
 
                 if !self.attempt_play_all_tracks() {
                     let mut content = self.content.write().unwrap();
-                    if let Some(item) = content.get_mut(self.selected) {
+                    if let Some(item) = content.get_mut(self.get_selected_index()) {
                         item.play(self.queue.clone());
                     }
                 }
 
                 return Ok(CommandResult::Consumed(None));
             }
+            Command::PlayNext if !self.marked().is_empty() => {
+                let tracks: Vec<Playable> = self
+                    .marked_tracks()
+                    .into_iter()
+                    .map(Playable::Track)
+                    .collect();
+                let count = tracks.len();
+                self.queue.insert_all_after_current(tracks);
+                self.clear_marks();
+                return Ok(CommandResult::Consumed(Some(queued_message(count))));
+            }
+            Command::Queue if !self.marked().is_empty() => {
+                let tracks = self.marked_tracks();
+                for track in tracks.iter() {
+                    self.queue.append(Playable::Track(track.clone()));
+                }
+                self.clear_marks();
+                return Ok(CommandResult::Consumed(Some(queued_message(tracks.len()))));
+            }
+            Command::Save | Command::Delete if !self.marked().is_empty() => {
+                let save = matches!(cmd, Command::Save);
+                let tracks: Vec<Track> = self
+                    .marked_tracks()
+                    .into_iter()
+                    .filter(|track| track.id.is_some())
+                    .collect();
+                let library = self.library.clone();
+                let action = if save { "Saving" } else { "Removing" };
+                change_in_batches(s, action.into(), tracks, move |batch| {
+                    let batch = batch.iter().collect();
+                    if save {
+                        library.save_tracks(batch, true)
+                    } else {
+                        library.unsave_tracks(batch, true)
+                    }
+                });
+                self.clear_marks();
+                return Ok(CommandResult::Consumed(None));
+            }
             Command::PlayNext => {
                 info!("played next");
                 let mut content = self.content.write().unwrap();
-                if let Some(item) = content.get_mut(self.selected) {
+                if let Some(item) = content.get_mut(self.get_selected_index()) {
                     item.play_next(self.queue.clone());
                 }
 
@@ -460,16 +1208,33 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
             }
             Command::Queue => {
                 let mut content = self.content.write().unwrap();
-                if let Some(item) = content.get_mut(self.selected) {
+                if let Some(item) = content.get_mut(self.get_selected_index()) {
                     item.queue(self.queue.clone());
                 }
 
                 return Ok(CommandResult::Consumed(None));
             }
+            Command::AddAlbum => {
+                let mut item = {
+                    let content = self.content.read().unwrap();
+                    content.get(self.get_selected_index()).cloned()
+                };
+
+                let album = item
+                    .as_mut()
+                    .and_then(|item| item.queue_album(self.queue.clone()));
+                return match album {
+                    Some(album) => Ok(CommandResult::Consumed(Some(format!(
+                        "Added {} to the queue",
+                        album.title
+                    )))),
+                    None => Err("The selected item has no album".into()),
+                };
+            }
             Command::Save => {
                 let mut item = {
                     let content = self.content.read().unwrap();
-                    content.get(self.selected).cloned()
+                    content.get(self.get_selected_index()).cloned()
                 };
 
                 if let Some(item) = item.as_mut() {
@@ -481,7 +1246,7 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
             Command::Delete => {
                 let mut item = {
                     let content = self.content.read().unwrap();
-                    content.get(self.selected).cloned()
+                    content.get(self.get_selected_index()).cloned()
                 };
 
                 if let Some(item) = item.as_mut() {
@@ -494,7 +1259,9 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
             Command::Share(mode) => {
                 let url = match mode {
                     TargetMode::Selected => self.content.read().ok().and_then(|content| {
-                        content.get(self.selected).and_then(ListItem::share_url)
+                        content
+                            .get(self.get_selected_index())
+                            .and_then(ListItem::share_url)
                     }),
                     TargetMode::Current => self
                         .queue
@@ -508,10 +1275,20 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
 
                 return Ok(CommandResult::Consumed(None));
             }
+            Command::Filter(text) => {
+                self.set_filter(text);
+                return Ok(CommandResult::Consumed(None));
+            }
             Command::Jump(mode) => match mode {
                 JumpMode::Query(query) => {
                     self.search_query = query.to_lowercase();
-                    self.search_indexes = self.get_indexes_of(query);
+                    let mut positions: Vec<usize> = self
+                        .get_indexes_of(query)
+                        .into_iter()
+                        .map(|index| self.position_of(index))
+                        .collect();
+                    positions.sort_unstable();
+                    self.search_indexes = positions;
                     self.search_selected_index = 0;
                     match self.search_indexes.first() {
                         Some(&index) => {
@@ -573,6 +1350,15 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
                         self.try_paginate();
                         return Ok(CommandResult::Consumed(None));
                     }
+                    MoveMode::Playing => {
+                        return Ok(CommandResult::Consumed(match self.playing_position() {
+                            Some(position) => {
+                                self.move_focus_to(position);
+                                None
+                            }
+                            None => Some("The playing track is not in this list".into()),
+                        }));
+                    }
                     _ => return Ok(CommandResult::Consumed(None)),
                 }
             }
@@ -583,7 +1369,9 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
                     TargetMode::Current => self.queue.get_current().map(|t| t.as_listitem()),
                     TargetMode::Selected => {
                         let content = self.content.read().unwrap();
-                        content.get(self.selected).map(|t| t.as_listitem())
+                        content
+                            .get(self.get_selected_index())
+                            .map(|t| t.as_listitem())
                     }
                 };
 
@@ -601,7 +1389,7 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
             }
             Command::Goto(mode) => {
                 let mut content = self.content.write().unwrap();
-                if let Some(item) = content.get_mut(self.selected) {
+                if let Some(item) = content.get_mut(self.get_selected_index()) {
                     let queue = self.queue.clone();
                     let library = self.library.clone();
 
@@ -631,6 +1419,17 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
                                 };
                             }
                         }
+                        GotoMode::Related => {
+                            let artist = item.artist().or_else(|| {
+                                item.artists()
+                                    .and_then(|artists| artists.into_iter().next())
+                            });
+                            if let Some(artist) = artist {
+                                let view = ArtistView::related(queue, library, &artist)
+                                    .into_boxed_view_ext();
+                                return Ok(CommandResult::View(view));
+                            }
+                        }
                     }
                 }
             }
@@ -697,7 +1496,9 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
                     TargetMode::Current => self.queue.get_current().map(|t| t.as_listitem()),
                     TargetMode::Selected => {
                         let content = self.content.read().unwrap();
-                        content.get(self.selected).map(|t| t.as_listitem())
+                        content
+                            .get(self.get_selected_index())
+                            .map(|t| t.as_listitem())
                     }
                 };
 
@@ -709,9 +1510,191 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
                     };
                 }
             }
+            Command::Info(TargetMode::Selected) => {
+                let track = {
+                    let content = self.content.read().unwrap();
+                    content
+                        .get(self.get_selected_index())
+                        .and_then(|t| t.track())
+                };
+                if let Some(track) = track {
+                    let info = TrackInfo::new(track, self.queue.clone(), self.library.clone());
+                    return Ok(CommandResult::Modal(Box::new(info)));
+                }
+            }
+            Command::Radio(TargetMode::Selected) => {
+                let target = {
+                    let content = self.content.read().unwrap();
+                    content
+                        .get(self.get_selected_index())
+                        .map(|t| t.as_listitem())
+                };
+                if let Some(mut target) = target {
+                    let message =
+                        radio::start(target.as_mut(), self.queue.clone(), self.library.clone())?;
+                    return Ok(CommandResult::Consumed(Some(message)));
+                }
+            }
             _ => {}
         };
 
         Ok(CommandResult::Ignored)
     }
 }
+
+/// How many tracks are changed with each request when changing many of them
+/// at once.
+const BATCH_SIZE: usize = 50;
+
+/// Apply `change` to `tracks` in batches in the background, stopping at the
+/// first batch it fails on. For more than one batch the progress is shown in
+/// the statusbar, described by `action`.
+pub fn change_in_batches<F>(s: &Cursive, action: String, tracks: Vec<Track>, mut change: F)
+where
+    F: FnMut(&[Track]) -> bool + Send + 'static,
+{
+    let sink = s.cb_sink().clone();
+    // Without a result to show, the changes are only drawn
+    let report = move |result: Option<Result<Option<String>, String>>| {
+        let sent = sink.send(Box::new(move |s: &mut Cursive| {
+            if let Some(result) = result {
+                s.on_layout(|_, mut layout| layout.set_result(result));
+            }
+        }));
+        if sent.is_err() {
+            error!("could not report the progress of a change to many tracks");
+        }
+    };
+    thread::spawn(move || {
+        let total = tracks.len();
+        let batches = total > BATCH_SIZE;
+        for (i, batch) in tracks.chunks(BATCH_SIZE).enumerate() {
+            let done = i * BATCH_SIZE;
+            if batches {
+                let progress = format!("{action}: {done}/{total} tracks…");
+                report(Some(Ok(Some(progress))));
+            }
+            if !change(batch) {
+                let failure = format!("{action}: failed after {done}/{total} tracks");
+                report(Some(Err(failure)));
+                return;
+            }
+        }
+        let done = format!("{action}: {total}/{total} tracks");
+        report(batches.then_some(Ok(Some(done))));
+    });
+}
+
+/// What is shown in a row of a list.
+enum Row {
+    /// The item at the position.
+    Item(usize),
+    /// The heading of the disc with the number.
+    Disc(i32),
+}
+
+/// What is shown in `row` of a list whose discs start at `starts`.
+fn row_at(starts: &[(usize, i32)], row: usize) -> Row {
+    for (headings, &(start, disc)) in starts.iter().enumerate() {
+        match row.cmp(&(start + headings)) {
+            Ordering::Equal => return Row::Disc(disc),
+            Ordering::Less => return Row::Item(row - headings),
+            Ordering::Greater => {}
+        }
+    }
+    Row::Item(row - starts.len())
+}
+
+/// The row of the item at `position` of a list whose discs start at
+/// `starts`.
+fn row_of(starts: &[(usize, i32)], position: usize) -> usize {
+    position
+        + starts
+            .iter()
+            .filter(|&&(start, _)| start <= position)
+            .count()
+}
+
+/// A bar of five cells, filled up by how popular an item is.
+fn popularity_bar(popularity: u32) -> String {
+    let filled = (popularity.min(100) as usize + 10) / 20;
+    format!("{}{}", "▮".repeat(filled), "▯".repeat(5 - filled))
+}
+
+fn queued_message(count: usize) -> String {
+    match count {
+        1 => "Added 1 track to the queue".into(),
+        _ => format!("Added {count} tracks to the queue"),
+    }
+}
+
+/// The key that `sort` without one sorts by after `key`, which is none after
+/// the last one.
+pub fn next_sort_key(key: Option<&SortKey>) -> Option<SortKey> {
+    match key {
+        None => Some(SortKey::Title),
+        Some(SortKey::Title) => Some(SortKey::Artist),
+        Some(SortKey::Artist) => Some(SortKey::Album),
+        Some(SortKey::Album) => Some(SortKey::Duration),
+        Some(SortKey::Duration) => Some(SortKey::Added),
+        Some(SortKey::Added) => Some(SortKey::Released),
+        Some(SortKey::Released) => None,
+    }
+}
+
+/// How the title of a sorted list tells what it's sorted by.
+pub fn sorting_title(sorting: &SortingOrder) -> String {
+    let arrow = match sorting.direction {
+        SortDirection::Ascending => "↑",
+        SortDirection::Descending => "↓",
+    };
+    format!("(by {} {arrow})", sorting.key)
+}
+
+#[cfg(test)]
+mod tests {
+    use cursive::Vec2;
+
+    use super::{matching, row_at, row_of, ListFilter, Row};
+
+    #[test]
+    fn disc_headings_take_their_own_rows() {
+        // Three tracks on disc 1, then two on disc 2
+        let starts = [(0, 1), (3, 2)];
+        let rows: Vec<String> = (0..7)
+            .map(|row| match row_at(&starts, row) {
+                Row::Item(position) => position.to_string(),
+                Row::Disc(disc) => format!("disc {disc}"),
+            })
+            .collect();
+        assert_eq!(rows, ["disc 1", "0", "1", "2", "disc 2", "3", "4"]);
+        assert_eq!(row_of(&starts, 0), 1);
+        assert_eq!(row_of(&starts, 3), 5);
+        assert_eq!(row_of(&[], 3), 3);
+    }
+
+    #[test]
+    fn filter_is_matched_again_when_stale() {
+        let texts = || ["Café del Mar".to_string(), "Mar de Cristal".to_string()].into_iter();
+        let mut filter = ListFilter {
+            text: "CAFE".into(),
+            query: "cafe".into(),
+            matched_query: String::new(),
+            matches: Vec::new(),
+            shown: Vec::new(),
+            previous: (0, Vec2::zero()),
+        };
+        assert!(filter.is_stale(2));
+        filter.matches = matching(&filter.query, texts());
+        filter.matched_query = filter.query.clone();
+        assert_eq!(filter.matches, [true, false]);
+        assert!(!filter.is_stale(2));
+
+        // Typing on changes the query but not the number of items
+        filter.query = "mar".into();
+        assert!(filter.is_stale(2));
+        assert_eq!(matching(&filter.query, texts()), [true, true]);
+        filter.matched_query = filter.query.clone();
+        assert!(filter.is_stale(3));
+    }
+}
@@ -0,0 +1,224 @@
+use std::sync::{Arc, RwLock};
+
+use cursive::theme::{ColorStyle, ColorType, Effect, PaletteColor};
+use cursive::{Cursive, Printer, Vec2, View};
+use unicode_width::UnicodeWidthStr;
+
+use crate::command::{Command, MoveAmount, MoveMode};
+use crate::commands::CommandResult;
+use crate::config::LyricsProvider;
+use crate::library::Library;
+use crate::lyrics::{self, Lyrics};
+use crate::model::playable::Playable;
+use crate::queue::Queue;
+use crate::traits::ViewExt;
+use crate::ui::columns::fit;
+
+#[derive(Clone)]
+enum State {
+    Loading,
+    Loaded(Lyrics),
+    Failed(String),
+}
+
+/// Shows the lyrics of the current track. Synced lyrics follow playback,
+/// keeping the current line centered, until the selection is moved.
+pub struct LyricsView {
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+    provider: LyricsProvider,
+    /// The URI of the track the lyrics are of, and how far they are.
+    lyrics: Arc<RwLock<Option<(String, State)>>>,
+    selected: usize,
+    /// Whether the selection follows the current line.
+    follow: bool,
+    scroll: usize,
+}
+
+impl LyricsView {
+    pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> Self {
+        let provider = library.cfg.values().lyrics_provider.unwrap_or_default();
+        Self {
+            queue,
+            library,
+            provider,
+            lyrics: Arc::new(RwLock::new(None)),
+            selected: 0,
+            follow: true,
+            scroll: 0,
+        }
+    }
+
+    /// Fetch the lyrics of the current track in the background, unless they
+    /// are of it already.
+    fn update(&mut self) {
+        let track = match self.queue.get_current() {
+            Some(Playable::Track(track)) => track,
+            Some(Playable::Episode(_)) | None => {
+                *self.lyrics.write().unwrap() = None;
+                return;
+            }
+        };
+        let mut lyrics = self.lyrics.write().unwrap();
+        if lyrics.as_ref().map(|(uri, _)| uri) == Some(&track.uri) {
+            return;
+        }
+        *lyrics = Some((track.uri.clone(), State::Loading));
+        self.selected = 0;
+        self.scroll = 0;
+        self.follow = true;
+
+        let shared = self.lyrics.clone();
+        let library = self.library.clone();
+        let api = self.queue.get_spotify().api;
+        let provider = self.provider;
+        std::thread::spawn(move || {
+            let state = match lyrics::fetch(&api, &track, provider) {
+                Ok(lyrics) => State::Loaded(lyrics),
+                Err(e) => State::Failed(e),
+            };
+            let mut lyrics = shared.write().unwrap();
+            // The track may have changed while these were fetched.
+            if let Some((uri, current)) = lyrics.as_mut() {
+                if *uri == track.uri {
+                    *current = state;
+                }
+            }
+            drop(lyrics);
+            library.trigger_redraw();
+        });
+    }
+
+    fn loaded(&self) -> Option<Lyrics> {
+        match self.lyrics.read().unwrap().as_ref() {
+            Some((_, State::Loaded(lyrics))) => Some(lyrics.clone()),
+            _ => None,
+        }
+    }
+
+    fn line_count(&self) -> usize {
+        match self.loaded() {
+            Some(Lyrics::Lines(lines)) => lines.len(),
+            _ => 0,
+        }
+    }
+
+    fn current_line(&self, lyrics: &Lyrics) -> Option<usize> {
+        let position = self.queue.get_spotify().get_current_progress();
+        lyrics.current_line(position.as_millis() as u32)
+    }
+}
+
+impl View for LyricsView {
+    fn draw(&self, printer: &Printer<'_, '_>) {
+        let message = match self.lyrics.read().unwrap().as_ref() {
+            None => "Nothing is playing".to_string(),
+            Some((_, State::Loading)) => "Loading lyrics…".to_string(),
+            Some((_, State::Failed(e))) => format!("Could not fetch lyrics: {e}"),
+            Some((_, State::Loaded(Lyrics::NotFound))) => "No lyrics found".to_string(),
+            Some((_, State::Loaded(Lyrics::Instrumental))) => "♪ Instrumental ♪".to_string(),
+            Some((_, State::Loaded(lyrics @ Lyrics::Lines(lines)))) => {
+                let current = self.current_line(lyrics);
+                let visible = lines.iter().enumerate().skip(self.scroll);
+                for (y, (index, line)) in visible.take(printer.size.y).enumerate() {
+                    let text = fit(&line.text, printer.size.x);
+                    let x = printer.size.x.saturating_sub(text.width()) / 2;
+                    let style = if index == self.selected && !self.follow {
+                        ColorStyle::highlight()
+                    } else if Some(index) == current {
+                        ColorStyle::new(
+                            ColorType::Color(*printer.theme.palette.custom("playing").unwrap()),
+                            ColorType::Palette(PaletteColor::Background),
+                        )
+                    } else {
+                        ColorStyle::primary()
+                    };
+                    printer.with_color(style, |printer| {
+                        if Some(index) == current {
+                            printer
+                                .with_effect(Effect::Bold, |printer| printer.print((x, y), &text));
+                        } else {
+                            printer.print((x, y), &text);
+                        }
+                    });
+                }
+                return;
+            }
+        };
+        let text = fit(&message, printer.size.x);
+        let x = printer.size.x.saturating_sub(text.width()) / 2;
+        printer.print((x, printer.size.y / 2), &text);
+    }
+
+    fn layout(&mut self, size: Vec2) {
+        self.update();
+
+        let lyrics = match self.loaded() {
+            Some(lyrics @ Lyrics::Lines(_)) => lyrics,
+            _ => return,
+        };
+        let count = self.line_count();
+        if self.follow {
+            if let Some(current) = self.current_line(&lyrics) {
+                self.selected = current;
+            }
+        }
+        // Synced lyrics keep the selection centered, plain ones only scroll
+        // when it leaves the screen.
+        if lyrics.synced() {
+            self.scroll = self.selected.saturating_sub(size.y / 2);
+        } else if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + size.y {
+            self.scroll = self.selected + 1 - size.y;
+        }
+        self.scroll = self.scroll.min(count.saturating_sub(size.y));
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        constraint
+    }
+}
+
+impl ViewExt for LyricsView {
+    fn title(&self) -> String {
+        "Lyrics".to_string()
+    }
+
+    fn on_command(&mut self, _s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        let count = self.line_count();
+        match cmd {
+            Command::Move(MoveMode::Playing, _) => {
+                self.follow = true;
+                return Ok(CommandResult::Consumed(None));
+            }
+            Command::Move(mode @ (MoveMode::Up | MoveMode::Down), amount) if count > 0 => {
+                let step = match amount {
+                    MoveAmount::Integer(step) => *step as usize,
+                    MoveAmount::Extreme => count,
+                };
+                self.selected = match mode {
+                    MoveMode::Up => self.selected.saturating_sub(step),
+                    _ => (self.selected + step).min(count - 1),
+                };
+                self.follow = false;
+                return Ok(CommandResult::Consumed(None));
+            }
+            Command::Play => {
+                let time = match self.loaded() {
+                    Some(Lyrics::Lines(lines)) => {
+                        lines.get(self.selected).and_then(|line| line.time_ms)
+                    }
+                    _ => None,
+                };
+                if let Some(time) = time {
+                    self.queue.get_spotify().seek(time);
+                    self.follow = true;
+                    return Ok(CommandResult::Consumed(None));
+                }
+            }
+            _ => {}
+        }
+        Ok(CommandResult::Ignored)
+    }
+}
@@ -1,21 +1,30 @@
 pub mod album;
 pub mod artist;
 pub mod browse;
+pub mod columns;
 pub mod contextmenu;
+pub mod followed_artists;
 pub mod help;
+pub mod launcher;
 pub mod layout;
 pub mod library;
 pub mod listview;
+pub mod lyrics;
 pub mod modal;
+pub mod new_releases;
 pub mod pagination;
 pub mod playlist;
 pub mod playlists;
 pub mod queue;
+pub mod recently_played;
 pub mod search;
 pub mod search_results;
 pub mod show;
+pub mod stats;
 pub mod statusbar;
 pub mod tabview;
+pub mod top;
+pub mod trackinfo;
 
 #[cfg(feature = "cover")]
 pub mod cover;
@@ -0,0 +1,125 @@
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+use cursive::view::ViewWrapper;
+use cursive::Cursive;
+
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::library::Library;
+use crate::model::album::Album;
+use crate::queue::Queue;
+use crate::traits::ViewExt;
+use crate::ui::listview::ListView;
+use crate::ui::pagination::ApiResult;
+
+/// The albums that were released recently in the user's country. They are
+/// fetched once and kept until the library is updated.
+pub struct NewReleasesView {
+    list: ListView<Album>,
+    releases: ApiResult<Album>,
+    /// Whether only the releases by followed artists are shown.
+    followed_only: bool,
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+}
+
+impl NewReleasesView {
+    pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> Self {
+        let releases = queue.get_spotify().api.new_releases(library.country());
+        let list = ListView::new(releases.items.clone(), queue.clone(), library.clone());
+        releases.clone().apply_pagination(list.get_pagination());
+
+        Self {
+            list,
+            releases,
+            followed_only: false,
+            queue,
+            library,
+        }
+    }
+
+    fn refresh(&mut self) {
+        self.releases = self
+            .queue
+            .get_spotify()
+            .api
+            .new_releases(self.library.country());
+        self.show_releases();
+    }
+
+    /// Rebuild the list from the releases fetched so far. As the filtered
+    /// list can't be paginated, all releases are fetched in the background
+    /// and filtered once they are.
+    fn show_releases(&mut self) {
+        self.list = if self.followed_only {
+            let shown = Arc::new(RwLock::new(Vec::new()));
+            let list = ListView::new(shown.clone(), self.queue.clone(), self.library.clone());
+            // A separate result, so as not to race the pagination of the
+            // unfiltered list for the next page.
+            let api = self.queue.get_spotify().api;
+            let library = self.library.clone();
+            std::thread::spawn(move || {
+                let releases = api.new_releases(library.country());
+                while releases.next().is_some() {}
+                let followed: HashSet<String> = library
+                    .followed_artists
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .filter_map(|artist| artist.id.clone())
+                    .collect();
+                *shown.write().unwrap() = releases
+                    .items
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .filter(|album| album.artist_ids.iter().any(|id| followed.contains(id)))
+                    .cloned()
+                    .collect();
+                library.trigger_redraw();
+            });
+            list
+        } else {
+            let list = ListView::new(
+                self.releases.items.clone(),
+                self.queue.clone(),
+                self.library.clone(),
+            );
+            self.releases
+                .clone()
+                .apply_pagination(list.get_pagination());
+            list
+        };
+    }
+}
+
+impl ViewWrapper for NewReleasesView {
+    wrap_impl!(self.list: ListView<Album>);
+}
+
+impl ViewExt for NewReleasesView {
+    fn title(&self) -> String {
+        if self.followed_only {
+            "New Releases (followed)".to_string()
+        } else {
+            "New Releases".to_string()
+        }
+    }
+
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            Command::FollowedOnly => {
+                self.followed_only = !self.followed_only;
+                self.show_releases();
+                Ok(CommandResult::Consumed(None))
+            }
+            // The library is updated as well.
+            Command::UpdateLibrary => {
+                self.refresh();
+                Ok(CommandResult::Ignored)
+            }
+            _ => self.list.on_command(s, cmd),
+        }
+    }
+}
@@ -9,6 +9,7 @@ pub struct ApiPage<I> {
     pub items: Vec<I>,
 }
 pub type FetchPageFn<I> = dyn Fn(u32) -> Option<ApiPage<I>> + Send + Sync;
+#[derive(Clone)]
 pub struct ApiResult<I> {
     offset: Arc<RwLock<u32>>,
     limit: u32,
@@ -53,6 +54,8 @@ impl<I: ListItem + Clone> ApiResult<I> {
         (self.offset() + self.limit) >= self.total
     }
 
+    /// Let `pagination` load the next pages into `items`, which must be the
+    /// content of its list.
     pub fn apply_pagination(self, pagination: &Pagination<I>) {
         let total = self.total as usize;
         let fetched_items = self.items.read().unwrap().len();
@@ -60,44 +63,57 @@ impl<I: ListItem + Clone> ApiResult<I> {
             fetched_items,
             total,
             Box::new(move |_| {
-                self.next();
+                if self.at_end() {
+                    Some(Vec::new())
+                } else {
+                    self.fetch_next()
+                }
             }),
         )
     }
 
     pub fn next(&self) -> Option<Vec<I>> {
-        let offset = self.offset() + self.limit;
-        debug!("fetching next page at offset {}", offset);
-        if !self.at_end() {
-            if let Some(next_page) = (self.fetch_page)(offset) {
-                *self.offset.write().unwrap() = next_page.offset;
-                self.items.write().unwrap().extend(next_page.items.clone());
-                Some(next_page.items)
-            } else {
-                None
-            }
-        } else {
+        if self.at_end() {
             debug!("paginator is at end");
-            None
+            return None;
         }
+        let items = self.fetch_next()?;
+        self.items.write().unwrap().extend(items.clone());
+        Some(items)
+    }
+
+    /// Fetch the page after the current one without adding its items.
+    fn fetch_next(&self) -> Option<Vec<I>> {
+        let offset = self.offset() + self.limit;
+        debug!("fetching next page at offset {}", offset);
+        let next_page = (self.fetch_page)(offset)?;
+        *self.offset.write().unwrap() = next_page.offset;
+        Some(next_page.items)
     }
 }
 
-pub type Paginator<I> = Box<dyn Fn(Arc<RwLock<Vec<I>>>) + Send + Sync>;
+/// Fetch the page of items that follows the given ones, or `None` if it
+/// couldn't be fetched. An empty page ends the list.
+pub type Paginator<I> = Box<dyn Fn(Arc<RwLock<Vec<I>>>) -> Option<Vec<I>> + Send + Sync>;
 
 /// Manages the loading of ListItems, to increase performance and decrease
 /// memory usage.
 ///
 /// `loaded_content`: The amount of currently loaded items
 /// `max_content`: The maximum amount of items
-/// `callback`: TODO: document
-/// `busy`: TODO: document
+/// `callback`: Fetches the next page, see [Paginator]
+/// `busy`: Whether a page is being fetched, so that only one is at a time
+/// `failed`: Whether fetching the last page failed
+/// `generation`: Changes whenever the list is replaced, so that pages that
+/// were fetched for the previous one are dropped
 #[derive(Clone)]
 pub struct Pagination<I: ListItem> {
     loaded_content: Arc<RwLock<usize>>,
     max_content: Arc<RwLock<Option<usize>>>,
     callback: Arc<RwLock<Option<Paginator<I>>>>,
     busy: Arc<RwLock<bool>>,
+    failed: Arc<RwLock<bool>>,
+    generation: Arc<RwLock<usize>>,
 }
 
 impl<I: ListItem> Default for Pagination<I> {
@@ -107,21 +123,38 @@ impl<I: ListItem> Default for Pagination<I> {
             max_content: Arc::new(RwLock::new(None)),
             callback: Arc::new(RwLock::new(None)),
             busy: Arc::new(RwLock::new(false)),
+            failed: Arc::new(RwLock::new(false)),
+            generation: Arc::new(RwLock::new(0)),
         }
     }
 }
 
 impl<I: ListItem + Clone> Pagination<I> {
     pub fn clear(&mut self) {
+        self.reset();
         *self.max_content.write().unwrap() = None;
         *self.callback.write().unwrap() = None;
     }
     pub fn set(&self, loaded_content: usize, max_content: usize, callback: Paginator<I>) {
+        self.reset();
         *self.loaded_content.write().unwrap() = loaded_content;
         *self.max_content.write().unwrap() = Some(max_content);
         *self.callback.write().unwrap() = Some(callback);
     }
 
+    /// Forget about the page that's being fetched, as the list is replaced.
+    fn reset(&self) {
+        *self.generation.write().unwrap() += 1;
+        *self.busy.write().unwrap() = false;
+        *self.failed.write().unwrap() = false;
+    }
+
+    /// Update the maximum amount of items, for sources that only tell
+    /// whether there are more items once a page was fetched.
+    pub fn set_max_content(&self, max_content: usize) {
+        *self.max_content.write().unwrap() = Some(max_content);
+    }
+
     pub fn loaded_content(&self) -> usize {
         *self.loaded_content.read().unwrap()
     }
@@ -130,24 +163,60 @@ impl<I: ListItem + Clone> Pagination<I> {
         *self.max_content.read().unwrap()
     }
 
-    fn is_busy(&self) -> bool {
+    pub fn is_busy(&self) -> bool {
         *self.busy.read().unwrap()
     }
 
+    pub fn has_failed(&self) -> bool {
+        *self.failed.read().unwrap()
+    }
+
+    fn generation(&self) -> usize {
+        *self.generation.read().unwrap()
+    }
+
+    /// Fetch the next page in the background and add it to `content`, unless
+    /// one is being fetched already. After a failure, this tries again.
     pub fn call(&self, content: &Arc<RwLock<Vec<I>>>, library: Arc<Library>) {
         let pagination = self.clone();
         let content = content.clone();
         if !self.is_busy() {
             *self.busy.write().unwrap() = true;
+            *self.failed.write().unwrap() = false;
+            let generation = self.generation();
+            library.trigger_redraw();
             std::thread::spawn(move || {
-                let cb = pagination.callback.read().unwrap();
-                if let Some(ref cb) = *cb {
-                    debug!("calling paginator!");
-                    cb(content.clone());
-                    *pagination.loaded_content.write().unwrap() = content.read().unwrap().len();
-                    *pagination.busy.write().unwrap() = false;
-                    library.trigger_redraw();
+                let offset = content.read().unwrap().len();
+                let page = match *pagination.callback.read().unwrap() {
+                    Some(ref cb) => {
+                        debug!("calling paginator at offset {}", offset);
+                        cb(content.clone())
+                    }
+                    None => {
+                        *pagination.busy.write().unwrap() = false;
+                        return;
+                    }
+                };
+                if pagination.generation() != generation {
+                    debug!("dropping page fetched for a replaced list");
+                    return;
+                }
+                match page {
+                    Some(page) => {
+                        let mut content = content.write().unwrap();
+                        if page.is_empty() {
+                            *pagination.max_content.write().unwrap() = Some(content.len());
+                        }
+                        // Items may have been removed meanwhile, the page still
+                        // follows the ones it was fetched after.
+                        let at = offset.min(content.len());
+                        content.splice(at..at, page);
+                        *pagination.loaded_content.write().unwrap() = content.len();
+                    }
+                    None => *pagination.failed.write().unwrap() = true,
                 }
+                *pagination.busy.write().unwrap() = false;
+                library.trigger_redraw();
             });
         }
     }
@@ -1,10 +1,13 @@
+use std::cmp::min;
 use std::sync::{Arc, RwLock};
 
-use cursive::view::ViewWrapper;
+use cursive::view::{Margins, ViewWrapper};
+use cursive::views::Dialog;
 use cursive::Cursive;
 
-use crate::command::Command;
+use crate::command::{Command, PlaylistAction, ShiftMode};
 use crate::commands::CommandResult;
+use crate::ext_traits::CursiveExt;
 use crate::library::Library;
 use crate::model::playable::Playable;
 use crate::model::playlist::Playlist;
@@ -12,14 +15,15 @@ use crate::queue::Queue;
 use crate::spotify::Spotify;
 
 use crate::traits::ViewExt;
-use crate::ui::listview::ListView;
+use crate::ui::listview::{sorting_title, ListView};
+use crate::ui::modal::Modal;
 
 pub struct PlaylistView {
-    playlist: Playlist,
+    playlist: Arc<RwLock<Playlist>>,
+    tracks: Arc<RwLock<Vec<Playable>>>,
     list: ListView<Playable>,
     spotify: Spotify,
     library: Arc<Library>,
-    queue: Arc<Queue>,
 }
 
 impl PlaylistView {
@@ -27,10 +31,6 @@ impl PlaylistView {
         let mut playlist = playlist.clone();
         playlist.load_tracks(queue.get_spotify());
 
-        if let Some(order) = library.cfg.state().playlist_orders.get(&playlist.id) {
-            playlist.sort(&order.key, &order.direction);
-        }
-
         let tracks = if let Some(t) = playlist.tracks.as_ref() {
             t.clone()
         } else {
@@ -38,20 +38,220 @@ impl PlaylistView {
         };
 
         let spotify = queue.get_spotify();
-        let list = ListView::new(
-            Arc::new(RwLock::new(tracks)),
-            queue.clone(),
-            library.clone(),
-        );
+        let tracks = Arc::new(RwLock::new(tracks));
+        let mut list =
+            ListView::new(tracks.clone(), queue, library.clone()).with_columns_view("playlist");
+        if playlist.collaborative {
+            list = list.with_added_by();
+        }
+        let order = library
+            .cfg
+            .state()
+            .playlist_orders
+            .get(&playlist.id)
+            .cloned();
+        list.sort(order);
 
         Self {
-            playlist,
+            playlist: Arc::new(RwLock::new(playlist)),
+            tracks,
             list,
             spotify,
             library,
-            queue,
         }
     }
+
+    fn dedup_dialog(&self, duplicates: Vec<usize>) -> Modal<Dialog> {
+        let playlist = self.playlist.clone();
+        let tracks = self.tracks.clone();
+        let spotify = self.spotify.clone();
+        let library = self.library.clone();
+        let count = duplicates.len();
+        let dialog = Dialog::text(format!(
+            "Are you sure you want to remove {count} duplicate tracks from this playlist?"
+        ))
+        .padding(Margins::lrtb(1, 1, 1, 0))
+        .title("Remove duplicates")
+        .dismiss_button("No")
+        .button("Yes", move |s: &mut Cursive| {
+            let mut playlist = playlist.write().unwrap();
+            let deleted = playlist.delete_tracks(&duplicates, spotify.clone(), library.clone());
+            *tracks.write().unwrap() = playlist.tracks.clone().unwrap_or_default();
+            s.pop_layer();
+            if deleted < count {
+                s.add_layer(Dialog::info(format!(
+                    "Only {deleted} of {count} duplicate tracks could be removed"
+                )));
+            }
+        });
+        Modal::new(dialog)
+    }
+
+    /// Ask whether to remove only the tracks at `indices` or every
+    /// `occurrence` of them.
+    fn occurrences_dialog(&self, indices: Vec<usize>, occurrences: Vec<usize>) -> Modal<Dialog> {
+        let remove = {
+            let playlist = self.playlist.clone();
+            let tracks = self.tracks.clone();
+            let spotify = self.spotify.clone();
+            let library = self.library.clone();
+            move |s: &mut Cursive, indices: &[usize]| {
+                s.pop_layer();
+                let result = remove_tracks(
+                    &playlist,
+                    &tracks,
+                    indices,
+                    spotify.clone(),
+                    library.clone(),
+                );
+                if let Err(e) = result {
+                    s.add_layer(Dialog::info(e));
+                }
+            }
+        };
+        let remove = Arc::new(remove);
+        let this = match indices.len() {
+            1 => "This occurrence",
+            _ => "These occurrences",
+        };
+        let dialog = Dialog::text("The tracks to remove are listed more than once")
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .title("Remove tracks")
+            .dismiss_button("Cancel")
+            .button(this, {
+                let remove = remove.clone();
+                move |s| remove(s, &indices)
+            })
+            .button("All occurrences", move |s| remove(s, &occurrences));
+        Modal::new(dialog)
+    }
+
+    /// Refuse to change the tracks of a playlist that someone else owns,
+    /// unless it's collaborative.
+    fn check_writable(&self) -> Result<(), String> {
+        let playlist = self.playlist.read().unwrap();
+        if self.library.is_own_playlist(&playlist) || playlist.collaborative {
+            Ok(())
+        } else {
+            Err(format!("\"{}\" is read-only", playlist.name))
+        }
+    }
+
+    /// Move the track at `from` to `to`, showing it there right away and
+    /// moving it back if Spotify refuses.
+    fn move_track(&mut self, from: usize, to: usize) -> Result<CommandResult, String> {
+        self.check_writable()?;
+        if self.list.sorting().is_some() {
+            return Err("Tracks can't be moved while the playlist is sorted".into());
+        }
+
+        {
+            let mut tracks = self.tracks.write().unwrap();
+            let track = tracks.remove(from);
+            tracks.insert(to, track);
+        }
+        self.list.move_focus_to(to);
+
+        let mut playlist = self.playlist.write().unwrap();
+        let moved = playlist.move_track(from, to, self.spotify.clone(), self.library.clone());
+        *self.tracks.write().unwrap() = playlist.tracks.clone().unwrap_or_default();
+        if moved {
+            Ok(CommandResult::Consumed(None))
+        } else {
+            self.list.move_focus_to(from);
+            Err("Could not move the track, the playlist may have changed elsewhere".into())
+        }
+    }
+}
+
+/// Remove the tracks at `indices` from `playlist` and from the `tracks` that
+/// show it, putting back those that Spotify didn't remove.
+fn remove_tracks(
+    playlist: &RwLock<Playlist>,
+    tracks: &RwLock<Vec<Playable>>,
+    indices: &[usize],
+    spotify: Spotify,
+    library: Arc<Library>,
+) -> Result<(), String> {
+    {
+        let mut tracks = tracks.write().unwrap();
+        let mut index = 0;
+        tracks.retain(|_| {
+            index += 1;
+            !indices.contains(&(index - 1))
+        });
+    }
+
+    let mut playlist = playlist.write().unwrap();
+    let removed = playlist.delete_tracks(indices, spotify, library);
+    *tracks.write().unwrap() = playlist.tracks.clone().unwrap_or_default();
+    match indices.len() - removed {
+        0 => Ok(()),
+        1 if removed == 0 => {
+            Err("Could not remove the track, the playlist may have changed elsewhere".into())
+        }
+        failed => Err(format!(
+            "Could not remove {failed} of {} tracks, the playlist may have changed elsewhere",
+            indices.len()
+        )),
+    }
+}
+
+/// Ask whether to delete the playlist with `id`, and leave the view showing
+/// it if `close_view` is set.
+pub fn delete_dialog(library: Arc<Library>, id: String, close_view: bool) -> Modal<Dialog> {
+    let dialog = Dialog::text("Are you sure you want to delete this playlist?")
+        .padding(Margins::lrtb(1, 1, 1, 0))
+        .title("Delete playlist")
+        .dismiss_button("No")
+        .button("Yes", move |s: &mut Cursive| {
+            library.delete_playlist(&id);
+            s.pop_layer();
+            if close_view {
+                s.on_layout(|_, mut l| l.pop_view());
+            }
+        });
+    Modal::new(dialog)
+}
+
+/// Rename `playlist` or change its visibility. Creating and deleting
+/// playlists is left to the caller.
+pub fn change_playlist(
+    library: &Library,
+    playlist: &Playlist,
+    action: &PlaylistAction,
+) -> Result<CommandResult, String> {
+    let title = &playlist.name;
+    let (name, public, collaborative, message) = match action {
+        PlaylistAction::Rename(name) => (
+            Some(name.as_str()),
+            None,
+            None,
+            format!("Renamed \"{title}\" to \"{name}\""),
+        ),
+        PlaylistAction::Public => (
+            None,
+            Some(true),
+            Some(false),
+            format!("\"{title}\" is public"),
+        ),
+        PlaylistAction::Private => (
+            None,
+            Some(false),
+            Some(false),
+            format!("\"{title}\" is private"),
+        ),
+        // Collaborative playlists can't be public
+        PlaylistAction::Collaborative => (
+            None,
+            Some(false),
+            Some(true),
+            format!("\"{title}\" is collaborative"),
+        ),
+        PlaylistAction::New(_, _) | PlaylistAction::Delete => return Ok(CommandResult::Ignored),
+    };
+    library.change_playlist(&playlist.id, name, public, collaborative)?;
+    Ok(CommandResult::Consumed(Some(message)))
 }
 
 impl ViewWrapper for PlaylistView {
@@ -60,11 +260,15 @@ impl ViewWrapper for PlaylistView {
 
 impl ViewExt for PlaylistView {
     fn title(&self) -> String {
-        self.playlist.name.clone()
+        let name = self.playlist.read().unwrap().name.clone();
+        match self.list.sorting() {
+            Some(sorting) => format!("{name} {}", sorting_title(sorting)),
+            None => name,
+        }
     }
 
     fn title_sub(&self) -> String {
-        if let Some(tracks) = self.playlist.tracks.as_ref() {
+        if let Some(tracks) = self.playlist.read().unwrap().tracks.as_ref() {
             let duration_secs = tracks.iter().map(|p| p.duration() as u64 / 1000).sum();
             let duration = std::time::Duration::from_secs(duration_secs);
             format!(
@@ -79,37 +283,98 @@ impl ViewExt for PlaylistView {
 
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         if let Command::Delete = cmd {
-            let pos = self.list.get_selected_index();
-            if self
-                .playlist
-                .delete_track(pos, self.spotify.clone(), self.library.clone())
-            {
-                self.list.remove(pos);
+            self.check_writable()?;
+            let len = self.tracks.read().unwrap().len();
+            let indices: Vec<usize> = match self.list.marked() {
+                marked if marked.is_empty() => vec![self.list.get_selected_index()],
+                marked => marked,
+            }
+            .into_iter()
+            .filter(|&index| index < len)
+            .collect();
+            if indices.is_empty() {
+                return Ok(CommandResult::Consumed(None));
+            }
+
+            let occurrences = self.playlist.read().unwrap().occurrences(&indices);
+            if occurrences.len() > indices.len() {
+                let dialog = self.occurrences_dialog(indices, occurrences);
+                return Ok(CommandResult::Modal(Box::new(dialog)));
             }
+            remove_tracks(
+                &self.playlist,
+                &self.tracks,
+                &indices,
+                self.spotify.clone(),
+                self.library.clone(),
+            )?;
             return Ok(CommandResult::Consumed(None));
         }
 
-        if let Command::Sort(key, direction) = cmd {
-            self.library.cfg.with_state_mut(|mut state| {
-                let order = crate::config::SortingOrder {
-                    key: key.clone(),
-                    direction: direction.clone(),
-                };
-                state
-                    .playlist_orders
-                    .insert(self.playlist.id.clone(), order);
-            });
-
-            self.playlist.sort(key, direction);
-            let tracks = self.playlist.tracks.as_ref().unwrap_or(&Vec::new()).clone();
-            self.list = ListView::new(
-                Arc::new(RwLock::new(tracks)),
-                self.queue.clone(),
-                self.library.clone(),
-            );
+        if let Command::Shift(mode, amount) = cmd {
+            let len = self.tracks.read().unwrap().len();
+            if len == 0 {
+                return Ok(CommandResult::Consumed(None));
+            }
+            let from = self.list.get_selected_index();
+            let amount = amount.unwrap_or(1);
+            let to = match mode {
+                ShiftMode::Up => from.saturating_sub(amount.max(0) as usize),
+                ShiftMode::Down => min(from + amount.max(0) as usize, len - 1),
+                ShiftMode::To => min((amount.max(1) - 1) as usize, len - 1),
+            };
+            return self.move_track(from, to);
+        }
+
+        if let Command::Dedup = cmd {
+            let duplicates = self.playlist.read().unwrap().duplicates();
+            if duplicates.is_empty() {
+                return Ok(CommandResult::Consumed(Some(
+                    "There are no duplicate tracks in this playlist".into(),
+                )));
+            }
+            s.add_layer(self.dedup_dialog(duplicates));
             return Ok(CommandResult::Consumed(None));
         }
 
+        if let Command::Playlist(action) = cmd {
+            let playlist = self.playlist.read().unwrap().clone();
+            if let PlaylistAction::Delete = action {
+                let dialog = delete_dialog(self.library.clone(), playlist.id, true);
+                return Ok(CommandResult::Modal(Box::new(dialog)));
+            }
+            let result = change_playlist(&self.library, &playlist, action)?;
+            if let Some(changed) = self
+                .library
+                .playlists()
+                .iter()
+                .find(|changed| changed.id == playlist.id)
+            {
+                let mut playlist = self.playlist.write().unwrap();
+                playlist.name = changed.name.clone();
+                playlist.collaborative = changed.collaborative;
+            }
+            return Ok(result);
+        }
+
+        if let Command::Sort(_, _) = cmd {
+            let result = self.list.on_command(s, cmd);
+            // Remember the order of the playlist, which is only shown in it
+            let id = self.playlist.read().unwrap().id.clone();
+            let sorting = self.list.sorting().cloned();
+            self.library
+                .cfg
+                .with_state_mut(|mut state| match sorting.clone() {
+                    Some(order) => {
+                        state.playlist_orders.insert(id.clone(), order);
+                    }
+                    None => {
+                        state.playlist_orders.remove(&id);
+                    }
+                });
+            return result;
+        }
+
         self.list.on_command(s, cmd)
     }
 }
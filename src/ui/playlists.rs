@@ -1,55 +1,164 @@
-use std::sync::Arc;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
 
-use cursive::view::{Margins, ViewWrapper};
+use cursive::event::{Event, EventResult, Key};
+use cursive::view::{View, ViewWrapper};
 use cursive::views::Dialog;
-use cursive::Cursive;
+use cursive::{Cursive, Vec2};
 
-use crate::command::Command;
+use crate::command::{Command, JumpMode, PlaylistAction};
 use crate::commands::CommandResult;
 use crate::library::Library;
-use crate::model::playlist::Playlist;
+use crate::model::playlist_folder::PlaylistEntry;
 use crate::queue::Queue;
 use crate::traits::ViewExt;
 use crate::ui::listview::ListView;
 use crate::ui::modal::Modal;
+use crate::ui::playlist::{change_playlist, delete_dialog};
 
+/// The playlists in the folders of the user's rootlist. Folders are expanded
+/// with `l` or Enter and collapsed with `h`.
 pub struct PlaylistsView {
-    list: ListView<Playlist>,
+    list: ListView<PlaylistEntry>,
+    entries: Arc<RwLock<Vec<PlaylistEntry>>>,
     library: Arc<Library>,
+    /// A hash of what the entries were built from, to rebuild them when it
+    /// changes.
+    built_from: u64,
 }
 
 impl PlaylistsView {
     pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> Self {
-        Self {
-            list: ListView::new(library.playlists.clone(), queue, library.clone()),
+        let entries = Arc::new(RwLock::new(Vec::new()));
+        let mut view = Self {
+            list: ListView::new(entries.clone(), queue, library.clone()),
+            entries,
             library,
+            built_from: 0,
+        };
+        view.rebuild();
+        view
+    }
+
+    /// Rebuild the entries if the playlists, their folders or which folders
+    /// are expanded changed.
+    fn rebuild(&mut self) {
+        let playlists = self.library.playlists();
+        let rootlist = self.library.rootlist.read().unwrap();
+        let mut expanded: Vec<String> = self.library.expanded_folders().into_iter().collect();
+        expanded.sort();
+
+        let mut hasher = DefaultHasher::new();
+        for playlist in playlists.iter() {
+            (&playlist.id, &playlist.name, &playlist.snapshot_id).hash(&mut hasher);
+            playlist.num_tracks.hash(&mut hasher);
+        }
+        rootlist.hash(&mut hasher);
+        expanded.hash(&mut hasher);
+        let built_from = hasher.finish();
+
+        if built_from != self.built_from {
+            self.built_from = built_from;
+            *self.entries.write().unwrap() =
+                PlaylistEntry::build(&rootlist, &playlists, &expanded.into_iter().collect());
+        }
+    }
+
+    fn selected(&self) -> Option<PlaylistEntry> {
+        self.entries
+            .read()
+            .unwrap()
+            .get(self.list.get_selected_index())
+            .cloned()
+    }
+
+    /// Expand or collapse the selected folder. Collapsing a playlist in a
+    /// folder collapses that folder and selects it. Returns whether anything
+    /// changed.
+    fn set_expanded(&mut self, expand: bool) -> bool {
+        let index = self.list.get_selected_index();
+        match self.selected() {
+            Some(PlaylistEntry::Folder { id, expanded, .. }) if expanded != expand => {
+                self.library.set_folder_expanded(&id, expand);
+                self.rebuild();
+                true
+            }
+            Some(PlaylistEntry::Playlist { depth, .. }) if !expand && depth > 0 => {
+                let parent = self.entries.read().unwrap()[..index]
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .find_map(|(i, entry)| match entry {
+                        PlaylistEntry::Folder { id, depth: d, .. } if *d == depth - 1 => {
+                            Some((i, id.clone()))
+                        }
+                        _ => None,
+                    });
+                match parent {
+                    Some((parent, id)) => {
+                        self.library.set_folder_expanded(&id, false);
+                        self.rebuild();
+                        self.list.move_focus_to(parent);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            _ => false,
         }
     }
 
+    /// Expand the folders holding the playlists whose name matches `query`,
+    /// so that they can be jumped to.
+    fn expand_matches(&mut self, query: &str) {
+        let query = query.to_lowercase();
+        let folders: Vec<String> = {
+            let rootlist = self.library.rootlist.read().unwrap();
+            self.library
+                .playlists()
+                .iter()
+                .filter(|playlist| playlist.name.to_lowercase().contains(&query))
+                .flat_map(|playlist| PlaylistEntry::folders_of(&rootlist, &playlist.id))
+                .collect()
+        };
+        for id in folders {
+            self.library.set_folder_expanded(&id, true);
+        }
+        self.rebuild();
+    }
+
     pub fn delete_dialog(&mut self) -> Option<Modal<Dialog>> {
-        let playlists = self.library.playlists();
-        let current = playlists.get(self.list.get_selected_index());
-
-        if let Some(playlist) = current {
-            let library = self.library.clone();
-            let id = playlist.id.clone();
-            let dialog = Dialog::text("Are you sure you want to delete this playlist?")
-                .padding(Margins::lrtb(1, 1, 1, 0))
-                .title("Delete playlist")
-                .dismiss_button("No")
-                .button("Yes", move |s: &mut Cursive| {
-                    library.delete_playlist(&id);
-                    s.pop_layer();
-                });
-            Some(Modal::new(dialog))
-        } else {
-            None
+        match self.selected() {
+            Some(PlaylistEntry::Playlist { playlist, .. }) => {
+                Some(delete_dialog(self.library.clone(), playlist.id, false))
+            }
+            _ => None,
         }
     }
 }
 
 impl ViewWrapper for PlaylistsView {
-    wrap_impl!(self.list: ListView<Playlist>);
+    wrap_impl!(self.list: ListView<PlaylistEntry>);
+
+    fn wrap_layout(&mut self, size: Vec2) {
+        self.rebuild();
+        self.list.layout(size);
+    }
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        let expand = match event {
+            Event::Char('l') | Event::Key(Key::Right) => Some(true),
+            Event::Char('h') | Event::Key(Key::Left) => Some(false),
+            _ => None,
+        };
+        match expand {
+            Some(expand) if self.set_expanded(expand) => EventResult::consumed(),
+            // Leave moving between tabs to the library
+            Some(_) => EventResult::Ignored,
+            None => self.list.on_event(event),
+        }
+    }
 }
 
 impl ViewExt for PlaylistsView {
@@ -58,11 +167,34 @@ impl ViewExt for PlaylistsView {
     }
 
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
-        if let Command::Delete = cmd {
-            if let Some(dialog) = self.delete_dialog() {
-                s.add_layer(dialog);
+        match cmd {
+            Command::Delete => {
+                if let Some(dialog) = self.delete_dialog() {
+                    s.add_layer(dialog);
+                }
+                return Ok(CommandResult::Consumed(None));
+            }
+            Command::Play => {
+                if let Some(PlaylistEntry::Folder { id, expanded, .. }) = self.selected() {
+                    self.library.set_folder_expanded(&id, !expanded);
+                    self.rebuild();
+                    return Ok(CommandResult::Consumed(None));
+                }
+            }
+            Command::Jump(JumpMode::Query(query)) => self.expand_matches(query),
+            Command::Playlist(PlaylistAction::Delete) => {
+                return self.on_command(s, &Command::Delete);
+            }
+            Command::Playlist(PlaylistAction::New(_, _)) => {}
+            Command::Playlist(action) => {
+                return match self.selected() {
+                    Some(PlaylistEntry::Playlist { playlist, .. }) => {
+                        change_playlist(&self.library, &playlist, action)
+                    }
+                    _ => Err("Select a playlist to change".into()),
+                };
             }
-            return Ok(CommandResult::Consumed(None));
+            _ => {}
         }
 
         self.list.on_command(s, cmd)
@@ -24,7 +24,9 @@ pub struct QueueView {
 impl QueueView {
     pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> QueueView {
         let list = ListView::new(queue.queue.clone(), queue.clone(), library.clone())
-            .with_order(queue.get_random_order());
+            .with_order(queue.get_random_order())
+            .with_album_separators()
+            .with_columns_view("queue");
 
         QueueView {
             list,
@@ -49,7 +51,7 @@ impl QueueView {
                 s.pop_layer();
                 let edit = EditView::new()
                     .on_submit(move |s: &mut Cursive, name| {
-                        library.save_playlist(name, &tracks);
+                        library.save_playlist(name, &tracks, None);
                         s.pop_layer();
                     })
                     .with_name("name")
@@ -129,6 +131,16 @@ impl ViewExt for QueueView {
             Command::Queue => {
                 return Ok(CommandResult::Ignored);
             }
+            Command::Delete if !self.list.marked().is_empty() => {
+                let mut marked = self.list.marked();
+                marked.sort_unstable();
+                // Later items first, so the earlier indices stay the same
+                for index in marked.iter().rev() {
+                    self.queue.remove(*index);
+                }
+                self.list.move_focus(0);
+                return Ok(CommandResult::Consumed(None));
+            }
             Command::Delete => {
                 let selected = self.list.get_selected_index();
                 let len = self.queue.len();
@@ -161,17 +173,42 @@ impl ViewExt for QueueView {
                         self.list.move_focus(amount);
                         return Ok(CommandResult::Consumed(None));
                     }
+                    ShiftMode::To if len > 0 => {
+                        let target = min((amount.max(1) - 1) as usize, len - 1);
+                        self.queue.shift(selected, target);
+                        self.list.move_focus(target as i32 - selected as i32);
+                        return Ok(CommandResult::Consumed(None));
+                    }
                     _ => {}
                 }
             }
+            Command::Dedup => {
+                let removed = self.queue.dedup();
+                self.list.move_focus(0);
+                let message = match removed {
+                    1 => "Removed 1 duplicate track".to_string(),
+                    _ => format!("Removed {removed} duplicate tracks"),
+                };
+                return Ok(CommandResult::Consumed(Some(message)));
+            }
+            Command::Undo => {
+                return match self.queue.undo_dedup() {
+                    true => Ok(CommandResult::Consumed(Some(
+                        "Restored the removed duplicate tracks".into(),
+                    ))),
+                    false => Err("There are no removed duplicate tracks to restore".into()),
+                };
+            }
             Command::SaveQueue => {
                 let dialog = Self::save_dialog(self.queue.clone(), self.library.clone());
                 s.add_layer(dialog);
                 return Ok(CommandResult::Consumed(None));
             }
             Command::Move(MoveMode::Playing, _) => {
+                // Duplicates in the queue would all show as playing
                 if let Some(playing) = self.queue.get_current_index() {
-                    self.list.move_focus_to(playing);
+                    let position = self.list.position_of(playing);
+                    self.list.move_focus_to(position);
                 }
                 return Ok(CommandResult::Consumed(None));
             }
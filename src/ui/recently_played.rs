@@ -0,0 +1,217 @@
+use std::cmp::Reverse;
+use std::sync::{Arc, Mutex, RwLock};
+
+use chrono::{DateTime, Utc};
+use cursive::view::ViewWrapper;
+use cursive::Cursive;
+
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::history::History;
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::model::track::Track;
+use crate::queue::Queue;
+use crate::spotify_api::WebApi;
+use crate::traits::{ListItem, ViewExt};
+use crate::ui::listview::ListView;
+
+/// How many plays Spotify returns at once.
+const PAGE_SIZE: usize = 50;
+
+/// The items played on any device as told by Spotify, merged with the local
+/// history, which also has the episodes Spotify leaves out. Older plays are
+/// fetched page by page while scrolling, and updating the library starts over
+/// from the most recent ones.
+pub struct RecentlyPlayedView {
+    list: ListView<Playable>,
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+    history: Arc<History>,
+}
+
+impl RecentlyPlayedView {
+    pub fn new(queue: Arc<Queue>, library: Arc<Library>, history: Arc<History>) -> Self {
+        let list = Self::plays_list(&queue, &library, &history);
+        Self {
+            list,
+            queue,
+            library,
+            history,
+        }
+    }
+
+    fn plays_list(
+        queue: &Arc<Queue>,
+        library: &Arc<Library>,
+        history: &History,
+    ) -> ListView<Playable> {
+        let plays = Plays {
+            api: queue.get_spotify().api,
+            local: history.items.read().unwrap().clone(),
+            before: None,
+            remote_done: false,
+        };
+        let items = Arc::new(RwLock::new(Vec::new()));
+        let list = ListView::new(items.clone(), queue.clone(), library.clone()).with_added_at();
+
+        let pagination = list.get_pagination().clone();
+        let plays = Mutex::new(plays);
+        list.get_pagination().set(
+            0,
+            PAGE_SIZE,
+            Box::new(move |items| {
+                let mut plays = plays.lock().unwrap();
+                let last = items.read().unwrap().last().cloned();
+                let page = plays.next_page(last.as_ref());
+                let more = if plays.is_done() { 0 } else { PAGE_SIZE };
+                pagination.set_max_content(items.read().unwrap().len() + page.len() + more);
+                Some(page)
+            }),
+        );
+        // The first page is fetched in the background like the ones after it.
+        list.get_pagination().call(&items, library.clone());
+        list
+    }
+}
+
+/// The plays that are yet to be listed.
+struct Plays {
+    api: WebApi,
+    /// The items of the local history that are older than the plays listed
+    /// so far, most recent first.
+    local: Vec<Playable>,
+    /// When the oldest play fetched from Spotify so far was played, or
+    /// `None` before the first page.
+    before: Option<DateTime<Utc>>,
+    /// Whether Spotify has no older plays.
+    remote_done: bool,
+}
+
+impl Plays {
+    /// Whether Spotify has no older plays, so all of the local history was
+    /// listed.
+    fn is_done(&self) -> bool {
+        self.remote_done && self.local.is_empty()
+    }
+
+    /// The next page of plays from Spotify, merged with the local plays
+    /// from the same time. `last` is the play listed last.
+    fn next_page(&mut self, last: Option<&Playable>) -> Vec<Playable> {
+        let page = if self.remote_done {
+            None
+        } else {
+            self.api.current_user_recently_played(self.before)
+        };
+        let remote: Vec<Playable> = page
+            .as_ref()
+            .map(|page| {
+                page.items
+                    .iter()
+                    .map(|play| {
+                        let mut track = Track::from(&play.track);
+                        track.added_at = Some(play.played_at);
+                        Playable::Track(track)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let oldest = match page {
+            Some(page) if page.next.is_some() => remote.last().and_then(ListItem::added_at),
+            _ => None,
+        };
+        self.before = oldest;
+        self.remote_done = oldest.is_none();
+        merge_plays(remote, &mut self.local, oldest, last)
+    }
+}
+
+/// Merge the `remote` plays with the `local` ones played after `until`, or
+/// all of them if `None`, most recent first. Repeated plays of the same item
+/// are listed once, also when it was the `last` one listed before.
+fn merge_plays(
+    remote: Vec<Playable>,
+    local: &mut Vec<Playable>,
+    until: Option<DateTime<Utc>>,
+    last: Option<&Playable>,
+) -> Vec<Playable> {
+    let split = until.map_or(local.len(), |until| {
+        local
+            .iter()
+            .position(|play| play.added_at() < Some(until))
+            .unwrap_or(local.len())
+    });
+    let mut plays: Vec<Playable> = local.drain(..split).chain(remote).collect();
+    plays.sort_by_key(|play| Reverse(play.added_at()));
+
+    let mut last = last.map(Playable::uri);
+    plays.retain(|play| {
+        let repeated = last.as_ref() == Some(&play.uri());
+        last = Some(play.uri());
+        !repeated
+    });
+    plays
+}
+
+impl ViewWrapper for RecentlyPlayedView {
+    wrap_impl!(self.list: ListView<Playable>);
+}
+
+impl ViewExt for RecentlyPlayedView {
+    fn title(&self) -> String {
+        "Recently Played".to_string()
+    }
+
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            // The library is updated as well.
+            Command::UpdateLibrary => {
+                self.list = Self::plays_list(&self.queue, &self.library, &self.history);
+                Ok(CommandResult::Ignored)
+            }
+            _ => self.list.on_command(s, cmd),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use super::merge_plays;
+    use crate::model::playable::Playable;
+    use crate::model::track::Track;
+
+    fn at(minute: u32) -> Option<DateTime<Utc>> {
+        Some(Utc.with_ymd_and_hms(2023, 1, 1, 12, minute, 0).unwrap())
+    }
+
+    fn play(id: &str, minute: u32) -> Playable {
+        Playable::Track(Track {
+            id: Some(id.to_string()),
+            uri: format!("spotify:track:{id}"),
+            title: id.to_string(),
+            added_at: at(minute),
+            ..Default::default()
+        })
+    }
+
+    fn ids(plays: &[Playable]) -> Vec<String> {
+        plays.iter().filter_map(|play| play.id()).collect()
+    }
+
+    #[test]
+    fn test_merge_plays() {
+        let mut local = vec![play("a", 50), play("b", 40), play("c", 10)];
+        let remote = vec![play("a", 50), play("d", 45), play("d", 44), play("e", 30)];
+
+        // the local plays older than the page from Spotify are kept for later
+        let merged = merge_plays(remote, &mut local, at(30), None);
+        assert_eq!(ids(&merged), vec!["a", "d", "b", "e"]);
+        assert_eq!(ids(&local), vec!["c"]);
+
+        let merged = merge_plays(vec![play("e", 20)], &mut local, None, merged.last());
+        assert_eq!(ids(&merged), vec!["c"]);
+        assert!(local.is_empty());
+    }
+}
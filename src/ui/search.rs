@@ -3,8 +3,10 @@
 use cursive::direction::Orientation;
 use cursive::event::{AnyCb, Event, EventResult, Key};
 use cursive::traits::{Finder, Nameable, View};
-use cursive::view::{IntoBoxedView, Selector, ViewNotFound, ViewWrapper};
-use cursive::views::{EditView, NamedView, ViewRef};
+use cursive::view::{IntoBoxedView, Margins, Selector, ViewNotFound, ViewWrapper};
+use cursive::views::{
+    Dialog, EditView, LinearLayout, NamedView, OnEventView, ScrollView, SelectView, ViewRef,
+};
 use cursive::{Cursive, Printer, Vec2};
 use std::cell::RefCell;
 use std::sync::{Arc, Mutex, RwLock};
@@ -20,29 +22,53 @@ use crate::model::playlist::Playlist;
 use crate::model::show::Show;
 use crate::model::track::Track;
 use crate::queue::Queue;
+use crate::search_history::SearchHistory;
+use crate::search_query::SearchQuery;
 use crate::spotify::{Spotify, UriType};
 use crate::traits::{ListItem, ViewExt};
 use crate::ui::layout::Layout;
 use crate::ui::listview::ListView;
+use crate::ui::modal::Modal;
 use crate::ui::pagination::Pagination;
 use crate::ui::search_results::SearchResultsView;
 use crate::ui::tabview::TabView;
+use crate::utils::fuzzy_score;
 use rspotify::model::search::SearchResult;
 
 pub struct SearchView {
     edit: NamedView<EditView>,
     edit_focused: bool,
+    history: Arc<SearchHistory>,
+    /// The query of the history that was recalled, as an index into it.
+    recall: Option<usize>,
+    /// What was typed before recalling queries of the history.
+    draft: String,
 }
 
 pub const EDIT_ID: &str = "search_edit";
+const HISTORY_SELECT_ID: &str = "search_history_select";
 
 impl SearchView {
-    pub fn new(events: EventManager, queue: Arc<Queue>, library: Arc<Library>) -> SearchView {
+    pub fn new(
+        events: EventManager,
+        queue: Arc<Queue>,
+        library: Arc<Library>,
+        history: Arc<SearchHistory>,
+    ) -> SearchView {
         let searchfield = EditView::new()
-            .on_submit(move |s, input| {
-                if !input.is_empty() {
+            .on_submit({
+                let history = history.clone();
+                move |s, input| {
+                    if input.is_empty() {
+                        return;
+                    }
+                    history.add(input);
+                    // Filters can be typed like the arguments of `search`.
+                    let words: Vec<&str> = input.split_whitespace().collect();
+                    let query = SearchQuery::parse(&words)
+                        .unwrap_or_else(|_| SearchQuery::new(input.to_string()));
                     let results = SearchResultsView::new(
-                        input.to_string(),
+                        query,
                         events.clone(),
                         queue.clone(),
                         library.clone(),
@@ -55,15 +81,99 @@ impl SearchView {
         SearchView {
             edit: searchfield,
             edit_focused: true,
+            history,
+            recall: None,
+            draft: String::new(),
         }
     }
 
     pub fn clear(&mut self) {
+        self.recall = None;
         self.edit
             .call_on(&Selector::Name(EDIT_ID), |v: &mut EditView| {
                 v.set_content("");
             });
     }
+
+    /// Replace the query by an older or a more recent one of the history,
+    /// going back to what was typed after the most recent one.
+    fn recall(&mut self, older: bool) {
+        let queries = self.history.queries();
+        let next = match (self.recall, older) {
+            (None, true) => 0,
+            (Some(index), true) => index + 1,
+            (None, false) | (Some(0), false) => {
+                if self.recall.take().is_some() {
+                    let draft = std::mem::take(&mut self.draft);
+                    self.edit.get_mut().set_content(draft);
+                }
+                return;
+            }
+            (Some(index), false) => index - 1,
+        };
+        if let Some(query) = queries.get(next) {
+            let mut edit = self.edit.get_mut();
+            if self.recall.is_none() {
+                self.draft = edit.get_content().to_string();
+            }
+            edit.set_content(query.clone());
+            self.recall = Some(next);
+        }
+    }
+
+    /// A dialog to recall one of the queries of the history, which are
+    /// filtered by fuzzily matching them with what is typed.
+    fn history_dialog(history: &SearchHistory) -> OnEventView<Modal<Dialog>> {
+        let queries = history.queries();
+        let mut select: SelectView<String> = SelectView::new();
+        select.add_all_str(queries.iter().cloned());
+        select.set_on_submit(|s, query: &String| Self::recall_from_dialog(s, query));
+
+        let filter = EditView::new()
+            .on_edit(move |s, filter, _| {
+                s.call_on_name(HISTORY_SELECT_ID, |select: &mut SelectView<String>| {
+                    let mut matches: Vec<(usize, &String)> = queries
+                        .iter()
+                        .filter_map(|query| fuzzy_score(filter, query).map(|score| (score, query)))
+                        .collect();
+                    // Stable, so that equal matches stay most recent first
+                    matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+                    select.clear();
+                    select.add_all_str(matches.into_iter().map(|(_, query)| query.clone()));
+                });
+            })
+            .on_submit(|s, _| {
+                let selected = s
+                    .call_on_name(HISTORY_SELECT_ID, |select: &mut SelectView<String>| {
+                        select.selection()
+                    })
+                    .flatten();
+                if let Some(query) = selected {
+                    Self::recall_from_dialog(s, &query);
+                }
+            });
+
+        let dialog = Dialog::new()
+            .title("Search history")
+            .dismiss_button("Close")
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .content(
+                LinearLayout::vertical()
+                    .child(filter)
+                    .child(ScrollView::new(select.with_name(HISTORY_SELECT_ID))),
+            );
+        OnEventView::new(Modal::new(dialog)).on_event(Key::Esc, |s| {
+            s.pop_layer();
+        })
+    }
+
+    /// Close the history dialog and put `query` into the search field, to
+    /// be searched for or edited first.
+    fn recall_from_dialog(s: &mut Cursive, query: &str) {
+        s.pop_layer();
+        s.call_on_name(EDIT_ID, |edit: &mut EditView| edit.set_content(query));
+        let _ = s.focus_name(EDIT_ID);
+    }
 }
 
 impl View for SearchView {
@@ -85,6 +195,25 @@ impl View for SearchView {
             return EventResult::Consumed(None);
         } else if self.edit_focused && event == Event::Key(Key::Esc) {
             self.clear();
+        } else if self.edit_focused {
+            match event {
+                Event::Key(Key::Up) => {
+                    self.recall(true);
+                    return EventResult::Consumed(None);
+                }
+                Event::Key(Key::Down) => {
+                    self.recall(false);
+                    return EventResult::Consumed(None);
+                }
+                Event::CtrlChar('r') => {
+                    let dialog = Self::history_dialog(&self.history);
+                    return EventResult::with_cb_once(move |s| s.add_layer(dialog));
+                }
+                Event::Key(Key::Enter) => {
+                    self.recall = None;
+                }
+                _ => {}
+            }
         }
 
         if self.edit_focused {
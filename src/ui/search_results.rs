@@ -1,4 +1,4 @@
-use crate::command::Command;
+use crate::command::{Command, SearchFilterMode};
 use crate::commands::CommandResult;
 use crate::events::EventManager;
 use crate::library::Library;
@@ -9,6 +9,7 @@ use crate::model::playlist::Playlist;
 use crate::model::show::Show;
 use crate::model::track::Track;
 use crate::queue::Queue;
+use crate::search_query::{SearchCategory, SearchQuery};
 use crate::spotify::{Spotify, UriType};
 use crate::spotify_url::SpotifyUrl;
 use crate::traits::{ListItem, ViewExt};
@@ -22,7 +23,7 @@ use rspotify::model::SearchType;
 use std::sync::{Arc, RwLock};
 
 pub struct SearchResultsView {
-    search_term: String,
+    query: SearchQuery,
     results_tracks: Arc<RwLock<Vec<Track>>>,
     pagination_tracks: Pagination<Track>,
     results_albums: Arc<RwLock<Vec<Album>>>,
@@ -38,6 +39,8 @@ pub struct SearchResultsView {
     tabs: TabView,
     spotify: Spotify,
     events: EventManager,
+    queue: Arc<Queue>,
+    library: Arc<Library>,
 }
 
 type SearchHandler<I> =
@@ -45,7 +48,7 @@ type SearchHandler<I> =
 
 impl SearchResultsView {
     pub fn new(
-        search_term: String,
+        query: SearchQuery,
         events: EventManager,
         queue: Arc<Queue>,
         library: Arc<Library>,
@@ -57,7 +60,8 @@ impl SearchResultsView {
         let results_shows = Arc::new(RwLock::new(Vec::new()));
         let results_episodes = Arc::new(RwLock::new(Vec::new()));
 
-        let list_tracks = ListView::new(results_tracks.clone(), queue.clone(), library.clone());
+        let list_tracks = ListView::new(results_tracks.clone(), queue.clone(), library.clone())
+            .with_columns_view("search");
         let pagination_tracks = list_tracks.get_pagination().clone();
         let list_albums = ListView::new(results_albums.clone(), queue.clone(), library.clone());
         let pagination_albums = list_albums.get_pagination().clone();
@@ -68,19 +72,32 @@ impl SearchResultsView {
         let pagination_playlists = list_playlists.get_pagination().clone();
         let list_shows = ListView::new(results_shows.clone(), queue.clone(), library.clone());
         let pagination_shows = list_shows.get_pagination().clone();
-        let list_episodes = ListView::new(results_episodes.clone(), queue.clone(), library);
+        let list_episodes = ListView::new(results_episodes.clone(), queue.clone(), library.clone());
         let pagination_episodes = list_episodes.get_pagination().clone();
 
-        let tabs = TabView::new()
-            .tab("tracks", list_tracks.with_title("Tracks"))
-            .tab("albums", list_albums.with_title("Albums"))
-            .tab("artists", list_artists.with_title("Artists"))
-            .tab("playlists", list_playlists.with_title("Playlists"))
-            .tab("shows", list_shows.with_title("Podcasts"))
-            .tab("episodes", list_episodes.with_title("Podcast Episodes"));
+        // Only the categories that are searched for get a tab
+        let mut tabs = TabView::new();
+        if query.searches(SearchCategory::Tracks) {
+            tabs.add_tab("tracks", list_tracks.with_title("Tracks"));
+        }
+        if query.searches(SearchCategory::Albums) {
+            tabs.add_tab("albums", list_albums.with_title("Albums"));
+        }
+        if query.searches(SearchCategory::Artists) {
+            tabs.add_tab("artists", list_artists.with_title("Artists"));
+        }
+        if query.searches(SearchCategory::Playlists) {
+            tabs.add_tab("playlists", list_playlists.with_title("Playlists"));
+        }
+        if query.searches(SearchCategory::Shows) {
+            tabs.add_tab("shows", list_shows.with_title("Podcasts"));
+        }
+        if query.searches(SearchCategory::Episodes) {
+            tabs.add_tab("episodes", list_episodes.with_title("Podcast Episodes"));
+        }
 
         let mut view = SearchResultsView {
-            search_term,
+            query,
             results_tracks,
             pagination_tracks,
             results_albums,
@@ -96,6 +113,8 @@ impl SearchResultsView {
             tabs,
             spotify: queue.get_spotify(),
             events,
+            queue,
+            library,
         };
 
         view.run_search();
@@ -368,13 +387,13 @@ impl SearchResultsView {
             if let Some(mut paginator) = paginator {
                 let loaded_items = results.read().unwrap().len();
                 if total_items > loaded_items {
-                    let ev = ev.clone();
-
                     // paginator callback
                     let cb = move |items: Arc<RwLock<Vec<I>>>| {
                         let offset = items.read().unwrap().len();
-                        handler(&spotify, &results, &query, offset, true);
-                        ev.trigger();
+                        let page = Arc::new(RwLock::new(Vec::new()));
+                        let total = handler(&spotify, &page, &query, offset, false);
+                        let page = std::mem::take(&mut *page.write().unwrap());
+                        (total > 0).then_some(page)
                     };
                     paginator.set(loaded_items, total_items, Box::new(cb));
                 } else {
@@ -386,7 +405,7 @@ impl SearchResultsView {
     }
 
     pub fn run_search(&mut self) {
-        let query = self.search_term.clone();
+        let query = self.query.to_spotify();
 
         // check if API token refresh is necessary before commencing multiple
         // requests to avoid deadlock, as the parallel requests might
@@ -394,7 +413,7 @@ impl SearchResultsView {
         self.spotify.api.update_token();
 
         // is the query a Spotify URI?
-        if let Some(uritype) = UriType::from_uri(&query) {
+        if let Some(uritype) = UriType::from_uri(&query).filter(|_| !self.query.is_filtered()) {
             match uritype {
                 UriType::Track => {
                     self.perform_search(
@@ -403,7 +422,7 @@ impl SearchResultsView {
                         &query,
                         None,
                     );
-                    self.tabs.move_focus_to(0);
+                    self.tabs.move_focus_to_id("tracks");
                 }
                 UriType::Album => {
                     self.perform_search(
@@ -412,7 +431,7 @@ impl SearchResultsView {
                         &query,
                         None,
                     );
-                    self.tabs.move_focus_to(1);
+                    self.tabs.move_focus_to_id("albums");
                 }
                 UriType::Artist => {
                     self.perform_search(
@@ -421,7 +440,7 @@ impl SearchResultsView {
                         &query,
                         None,
                     );
-                    self.tabs.move_focus_to(2);
+                    self.tabs.move_focus_to_id("artists");
                 }
                 UriType::Playlist => {
                     self.perform_search(
@@ -430,7 +449,7 @@ impl SearchResultsView {
                         &query,
                         None,
                     );
-                    self.tabs.move_focus_to(3);
+                    self.tabs.move_focus_to_id("playlists");
                 }
                 UriType::Show => {
                     self.perform_search(
@@ -439,7 +458,7 @@ impl SearchResultsView {
                         &query,
                         None,
                     );
-                    self.tabs.move_focus_to(4);
+                    self.tabs.move_focus_to_id("shows");
                 }
                 UriType::Episode => {
                     self.perform_search(
@@ -448,12 +467,13 @@ impl SearchResultsView {
                         &query,
                         None,
                     );
-                    self.tabs.move_focus_to(5);
+                    self.tabs.move_focus_to_id("episodes");
                 }
             }
         // Is the query a spotify URL?
         // https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC
-        } else if let Some(url) = SpotifyUrl::from_url(&query) {
+        } else if let Some(url) = SpotifyUrl::from_url(&query).filter(|_| !self.query.is_filtered())
+        {
             match url.uri_type {
                 UriType::Track => {
                     self.perform_search(
@@ -462,7 +482,7 @@ impl SearchResultsView {
                         &url.id,
                         None,
                     );
-                    self.tabs.move_focus_to(0);
+                    self.tabs.move_focus_to_id("tracks");
                 }
                 UriType::Album => {
                     self.perform_search(
@@ -471,7 +491,7 @@ impl SearchResultsView {
                         &url.id,
                         None,
                     );
-                    self.tabs.move_focus_to(1);
+                    self.tabs.move_focus_to_id("albums");
                 }
                 UriType::Artist => {
                     self.perform_search(
@@ -480,7 +500,7 @@ impl SearchResultsView {
                         &url.id,
                         None,
                     );
-                    self.tabs.move_focus_to(2);
+                    self.tabs.move_focus_to_id("artists");
                 }
                 UriType::Playlist => {
                     self.perform_search(
@@ -489,7 +509,7 @@ impl SearchResultsView {
                         &url.id,
                         None,
                     );
-                    self.tabs.move_focus_to(3);
+                    self.tabs.move_focus_to_id("playlists");
                 }
                 UriType::Show => {
                     self.perform_search(
@@ -498,7 +518,7 @@ impl SearchResultsView {
                         &url.id,
                         None,
                     );
-                    self.tabs.move_focus_to(4);
+                    self.tabs.move_focus_to_id("shows");
                 }
                 UriType::Episode => {
                     self.perform_search(
@@ -507,46 +527,58 @@ impl SearchResultsView {
                         &url.id,
                         None,
                     );
-                    self.tabs.move_focus_to(5);
+                    self.tabs.move_focus_to_id("episodes");
                 }
             }
         } else {
-            self.perform_search(
-                Box::new(Self::search_track),
-                &self.results_tracks,
-                &query,
-                Some(&self.pagination_tracks),
-            );
-            self.perform_search(
-                Box::new(Self::search_album),
-                &self.results_albums,
-                &query,
-                Some(&self.pagination_albums),
-            );
-            self.perform_search(
-                Box::new(Self::search_artist),
-                &self.results_artists,
-                &query,
-                Some(&self.pagination_artists),
-            );
-            self.perform_search(
-                Box::new(Self::search_playlist),
-                &self.results_playlists,
-                &query,
-                Some(&self.pagination_playlists),
-            );
-            self.perform_search(
-                Box::new(Self::search_show),
-                &self.results_shows,
-                &query,
-                Some(&self.pagination_shows),
-            );
-            self.perform_search(
-                Box::new(Self::search_episode),
-                &self.results_episodes,
-                &query,
-                Some(&self.pagination_episodes),
-            );
+            if self.query.searches(SearchCategory::Tracks) {
+                self.perform_search(
+                    Box::new(Self::search_track),
+                    &self.results_tracks,
+                    &query,
+                    Some(&self.pagination_tracks),
+                );
+            }
+            if self.query.searches(SearchCategory::Albums) {
+                self.perform_search(
+                    Box::new(Self::search_album),
+                    &self.results_albums,
+                    &query,
+                    Some(&self.pagination_albums),
+                );
+            }
+            if self.query.searches(SearchCategory::Artists) {
+                self.perform_search(
+                    Box::new(Self::search_artist),
+                    &self.results_artists,
+                    &query,
+                    Some(&self.pagination_artists),
+                );
+            }
+            if self.query.searches(SearchCategory::Playlists) {
+                self.perform_search(
+                    Box::new(Self::search_playlist),
+                    &self.results_playlists,
+                    &query,
+                    Some(&self.pagination_playlists),
+                );
+            }
+            if self.query.searches(SearchCategory::Shows) {
+                self.perform_search(
+                    Box::new(Self::search_show),
+                    &self.results_shows,
+                    &query,
+                    Some(&self.pagination_shows),
+                );
+            }
+            if self.query.searches(SearchCategory::Episodes) {
+                self.perform_search(
+                    Box::new(Self::search_episode),
+                    &self.results_episodes,
+                    &query,
+                    Some(&self.pagination_episodes),
+                );
+            }
         }
     }
 }
@@ -557,9 +589,44 @@ impl ViewWrapper for SearchResultsView {
 
 impl ViewExt for SearchResultsView {
     fn title(&self) -> String {
-        format!("Search: {}", self.search_term)
+        match self.query.filters() {
+            filters if filters.is_empty() => format!("Search: {}", self.query.text),
+            filters => format!("Search: {} ({})", self.query.text, filters.join(", ")),
+        }
     }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        if let Command::SearchFilter(mode) = cmd {
+            let mut query = self.query.clone();
+            let focus = match mode {
+                SearchFilterMode::Only => match query.category.take() {
+                    Some(category) => Some(category),
+                    None => {
+                        query.category = self.tabs.selected_id().and_then(SearchCategory::from_id);
+                        None
+                    }
+                },
+                SearchFilterMode::Clear => {
+                    let focus = query.category;
+                    query = SearchQuery::new(query.text);
+                    focus
+                }
+            };
+            if query.is_empty() {
+                return Err("There is no text to search for without the filters".into());
+            }
+            *self = SearchResultsView::new(
+                query,
+                self.events.clone(),
+                self.queue.clone(),
+                self.library.clone(),
+            );
+            if let Some(category) = focus {
+                self.tabs.move_focus_to_id(category.id());
+            }
+            return Ok(CommandResult::Consumed(None));
+        }
+
         self.tabs.on_command(s, cmd)
     }
 }
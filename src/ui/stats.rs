@@ -0,0 +1,57 @@
+use cursive::theme::Effect;
+use cursive::utils::markup::StyledString;
+use cursive::view::ViewWrapper;
+use cursive::views::{ScrollView, TextView};
+use cursive::Cursive;
+
+use crate::command::{Command, StatsKind};
+use crate::commands::CommandResult;
+use crate::traits::ViewExt;
+use crate::ui::help::scroll_text;
+
+/// Lists the most played tracks or artists with their play counts.
+pub struct StatsView {
+    view: ScrollView<TextView>,
+    title: String,
+}
+
+impl StatsView {
+    pub fn new(kind: &StatsKind, window: &str, top: Vec<(usize, String)>) -> StatsView {
+        let title = match kind {
+            StatsKind::Tracks => "Top tracks",
+            StatsKind::Artists => "Top artists",
+        };
+        let mut text = StyledString::styled(format!("{title} {window}\n\n"), Effect::Bold);
+        if top.is_empty() {
+            text.append_plain("Nothing was played yet.\n");
+        }
+        for (count, name) in top {
+            text.append_plain(format!("{count:>5}  {name}\n"));
+        }
+
+        StatsView {
+            view: ScrollView::new(TextView::new(text)),
+            title: title.to_string(),
+        }
+    }
+}
+
+impl ViewWrapper for StatsView {
+    wrap_impl!(self.view: ScrollView<TextView>);
+}
+
+impl ViewExt for StatsView {
+    fn title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn on_command(&mut self, _s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            Command::Move(mode, amount) => {
+                scroll_text(&mut self.view, mode, amount);
+                Ok(CommandResult::Consumed(None))
+            }
+            _ => Ok(CommandResult::Ignored),
+        }
+    }
+}
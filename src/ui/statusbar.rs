@@ -1,4 +1,6 @@
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use cursive::align::HAlign;
 use cursive::event::{Event, EventResult, MouseButton, MouseEvent};
@@ -6,29 +8,68 @@ use cursive::theme::{ColorStyle, ColorType, PaletteColor};
 use cursive::traits::View;
 use cursive::vec::Vec2;
 use cursive::Printer;
+use log::warn;
 use unicode_width::UnicodeWidthStr;
 
+use crate::config::NormalizationMode;
 use crate::library::Library;
 use crate::model::playable::Playable;
 use crate::queue::{Queue, RepeatSetting};
-use crate::spotify::{PlayerEvent, Spotify};
+use crate::spotify::{PlayerEvent, SleepTimer, Spotify};
+use crate::template::Template;
 use crate::utils::ms_to_hms;
 
+/// The fields a `statusbar_format` template may use.
+const FIELDS: &[&str] = &[
+    "state",
+    "title",
+    "artists",
+    "album",
+    "position",
+    "duration",
+    "shuffle",
+    "repeat",
+    "volume",
+    "saved",
+    "sleep",
+    "bitrate",
+    "normalization",
+    "buffering",
+    "updating",
+];
+
 pub struct StatusBar {
     queue: Arc<Queue>,
     spotify: Spotify,
     library: Arc<Library>,
+    /// The template the line below the progress bar is shown with, if the
+    /// `statusbar_format` is one rather than a track format.
+    template: Option<Template>,
     last_size: Vec2,
 }
 
 impl StatusBar {
     pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> StatusBar {
         let spotify = queue.get_spotify();
+        let template = library
+            .cfg
+            .values()
+            .statusbar_format
+            .as_ref()
+            .filter(|format| format.contains('{'))
+            .and_then(|format| match Template::parse(format, FIELDS) {
+                Ok(template) => Some(template),
+                Err(e) => {
+                    warn!("invalid statusbar_format, using the default: {}", e);
+                    None
+                }
+            });
 
         StatusBar {
             queue,
             spotify,
             library,
+            template,
             last_size: Vec2::new(0, 0),
         }
     }
@@ -60,11 +101,38 @@ impl StatusBar {
         match status {
             PlayerEvent::Playing(_) => indicators.0,
             PlayerEvent::Paused(_) => indicators.1,
-            PlayerEvent::Stopped | PlayerEvent::FinishedTrack => indicators.2,
+            PlayerEvent::Loading(..)
+            | PlayerEvent::Stopped
+            | PlayerEvent::FinishedTrack
+            | PlayerEvent::Unavailable(_)
+            | PlayerEvent::Reconnecting(_)
+            | PlayerEvent::ConnectionLost
+            | PlayerEvent::Disconnected(_)
+            | PlayerEvent::SleepTimer(_)
+            | PlayerEvent::Muted(_)
+            | PlayerEvent::VolumeChanged(_)
+            | PlayerEvent::TrackStarted(_)
+            | PlayerEvent::Progress { .. } => indicators.2,
+        }
+    }
+
+    fn sleep_display(&self) -> String {
+        match self.spotify.sleep_timer() {
+            Some(SleepTimer::At(deadline)) => {
+                let remaining = deadline
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default();
+                format!("[S {}] ", ms_to_hms(remaining.as_millis() as u32))
+            }
+            Some(SleepTimer::EndOfTrack) => "[S track] ".to_string(),
+            None => "".to_string(),
         }
     }
 
     fn volume_display(&self) -> String {
+        if self.spotify.is_muted() {
+            return " [muted]".to_string();
+        }
         format!(
             " [{}%]",
             (self.spotify.volume() as f64 / 65535_f64 * 100.0).round() as u16
@@ -81,6 +149,30 @@ impl StatusBar {
             .unwrap_or_else(|| "%artists - %title".to_string());
         Playable::format(t, &format, self.library.clone())
     }
+
+    /// The fields of templates that are about the current item.
+    fn playable_values(&self, playable: &Playable) -> [(&'static str, String); 5] {
+        let (title, artists, album) = match playable {
+            Playable::Track(track) => (
+                track.title.clone(),
+                track.artists.join(", "),
+                track.album.clone().unwrap_or_default(),
+            ),
+            Playable::Episode(episode) => (episode.name.clone(), String::new(), String::new()),
+        };
+        let saved = if self.library.is_saved_track(playable) {
+            self.library.saved_indicator()
+        } else {
+            String::new()
+        };
+        [
+            ("title", title),
+            ("artists", artists),
+            ("album", album),
+            ("duration", playable.duration_str()),
+            ("saved", saved),
+        ]
+    }
 }
 
 impl View for StatusBar {
@@ -119,10 +211,6 @@ impl View for StatusBar {
             );
         });
 
-        printer.with_color(style, |printer| {
-            printer.print((1, 1), self.playback_indicator());
-        });
-
         let updating = if !*self.library.is_done.read().unwrap() {
             if self.use_nerdfont() {
                 "\u{f9e5} "
@@ -133,17 +221,20 @@ impl View for StatusBar {
             ""
         };
 
+        let remaining = self.queue.get_repeat_remaining();
         let repeat = if self.use_nerdfont() {
             match self.queue.get_repeat() {
-                RepeatSetting::None => "",
-                RepeatSetting::RepeatPlaylist => "\u{f955} ",
-                RepeatSetting::RepeatTrack => "\u{f957} ",
+                RepeatSetting::None => "".to_string(),
+                RepeatSetting::RepeatPlaylist => "\u{f955} ".to_string(),
+                RepeatSetting::RepeatTrack => "\u{f957} ".to_string(),
+                RepeatSetting::RepeatCount(_) => format!("\u{f957}×{remaining} "),
             }
         } else {
             match self.queue.get_repeat() {
-                RepeatSetting::None => "",
-                RepeatSetting::RepeatPlaylist => "[R] ",
-                RepeatSetting::RepeatTrack => "[R1] ",
+                RepeatSetting::None => "".to_string(),
+                RepeatSetting::RepeatPlaylist => "[R] ".to_string(),
+                RepeatSetting::RepeatTrack => "[R1] ".to_string(),
+                RepeatSetting::RepeatCount(_) => format!("[R1x{remaining}] "),
             }
         };
 
@@ -173,19 +264,70 @@ impl View for StatusBar {
             None => "".to_string(),
         };
 
+        let bitrate = format!("[{}k] ", self.spotify.bitrate());
+        // Templates place the bitrate themselves.
+        let shown_bitrate = if self.library.cfg.values().show_bitrate.unwrap_or(false) {
+            bitrate.as_str()
+        } else {
+            ""
+        };
+
+        let normalization = match self.spotify.normalization() {
+            NormalizationMode::Off => "".to_string(),
+            mode => format!("[N {mode}] "),
+        };
+
+        let buffering = match self.spotify.get_current_status() {
+            PlayerEvent::Loading(..) => "[Buffering…] ",
+            _ => "",
+        };
+
+        let stop_after_track = if self.queue.get_stop_after_track() {
+            "[⏹ after track] "
+        } else {
+            ""
+        };
+
         let right = updating.to_string()
-            + repeat
+            + buffering
+            + &repeat
             + shuffle
+            + stop_after_track
+            + &self.sleep_display()
+            + shown_bitrate
+            + &normalization
             // + saved
             + &playback_duration_status
             + &volume;
         let offset = HAlign::Right.get_offset(right.width(), printer.size.x);
 
-        printer.with_color(style, |printer| {
-            if let Some(ref t) = self.queue.get_current() {
-                printer.print((4, 1), &self.format_track(t));
+        printer.with_color(style, |printer| match &self.template {
+            Some(template) => {
+                let mut values = HashMap::from([
+                    ("state", self.playback_indicator().trim_end().to_string()),
+                    ("shuffle", shuffle.trim_end().to_string()),
+                    ("repeat", repeat.trim_end().to_string()),
+                    ("volume", volume.trim().to_string()),
+                    ("sleep", self.sleep_display().trim_end().to_string()),
+                    ("bitrate", bitrate.trim_end().to_string()),
+                    ("normalization", normalization.trim_end().to_string()),
+                    ("buffering", buffering.trim_end().to_string()),
+                    ("updating", updating.trim_end().to_string()),
+                ]);
+                if let Some(playable) = self.queue.get_current() {
+                    values.extend(self.playable_values(&playable));
+                    values.insert("position", formatted_elapsed.clone());
+                }
+                let line = template.render(&values, printer.size.x.saturating_sub(2));
+                printer.print((1, 1), &line);
+            }
+            None => {
+                printer.print((1, 1), self.playback_indicator());
+                if let Some(ref t) = self.queue.get_current() {
+                    printer.print((4, 1), &self.format_track(t));
+                }
+                printer.print((offset, 1), &right);
             }
-            printer.print((offset, 1), &right);
         });
 
         if let Some(t) = self.queue.get_current() {
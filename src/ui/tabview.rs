@@ -46,11 +46,29 @@ impl TabView {
         self
     }
 
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
     pub fn move_focus_to(&mut self, target: usize) {
         let len = self.tabs.len().saturating_sub(1);
         self.selected = min(target, len);
     }
 
+    /// The ID of the selected tab.
+    pub fn selected_id(&self) -> Option<&str> {
+        self.ids
+            .iter()
+            .find(|(_, &index)| index == self.selected)
+            .map(|(id, _)| id.as_str())
+    }
+
+    pub fn move_focus_to_id(&mut self, id: &str) {
+        if let Some(&index) = self.ids.get(id) {
+            self.move_focus_to(index);
+        }
+    }
+
     pub fn move_focus(&mut self, delta: i32) {
         let new = self.selected as i32 + delta;
         self.move_focus_to(max(new, 0) as usize);
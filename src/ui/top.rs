@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use chrono::Local;
+use cursive::event::{Event, EventResult};
+use cursive::view::{View, ViewWrapper};
+use cursive::Cursive;
+use rspotify::model::TimeRange;
+
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::model::track::Track;
+use crate::queue::Queue;
+use crate::traits::ViewExt;
+use crate::ui::listview::ListView;
+use crate::ui::pagination::ApiResult;
+use crate::ui::tabview::TabView;
+
+/// The user's top tracks and artists according to Spotify, within a time
+/// range that is switched with `t`.
+pub struct TopView {
+    tabs: TabView,
+    range: TimeRange,
+    tracks: ApiResult<Track>,
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+}
+
+impl TopView {
+    pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> Self {
+        let range = TimeRange::MediumTerm;
+        let (tabs, tracks) = Self::tabs(range, &queue, &library);
+        Self {
+            tabs,
+            range,
+            tracks,
+            queue,
+            library,
+        }
+    }
+
+    fn tabs(
+        range: TimeRange,
+        queue: &Arc<Queue>,
+        library: &Arc<Library>,
+    ) -> (TabView, ApiResult<Track>) {
+        let api = &queue.get_spotify().api;
+        let tracks = api.current_user_top_tracks(range);
+        let tracks_list = ListView::new(tracks.items.clone(), queue.clone(), library.clone())
+            .with_title("Tracks");
+        tracks
+            .clone()
+            .apply_pagination(tracks_list.get_pagination());
+
+        let artists = api.current_user_top_artists(range);
+        let artists_list = ListView::new(artists.items.clone(), queue.clone(), library.clone())
+            .with_title("Artists");
+        artists.apply_pagination(artists_list.get_pagination());
+
+        let tabs = TabView::new()
+            .tab("tracks", tracks_list)
+            .tab("artists", artists_list);
+        (tabs, tracks)
+    }
+
+    /// Switch to the next time range, keeping the selected tab.
+    fn next_range(&mut self) {
+        self.range = match self.range {
+            TimeRange::ShortTerm => TimeRange::MediumTerm,
+            TimeRange::MediumTerm => TimeRange::LongTerm,
+            TimeRange::LongTerm => TimeRange::ShortTerm,
+        };
+        let selected = self.tabs.selected();
+        let (tabs, tracks) = Self::tabs(self.range, &self.queue, &self.library);
+        self.tabs = tabs;
+        self.tabs.move_focus_to(selected);
+        self.tracks = tracks;
+    }
+
+    /// Save all of the top tracks as a new private playlist named after the
+    /// time range and the current month.
+    fn save_tracks(&self) -> Result<String, String> {
+        while self.tracks.next().is_some() {}
+        let tracks: Vec<Playable> = self
+            .tracks
+            .items
+            .read()
+            .unwrap()
+            .iter()
+            .cloned()
+            .map(Playable::Track)
+            .collect();
+        let name = format!(
+            "Top Tracks ({}) — {}",
+            range_name(self.range),
+            Local::now().format("%Y-%m")
+        );
+        if self.library.save_playlist(&name, &tracks, Some(false)) {
+            Ok(format!("Saved the top tracks as {name}"))
+        } else {
+            Err("Could not create the playlist".into())
+        }
+    }
+}
+
+/// About how far back Spotify looks for each time range.
+fn range_name(range: TimeRange) -> &'static str {
+    match range {
+        TimeRange::ShortTerm => "4 weeks",
+        TimeRange::MediumTerm => "6 months",
+        TimeRange::LongTerm => "all time",
+    }
+}
+
+impl ViewWrapper for TopView {
+    wrap_impl!(self.tabs: TabView);
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        if event == Event::Char('t') {
+            self.next_range();
+            return EventResult::consumed();
+        }
+        self.tabs.on_event(event)
+    }
+}
+
+impl ViewExt for TopView {
+    fn title(&self) -> String {
+        format!("Top ({})", range_name(self.range))
+    }
+
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            Command::SaveQueue => self
+                .save_tracks()
+                .map(|message| CommandResult::Consumed(Some(message))),
+            _ => self.tabs.on_command(s, cmd),
+        }
+    }
+}
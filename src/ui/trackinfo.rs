@@ -0,0 +1,260 @@
+use std::sync::Arc;
+use std::thread;
+
+use cursive::traits::Nameable;
+use cursive::view::{Margins, ViewWrapper};
+use cursive::views::{
+    Dialog, DummyView, LinearLayout, NamedView, ScrollView, TextContent, TextView,
+};
+use cursive::Cursive;
+use rspotify::model::{AudioFeatures, Modality};
+
+use crate::command::{Command, GotoMode};
+use crate::commands::CommandResult;
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::model::track::Track;
+use crate::queue::Queue;
+#[cfg(feature = "share_clipboard")]
+use crate::sharing::write_share;
+use crate::traits::{ListItem, ViewExt};
+use crate::ui::contextmenu::ContextMenu;
+use crate::ui::modal::Modal;
+use crate::utils::ms_to_hms;
+
+const PITCH_CLASSES: [&str; 12] = [
+    "C", "C♯", "D", "D♯", "E", "F", "F♯", "G", "G♯", "A", "A♯", "B",
+];
+
+/// A popup with the details of a track. What the track's data doesn't
+/// include, like its audio features, is fetched once the popup is shown.
+///
+/// Going to the album or the artist, sharing and saving work on the track
+/// while the popup is open.
+pub struct TrackInfo {
+    dialog: Modal<Dialog>,
+    track: Track,
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+    saved: TextContent,
+}
+
+impl TrackInfo {
+    pub fn new(track: Track, queue: Arc<Queue>, library: Arc<Library>) -> NamedView<Self> {
+        let saved = TextContent::new(saved_text(&track, &library));
+        let fetched = TextContent::new("Loading…");
+        if let Some(id) = track.id.clone() {
+            let fetched = fetched.clone();
+            let library = library.clone();
+            let spotify = queue.get_spotify();
+            thread::spawn(move || {
+                let full = spotify.api.track(&id);
+                let features = spotify.api.track_features(&id);
+                let mut lines = vec![
+                    field(
+                        "Popularity",
+                        full.as_ref().map(|full| format!("{}/100", full.popularity)),
+                    ),
+                    field(
+                        "Explicit",
+                        full.as_ref().map(|full| yes_no(full.explicit).to_string()),
+                    ),
+                ];
+                lines.extend(features_lines(features.as_ref()));
+                fetched.set_content(lines.join("\n"));
+                library.trigger_redraw();
+            });
+        } else {
+            fetched.set_content("Not on Spotify");
+        }
+
+        let details = [
+            field("Artists", Some(track.artists.join(", "))),
+            field("Album", track.album.clone()),
+            field("Released", track.release_date.clone()),
+            field(
+                "Disc/track",
+                Some(format!("{}/{}", track.disc_number, track.track_number)),
+            ),
+            field("Duration", Some(ms_to_hms(track.duration))),
+            field("Track URI", Some(track.uri.clone())),
+            field(
+                "Album URI",
+                track
+                    .album_id
+                    .as_ref()
+                    .map(|id| format!("spotify:album:{id}")),
+            ),
+        ];
+
+        let content = LinearLayout::vertical()
+            .child(TextView::new(details.join("\n")))
+            .child(TextView::new_with_content(saved.clone()))
+            .child(DummyView)
+            .child(TextView::new(playlists_text(&track, &library)))
+            .child(DummyView)
+            .child(TextView::new_with_content(fetched));
+
+        let dialog = Dialog::new()
+            .title(track.title.clone())
+            .dismiss_button("Close")
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .content(ScrollView::new(content));
+
+        TrackInfo {
+            dialog: Modal::new_ext(dialog),
+            track,
+            queue,
+            library,
+            saved,
+        }
+        .with_name("trackinfo")
+    }
+}
+
+impl ViewExt for TrackInfo {
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            Command::Back => {
+                s.pop_layer();
+            }
+            #[cfg(feature = "share_clipboard")]
+            Command::Share(_) => {
+                if let Some(url) = self.track.share_url() {
+                    write_share(url);
+                }
+            }
+            Command::Goto(GotoMode::Album) => {
+                let album = self.track.album(self.queue.clone());
+                if let Some(view) =
+                    album.and_then(|album| album.open(self.queue.clone(), self.library.clone()))
+                {
+                    s.pop_layer();
+                    return Ok(CommandResult::View(view));
+                }
+            }
+            Command::Goto(GotoMode::Artist) => {
+                let mut artists = self.track.artists().unwrap_or_default();
+                if artists.len() == 1 {
+                    if let Some(view) = artists
+                        .remove(0)
+                        .open(self.queue.clone(), self.library.clone())
+                    {
+                        s.pop_layer();
+                        return Ok(CommandResult::View(view));
+                    }
+                } else if !artists.is_empty() {
+                    let dialog = ContextMenu::select_artist_dialog(
+                        self.library.clone(),
+                        self.queue.clone(),
+                        artists,
+                    );
+                    s.pop_layer();
+                    return Ok(CommandResult::Modal(Box::new(dialog)));
+                }
+            }
+            Command::Save if self.track.id.is_some() => {
+                // Liking happens in the background, so the library can't tell
+                // the new state yet.
+                let saved = !self
+                    .library
+                    .is_saved_track(&Playable::Track(self.track.clone()));
+                self.track.toggle_saved(self.library.clone());
+                self.saved
+                    .set_content(field("Saved", Some(yes_no(saved).to_string())));
+            }
+            _ => {}
+        }
+        Ok(CommandResult::Consumed(None))
+    }
+}
+
+impl ViewWrapper for TrackInfo {
+    wrap_impl!(self.dialog: Modal<Dialog>);
+}
+
+/// A line of the popup, which tells if the value is unknown.
+fn field(name: &str, value: Option<String>) -> String {
+    let value = value.unwrap_or_else(|| "unknown".to_string());
+    format!("{:<13}{value}", format!("{name}:"))
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+fn saved_text(track: &Track, library: &Library) -> String {
+    let saved = track
+        .id
+        .as_ref()
+        .map(|_| yes_no(library.is_saved_track(&Playable::Track(track.clone()))).to_string());
+    field("Saved", saved)
+}
+
+/// The playlists of the library that contain the track, as far as they are
+/// loaded. Those that aren't are counted as unknown rather than fetched.
+fn playlists_text(track: &Track, library: &Library) -> String {
+    let id = match &track.id {
+        Some(id) => id,
+        None => return field("In playlists", None),
+    };
+    let playlists = library.playlists();
+    let containing: Vec<&str> = playlists
+        .iter()
+        .filter(|playlist| playlist.has_track(id))
+        .map(|playlist| playlist.name.as_str())
+        .collect();
+    let unknown = playlists
+        .iter()
+        .filter(|playlist| playlist.tracks.is_none())
+        .count();
+
+    let mut text = field(
+        "In playlists",
+        Some(if containing.is_empty() {
+            "none that are loaded".to_string()
+        } else {
+            containing.join(", ")
+        }),
+    );
+    if unknown > 0 {
+        text.push_str(&format!(
+            "\n{:<13}{unknown} playlists that aren't loaded yet",
+            "Unknown:"
+        ));
+    }
+    text
+}
+
+/// The lines of the audio features, which Spotify doesn't have for every
+/// track.
+fn features_lines(features: Option<&AudioFeatures>) -> Vec<String> {
+    let key = features.and_then(|features| {
+        let pitch = PITCH_CLASSES.get(usize::try_from(features.key).ok()?)?;
+        let mode = match features.mode {
+            Modality::Major => " major",
+            Modality::Minor => " minor",
+            Modality::NoResult => "",
+        };
+        Some(format!("{pitch}{mode}"))
+    });
+    vec![
+        field(
+            "Tempo",
+            features.map(|features| format!("{:.0} BPM", features.tempo)),
+        ),
+        field("Key", key),
+        field(
+            "Energy",
+            features.map(|features| format!("{:.2}", features.energy)),
+        ),
+        field(
+            "Danceability",
+            features.map(|features| format!("{:.2}", features.danceability)),
+        ),
+    ]
+}
@@ -2,6 +2,9 @@
 
 use std::fmt::Write;
 
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
 /// Returns a human readable String of a Duration
 ///
 /// Example: `3h 12m 53s`
@@ -49,13 +52,80 @@ pub fn cache_path_for_url(url: String) -> std::path::PathBuf {
     path
 }
 
+/// Download `url` to `path`. The file only appears at `path` once it is
+/// complete, so a download in progress is never mistaken for a finished one.
 pub fn download(url: String, path: std::path::PathBuf) -> Result<(), std::io::Error> {
     let mut resp = reqwest::blocking::get(url)
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
 
     std::fs::create_dir_all(path.parent().unwrap())?;
-    let mut file = std::fs::File::create(path)?;
+    let partial = path.with_extension("part");
+    let mut file = std::fs::File::create(&partial)?;
 
     std::io::copy(&mut resp, &mut file)?;
-    Ok(())
+    std::fs::rename(partial, path)
+}
+
+/// `text` in lowercase and without diacritics, so that e.g. "é" matches "e".
+pub fn fold(text: &str) -> String {
+    text.nfd()
+        .filter(|&c| !is_combining_mark(c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// How well `query` fuzzily matches `text`, ignoring case, or `None` if the
+/// characters of `query` don't all appear in `text` in order. Matches that
+/// are consecutive or start a word score higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let mut query = query.chars().flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut previous_matched = false;
+
+    for c in text.chars().flat_map(char::to_lowercase) {
+        let wanted = match query.peek() {
+            Some(&wanted) => wanted,
+            None => break,
+        };
+        if c == wanted {
+            query.next();
+            score += 1;
+            if previous_matched {
+                score += 2;
+            }
+            if previous.map_or(true, |p| !p.is_alphanumeric()) {
+                score += 2;
+            }
+        }
+        previous_matched = c == wanted;
+        previous = Some(c);
+    }
+
+    query.peek().is_none().then_some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fold, fuzzy_score};
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(fold("Beyoncé"), "beyonce");
+        assert_eq!(fold("SIGUR RÓS"), "sigur ros");
+        assert_eq!(fold("Motörhead"), "motorhead");
+        assert_eq!(fold("Ærøskøbing"), "ærøskøbing");
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("rck", "Road Trip"), None);
+        assert_eq!(fuzzy_score("", "Road Trip"), Some(0));
+
+        // consecutive matches beat scattered ones
+        assert!(fuzzy_score("rock", "Rock Classics") > fuzzy_score("rock", "Road Clock"));
+        // matches that start a word beat those in the middle of one
+        assert!(fuzzy_score("tm", "Top Mix") > fuzzy_score("tm", "Atoms"));
+        assert_eq!(fuzzy_score("CHILL", "chill"), fuzzy_score("chill", "Chill"));
+    }
 }